@@ -3,11 +3,16 @@ import { MethodKind as ConnectMethodKind, type ServiceType } from "@connectrpc/c
 import {
   Action,
   ActionAck,
+  CancelJobRequest,
   EvaluateRequest,
   EvaluateResponse,
   FrequencyStreamRequest,
   FrequencyUpdate,
   GestureEvaluation,
+  GetJobRequest,
+  Job,
+  ListJobsRequest,
+  ListJobsResponse,
 } from "./actions_pb";
 import { PointerEvent } from "./symbolcast_pb";
 
@@ -46,5 +51,23 @@ export const ActionsService = {
       O: GestureEvaluation,
       kind: MethodKind.ClientStreaming,
     },
+    getJob: {
+      name: "GetJob",
+      I: GetJobRequest,
+      O: Job,
+      kind: MethodKind.Unary,
+    },
+    listJobs: {
+      name: "ListJobs",
+      I: ListJobsRequest,
+      O: ListJobsResponse,
+      kind: MethodKind.Unary,
+    },
+    cancelJob: {
+      name: "CancelJob",
+      I: CancelJobRequest,
+      O: Job,
+      kind: MethodKind.Unary,
+    },
   },
 } as const satisfies ServiceType;
@@ -45,11 +45,24 @@ export class ActionAck extends Message<ActionAck> {
   ]);
 }
 
+export enum JobPriority {
+  NORMAL = 0,
+  LOW = 1,
+  HIGH = 2,
+}
+
+proto3.util.setEnumType(JobPriority, "eco.actions.JobPriority", [
+  { no: 0, name: "JOB_PRIORITY_NORMAL" },
+  { no: 1, name: "JOB_PRIORITY_LOW" },
+  { no: 2, name: "JOB_PRIORITY_HIGH" },
+]);
+
 export class EvaluateRequest extends Message<EvaluateRequest> {
   jobId = "";
   expression = "";
   requestedBy = "";
   model = "";
+  priority = JobPriority.NORMAL;
 
   constructor(data?: Partial<PlainMessage<EvaluateRequest>>) {
     super();
@@ -63,6 +76,7 @@ export class EvaluateRequest extends Message<EvaluateRequest> {
     { no: 2, name: "expression", kind: "scalar", T: 9 },
     { no: 3, name: "requested_by", kind: "scalar", T: 9 },
     { no: 4, name: "model", kind: "scalar", T: 9 },
+    { no: 5, name: "priority", kind: "enum", T: JobPriority },
   ]);
 }
 
@@ -102,11 +116,32 @@ export class FrequencyStreamRequest extends Message<FrequencyStreamRequest> {
   ]);
 }
 
+export enum JobState {
+  UNSPECIFIED = 0,
+  QUEUED = 1,
+  RUNNING = 2,
+  SUCCEEDED = 3,
+  FAILED = 4,
+  CANCELLED = 5,
+}
+
+proto3.util.setEnumType(JobState, "eco.actions.JobState", [
+  { no: 0, name: "JOB_STATE_UNSPECIFIED" },
+  { no: 1, name: "JOB_STATE_QUEUED" },
+  { no: 2, name: "JOB_STATE_RUNNING" },
+  { no: 3, name: "JOB_STATE_SUCCEEDED" },
+  { no: 4, name: "JOB_STATE_FAILED" },
+  { no: 5, name: "JOB_STATE_CANCELLED" },
+]);
+
 export class FrequencyUpdate extends Message<FrequencyUpdate> {
   jobId = "";
   frequency = 0;
   amplitude = 0;
   timestampMs = proto3.util.long(0);
+  sequence = proto3.util.long(0);
+  complete = false;
+  state = JobState.UNSPECIFIED;
 
   constructor(data?: Partial<PlainMessage<FrequencyUpdate>>) {
     super();
@@ -120,6 +155,106 @@ export class FrequencyUpdate extends Message<FrequencyUpdate> {
     { no: 2, name: "frequency", kind: "scalar", T: 1 },
     { no: 3, name: "amplitude", kind: "scalar", T: 1 },
     { no: 4, name: "timestamp_ms", kind: "scalar", T: 3 },
+    { no: 5, name: "sequence", kind: "scalar", T: 4 },
+    { no: 6, name: "complete", kind: "scalar", T: 8 },
+    { no: 7, name: "state", kind: "enum", T: JobState },
+  ]);
+}
+
+export class Job extends Message<Job> {
+  jobId = "";
+  expression = "";
+  requestedBy = "";
+  model = "";
+  state = JobState.UNSPECIFIED;
+  createdAtMs = proto3.util.long(0);
+  startedAtMs = proto3.util.long(0);
+  finishedAtMs = proto3.util.long(0);
+  result?: EvaluateResponse;
+  error = "";
+
+  constructor(data?: Partial<PlainMessage<Job>>) {
+    super();
+    proto3.util.initPartial(data, this);
+  }
+
+  static readonly runtime = proto3;
+  static readonly typeName = "eco.actions.Job";
+  static readonly fields = proto3.util.newFieldList(() => [
+    { no: 1, name: "job_id", kind: "scalar", T: 9 },
+    { no: 2, name: "expression", kind: "scalar", T: 9 },
+    { no: 3, name: "requested_by", kind: "scalar", T: 9 },
+    { no: 4, name: "model", kind: "scalar", T: 9 },
+    { no: 5, name: "state", kind: "enum", T: JobState },
+    { no: 6, name: "created_at_ms", kind: "scalar", T: 3 },
+    { no: 7, name: "started_at_ms", kind: "scalar", T: 3 },
+    { no: 8, name: "finished_at_ms", kind: "scalar", T: 3 },
+    { no: 9, name: "result", kind: "message", T: EvaluateResponse },
+    { no: 10, name: "error", kind: "scalar", T: 9 },
+  ]);
+}
+
+export class GetJobRequest extends Message<GetJobRequest> {
+  jobId = "";
+
+  constructor(data?: Partial<PlainMessage<GetJobRequest>>) {
+    super();
+    proto3.util.initPartial(data, this);
+  }
+
+  static readonly runtime = proto3;
+  static readonly typeName = "eco.actions.GetJobRequest";
+  static readonly fields = proto3.util.newFieldList(() => [
+    { no: 1, name: "job_id", kind: "scalar", T: 9 },
+  ]);
+}
+
+export class ListJobsRequest extends Message<ListJobsRequest> {
+  requestedBy = "";
+  state = JobState.UNSPECIFIED;
+  limit = 0;
+
+  constructor(data?: Partial<PlainMessage<ListJobsRequest>>) {
+    super();
+    proto3.util.initPartial(data, this);
+  }
+
+  static readonly runtime = proto3;
+  static readonly typeName = "eco.actions.ListJobsRequest";
+  static readonly fields = proto3.util.newFieldList(() => [
+    { no: 1, name: "requested_by", kind: "scalar", T: 9 },
+    { no: 2, name: "state", kind: "enum", T: JobState },
+    { no: 3, name: "limit", kind: "scalar", T: 13 },
+  ]);
+}
+
+export class ListJobsResponse extends Message<ListJobsResponse> {
+  jobs: Job[] = [];
+
+  constructor(data?: Partial<PlainMessage<ListJobsResponse>>) {
+    super();
+    proto3.util.initPartial(data, this);
+  }
+
+  static readonly runtime = proto3;
+  static readonly typeName = "eco.actions.ListJobsResponse";
+  static readonly fields = proto3.util.newFieldList(() => [
+    { no: 1, name: "jobs", kind: "message", T: Job, repeated: true },
+  ]);
+}
+
+export class CancelJobRequest extends Message<CancelJobRequest> {
+  jobId = "";
+
+  constructor(data?: Partial<PlainMessage<CancelJobRequest>>) {
+    super();
+    proto3.util.initPartial(data, this);
+  }
+
+  static readonly runtime = proto3;
+  static readonly typeName = "eco.actions.CancelJobRequest";
+  static readonly fields = proto3.util.newFieldList(() => [
+    { no: 1, name: "job_id", kind: "scalar", T: 9 },
   ]);
 }
 
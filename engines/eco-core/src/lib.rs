@@ -1,3 +1,5 @@
 pub mod manifest;
 
-pub use manifest::{ComponentRef, EcoManifest, PortalRef, SymbolCastConfig};
+pub use manifest::{
+    ComponentRef, EcoManifest, GestureBinding, GestureParam, PortalRef, SymbolCastConfig,
+};
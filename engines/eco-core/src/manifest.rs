@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -28,6 +29,31 @@ pub struct ComponentRef {
     pub transport: Option<String>,
 }
 
+/// A template parameter derived from the gesture's confidence as
+/// `offset + scale * confidence`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GestureParam {
+    #[serde(default = "default_param_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+fn default_param_scale() -> f64 {
+    1.0
+}
+
+/// The Q++ circuit a recognized gesture runs. `circuit` may reference
+/// `{confidence}` and any parameter declared in `params` as `{name}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GestureBinding {
+    pub circuit: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub params: BTreeMap<String, GestureParam>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SymbolCastConfig {
     pub model: Option<String>,
@@ -35,6 +61,8 @@ pub struct SymbolCastConfig {
     pub threshold: Option<f32>,
     #[serde(default)]
     pub stream: Option<String>,
+    #[serde(default)]
+    pub gestures: BTreeMap<String, GestureBinding>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -79,6 +107,10 @@ mod tests {
             [symbolcast]
             model = "symbolcast.onnx"
             threshold = 0.73
+
+            [symbolcast.gestures.spiral]
+            circuit = "RX({angle}, q0); CNOT(q0, q1)"
+            params = { angle = { scale = 3.0 } }
         "#;
 
         let manifest: EcoManifest = toml::from_str(raw).expect("parse manifest");
@@ -86,5 +118,8 @@ mod tests {
         assert_eq!(manifest.portals.len(), 1);
         assert_eq!(manifest.components.len(), 1);
         assert_eq!(manifest.symbolcast.threshold, Some(0.73));
+        let spiral = &manifest.symbolcast.gestures["spiral"];
+        assert_eq!(spiral.params["angle"].scale, 3.0);
+        assert_eq!(spiral.params["angle"].offset, 0.0);
     }
 }
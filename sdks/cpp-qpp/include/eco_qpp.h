@@ -2,7 +2,9 @@
 
 #include <cstdint>
 #include <memory>
+#include <stdexcept>
 #include <string>
+#include <vector>
 
 namespace eco::qpp {
 struct QuantumResult {
@@ -10,7 +12,27 @@ struct QuantumResult {
   double fidelity;
 };
 
+// Errors raised by `evaluate_expression`. Each message starts with a stable
+// `<kind>: ` prefix so bindings can map them back to typed errors.
+struct ParseError : std::runtime_error {
+  explicit ParseError(const std::string &detail)
+      : std::runtime_error("parse: " + detail) {}
+};
+
+struct UnsupportedGateError : std::runtime_error {
+  explicit UnsupportedGateError(const std::string &gate)
+      : std::runtime_error("unsupported_gate: " + gate) {}
+};
+
+struct NumericalError : std::runtime_error {
+  explicit NumericalError(const std::string &detail)
+      : std::runtime_error("numerical: " + detail) {}
+};
+
 std::unique_ptr<QuantumResult> evaluate_expression(const std::string &source);
+// Energy and fidelity after each operation, interleaved:
+// `[energy_0, fidelity_0, energy_1, fidelity_1, ...]`.
+std::unique_ptr<std::vector<double>> evaluate_steps(const std::string &source);
 double qpp_energy(const QuantumResult &result);
 double qpp_fidelity(const QuantumResult &result);
 }
@@ -1,13 +1,310 @@
 #include "eco_qpp.h"
 
+#include <algorithm>
+#include <array>
+#include <bitset>
+#include <cctype>
 #include <cmath>
+#include <complex>
+#include <cstdlib>
+#include <string_view>
+#include <utility>
+#include <vector>
 
 namespace eco::qpp {
+namespace {
+using Complex = std::complex<double>;
+
+// Statevectors hold 2^(max qubit + 1) amplitudes; keep in step with the
+// agent's expression validator.
+constexpr unsigned kMaxQubit = 19;
+constexpr double kNormTolerance = 1e-9;
+
+struct GateSpec {
+  std::string_view name;
+  bool takes_angle;
+  std::size_t qubits;
+};
+
+constexpr GateSpec kGates[] = {
+    {"H", false, 1},    {"X", false, 1},  {"Y", false, 1},
+    {"Z", false, 1},    {"S", false, 1},  {"T", false, 1},
+    {"RX", true, 1},    {"RY", true, 1},  {"RZ", true, 1},
+    {"CNOT", false, 2}, {"CZ", false, 2}, {"SWAP", false, 2},
+    {"MEASURE", false, 1},
+};
+
+std::string_view trim(std::string_view value) {
+  while (!value.empty() && std::isspace(static_cast<unsigned char>(value.front()))) {
+    value.remove_prefix(1);
+  }
+  while (!value.empty() && std::isspace(static_cast<unsigned char>(value.back()))) {
+    value.remove_suffix(1);
+  }
+  return value;
+}
+
+bool is_identifier(std::string_view value) {
+  if (value.empty() ||
+      !(std::isalpha(static_cast<unsigned char>(value.front())) || value.front() == '_')) {
+    return false;
+  }
+  for (char c : value) {
+    if (!(std::isalnum(static_cast<unsigned char>(c)) || c == '_')) {
+      return false;
+    }
+  }
+  return true;
+}
+
+unsigned parse_qubit(std::string_view text, std::string_view gate) {
+  const std::string owned(text);
+  if (owned.size() < 2 || owned.front() != 'q' ||
+      !std::all_of(owned.begin() + 1, owned.end(),
+                   [](char c) { return std::isdigit(static_cast<unsigned char>(c)); })) {
+    throw ParseError("invalid qubit `" + owned + "` for " + std::string(gate));
+  }
+  const unsigned long index = std::strtoul(owned.c_str() + 1, nullptr, 10);
+  if (owned.size() > 10 || index > kMaxQubit) {
+    throw ParseError("qubit `" + owned + "` is out of range");
+  }
+  return static_cast<unsigned>(index);
+}
+
+double parse_angle(std::string_view text, std::string_view gate) {
+  const std::string owned(text);
+  char *end = nullptr;
+  const double angle = std::strtod(owned.c_str(), &end);
+  if (owned.empty() || end != owned.c_str() + owned.size()) {
+    throw ParseError("invalid angle `" + owned + "` for " + std::string(gate));
+  }
+  if (!std::isfinite(angle)) {
+    throw NumericalError("non-finite angle for " + std::string(gate));
+  }
+  return angle;
+}
+
+struct Operation {
+  std::string_view gate;
+  double angle;
+  std::vector<unsigned> qubits;
+};
+
+Operation parse_statement(std::string_view statement) {
+  const auto open = statement.find('(');
+  if (open == std::string_view::npos || statement.back() != ')') {
+    throw ParseError("expected `GATE(args)` but found `" + std::string(statement) + "`");
+  }
+  const auto name = trim(statement.substr(0, open));
+  const auto inner = statement.substr(open + 1, statement.size() - open - 2);
+
+  std::vector<std::string_view> args;
+  std::size_t start = 0;
+  while (start <= inner.size()) {
+    const auto comma = inner.find(',', start);
+    const auto end = comma == std::string_view::npos ? inner.size() : comma;
+    args.push_back(trim(inner.substr(start, end - start)));
+    start = end + 1;
+  }
+
+  const GateSpec *spec = nullptr;
+  for (const auto &gate : kGates) {
+    if (gate.name == name) {
+      spec = &gate;
+      break;
+    }
+  }
+  if (spec == nullptr) {
+    if (!is_identifier(name)) {
+      throw ParseError("invalid gate name `" + std::string(name) + "`");
+    }
+    throw UnsupportedGateError(std::string(name));
+  }
+
+  const std::size_t expected = spec->qubits + (spec->takes_angle ? 1 : 0);
+  if (args.size() != expected) {
+    throw ParseError(std::string(name) + " expects " + std::to_string(expected) +
+                     " argument(s), found " + std::to_string(args.size()));
+  }
+  Operation operation{spec->name, 0.0, {}};
+  std::size_t index = 0;
+  if (spec->takes_angle) {
+    operation.angle = parse_angle(args[index++], name);
+  }
+  for (; index < args.size(); ++index) {
+    const unsigned qubit = parse_qubit(args[index], name);
+    if (std::find(operation.qubits.begin(), operation.qubits.end(), qubit) !=
+        operation.qubits.end()) {
+      throw ParseError("qubit used more than once in " + std::string(name));
+    }
+    operation.qubits.push_back(qubit);
+  }
+  return operation;
+}
+
+std::vector<Operation> parse(std::string_view source) {
+  std::vector<Operation> operations;
+  std::size_t start = 0;
+  while (start <= source.size()) {
+    const auto end = source.find_first_of(";\n", start);
+    const auto stop = end == std::string_view::npos ? source.size() : end;
+    const auto statement = trim(source.substr(start, stop - start));
+    if (!statement.empty()) {
+      operations.push_back(parse_statement(statement));
+    }
+    start = stop + 1;
+  }
+  if (operations.empty()) {
+    throw ParseError("expression is empty");
+  }
+  return operations;
+}
+
+// Statevector simulation. Qubit k is bit k of the basis-state index; the
+// gate conventions and observables match the agent's Rust simulator.
+using Matrix = std::array<std::array<Complex, 2>, 2>;
+
+Matrix matrix(Complex m00, Complex m01, Complex m10, Complex m11) {
+  Matrix m;
+  m[0] = {m00, m01};
+  m[1] = {m10, m11};
+  return m;
+}
+
+Matrix single_qubit_matrix(std::string_view gate, double angle) {
+  const double c = std::cos(angle / 2.0);
+  const double s = std::sin(angle / 2.0);
+  const Complex i(0.0, 1.0);
+  if (gate == "H") {
+    const double h = 1.0 / std::sqrt(2.0);
+    return matrix(h, h, h, -h);
+  }
+  if (gate == "X") return matrix(0.0, 1.0, 1.0, 0.0);
+  if (gate == "Y") return matrix(0.0, -i, i, 0.0);
+  if (gate == "Z") return matrix(1.0, 0.0, 0.0, -1.0);
+  if (gate == "S") return matrix(1.0, 0.0, 0.0, i);
+  if (gate == "T") return matrix(1.0, 0.0, 0.0, std::polar(1.0, std::atan(1.0)));
+  if (gate == "RX") return matrix(c, Complex(0.0, -s), Complex(0.0, -s), c);
+  if (gate == "RY") return matrix(c, -s, s, c);
+  if (gate == "RZ") {
+    return matrix(std::polar(1.0, -angle / 2.0), 0.0, 0.0, std::polar(1.0, angle / 2.0));
+  }
+  throw UnsupportedGateError(std::string(gate));
+}
+
+void apply(std::vector<Complex> &state, const Operation &op) {
+  const std::size_t dim = state.size();
+  if (op.gate == "CNOT") {
+    const std::size_t control = std::size_t{1} << op.qubits[0];
+    const std::size_t target = std::size_t{1} << op.qubits[1];
+    for (std::size_t index = 0; index < dim; ++index) {
+      if ((index & control) && !(index & target)) {
+        std::swap(state[index], state[index | target]);
+      }
+    }
+  } else if (op.gate == "CZ") {
+    const std::size_t mask = (std::size_t{1} << op.qubits[0]) | (std::size_t{1} << op.qubits[1]);
+    for (std::size_t index = 0; index < dim; ++index) {
+      if ((index & mask) == mask) {
+        state[index] *= -1.0;
+      }
+    }
+  } else if (op.gate == "SWAP") {
+    const std::size_t first = std::size_t{1} << op.qubits[0];
+    const std::size_t second = std::size_t{1} << op.qubits[1];
+    for (std::size_t index = 0; index < dim; ++index) {
+      if ((index & first) && !(index & second)) {
+        std::swap(state[index], state[index ^ first ^ second]);
+      }
+    }
+  } else if (op.gate == "MEASURE") {
+    // Collapse onto the more likely outcome (0 on a tie) to stay deterministic.
+    const std::size_t bit = std::size_t{1} << op.qubits[0];
+    double one = 0.0;
+    for (std::size_t index = 0; index < dim; ++index) {
+      if (index & bit) {
+        one += std::norm(state[index]);
+      }
+    }
+    const bool keep_set = one > 0.5;
+    const double factor = 1.0 / std::sqrt(keep_set ? one : 1.0 - one);
+    for (std::size_t index = 0; index < dim; ++index) {
+      state[index] = (static_cast<bool>(index & bit) == keep_set) ? state[index] * factor
+                                                                   : Complex(0.0, 0.0);
+    }
+  } else {
+    const Matrix m = single_qubit_matrix(op.gate, op.angle);
+    const std::size_t bit = std::size_t{1} << op.qubits[0];
+    for (std::size_t index = 0; index < dim; ++index) {
+      if (index & bit) {
+        continue;
+      }
+      const Complex a0 = state[index];
+      const Complex a1 = state[index | bit];
+      state[index] = m[0][0] * a0 + m[0][1] * a1;
+      state[index | bit] = m[1][0] * a0 + m[1][1] * a1;
+    }
+  }
+}
+
+unsigned qubit_count(const std::vector<Operation> &operations) {
+  unsigned qubits = 1;
+  for (const auto &op : operations) {
+    for (unsigned qubit : op.qubits) {
+      qubits = std::max(qubits, qubit + 1);
+    }
+  }
+  return qubits;
+}
+
+QuantumResult observe(const std::vector<Complex> &state) {
+  double norm = 0.0;
+  double energy = 0.0;
+  double fidelity = 0.0;
+  for (std::size_t index = 0; index < state.size(); ++index) {
+    const double probability = std::norm(state[index]);
+    norm += probability;
+    energy += probability * static_cast<double>(std::bitset<64>(index).count());
+    fidelity = std::max(fidelity, probability);
+  }
+  if (!std::isfinite(energy) || !std::isfinite(fidelity) ||
+      std::abs(norm - 1.0) > kNormTolerance) {
+    throw NumericalError("state lost normalization");
+  }
+  return QuantumResult{energy, fidelity};
+}
+
+std::vector<Complex> initial_state(const std::vector<Operation> &operations) {
+  std::vector<Complex> state(std::size_t{1} << qubit_count(operations), Complex(0.0, 0.0));
+  state[0] = 1.0;
+  return state;
+}
+
+QuantumResult simulate(const std::vector<Operation> &operations) {
+  std::vector<Complex> state = initial_state(operations);
+  for (const auto &op : operations) {
+    apply(state, op);
+  }
+  return observe(state);
+}
+} // namespace
+
 std::unique_ptr<QuantumResult> evaluate_expression(const std::string &source) {
-  // Placeholder quantum evaluation - compute deterministic fingerprint
-  double energy = static_cast<double>(source.size());
-  double fidelity = std::tanh(energy / 42.0);
-  return std::make_unique<QuantumResult>(QuantumResult{energy, fidelity});
+  return std::make_unique<QuantumResult>(simulate(parse(source)));
+}
+
+std::unique_ptr<std::vector<double>> evaluate_steps(const std::string &source) {
+  const std::vector<Operation> operations = parse(source);
+  std::vector<Complex> state = initial_state(operations);
+  auto steps = std::make_unique<std::vector<double>>();
+  steps->reserve(operations.size() * 2);
+  for (const auto &op : operations) {
+    apply(state, op);
+    const QuantumResult result = observe(state);
+    steps->push_back(result.energy);
+    steps->push_back(result.fidelity);
+  }
+  return steps;
 }
 
 double qpp_energy(const QuantumResult &result) { return result.energy; }
@@ -9,7 +9,6 @@ fn main() {
         .parent()
         .expect("repo root");
     let proto_dir = repo_root.join("proto");
-    let cpp_dir = repo_root.join("sdks").join("cpp-qpp");
 
     tonic_build::configure()
         .build_server(true)
@@ -24,6 +23,22 @@ fn main() {
         .compile(&[proto_dir.join("symbolcast.proto")], &[proto_dir.clone()])
         .expect("compile symbolcast proto");
 
+    #[cfg(feature = "cpp-qpp")]
+    compile_qpp_bridge(repo_root);
+
+    println!(
+        "cargo:rerun-if-changed={}",
+        proto_dir.join("actions.proto").display()
+    );
+    println!(
+        "cargo:rerun-if-changed={}",
+        proto_dir.join("symbolcast.proto").display()
+    );
+}
+
+#[cfg(feature = "cpp-qpp")]
+fn compile_qpp_bridge(repo_root: &std::path::Path) {
+    let cpp_dir = repo_root.join("sdks").join("cpp-qpp");
     cxx_build::bridge("src/qpp_bridge.rs")
         .file(cpp_dir.join("src").join("qpp.cpp"))
         .flag_if_supported("-std=c++17")
@@ -39,12 +54,4 @@ fn main() {
         "cargo:rerun-if-changed={}",
         cpp_dir.join("include").join("eco_qpp.h").display()
     );
-    println!(
-        "cargo:rerun-if-changed={}",
-        proto_dir.join("actions.proto").display()
-    );
-    println!(
-        "cargo:rerun-if-changed={}",
-        proto_dir.join("symbolcast.proto").display()
-    );
 }
@@ -0,0 +1,211 @@
+use crate::error::AgentError;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+
+/// Action kind of an evaluation, submitted through `Cast` or `Evaluate`.
+pub const EVALUATE_ACTION: &str = "qpp.evaluate";
+/// Action kind of `RecognizeGesture`.
+pub const GESTURE_ACTION: &str = "gesture.recognize";
+/// Action kind of `GetJob`, `ListJobs` and `StreamFrequencies` on the
+/// caller's own jobs.
+pub const READ_JOBS_ACTION: &str = "jobs.read";
+/// Action kind of reading jobs that other requesters submitted.
+pub const READ_ALL_JOBS_ACTION: &str = "jobs.read_all";
+/// Action kind of `CancelJob`.
+pub const CANCEL_JOB_ACTION: &str = "jobs.cancel";
+
+/// Claims of the session tokens issued by ethos-gateway.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub email: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub is_guest: bool,
+    pub exp: usize,
+}
+
+/// Who may perform an action kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Any valid token, guest sessions included.
+    Everyone,
+    /// Valid tokens of registered (non-guest) users.
+    Members,
+    /// Valid tokens of the configured admin subjects.
+    Admins,
+    /// Nobody; the action is disabled.
+    Nobody,
+}
+
+impl Access {
+    fn parse(value: &str) -> Result<Self, AgentError> {
+        match value.trim() {
+            "everyone" => Ok(Access::Everyone),
+            "members" => Ok(Access::Members),
+            "admins" => Ok(Access::Admins),
+            "nobody" => Ok(Access::Nobody),
+            other => Err(AgentError::InvalidConfig(format!(
+                "unknown access level {other}"
+            ))),
+        }
+    }
+
+    fn allows(self, claims: &Claims, admins: &HashSet<String>) -> bool {
+        match self {
+            Access::Everyone => true,
+            Access::Members => !claims.is_guest,
+            Access::Admins => !claims.is_guest && admins.contains(&claims.sub),
+            Access::Nobody => false,
+        }
+    }
+}
+
+/// Access rules per action kind. Kinds without a rule are open to members.
+#[derive(Clone, Debug)]
+pub struct Permissions {
+    rules: HashMap<String, Access>,
+    admins: HashSet<String>,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        let mut rules = HashMap::new();
+        rules.insert(EVALUATE_ACTION.to_string(), Access::Members);
+        rules.insert(GESTURE_ACTION.to_string(), Access::Members);
+        rules.insert(READ_JOBS_ACTION.to_string(), Access::Everyone);
+        rules.insert(READ_ALL_JOBS_ACTION.to_string(), Access::Admins);
+        rules.insert(CANCEL_JOB_ACTION.to_string(), Access::Members);
+        Self {
+            rules,
+            admins: HashSet::new(),
+        }
+    }
+}
+
+impl Permissions {
+    /// Parses overrides such as `qpp.evaluate=everyone,jobs.cancel=nobody`
+    /// on top of the defaults.
+    pub fn parse(spec: &str) -> Result<Self, AgentError> {
+        let mut permissions = Self::default();
+        for entry in spec.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (kind, access) = entry.split_once('=').ok_or_else(|| {
+                AgentError::InvalidConfig(format!("invalid permission entry {entry}"))
+            })?;
+            permissions.set(kind.trim(), Access::parse(access)?);
+        }
+        Ok(permissions)
+    }
+
+    /// Sets the subjects `Access::Admins` lets through.
+    pub fn with_admins(mut self, admins: impl IntoIterator<Item = String>) -> Self {
+        self.admins = admins.into_iter().collect();
+        self
+    }
+
+    pub fn set(&mut self, kind: &str, access: Access) {
+        self.rules.insert(kind.to_string(), access);
+    }
+
+    pub fn access(&self, kind: &str) -> Access {
+        self.rules.get(kind).copied().unwrap_or(Access::Members)
+    }
+}
+
+/// Validates gateway tokens on incoming gRPC calls and checks them against
+/// the per-kind permissions.
+#[derive(Clone)]
+pub struct Authenticator {
+    secret: String,
+    permissions: Permissions,
+}
+
+impl Authenticator {
+    pub fn new(secret: impl Into<String>, permissions: Permissions) -> Self {
+        Self {
+            secret: secret.into(),
+            permissions,
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn authenticate(&self, metadata: &MetadataMap) -> Result<Claims, Status> {
+        let token = metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(Status::unauthenticated("missing authorization"))?;
+        decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret.as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        )
+        .map(|data| data.claims)
+        .map_err(|_| Status::unauthenticated("invalid token"))
+    }
+
+    /// Authenticates the caller and checks it may perform `kind`.
+    #[allow(clippy::result_large_err)]
+    pub fn authorize(&self, metadata: &MetadataMap, kind: &str) -> Result<Claims, Status> {
+        let claims = self.authenticate(metadata)?;
+        if !self.permits(&claims, kind) {
+            return Err(Status::permission_denied(format!(
+                "{} may not perform {kind}",
+                claims.sub
+            )));
+        }
+        Ok(claims)
+    }
+
+    /// Whether an authenticated caller may perform `kind`.
+    pub fn permits(&self, claims: &Claims, kind: &str) -> bool {
+        self.permissions
+            .access(kind)
+            .allows(claims, &self.permissions.admins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(is_guest: bool) -> Claims {
+        Claims {
+            sub: "user".to_string(),
+            email: "user@example.com".to_string(),
+            display_name: None,
+            is_guest,
+            exp: 0,
+        }
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_defaults() {
+        let permissions =
+            Permissions::parse("qpp.evaluate=everyone, jobs.cancel=nobody").expect("parse");
+        let admins = HashSet::new();
+        assert!(permissions
+            .access(EVALUATE_ACTION)
+            .allows(&claims(true), &admins));
+        assert!(!permissions
+            .access(CANCEL_JOB_ACTION)
+            .allows(&claims(false), &admins));
+        assert_eq!(permissions.access(READ_JOBS_ACTION), Access::Everyone);
+        assert_eq!(permissions.access(READ_ALL_JOBS_ACTION), Access::Admins);
+        assert_eq!(permissions.access("unknown.kind"), Access::Members);
+        assert!(Permissions::parse("qpp.evaluate").is_err());
+        assert!(Permissions::parse("qpp.evaluate=root").is_err());
+    }
+
+    #[test]
+    fn admin_access_needs_a_listed_registered_subject() {
+        let admins = HashSet::from(["user".to_string()]);
+        assert!(Access::Admins.allows(&claims(false), &admins));
+        assert!(!Access::Admins.allows(&claims(true), &admins));
+        assert!(!Access::Admins.allows(&claims(false), &HashSet::new()));
+    }
+}
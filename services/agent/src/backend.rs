@@ -0,0 +1,94 @@
+use crate::circuit::Circuit;
+use crate::error::{AgentError, BridgeError};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+/// Energy and fidelity of a simulated circuit.
+///
+/// Every backend reports the same observables of the final state so results
+/// can be compared across backends:
+/// - `energy` is the expectation of `Σ (I - Z_k) / 2`, i.e. the expected
+///   number of qubits measured as `1`;
+/// - `fidelity` is the probability of the most likely basis state.
+#[derive(Clone, Copy, Debug)]
+pub struct QuantumEvaluation {
+    pub energy: f64,
+    pub fidelity: f64,
+}
+
+pub trait QuantumBackend: Send + Sync {
+    /// Name used to select the backend through `EvaluateRequest.model`.
+    fn name(&self) -> &'static str;
+
+    fn evaluate(&self, circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError>;
+
+    /// Runs the circuit once, handing `step` the observables after each
+    /// operation until it breaks. Returns the observables of the last state
+    /// reached. Backends that cannot observe intermediate states report
+    /// only the final one.
+    fn evaluate_steps(
+        &self,
+        circuit: &Circuit,
+        step: &mut dyn FnMut(QuantumEvaluation) -> ControlFlow<()>,
+    ) -> Result<QuantumEvaluation, BridgeError> {
+        let evaluation = self.evaluate(circuit)?;
+        let _ = step(evaluation);
+        Ok(evaluation)
+    }
+}
+
+/// The backends available to the executor, keyed by model name.
+#[derive(Clone)]
+pub struct Backends {
+    default_model: String,
+    backends: HashMap<String, Arc<dyn QuantumBackend>>,
+}
+
+impl Backends {
+    /// Registers every backend compiled into the agent. The C++ runtime is
+    /// the default when it is available.
+    pub fn builtin() -> Self {
+        let simulator = Arc::new(crate::simulator::StatevectorBackend);
+        #[cfg(feature = "cpp-qpp")]
+        {
+            let mut backends = Self::new(Arc::new(crate::qpp_bridge::CppBackend));
+            backends.register(simulator);
+            backends
+        }
+        #[cfg(not(feature = "cpp-qpp"))]
+        Self::new(simulator)
+    }
+
+    pub fn new(default: Arc<dyn QuantumBackend>) -> Self {
+        let mut backends = Self {
+            default_model: default.name().to_string(),
+            backends: HashMap::new(),
+        };
+        backends.register(default);
+        backends
+    }
+
+    pub fn register(&mut self, backend: Arc<dyn QuantumBackend>) {
+        self.backends.insert(backend.name().to_string(), backend);
+    }
+
+    pub fn set_default(&mut self, model: &str) -> Result<(), AgentError> {
+        self.resolve(Some(model))?;
+        self.default_model = model.to_string();
+        Ok(())
+    }
+
+    /// Picks the backend for a requested model; empty or missing models use
+    /// the default backend.
+    pub fn resolve(&self, model: Option<&str>) -> Result<Arc<dyn QuantumBackend>, AgentError> {
+        let model = match model.map(str::trim) {
+            Some(model) if !model.is_empty() => model,
+            _ => self.default_model.as_str(),
+        };
+        self.backends
+            .get(model)
+            .cloned()
+            .ok_or_else(|| AgentError::UnknownModel(model.to_string()))
+    }
+}
@@ -0,0 +1,498 @@
+use std::f64::consts::PI;
+use std::fmt;
+use thiserror::Error;
+
+/// Highest qubit index an expression may reference. Simulators hold the
+/// full statevector, so this caps their memory at 2^20 amplitudes.
+pub const MAX_QUBIT: u32 = 19;
+/// Rotation angles are limited to one full turn in either direction.
+pub const MAX_ANGLE: f64 = 2.0 * PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gate {
+    H,
+    X,
+    Y,
+    Z,
+    S,
+    T,
+    Rx,
+    Ry,
+    Rz,
+    Cnot,
+    Cz,
+    Swap,
+    Measure,
+}
+
+impl Gate {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "H" => Some(Gate::H),
+            "X" => Some(Gate::X),
+            "Y" => Some(Gate::Y),
+            "Z" => Some(Gate::Z),
+            "S" => Some(Gate::S),
+            "T" => Some(Gate::T),
+            "RX" => Some(Gate::Rx),
+            "RY" => Some(Gate::Ry),
+            "RZ" => Some(Gate::Rz),
+            "CNOT" => Some(Gate::Cnot),
+            "CZ" => Some(Gate::Cz),
+            "SWAP" => Some(Gate::Swap),
+            "MEASURE" => Some(Gate::Measure),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Gate::H => "H",
+            Gate::X => "X",
+            Gate::Y => "Y",
+            Gate::Z => "Z",
+            Gate::S => "S",
+            Gate::T => "T",
+            Gate::Rx => "RX",
+            Gate::Ry => "RY",
+            Gate::Rz => "RZ",
+            Gate::Cnot => "CNOT",
+            Gate::Cz => "CZ",
+            Gate::Swap => "SWAP",
+            Gate::Measure => "MEASURE",
+        }
+    }
+
+    /// Number of angle parameters the gate takes before its qubits.
+    pub fn params(&self) -> usize {
+        match self {
+            Gate::Rx | Gate::Ry | Gate::Rz => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn qubits(&self) -> usize {
+        match self {
+            Gate::Cnot | Gate::Cz | Gate::Swap => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Line and column (both 1-based) of a character in the source expression.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum ParseErrorKind {
+    #[error("expression is empty")]
+    Empty,
+    #[error("expected {expected}, found {found}")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("unknown gate `{0}`")]
+    UnknownGate(String),
+    #[error("{gate} expects {expected} argument(s), found {found}")]
+    Arity {
+        gate: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("invalid qubit `{0}`, expected `q<index>`")]
+    InvalidQubit(String),
+    #[error("qubit index {0} is out of range (max {MAX_QUBIT})")]
+    QubitOutOfRange(u64),
+    #[error("qubit q{0} is used more than once in the same gate")]
+    DuplicateQubit(u32),
+    #[error("invalid angle `{0}`")]
+    InvalidAngle(String),
+    #[error("angle {0} is outside [-2π, 2π]")]
+    AngleOutOfRange(f64),
+}
+
+#[derive(Clone, Debug, PartialEq, Error)]
+#[error("{kind} at {position}")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    pub gate: Gate,
+    pub params: Vec<f64>,
+    pub qubits: Vec<u32>,
+    pub position: Position,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.gate.as_str())?;
+        let params = self.params.iter().map(|param| param.to_string());
+        let qubits = self.qubits.iter().map(|qubit| format!("q{qubit}"));
+        let args: Vec<String> = params.chain(qubits).collect();
+        write!(f, "{})", args.join(", "))
+    }
+}
+
+/// A validated Q++ expression. `Display` renders the canonical form that is
+/// handed to the runtime.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Circuit {
+    pub operations: Vec<Operation>,
+}
+
+impl Circuit {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Parser::new(source).parse()
+    }
+}
+
+impl fmt::Display for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, operation) in self.operations.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{operation}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Number(&'a str),
+    LParen,
+    RParen,
+    Comma,
+    Separator,
+    End,
+}
+
+impl Token<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(text) | Token::Number(text) => format!("`{text}`"),
+            Token::LParen => "`(`".to_string(),
+            Token::RParen => "`)`".to_string(),
+            Token::Comma => "`,`".to_string(),
+            Token::Separator => "statement separator".to_string(),
+            Token::End => "end of expression".to_string(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn parse(mut self) -> Result<Circuit, ParseError> {
+        let mut operations = Vec::new();
+        loop {
+            let (token, position) = self.next_token()?;
+            match token {
+                Token::End => break,
+                Token::Separator => continue,
+                Token::Ident(name) => operations.push(self.operation(name, position)?),
+                other => {
+                    return Err(unexpected("gate name", &other, position));
+                }
+            }
+            let (token, position) = self.next_token()?;
+            match token {
+                Token::Separator => {}
+                Token::End => break,
+                other => return Err(unexpected("`;` or newline", &other, position)),
+            }
+        }
+        if operations.is_empty() {
+            return Err(ParseError {
+                kind: ParseErrorKind::Empty,
+                position: Position { line: 1, column: 1 },
+            });
+        }
+        Ok(Circuit { operations })
+    }
+
+    fn operation(&mut self, name: &str, position: Position) -> Result<Operation, ParseError> {
+        let gate = Gate::parse(name).ok_or_else(|| ParseError {
+            kind: ParseErrorKind::UnknownGate(name.to_string()),
+            position,
+        })?;
+        self.expect(Token::LParen, "`(`")?;
+
+        let mut args = Vec::new();
+        let (mut token, mut arg_position) = self.next_token()?;
+        if token != Token::RParen {
+            loop {
+                match token {
+                    Token::Ident(text) | Token::Number(text) => args.push((text, arg_position)),
+                    other => return Err(unexpected("argument", &other, arg_position)),
+                }
+                let (next, next_position) = self.next_token()?;
+                match next {
+                    Token::Comma => {}
+                    Token::RParen => break,
+                    other => return Err(unexpected("`,` or `)`", &other, next_position)),
+                }
+                (token, arg_position) = self.next_token()?;
+            }
+        }
+
+        let expected = gate.params() + gate.qubits();
+        if args.len() != expected {
+            return Err(ParseError {
+                kind: ParseErrorKind::Arity {
+                    gate: gate.as_str(),
+                    expected,
+                    found: args.len(),
+                },
+                position,
+            });
+        }
+        let (param_args, qubit_args) = args.split_at(gate.params());
+        let params = param_args
+            .iter()
+            .map(|(text, position)| parse_angle(text, *position))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut qubits = Vec::with_capacity(qubit_args.len());
+        for (text, position) in qubit_args {
+            let qubit = parse_qubit(text, *position)?;
+            if qubits.contains(&qubit) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::DuplicateQubit(qubit),
+                    position: *position,
+                });
+            }
+            qubits.push(qubit);
+        }
+        Ok(Operation {
+            gate,
+            params,
+            qubits,
+            position,
+        })
+    }
+
+    fn expect(&mut self, expected: Token<'_>, label: &'static str) -> Result<(), ParseError> {
+        let (token, position) = self.next_token()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(unexpected(label, &token, position))
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self, ch: char) {
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn next_token(&mut self) -> Result<(Token<'a>, Position), ParseError> {
+        while let Some(ch) = self.peek() {
+            if ch == '\n' || !ch.is_whitespace() {
+                break;
+            }
+            self.bump(ch);
+        }
+        let position = self.position();
+        let Some(ch) = self.peek() else {
+            return Ok((Token::End, position));
+        };
+        let single = match ch {
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            ',' => Some(Token::Comma),
+            ';' | '\n' => Some(Token::Separator),
+            _ => None,
+        };
+        if let Some(token) = single {
+            self.bump(ch);
+            return Ok((token, position));
+        }
+
+        let start = self.offset;
+        let is_number = ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.');
+        if !(is_number || ch.is_alphabetic() || ch == '_') {
+            return Err(ParseError {
+                kind: ParseErrorKind::Unexpected {
+                    expected: "gate, qubit or number",
+                    found: format!("`{ch}`"),
+                },
+                position,
+            });
+        }
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() || matches!(ch, '(' | ')' | ',' | ';') {
+                break;
+            }
+            self.bump(ch);
+        }
+        let text = &self.source[start..self.offset];
+        let token = if is_number {
+            Token::Number(text)
+        } else {
+            Token::Ident(text)
+        };
+        Ok((token, position))
+    }
+}
+
+fn unexpected(expected: &'static str, found: &Token<'_>, position: Position) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::Unexpected {
+            expected,
+            found: found.describe(),
+        },
+        position,
+    }
+}
+
+fn parse_angle(text: &str, position: Position) -> Result<f64, ParseError> {
+    let angle: f64 = text.parse().map_err(|_| ParseError {
+        kind: ParseErrorKind::InvalidAngle(text.to_string()),
+        position,
+    })?;
+    if !angle.is_finite() {
+        return Err(ParseError {
+            kind: ParseErrorKind::InvalidAngle(text.to_string()),
+            position,
+        });
+    }
+    if angle.abs() > MAX_ANGLE {
+        return Err(ParseError {
+            kind: ParseErrorKind::AngleOutOfRange(angle),
+            position,
+        });
+    }
+    Ok(angle)
+}
+
+fn parse_qubit(text: &str, position: Position) -> Result<u32, ParseError> {
+    let invalid = || ParseError {
+        kind: ParseErrorKind::InvalidQubit(text.to_string()),
+        position,
+    };
+    let digits = text.strip_prefix('q').ok_or_else(invalid)?;
+    if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let index: u64 = digits.parse().map_err(|_| invalid())?;
+    if index > u64::from(MAX_QUBIT) {
+        return Err(ParseError {
+            kind: ParseErrorKind::QubitOutOfRange(index),
+            position,
+        });
+    }
+    Ok(index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> ParseError {
+        Circuit::parse(source).expect_err("expression should be rejected")
+    }
+
+    #[test]
+    fn parses_and_canonicalizes_expressions() {
+        let circuit = Circuit::parse("  H( q0 );RX(0.50,q1)\nCNOT(q0 , q1);").expect("parse");
+        assert_eq!(circuit.operations.len(), 3);
+        assert_eq!(circuit.operations[1].gate, Gate::Rx);
+        assert_eq!(circuit.operations[1].params, vec![0.5]);
+        assert_eq!(
+            circuit.operations[2].position,
+            Position { line: 2, column: 1 }
+        );
+        assert_eq!(circuit.to_string(), "H(q0); RX(0.5, q1); CNOT(q0, q1)");
+        let canonical = circuit.to_string();
+        let reparsed = Circuit::parse(&canonical).expect("reparse");
+        assert_eq!(reparsed.to_string(), canonical);
+    }
+
+    #[test]
+    fn reports_errors_with_positions() {
+        let err = error("H(q0); TOFFOLI(q0, q1, q2)");
+        assert_eq!(err.kind, ParseErrorKind::UnknownGate("TOFFOLI".to_string()));
+        assert_eq!(err.position, Position { line: 1, column: 8 });
+
+        let err = error("H(q0)\nCNOT(q0)");
+        assert!(matches!(
+            err.kind,
+            ParseErrorKind::Arity {
+                expected: 2,
+                found: 1,
+                ..
+            }
+        ));
+        assert_eq!(err.position, Position { line: 2, column: 1 });
+
+        let err = error("RX(0.5, spiral)");
+        assert_eq!(err.kind, ParseErrorKind::InvalidQubit("spiral".to_string()));
+        assert_eq!(err.position, Position { line: 1, column: 9 });
+
+        assert_eq!(error("H(q0").position, Position { line: 1, column: 5 });
+        assert_eq!(error(" ; ").kind, ParseErrorKind::Empty);
+    }
+
+    #[test]
+    fn validates_qubits_and_angles() {
+        assert_eq!(error("X(q20)").kind, ParseErrorKind::QubitOutOfRange(20));
+        assert_eq!(
+            error("SWAP(q1, q1)").kind,
+            ParseErrorKind::DuplicateQubit(1)
+        );
+        assert_eq!(
+            error("RZ(7, q0)").kind,
+            ParseErrorKind::AngleOutOfRange(7.0)
+        );
+        assert_eq!(
+            error("RY(inf, q0)").kind,
+            ParseErrorKind::InvalidAngle("inf".to_string())
+        );
+        assert!(Circuit::parse("RY(-6.28, q19)").is_ok());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::circuit::ParseError;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,13 +17,93 @@ pub enum AgentError {
     #[error("gRPC transport error: {0}")]
     Transport(#[from] tonic::transport::Error),
     #[error("gRPC status: {0}")]
-    Status(#[from] tonic::Status),
+    Status(Box<tonic::Status>),
     #[error("invalid action: {0}")]
     InvalidAction(String),
+    #[error("invalid expression: {0}")]
+    Expression(#[from] ParseError),
+    #[error("unknown model: {0}")]
+    UnknownModel(String),
     #[error("invalid configuration: {0}")]
     InvalidConfig(String),
     #[error("symbolcast error: {0}")]
     SymbolCast(String),
     #[error("quantum bridge error: {0}")]
-    Bridge(#[from] cxx::Exception),
+    Bridge(#[from] BridgeError),
+    #[error("job store error: {0}")]
+    Store(#[from] rusqlite::Error),
+    #[error("job not found: {0}")]
+    JobNotFound(String),
+    #[error("job already exists: {0}")]
+    DuplicateJob(String),
+    #[error("job cancelled: {0}")]
+    Cancelled(String),
+    #[error("job queue is full (capacity {0})")]
+    QueueFull(usize),
+    #[error("evaluation worker failed: {0}")]
+    Worker(String),
+    #[error("evaluation timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("evaluation panicked: {0}")]
+    Panic(String),
+    #[error("rate limit exceeded for {0}")]
+    RateLimited(String),
+    #[error("daily quota exhausted for {0}")]
+    QuotaExceeded(String),
+    #[error("agent is shutting down")]
+    ShuttingDown,
+    #[error("metrics endpoint error: {0}")]
+    Metrics(String),
+}
+
+/// Failures reported by a quantum backend, most of them raised by the Q++
+/// runtime behind the C++ bridge.
+#[derive(Debug, Error)]
+pub enum BridgeError {
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("unsupported gate: {0}")]
+    UnsupportedGate(String),
+    #[error("numerical failure: {0}")]
+    Numerical(String),
+    #[error("runtime returned no result")]
+    NullResult,
+    #[error("{0}")]
+    Runtime(String),
+}
+
+#[cfg(feature = "cpp-qpp")]
+impl From<cxx::Exception> for BridgeError {
+    /// Maps the `<kind>: ` prefix the runtime puts on its exception messages
+    /// back to a typed error.
+    fn from(exception: cxx::Exception) -> Self {
+        let message = exception.what();
+        match message.split_once(": ") {
+            Some(("parse", detail)) => BridgeError::Parse(detail.to_string()),
+            Some(("unsupported_gate", gate)) => BridgeError::UnsupportedGate(gate.to_string()),
+            Some(("numerical", detail)) => BridgeError::Numerical(detail.to_string()),
+            _ => BridgeError::Runtime(message.to_string()),
+        }
+    }
+}
+
+impl AgentError {
+    /// Whether an evaluation was turned away before its job was recorded,
+    /// so there is no outcome of its own and the id may be submitted again.
+    pub fn rejected_before_recording(&self) -> bool {
+        matches!(
+            self,
+            AgentError::QueueFull(_)
+                | AgentError::ShuttingDown
+                | AgentError::Expression(_)
+                | AgentError::UnknownModel(_)
+                | AgentError::DuplicateJob(_)
+        )
+    }
+}
+
+impl From<tonic::Status> for AgentError {
+    fn from(status: tonic::Status) -> Self {
+        AgentError::Status(Box::new(status))
+    }
 }
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use crate::store::JobState;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::{broadcast, RwLock};
@@ -9,41 +10,126 @@ pub struct FrequencySample {
     pub frequency: f64,
     pub amplitude: f64,
     pub timestamp_ms: i64,
+    pub sequence: u64,
+    /// Set on the final sample of a job to the state it finished in.
+    pub completion: Option<JobState>,
 }
 
+impl FrequencySample {
+    pub fn is_completion(&self) -> bool {
+        self.completion.is_some()
+    }
+}
+
+#[derive(Default)]
+struct JobSeries {
+    samples: VecDeque<FrequencySample>,
+    next_sequence: u64,
+    completed: bool,
+}
+
+/// Fans frequency samples out to subscribers and keeps the most recent
+/// samples of every job so late subscribers can replay them.
 #[derive(Clone)]
 pub struct FrequencyHub {
     sender: broadcast::Sender<FrequencySample>,
-    state: Arc<RwLock<HashMap<String, FrequencySample>>>,
+    state: Arc<RwLock<HashMap<String, JobSeries>>>,
+    history: usize,
 }
 
 impl FrequencyHub {
-    pub fn new(capacity: usize) -> Self {
+    pub fn new(capacity: usize, history: usize) -> Self {
         let (sender, _) = broadcast::channel(capacity);
         Self {
             sender,
             state: Arc::new(RwLock::new(HashMap::new())),
+            history: history.max(1),
         }
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<FrequencySample> {
-        self.sender.subscribe()
+    /// Returns the buffered samples for `job_id` together with a receiver for
+    /// everything published afterwards.
+    pub async fn subscribe_job(
+        &self,
+        job_id: &str,
+    ) -> (Vec<FrequencySample>, broadcast::Receiver<FrequencySample>) {
+        let guard = self.state.read().await;
+        let receiver = self.sender.subscribe();
+        let replay = guard
+            .get(job_id)
+            .map(|series| series.samples.iter().cloned().collect())
+            .unwrap_or_default();
+        (replay, receiver)
+    }
+
+    /// Starts a fresh series for a job, discarding samples from an earlier
+    /// run that used the same id.
+    pub async fn begin(&self, job_id: &str) {
+        let mut guard = self.state.write().await;
+        guard.insert(job_id.to_string(), JobSeries::default());
     }
 
-    pub async fn publish(&self, mut sample: FrequencySample) {
-        if sample.timestamp_ms == 0 {
-            sample.timestamp_ms = current_timestamp();
+    pub async fn publish(&self, job_id: &str, frequency: f64, amplitude: f64) {
+        self.push(job_id, frequency, amplitude, None).await;
+    }
+
+    /// Publishes the completion marker for a job. It repeats the last
+    /// reported values so consumers that ignore the marker keep a sane level.
+    pub async fn complete(&self, job_id: &str, state: JobState) {
+        let (frequency, amplitude) = self
+            .latest(job_id)
+            .await
+            .map(|sample| (sample.frequency, sample.amplitude))
+            .unwrap_or_default();
+        self.push(job_id, frequency, amplitude, Some(state)).await;
+    }
+
+    async fn push(
+        &self,
+        job_id: &str,
+        frequency: f64,
+        amplitude: f64,
+        completion: Option<JobState>,
+    ) {
+        let mut guard = self.state.write().await;
+        let series = guard.entry(job_id.to_string()).or_default();
+        if series.completed {
+            return;
         }
-        {
-            let mut guard = self.state.write().await;
-            guard.insert(sample.job_id.clone(), sample.clone());
+        let sample = FrequencySample {
+            job_id: job_id.to_string(),
+            frequency,
+            amplitude,
+            timestamp_ms: current_timestamp(),
+            sequence: series.next_sequence,
+            completion,
+        };
+        series.next_sequence += 1;
+        series.completed = completion.is_some();
+        if series.samples.len() >= self.history {
+            series.samples.pop_front();
         }
+        series.samples.push_back(sample.clone());
+        // Sent while holding the lock so `subscribe_job` never sees a sample
+        // both in its replay and on the channel, nor misses one in between.
         let _ = self.sender.send(sample);
     }
 
     pub async fn latest(&self, job_id: &str) -> Option<FrequencySample> {
         let guard = self.state.read().await;
-        guard.get(job_id).cloned()
+        guard
+            .get(job_id)
+            .and_then(|series| series.samples.back().cloned())
+    }
+
+    pub async fn forget(&self, job_ids: &[String]) {
+        if job_ids.is_empty() {
+            return;
+        }
+        let mut guard = self.state.write().await;
+        for job_id in job_ids {
+            guard.remove(job_id);
+        }
     }
 }
 
@@ -0,0 +1,467 @@
+use crate::circuit::{Circuit, MAX_ANGLE};
+use crate::error::AgentError;
+use crate::jobs::EvaluateJob;
+use crate::proto::symbolcast::Gesture;
+use crate::queue::JobPriority;
+use eco_core::{EcoManifest, GestureBinding};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Gesture mapping file. World overrides may point at a world manifest,
+/// whose `[symbolcast.gestures]` table is applied first, and may add inline
+/// gestures on top of it.
+///
+/// ```toml
+/// [gestures.triangle]
+/// circuit = "H(q0)"
+///
+/// [worlds.aurora]
+/// manifest = "../../examples/worlds/aurora/ECO.toml"
+///
+/// [worlds.aurora.gestures.circle]
+/// circuit = "RX({angle}, q0); CNOT(q0, q1)"
+/// params = { angle = { scale = 3.14159 } }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct GestureConfig {
+    #[serde(default)]
+    gestures: BTreeMap<String, GestureBinding>,
+    #[serde(default)]
+    worlds: BTreeMap<String, WorldConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WorldConfig {
+    #[serde(default)]
+    manifest: Option<PathBuf>,
+    #[serde(default)]
+    gestures: BTreeMap<String, GestureBinding>,
+}
+
+/// Resolved gesture bindings: defaults plus per-world overrides.
+#[derive(Clone, Debug, Default)]
+pub struct GestureMap {
+    defaults: HashMap<String, GestureBinding>,
+    worlds: HashMap<String, HashMap<String, GestureBinding>>,
+}
+
+impl GestureMap {
+    /// The mapping used when no configuration file is given.
+    pub fn builtin() -> Self {
+        let defaults = [
+            ("triangle", "H(q0)"),
+            ("circle", "X(q0)"),
+            ("square", "Z(q0)"),
+        ]
+        .into_iter()
+        .map(|(gesture, circuit)| {
+            let binding = GestureBinding {
+                circuit: circuit.to_string(),
+                model: None,
+                params: BTreeMap::new(),
+            };
+            (gesture.to_string(), binding)
+        })
+        .collect();
+        Self {
+            defaults,
+            worlds: HashMap::new(),
+        }
+    }
+
+    /// Loads a mapping file and the world manifests it references, and
+    /// returns the mapping with every file it was read from.
+    pub fn load(path: &Path) -> Result<(Self, Vec<PathBuf>), AgentError> {
+        let raw = std::fs::read_to_string(path).map_err(|err| {
+            AgentError::InvalidConfig(format!("unable to read {}: {err}", path.display()))
+        })?;
+        let config: GestureConfig = toml::from_str(&raw).map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid gesture map {}: {err}", path.display()))
+        })?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut sources = vec![path.to_path_buf()];
+        let mut worlds = HashMap::new();
+        for (world, world_config) in config.worlds {
+            let mut gestures = HashMap::new();
+            if let Some(manifest) = world_config.manifest {
+                let manifest = base.join(manifest);
+                let loaded = EcoManifest::load_from_path(&manifest).map_err(|err| {
+                    AgentError::InvalidConfig(format!(
+                        "invalid manifest {} for world {world}: {err}",
+                        manifest.display()
+                    ))
+                })?;
+                gestures.extend(loaded.symbolcast.gestures);
+                sources.push(manifest);
+            }
+            gestures.extend(world_config.gestures);
+            worlds.insert(world, gestures);
+        }
+        let map = Self {
+            defaults: config.gestures.into_iter().collect(),
+            worlds,
+        };
+        map.validate()?;
+        Ok((map, sources))
+    }
+
+    /// Renders every template once so broken bindings are reported when the
+    /// file is loaded rather than when the gesture is drawn.
+    fn validate(&self) -> Result<(), AgentError> {
+        let scopes = std::iter::once(("default", &self.defaults)).chain(
+            self.worlds
+                .iter()
+                .map(|(world, gestures)| (world.as_str(), gestures)),
+        );
+        for (scope, gestures) in scopes {
+            for (gesture, binding) in gestures {
+                render(binding, 1.0).map_err(|err| {
+                    AgentError::InvalidConfig(format!("gesture `{gesture}` ({scope}): {err}"))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn binding(&self, world: Option<&str>, gesture: &str) -> Option<&GestureBinding> {
+        world
+            .and_then(|world| self.worlds.get(world))
+            .and_then(|gestures| gestures.get(gesture))
+            .or_else(|| self.defaults.get(gesture))
+    }
+
+    /// Builds the evaluation job for a recognized gesture, or `None` when
+    /// the gesture has no binding in this world.
+    pub fn job_for(
+        &self,
+        world: Option<&str>,
+        gesture: &Gesture,
+    ) -> Result<Option<EvaluateJob>, AgentError> {
+        let Some(binding) = self.binding(world, &gesture.id) else {
+            return Ok(None);
+        };
+        let circuit = render(binding, f64::from(gesture.confidence))?;
+        Ok(Some(EvaluateJob {
+            id: format!("gesture-{}", Uuid::new_v4()),
+            expression: circuit.to_string(),
+            requested_by: "symbolcastd".to_string(),
+            model: binding.model.clone(),
+            priority: JobPriority::High,
+        }))
+    }
+}
+
+/// Substitutes `{confidence}` and the binding's parameters into its circuit
+/// template and parses the result.
+fn render(binding: &GestureBinding, confidence: f64) -> Result<Circuit, AgentError> {
+    let confidence = confidence.clamp(0.0, 1.0);
+    let mut values = vec![("confidence".to_string(), confidence)];
+    for (name, param) in &binding.params {
+        let value = (param.offset + param.scale * confidence).clamp(-MAX_ANGLE, MAX_ANGLE);
+        values.push((name.clone(), value));
+    }
+
+    let mut source = String::with_capacity(binding.circuit.len());
+    let mut rest = binding.circuit.as_str();
+    while let Some(start) = rest.find('{') {
+        source.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| {
+            AgentError::InvalidConfig(format!("unclosed `{{` in `{}`", binding.circuit))
+        })?;
+        let name = rest[start + 1..start + end].trim();
+        let value = values
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| AgentError::InvalidConfig(format!("unknown parameter `{name}`")))?;
+        source.push_str(&value.to_string());
+        rest = &rest[start + end + 1..];
+    }
+    source.push_str(rest);
+    Ok(Circuit::parse(&source)?)
+}
+
+/// Files the current map was loaded from, with their modification times.
+type SourceStamps = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// Shares the current gesture map and reloads it when its files change.
+#[derive(Clone)]
+pub struct GestureMapper {
+    map: Arc<RwLock<GestureMap>>,
+    path: Option<PathBuf>,
+    sources: Arc<RwLock<SourceStamps>>,
+}
+
+impl GestureMapper {
+    pub fn new(map: GestureMap) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(map)),
+            path: None,
+            sources: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, AgentError> {
+        let path = path.into();
+        let (map, sources) = GestureMap::load(&path)?;
+        let mapper = Self {
+            map: Arc::new(RwLock::new(map)),
+            path: Some(path),
+            sources: Arc::new(RwLock::new(Vec::new())),
+        };
+        mapper.remember(sources);
+        Ok(mapper)
+    }
+
+    pub fn job_for(
+        &self,
+        world: Option<&str>,
+        gesture: &Gesture,
+    ) -> Result<Option<EvaluateJob>, AgentError> {
+        self.map
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .job_for(world, gesture)
+    }
+
+    fn remember(&self, sources: Vec<PathBuf>) {
+        let stamped = sources
+            .into_iter()
+            .map(|source| {
+                let modified = modified_at(&source);
+                (source, modified)
+            })
+            .collect();
+        *self
+            .sources
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = stamped;
+    }
+
+    fn changed(&self) -> bool {
+        self.sources
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .any(|(source, modified)| modified_at(source) != *modified)
+    }
+
+    /// Reloads the mapping when the file or one of its manifests changed.
+    /// A file that fails to load leaves the previous mapping in place.
+    pub fn reload_if_changed(&self) -> Result<bool, AgentError> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        if !self.changed() {
+            return Ok(false);
+        }
+        let loaded = GestureMap::load(path);
+        // Remember the new timestamps either way so a broken file is only
+        // reported once per change.
+        let sources = match &loaded {
+            Ok((_, sources)) => sources.clone(),
+            Err(_) => self
+                .sources
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .iter()
+                .map(|(source, _)| source.clone())
+                .collect(),
+        };
+        self.remember(sources);
+        let (map, _) = loaded?;
+        *self
+            .map
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = map;
+        Ok(true)
+    }
+
+    /// Polls the mapping files and reloads them when they change.
+    pub async fn watch(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.reload_if_changed() {
+                Ok(true) => info!("gesture map reloaded"),
+                Ok(false) => {}
+                Err(err) => warn!(%err, "gesture map reload failed, keeping previous mapping"),
+            }
+        }
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gesture(id: &str, confidence: f32) -> Gesture {
+        Gesture {
+            id: id.to_string(),
+            label: id.to_string(),
+            confidence,
+        }
+    }
+
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("eco-agent-gestures-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).expect("create fixture dir");
+            Self { dir }
+        }
+
+        fn write(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.dir.join(name);
+            std::fs::write(&path, contents).expect("write fixture");
+            path
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    const MAP: &str = r#"
+        [gestures.triangle]
+        circuit = "H(q0); CNOT(q0, q1)"
+
+        [gestures.spiral]
+        circuit = "RX({angle}, q0); RZ({confidence}, q0)"
+        model = "statevector"
+        params = { angle = { scale = 2.0, offset = 0.5 } }
+
+        [worlds.aurora]
+        manifest = "ECO.toml"
+
+        [worlds.aurora.gestures.circle]
+        circuit = "X(q1)"
+    "#;
+
+    const MANIFEST: &str = r#"
+        name = "Aurora"
+        version = "0.1.0"
+        entry_scene = "aurora.glb"
+
+        [symbolcast.gestures.triangle]
+        circuit = "Y(q2)"
+    "#;
+
+    #[test]
+    fn builtin_mapping_ignores_unknown_gestures() {
+        let map = GestureMap::builtin();
+        let job = map
+            .job_for(None, &gesture("circle", 0.9))
+            .expect("render")
+            .expect("mapped");
+        assert_eq!(job.expression, "X(q0)");
+        assert!(map
+            .job_for(None, &gesture("spiral", 0.9))
+            .expect("render")
+            .is_none());
+    }
+
+    #[test]
+    fn templates_take_parameters_from_confidence() {
+        let fixture = Fixture::new();
+        fixture.write("ECO.toml", MANIFEST);
+        let (map, sources) = GestureMap::load(&fixture.write("gestures.toml", MAP)).expect("load");
+        assert_eq!(sources.len(), 2);
+
+        let job = map
+            .job_for(None, &gesture("spiral", 0.25))
+            .expect("render")
+            .expect("mapped");
+        assert_eq!(job.expression, "RX(1, q0); RZ(0.25, q0)");
+        assert_eq!(job.model.as_deref(), Some("statevector"));
+    }
+
+    #[test]
+    fn worlds_override_defaults_and_manifests() {
+        let fixture = Fixture::new();
+        fixture.write("ECO.toml", MANIFEST);
+        let (map, _) = GestureMap::load(&fixture.write("gestures.toml", MAP)).expect("load");
+        let expression = |world: Option<&str>, id: &str| {
+            map.job_for(world, &gesture(id, 1.0))
+                .expect("render")
+                .map(|job| job.expression)
+        };
+
+        assert_eq!(
+            expression(None, "triangle").as_deref(),
+            Some("H(q0); CNOT(q0, q1)")
+        );
+        assert_eq!(
+            expression(Some("aurora"), "triangle").as_deref(),
+            Some("Y(q2)")
+        );
+        assert_eq!(
+            expression(Some("aurora"), "circle").as_deref(),
+            Some("X(q1)")
+        );
+        assert_eq!(expression(Some("lobby"), "circle"), None);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected_on_load() {
+        let fixture = Fixture::new();
+        let path = fixture.write(
+            "gestures.toml",
+            "[gestures.triangle]\ncircuit = \"RX({spin}, q0)\"\n",
+        );
+        let err = GestureMap::load(&path).expect_err("unknown parameter");
+        assert!(err.to_string().contains("spin"), "{err}");
+    }
+
+    #[test]
+    fn mapper_reloads_changed_files_and_keeps_the_last_good_map() {
+        let fixture = Fixture::new();
+        let path = fixture.write(
+            "gestures.toml",
+            "[gestures.triangle]\ncircuit = \"H(q0)\"\n",
+        );
+        let mapper = GestureMapper::from_file(&path).expect("load");
+        assert!(!mapper.reload_if_changed().expect("unchanged"));
+
+        let touch = |contents: &str| {
+            std::fs::write(&path, contents).expect("rewrite");
+            // make the change visible on filesystems with coarse timestamps
+            let later = SystemTime::now() + Duration::from_secs(2);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(later))
+                .expect("bump mtime");
+        };
+        let expression = || {
+            mapper
+                .job_for(None, &gesture("triangle", 1.0))
+                .expect("render")
+                .map(|job| job.expression)
+        };
+
+        touch("[gestures.triangle]\ncircuit = \"Z(q3)\"\n");
+        assert!(mapper.reload_if_changed().expect("reload"));
+        assert_eq!(expression().as_deref(), Some("Z(q3)"));
+
+        touch("[gestures.triangle]\ncircuit = \"NOPE(q0)\"\n");
+        assert!(mapper.reload_if_changed().is_err());
+        assert_eq!(expression().as_deref(), Some("Z(q3)"));
+    }
+}
@@ -1,13 +1,24 @@
+use crate::auth::{
+    Authenticator, Claims, CANCEL_JOB_ACTION, EVALUATE_ACTION, GESTURE_ACTION,
+    READ_ALL_JOBS_ACTION, READ_JOBS_ACTION,
+};
 use crate::error::AgentError;
 use crate::frequency::{FrequencyHub, FrequencySample};
-use crate::jobs::{job_from_gesture, EvaluateJob, JobExecutor};
+use crate::gestures::GestureMapper;
+use crate::jobs::{EvaluateJob, JobExecutor};
+use crate::limits::RateLimiter;
+use crate::metrics::metrics;
 use crate::pipeline::{ActionCommand, ActionEvent};
 use crate::proto::actions::{
-    eco_actions_server::EcoActions, Action, ActionAck, EvaluateRequest, EvaluateResponse,
-    FrequencyStreamRequest, FrequencyUpdate, GestureEvaluation,
+    eco_actions_server::EcoActions, Action, ActionAck, CancelJobRequest, EvaluateRequest,
+    EvaluateResponse, FrequencyStreamRequest, FrequencyUpdate, GestureEvaluation, GetJobRequest,
+    Job, JobPriority as ProtoJobPriority, JobState as ProtoJobState, ListJobsRequest,
+    ListJobsResponse,
 };
 use crate::proto::symbolcast::PointerEvent;
 use crate::publisher::{ActionOutcome, ActionResultPublisher};
+use crate::queue::JobPriority;
+use crate::store::{JobQuery, JobRecord, JobState};
 use crate::symbolcast::SymbolCastInvoker;
 use async_stream::try_stream;
 use futures::Stream;
@@ -17,6 +28,12 @@ use tokio::sync::broadcast;
 use tonic::{Request, Response, Status};
 use tracing::{debug, info};
 
+const DEFAULT_LIST_LIMIT: usize = 50;
+const MAX_LIST_LIMIT: usize = 500;
+/// Metadata key naming the world a gesture was drawn in, used to pick the
+/// world's gesture overrides.
+pub const WORLD_METADATA_KEY: &str = "x-eco-world";
+
 pub struct ActionGrpcService<P, S>
 where
     P: ActionResultPublisher,
@@ -26,6 +43,9 @@ where
     publisher: Arc<P>,
     frequency: FrequencyHub,
     symbolcast: Arc<S>,
+    gestures: GestureMapper,
+    auth: Authenticator,
+    limits: RateLimiter,
 }
 
 impl<P, S> ActionGrpcService<P, S>
@@ -38,26 +58,71 @@ where
         publisher: Arc<P>,
         frequency: FrequencyHub,
         symbolcast: Arc<S>,
+        gestures: GestureMapper,
+        auth: Authenticator,
+        limits: RateLimiter,
     ) -> Self {
         Self {
             executor,
             publisher,
             frequency,
             symbolcast,
+            gestures,
+            auth,
+            limits,
         }
     }
 
-    async fn run_evaluation(&self, job: EvaluateJob) -> Result<ActionOutcome, AgentError> {
+    async fn run_evaluation(
+        &self,
+        kind: &str,
+        job: EvaluateJob,
+    ) -> Result<ActionOutcome, AgentError> {
         match self.executor.evaluate(job.clone()).await {
             Ok(result) => Ok(ActionOutcome::success(job, result)),
-            Err(err) => Ok(ActionOutcome::failure(
-                job.id,
-                job.requested_by,
-                format!("evaluation failed: {err}"),
-            )),
+            Err(err) => {
+                metrics().record_failure(kind, &err);
+                match err {
+                    // Rejected before the job was recorded, so there is no
+                    // outcome to publish.
+                    err if err.rejected_before_recording() => Err(err),
+                    err => Ok(ActionOutcome::failure(
+                        job.id,
+                        job.requested_by,
+                        format!("evaluation failed: {err}"),
+                    )),
+                }
+            }
         }
     }
 
+    /// Looks up a job the caller may read: one of their own, or anyone's
+    /// when they may perform `READ_ALL_JOBS_ACTION`.
+    #[allow(clippy::result_large_err)]
+    fn readable_job(&self, claims: &Claims, job_id: &str) -> Result<JobRecord, Status> {
+        let record = self
+            .executor
+            .store()
+            .get(job_id)
+            .map_err(|err| Status::internal(err.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("job {job_id} not found")))?;
+        if record.requested_by != claims.sub && !self.auth.permits(claims, READ_ALL_JOBS_ACTION) {
+            return Err(Status::permission_denied(format!(
+                "job {job_id} belongs to another requester"
+            )));
+        }
+        Ok(record)
+    }
+
+    /// Counts the action against the caller's limits.
+    #[allow(clippy::result_large_err)]
+    fn check_limits(&self, requester: &str, kind: &str) -> Result<(), Status> {
+        self.limits.check(requester, kind).map_err(|err| {
+            metrics().record_failure(kind, &err);
+            to_status(err)
+        })
+    }
+
     async fn publish_outcome(&self, outcome: ActionOutcome) -> Result<ActionOutcome, AgentError> {
         self.publisher.publish(&outcome).await?;
         Ok(outcome)
@@ -71,6 +136,9 @@ where
     S: SymbolCastInvoker,
 {
     async fn cast(&self, request: Request<Action>) -> Result<Response<ActionAck>, Status> {
+        let kind = &request.get_ref().kind;
+        let claims = self.auth.authorize(request.metadata(), kind)?;
+        self.check_limits(&claims.sub, kind)?;
         let action = request.into_inner();
         let payload = if action.payload.is_empty() {
             serde_json::Value::Null
@@ -82,7 +150,7 @@ where
             id: action.id,
             kind: action.kind,
             payload,
-            requested_by: Some(action.requested_by),
+            requested_by: Some(claims.sub),
         };
         match event
             .into_command()
@@ -100,6 +168,8 @@ where
         &self,
         request: Request<EvaluateRequest>,
     ) -> Result<Response<EvaluateResponse>, Status> {
+        let claims = self.auth.authorize(request.metadata(), EVALUATE_ACTION)?;
+        self.check_limits(&claims.sub, EVALUATE_ACTION)?;
         let req = request.into_inner();
         let id = if req.job_id.is_empty() {
             uuid::Uuid::new_v4().to_string()
@@ -109,21 +179,23 @@ where
         let job = EvaluateJob {
             id,
             expression: req.expression,
-            requested_by: if req.requested_by.is_empty() {
-                "grpc".to_string()
-            } else {
-                req.requested_by
-            },
+            requested_by: claims.sub,
             model: if req.model.is_empty() {
                 None
             } else {
                 Some(req.model)
             },
+            priority: match ProtoJobPriority::from_i32(req.priority) {
+                Some(ProtoJobPriority::Low) => JobPriority::Low,
+                Some(ProtoJobPriority::High) => JobPriority::High,
+                Some(ProtoJobPriority::Normal) => JobPriority::Normal,
+                None => return Err(Status::invalid_argument("unknown job priority")),
+            },
         };
         let outcome = self
-            .run_evaluation(job)
+            .run_evaluation(EVALUATE_ACTION, job)
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(to_status)?;
         let outcome = self
             .publish_outcome(outcome)
             .await
@@ -141,18 +213,27 @@ where
         &self,
         request: Request<FrequencyStreamRequest>,
     ) -> Result<Response<Self::StreamFrequenciesStream>, Status> {
+        let claims = self.auth.authorize(request.metadata(), READ_JOBS_ACTION)?;
         let job_id = request.into_inner().job_id;
-        let mut receiver = self.frequency.subscribe();
-        let frequency = self.frequency.clone();
+        self.readable_job(&claims, &job_id)?;
+        let (replay, mut receiver) = self.frequency.subscribe_job(&job_id).await;
 
-        let initial = frequency.latest(&job_id).await;
         let stream = try_stream! {
-            if let Some(sample) = initial {
+            let mut finished = false;
+            let mut last_sequence = None;
+            for sample in replay {
+                finished = sample.is_completion();
+                last_sequence = Some(sample.sequence);
                 yield to_update(sample);
             }
-            loop {
+            while !finished {
                 match receiver.recv().await {
                     Ok(sample) if sample.job_id == job_id => {
+                        if last_sequence.is_some_and(|last| sample.sequence <= last) {
+                            continue;
+                        }
+                        finished = sample.is_completion();
+                        last_sequence = Some(sample.sequence);
                         yield to_update(sample);
                     }
                     Ok(_) => continue,
@@ -171,6 +252,13 @@ where
         &self,
         request: Request<tonic::Streaming<PointerEvent>>,
     ) -> Result<Response<GestureEvaluation>, Status> {
+        let claims = self.auth.authorize(request.metadata(), GESTURE_ACTION)?;
+        self.check_limits(&claims.sub, GESTURE_ACTION)?;
+        let world = request
+            .metadata()
+            .get(WORLD_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
         let mut stream = request.into_inner();
         let mut events = Vec::new();
         while let Some(event) = stream.message().await? {
@@ -182,13 +270,17 @@ where
             .recognize(events)
             .await
             .map_err(|err| Status::internal(err.to_string()))?;
-        let job = job_from_gesture(&gesture)
+        let mut job = self
+            .gestures
+            .job_for(world.as_deref(), &gesture)
+            .map_err(|err| Status::internal(err.to_string()))?
             .ok_or_else(|| Status::failed_precondition("gesture not mapped"))?;
-        info!(gesture_id = %gesture.id, "gesture recognized, running job");
+        job.requested_by = claims.sub;
+        info!(gesture_id = %gesture.id, world = ?world, "gesture recognized, running job");
         let outcome = self
-            .run_evaluation(job)
+            .run_evaluation(GESTURE_ACTION, job)
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(to_status)?;
         let outcome = self
             .publish_outcome(outcome)
             .await
@@ -205,6 +297,89 @@ where
         };
         Ok(Response::new(response))
     }
+
+    async fn get_job(&self, request: Request<GetJobRequest>) -> Result<Response<Job>, Status> {
+        let claims = self.auth.authorize(request.metadata(), READ_JOBS_ACTION)?;
+        let job_id = request.into_inner().job_id;
+        let record = self.readable_job(&claims, &job_id)?;
+        Ok(Response::new(to_job(record)))
+    }
+
+    async fn list_jobs(
+        &self,
+        request: Request<ListJobsRequest>,
+    ) -> Result<Response<ListJobsResponse>, Status> {
+        let claims = self.auth.authorize(request.metadata(), READ_JOBS_ACTION)?;
+        let req = request.into_inner();
+        // Without access to every job, callers only see their own.
+        let requested_by = if !self.auth.permits(&claims, READ_ALL_JOBS_ACTION) {
+            Some(claims.sub)
+        } else if req.requested_by.is_empty() {
+            None
+        } else {
+            Some(req.requested_by)
+        };
+        let state = match ProtoJobState::from_i32(req.state) {
+            Some(ProtoJobState::Unspecified) => None,
+            Some(state) => Some(from_proto_state(state)),
+            None => return Err(Status::invalid_argument("unknown job state")),
+        };
+        let limit = if req.limit == 0 {
+            DEFAULT_LIST_LIMIT
+        } else {
+            (req.limit as usize).min(MAX_LIST_LIMIT)
+        };
+        let query = JobQuery {
+            requested_by,
+            state,
+            limit,
+        };
+        let jobs = self
+            .executor
+            .store()
+            .list(&query)
+            .map_err(|err| Status::internal(err.to_string()))?
+            .into_iter()
+            .map(to_job)
+            .collect();
+        Ok(Response::new(ListJobsResponse { jobs }))
+    }
+
+    async fn cancel_job(
+        &self,
+        request: Request<CancelJobRequest>,
+    ) -> Result<Response<Job>, Status> {
+        let claims = self.auth.authorize(request.metadata(), CANCEL_JOB_ACTION)?;
+        let job_id = request.into_inner().job_id;
+        let owner = self
+            .executor
+            .store()
+            .get(&job_id)
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map(|record| record.requested_by)
+            .ok_or_else(|| Status::not_found(format!("job {job_id} not found")))?;
+        if owner != claims.sub {
+            return Err(Status::permission_denied(format!(
+                "job {job_id} belongs to another requester"
+            )));
+        }
+        let record = self
+            .executor
+            .cancel(&job_id)
+            .await
+            .map_err(|err| match err {
+                AgentError::JobNotFound(_) => Status::not_found(format!("job {job_id} not found")),
+                other => Status::internal(other.to_string()),
+            })?;
+        if record.state != JobState::Cancelled {
+            return Err(Status::failed_precondition(format!(
+                "job {job_id} is {} and can no longer be cancelled",
+                record.state.as_str()
+            )));
+        }
+        info!(%job_id, "job cancelled");
+        Ok(Response::new(to_job(record)))
+    }
 }
 
 impl<P, S> ActionGrpcService<P, S>
@@ -216,12 +391,10 @@ where
         &self,
         command: ActionCommand,
     ) -> Result<ActionOutcome, Status> {
+        let kind = command.kind();
         match command {
             ActionCommand::Evaluate(job) => {
-                let evaluated = self
-                    .run_evaluation(job)
-                    .await
-                    .map_err(|err| Status::internal(err.to_string()))?;
+                let evaluated = self.run_evaluation(kind, job).await.map_err(to_status)?;
                 let published = self
                     .publish_outcome(evaluated)
                     .await
@@ -232,11 +405,72 @@ where
     }
 }
 
+fn to_status(err: AgentError) -> Status {
+    match err {
+        AgentError::QueueFull(_) | AgentError::RateLimited(_) | AgentError::QuotaExceeded(_) => {
+            Status::resource_exhausted(err.to_string())
+        }
+        AgentError::ShuttingDown => Status::unavailable(err.to_string()),
+        AgentError::DuplicateJob(_) => Status::already_exists(err.to_string()),
+        AgentError::Expression(_) | AgentError::UnknownModel(_) => {
+            Status::invalid_argument(err.to_string())
+        }
+        other => Status::internal(other.to_string()),
+    }
+}
+
 fn to_update(sample: FrequencySample) -> FrequencyUpdate {
     FrequencyUpdate {
         job_id: sample.job_id,
         frequency: sample.frequency,
         amplitude: sample.amplitude,
         timestamp_ms: sample.timestamp_ms,
+        sequence: sample.sequence,
+        complete: sample.completion.is_some(),
+        state: sample
+            .completion
+            .map(|state| to_proto_state(state) as i32)
+            .unwrap_or_default(),
+    }
+}
+
+fn to_job(record: JobRecord) -> Job {
+    let result = record.result.map(|result| EvaluateResponse {
+        job_id: record.id.clone(),
+        energy: result.energy,
+        fidelity: result.fidelity,
+        model: result.model,
+    });
+    Job {
+        job_id: record.id,
+        expression: record.expression,
+        requested_by: record.requested_by,
+        model: record.model.unwrap_or_default(),
+        state: to_proto_state(record.state) as i32,
+        created_at_ms: record.created_at_ms,
+        started_at_ms: record.started_at_ms.unwrap_or_default(),
+        finished_at_ms: record.finished_at_ms.unwrap_or_default(),
+        result,
+        error: record.error.unwrap_or_default(),
+    }
+}
+
+fn to_proto_state(state: JobState) -> ProtoJobState {
+    match state {
+        JobState::Queued => ProtoJobState::Queued,
+        JobState::Running => ProtoJobState::Running,
+        JobState::Succeeded => ProtoJobState::Succeeded,
+        JobState::Failed => ProtoJobState::Failed,
+        JobState::Cancelled => ProtoJobState::Cancelled,
+    }
+}
+
+fn from_proto_state(state: ProtoJobState) -> JobState {
+    match state {
+        ProtoJobState::Queued | ProtoJobState::Unspecified => JobState::Queued,
+        ProtoJobState::Running => JobState::Running,
+        ProtoJobState::Succeeded => JobState::Succeeded,
+        ProtoJobState::Failed => JobState::Failed,
+        ProtoJobState::Cancelled => JobState::Cancelled,
     }
 }
@@ -1,15 +1,74 @@
+use crate::backend::{Backends, QuantumBackend};
+use crate::circuit::Circuit;
 use crate::error::AgentError;
-use crate::frequency::{FrequencyHub, FrequencySample};
-use crate::proto::symbolcast::Gesture;
-use crate::qpp_bridge;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use uuid::Uuid;
+use crate::frequency::FrequencyHub;
+use crate::metrics::metrics;
+use crate::queue::{FairQueue, JobPriority};
+use crate::store::{JobRecord, JobState, JobStore, RetentionPolicy};
+use futures::FutureExt;
+use std::any::Any;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
 
+const DEFAULT_QUEUE_CAPACITY: usize = 256;
+const DEFAULT_EVALUATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct ExecutorConfig {
+    pub workers: usize,
+    pub queue_capacity: usize,
+    /// Upper bound on the time a single evaluation may take.
+    pub evaluation_timeout: Duration,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(2),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            evaluation_timeout: DEFAULT_EVALUATION_TIMEOUT,
+        }
+    }
+}
+
+/// Runs evaluations on a fixed pool of workers fed by a bounded, fair
+/// priority queue.
 #[derive(Clone)]
 pub struct JobExecutor {
-    engine: Arc<Mutex<()>>,
     frequency: FrequencyHub,
+    store: JobStore,
+    queue: Arc<Mutex<FairQueue<QueuedJob>>>,
+    ready: Arc<Notify>,
+    backends: Arc<Backends>,
+    running: Arc<Mutex<HashMap<String, Arc<Notify>>>>,
+    /// Signalled whenever a worker finishes a job, for `shutdown`.
+    finished: Arc<Notify>,
+    closed: Arc<AtomicBool>,
+    evaluation_timeout: Duration,
+}
+
+struct QueuedJob {
+    job: EvaluateJob,
+    plan: Plan,
+    reply: oneshot::Sender<Result<EvaluationResult, AgentError>>,
+    /// Span of the request that queued the job; the worker's span hangs
+    /// off it so the trace follows the job across the queue.
+    span: Span,
+    queued_at: Instant,
+}
+
+/// A validated circuit together with the backend that will run it.
+struct Plan {
+    circuit: Circuit,
+    backend: Arc<dyn QuantumBackend>,
 }
 
 #[derive(Clone, Debug)]
@@ -18,6 +77,7 @@ pub struct EvaluateJob {
     pub expression: String,
     pub requested_by: String,
     pub model: Option<String>,
+    pub priority: JobPriority,
 }
 
 #[derive(Clone, Debug)]
@@ -28,32 +88,333 @@ pub struct EvaluationResult {
 }
 
 impl JobExecutor {
-    pub fn new(frequency: FrequencyHub) -> Self {
-        Self {
-            engine: Arc::new(Mutex::new(())),
+    /// Creates the executor and spawns its workers on the current runtime.
+    pub fn new(
+        frequency: FrequencyHub,
+        store: JobStore,
+        backends: Backends,
+        config: ExecutorConfig,
+    ) -> Self {
+        let executor = Self {
             frequency,
+            store,
+            queue: Arc::new(Mutex::new(FairQueue::new(config.queue_capacity))),
+            ready: Arc::new(Notify::new()),
+            backends: Arc::new(backends),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            finished: Arc::new(Notify::new()),
+            closed: Arc::new(AtomicBool::new(false)),
+            evaluation_timeout: config.evaluation_timeout,
+        };
+        for worker in 0..config.workers {
+            tokio::spawn(executor.clone().run_worker(worker));
         }
+        executor
     }
 
-    pub async fn evaluate(&self, job: EvaluateJob) -> Result<EvaluationResult, AgentError> {
-        let _guard = self.engine.lock().await;
-        let raw = qpp_bridge::evaluate_expression(&job.expression)?;
-        let model = job.model.clone().unwrap_or_else(|| "cpp-qpp".to_string());
-        let result = EvaluationResult {
-            energy: raw.energy,
-            fidelity: raw.fidelity,
-            model: model.clone(),
+    pub fn store(&self) -> &JobStore {
+        &self.store
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.lock_queue().len()
+    }
+
+    pub fn running_jobs(&self) -> usize {
+        self.lock_running().len()
+    }
+
+    /// Validates and queues a job, then waits for a worker to finish it.
+    /// Invalid expressions and unknown models are rejected before anything
+    /// is recorded, and the job fails fast with `AgentError::QueueFull`,
+    /// leaving no record behind, when the queue is at capacity.
+    #[tracing::instrument(name = "job.evaluate", skip_all, fields(job_id = %job.id))]
+    pub async fn evaluate(&self, mut job: EvaluateJob) -> Result<EvaluationResult, AgentError> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(AgentError::ShuttingDown);
+        }
+        let circuit = Circuit::parse(&job.expression)?;
+        let backend = self.backends.resolve(job.model.as_deref())?;
+        job.expression = circuit.to_string();
+        self.store.enqueue(&job)?;
+        self.frequency.begin(&job.id).await;
+        let (reply, receiver) = oneshot::channel();
+        let pushed = {
+            let mut queue = self.lock_queue();
+            let queued = QueuedJob {
+                job: job.clone(),
+                plan: Plan { circuit, backend },
+                reply,
+                span: Span::current(),
+                queued_at: Instant::now(),
+            };
+            match queue.push(&job.requested_by, job.priority, queued) {
+                Ok(()) => Ok(queue.len()),
+                Err(_) => Err(queue.capacity()),
+            }
+        };
+        let queue_depth = match pushed {
+            Ok(depth) => depth,
+            Err(capacity) => {
+                self.store.discard(&job.id)?;
+                self.frequency.complete(&job.id, JobState::Failed).await;
+                self.frequency.forget(std::slice::from_ref(&job.id)).await;
+                return Err(AgentError::QueueFull(capacity));
+            }
         };
+        debug!(job_id = %job.id, queue_depth, "job queued");
+        self.ready.notify_one();
 
-        let sample = FrequencySample {
-            job_id: job.id.clone(),
-            frequency: derive_frequency(raw.energy, raw.fidelity),
-            amplitude: raw.fidelity,
-            timestamp_ms: 0,
+        receiver
+            .await
+            .unwrap_or_else(|_| Err(AgentError::Cancelled(job.id)))
+    }
+
+    /// Cancels a queued or running job. Queued jobs release their slot in
+    /// the queue; running jobs are aborted at the next await point.
+    pub async fn cancel(&self, job_id: &str) -> Result<JobRecord, AgentError> {
+        let record = self.store.cancel(job_id)?;
+        if record.state == JobState::Cancelled {
+            let removed = self
+                .lock_queue()
+                .remove_where(|queued| queued.job.id == job_id);
+            for queued in removed {
+                let _ = queued
+                    .reply
+                    .send(Err(AgentError::Cancelled(job_id.to_string())));
+            }
+            if let Some(abort) = self.lock_running().get(job_id) {
+                abort.notify_one();
+            }
+            self.frequency.complete(job_id, JobState::Cancelled).await;
+        }
+        Ok(record)
+    }
+
+    /// Lets queued and running jobs finish until `deadline` passes, then
+    /// stops accepting jobs and cancels whatever is left. Returns how many
+    /// jobs were cancelled.
+    pub async fn shutdown(&self, deadline: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + deadline;
+        loop {
+            let finished = self.finished.notified();
+            if self.queue_depth() == 0 && self.running_jobs() == 0 {
+                break;
+            }
+            if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                break;
+            }
+        }
+        self.closed.store(true, Ordering::Release);
+        let queued: Vec<String> = self
+            .lock_queue()
+            .remove_where(|_| true)
+            .into_iter()
+            .map(|queued| {
+                let _ = queued
+                    .reply
+                    .send(Err(AgentError::Cancelled(queued.job.id.clone())));
+                queued.job.id
+            })
+            .collect();
+        let running: Vec<String> = self.lock_running().keys().cloned().collect();
+        let mut cancelled = 0;
+        for job_id in queued.iter().chain(&running) {
+            match self.cancel(job_id).await {
+                Ok(record) if record.state == JobState::Cancelled => cancelled += 1,
+                Ok(_) => {}
+                Err(err) => warn!(%job_id, ?err, "unable to cancel job on shutdown"),
+            }
+        }
+        if cancelled > 0 {
+            info!(cancelled, "cancelled unfinished jobs on shutdown");
+        }
+        cancelled
+    }
+
+    fn lock_running(&self) -> MutexGuard<'_, HashMap<String, Arc<Notify>>> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_queue(&self) -> MutexGuard<'_, FairQueue<QueuedJob>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn run_worker(self, worker: usize) {
+        loop {
+            let next = self.lock_queue().pop();
+            match next {
+                Some(queued) => {
+                    debug!(
+                        worker,
+                        job_id = %queued.job.id,
+                        queue_depth = self.queue_depth(),
+                        "worker picked up job"
+                    );
+                    let model = queued.plan.backend.name();
+                    metrics().observe_queue_wait(model, queued.queued_at.elapsed());
+                    let span = info_span!(
+                        parent: &queued.span,
+                        "job.execute",
+                        job_id = %queued.job.id,
+                        model,
+                        worker,
+                    );
+                    let started = Instant::now();
+                    let result = self
+                        .execute_guarded(queued.job, queued.plan)
+                        .instrument(span)
+                        .await;
+                    let state = match &result {
+                        Ok(_) => JobState::Succeeded,
+                        Err(AgentError::Cancelled(_)) => JobState::Cancelled,
+                        Err(_) => JobState::Failed,
+                    };
+                    metrics().observe_run(model, state.as_str(), started.elapsed());
+                    let _ = queued.reply.send(result);
+                    self.finished.notify_waiters();
+                }
+                None => self.ready.notified().await,
+            }
+        }
+    }
+
+    /// Runs a job and turns a panic anywhere inside it into a failed job, so
+    /// the worker (and the server around it) keeps running.
+    async fn execute_guarded(
+        &self,
+        job: EvaluateJob,
+        plan: Plan,
+    ) -> Result<EvaluationResult, AgentError> {
+        let job_id = job.id.clone();
+        match AssertUnwindSafe(self.execute(job, plan))
+            .catch_unwind()
+            .await
+        {
+            Ok(result) => result,
+            Err(payload) => {
+                let err = AgentError::Panic(panic_message(payload.as_ref()));
+                error!(job_id = %job_id, %err, "job panicked");
+                if let Err(store_err) = self.store.fail(&job_id, &err.to_string()) {
+                    warn!(job_id = %job_id, ?store_err, "unable to record panicked job");
+                }
+                self.frequency.complete(&job_id, JobState::Failed).await;
+                Err(err)
+            }
+        }
+    }
+
+    async fn execute(&self, job: EvaluateJob, plan: Plan) -> Result<EvaluationResult, AgentError> {
+        // Registered before the job is marked running so a cancellation that
+        // lands right after `start` still finds it.
+        let abort = Arc::new(Notify::new());
+        self.lock_running().insert(job.id.clone(), abort.clone());
+        let outcome = self.execute_cancellable(&job, &plan, &abort).await;
+        self.lock_running().remove(&job.id);
+        outcome
+    }
+
+    async fn execute_cancellable(
+        &self,
+        job: &EvaluateJob,
+        plan: &Plan,
+        abort: &Notify,
+    ) -> Result<EvaluationResult, AgentError> {
+        if !self.store.start(&job.id)? {
+            return Err(AgentError::Cancelled(job.id.clone()));
+        }
+        self.frequency
+            .publish(&job.id, derive_frequency(0.0, 0.0), 0.0)
+            .await;
+        let evaluation = tokio::time::timeout(self.evaluation_timeout, self.run_steps(job, plan));
+        let outcome = tokio::select! {
+            outcome = evaluation => {
+                outcome.unwrap_or(Err(AgentError::Timeout(self.evaluation_timeout)))
+            }
+            _ = abort.notified() => Err(AgentError::Cancelled(job.id.clone())),
         };
-        self.frequency.publish(sample).await;
+        match outcome {
+            Ok(result) => {
+                // A cancel that lands as the evaluation finishes wins, so the
+                // job is never reported as succeeded after it was cancelled.
+                if !self.store.complete(&job.id, &result)? {
+                    return Err(AgentError::Cancelled(job.id.clone()));
+                }
+                self.frequency.complete(&job.id, JobState::Succeeded).await;
+                Ok(result)
+            }
+            Err(err @ AgentError::Cancelled(_)) => {
+                self.frequency.complete(&job.id, JobState::Cancelled).await;
+                Err(err)
+            }
+            Err(err) => {
+                self.store.fail(&job.id, &err.to_string())?;
+                self.frequency.complete(&job.id, JobState::Failed).await;
+                Err(err)
+            }
+        }
+    }
 
-        Ok(result)
+    /// Evaluates the circuit in a single pass, publishing a frequency
+    /// sample after every operation so subscribers see it evolve.
+    ///
+    /// The backend runs on the blocking pool and hands each snapshot over a
+    /// channel. A call that outlives the evaluation timeout cannot be
+    /// interrupted mid-operation; it stops at the next operation once the
+    /// job is gone and its result is discarded. The same applies to a job
+    /// that is cancelled mid-run.
+    async fn run_steps(
+        &self,
+        job: &EvaluateJob,
+        plan: &Plan,
+    ) -> Result<EvaluationResult, AgentError> {
+        if plan.circuit.operations.is_empty() {
+            return Err(AgentError::InvalidAction("empty expression".to_string()));
+        }
+        let (steps, mut snapshots) = mpsc::unbounded_channel();
+        let backend = plan.backend.clone();
+        let circuit = plan.circuit.clone();
+        let evaluation = tokio::task::spawn_blocking(move || {
+            backend.evaluate_steps(&circuit, &mut |snapshot| {
+                if steps.send(snapshot).is_ok() {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            })
+        });
+        while let Some(snapshot) = snapshots.recv().await {
+            self.frequency
+                .publish(
+                    &job.id,
+                    derive_frequency(snapshot.energy, snapshot.fidelity),
+                    snapshot.fidelity,
+                )
+                .await;
+        }
+        let raw = evaluation
+            .await
+            .map_err(|err| match err.try_into_panic() {
+                Ok(payload) => AgentError::Panic(panic_message(payload.as_ref())),
+                Err(err) => AgentError::Worker(err.to_string()),
+            })??;
+        Ok(EvaluationResult {
+            energy: raw.energy,
+            fidelity: raw.fidelity,
+            model: plan.backend.name().to_string(),
+        })
+    }
+
+    /// Applies the retention policy to the job store and drops frequency
+    /// samples for the jobs that were removed.
+    pub async fn prune(&self, policy: &RetentionPolicy) -> Result<usize, AgentError> {
+        let removed = self.store.prune(policy)?;
+        self.frequency.forget(&removed).await;
+        Ok(removed.len())
     }
 }
 
@@ -63,23 +424,10 @@ pub fn derive_frequency(energy: f64, fidelity: f64) -> f64 {
     0.05 + base + coherence
 }
 
-pub fn job_from_gesture(gesture: &Gesture) -> Option<EvaluateJob> {
-    let expression = match gesture.id.as_str() {
-        "triangle" => "H(q0)".to_string(),
-        "circle" => "X(q0)".to_string(),
-        "square" => "Z(q0)".to_string(),
-        other => {
-            if other.is_empty() {
-                return None;
-            }
-            format!("RX(0.5, {other})")
-        }
-    };
-
-    Some(EvaluateJob {
-        id: format!("gesture-{}", Uuid::new_v4()),
-        expression,
-        requested_by: "symbolcastd".to_string(),
-        model: None,
-    })
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
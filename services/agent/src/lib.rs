@@ -1,89 +1,353 @@
+mod auth;
+mod backend;
+mod circuit;
 mod error;
 mod frequency;
+mod gestures;
 mod grpc_service;
 mod jobs;
+mod limits;
+mod metrics;
 mod pipeline;
 mod proto;
 mod publisher;
+#[cfg(feature = "cpp-qpp")]
 mod qpp_bridge;
+mod queue;
+mod simulator;
+mod store;
 mod symbolcast;
+mod telemetry;
 
 pub use error::AgentError;
 
+use crate::auth::{Authenticator, Permissions};
+use crate::backend::Backends;
+use crate::gestures::{GestureMap, GestureMapper};
 use crate::grpc_service::ActionGrpcService;
-use crate::jobs::JobExecutor;
-use crate::pipeline::ActionPipeline;
+use crate::jobs::{ExecutorConfig, JobExecutor};
+use crate::limits::RateLimiter;
+use crate::pipeline::{ActionPipeline, ActionProcessor, DEFAULT_DEDUP_WINDOW};
 use crate::publisher::NatsPublisher;
+use crate::store::{JobStore, RetentionPolicy};
 use crate::symbolcast::GrpcSymbolCastInvoker;
 use async_nats::Client as NatsClient;
 use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
+use tokio::sync::watch;
 use tonic::transport::Server;
-use tracing::{error, info};
+use tonic_health::ServingStatus;
+use tracing::{error, info, warn};
+
+const DEFAULT_JOB_DB_PATH: &str = "./.tmp/eco-agent/jobs.db";
+const DEFAULT_FREQUENCY_HISTORY: usize = 128;
+const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+const DEFAULT_GESTURE_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long message handlers and gRPC calls get to wrap up once the
+/// evaluations have drained.
+const HANDLER_GRACE: Duration = Duration::from_secs(5);
+type ActionsServer = proto::actions::eco_actions_server::EcoActionsServer<
+    ActionGrpcService<NatsPublisher, GrpcSymbolCastInvoker>,
+>;
+
+/// Matches the default of ethos-gateway so local setups work unconfigured.
+const DEV_JWT_SECRET: &str = "insecure-dev-secret";
 
 pub async fn run() -> Result<(), AgentError> {
-    tracing_subscriber::fmt::init();
+    telemetry::init()?;
 
     let nats_url = env::var("NATS_URL").unwrap_or_else(|_| "nats://127.0.0.1:4222".to_string());
+    let metrics_addr: SocketAddr = env::var("AGENT_METRICS_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:9464".to_string())
+        .parse()
+        .map_err(|err| AgentError::InvalidConfig(format!("invalid AGENT_METRICS_ADDR: {err}")))?;
     let agent_addr: SocketAddr = env::var("AGENT_GRPC_ADDR")
         .unwrap_or_else(|_| "0.0.0.0:50051".to_string())
         .parse()
         .map_err(|err| AgentError::InvalidConfig(format!("invalid AGENT_GRPC_ADDR: {err}")))?;
     let symbolcast_url =
         env::var("SYMBOLCAST_URL").unwrap_or_else(|_| "http://127.0.0.1:50052".to_string());
+    let job_db_path =
+        env::var("AGENT_JOB_DB_PATH").unwrap_or_else(|_| DEFAULT_JOB_DB_PATH.to_string());
+    let retention = retention_from_env()?;
+    let executor_config = executor_config_from_env()?;
+    let dedup_window = match env::var("AGENT_ACTION_DEDUP_SECS") {
+        Ok(value) => Duration::from_secs(value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_ACTION_DEDUP_SECS: {err}"))
+        })?),
+        Err(_) => DEFAULT_DEDUP_WINDOW,
+    };
+    let mut backends = Backends::builtin();
+    if let Ok(model) = env::var("AGENT_DEFAULT_MODEL") {
+        backends.set_default(&model)?;
+    }
+    let shutdown_timeout = match env::var("AGENT_SHUTDOWN_TIMEOUT_SECS") {
+        Ok(value) => Duration::from_secs(value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_SHUTDOWN_TIMEOUT_SECS: {err}"))
+        })?),
+        Err(_) => DEFAULT_SHUTDOWN_TIMEOUT,
+    };
+    let frequency_history = match env::var("AGENT_FREQUENCY_HISTORY") {
+        Ok(value) => value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_FREQUENCY_HISTORY: {err}"))
+        })?,
+        Err(_) => DEFAULT_FREQUENCY_HISTORY,
+    };
 
     let nats: NatsClient = async_nats::connect(nats_url.clone()).await?;
     info!(%nats_url, "eco-agent connected to NATS");
 
-    let frequency = frequency::FrequencyHub::new(64);
-    let executor = JobExecutor::new(frequency.clone());
+    let store = JobStore::open(&job_db_path)?;
+    info!(%job_db_path, "eco-agent job store opened");
+
+    let frequency = frequency::FrequencyHub::new(256, frequency_history);
+    info!(
+        workers = executor_config.workers,
+        queue_capacity = executor_config.queue_capacity,
+        "starting evaluation workers"
+    );
+    let executor = JobExecutor::new(frequency.clone(), store, backends, executor_config);
+    tokio::spawn(run_retention(executor.clone(), retention));
     let publisher = Arc::new(NatsPublisher::new(nats.clone()));
 
-    let pipeline = ActionPipeline::new(nats.clone(), executor.clone(), publisher.clone());
+    let limits = match env::var("AGENT_RATE_LIMITS") {
+        Ok(spec) => RateLimiter::parse(&spec)?,
+        Err(_) => RateLimiter::default(),
+    };
+
+    let processor = ActionProcessor::new(
+        executor.clone(),
+        publisher.clone(),
+        limits.clone(),
+        dedup_window,
+    );
+    let pipeline = ActionPipeline::new(nats.clone(), processor);
 
     let symbolcast = Arc::new(GrpcSymbolCastInvoker::connect(symbolcast_url.clone()).await?);
+    let gestures = gesture_mapper_from_env()?;
+    let auth = authenticator_from_env()?;
 
     let grpc_service = ActionGrpcService::new(
         executor.clone(),
         publisher.clone(),
         frequency.clone(),
         symbolcast,
+        gestures,
+        auth,
+        limits,
     );
+    let (mut health, health_service) = tonic_health::server::health_reporter();
+    health.set_serving::<ActionsServer>().await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let grpc = Server::builder()
+        .trace_fn(telemetry::grpc_request_span)
         .accept_http1(true)
-        .add_service(tonic_web::enable(
-            proto::actions::eco_actions_server::EcoActionsServer::new(grpc_service),
-        ))
-        .serve(agent_addr);
+        .add_service(health_service)
+        .add_service(tonic_web::enable(ActionsServer::new(grpc_service)))
+        .serve_with_shutdown(agent_addr, {
+            let mut shutdown = shutdown_rx.clone();
+            async move {
+                let _ = shutdown.wait_for(|stop| *stop).await;
+            }
+        });
 
     info!(%agent_addr, "eco-agent gRPC server listening");
 
-    let pipeline_task = pipeline.run();
-    tokio::pin!(grpc);
-    tokio::pin!(pipeline_task);
+    let mut grpc_task = tokio::spawn(grpc);
+    let mut pipeline_task = tokio::spawn(async move { pipeline.run(shutdown_rx).await });
+    let metrics_task = metrics::serve(metrics_addr, executor.clone());
+    tokio::pin!(metrics_task);
 
     tokio::select! {
-        res = &mut grpc => {
-            if let Err(err) = res {
-                error!(?err, "gRPC server terminated");
-                return Err(AgentError::from(err));
-            }
+        res = &mut grpc_task => {
+            let err = match res {
+                Ok(Ok(())) => AgentError::Worker("gRPC server stopped".to_string()),
+                Ok(Err(err)) => AgentError::from(err),
+                Err(err) => AgentError::Worker(err.to_string()),
+            };
+            error!(?err, "gRPC server terminated");
+            return Err(err);
         }
         res = &mut pipeline_task => {
+            let err = match res {
+                Ok(Ok(())) => AgentError::Worker("action pipeline stopped".to_string()),
+                Ok(Err(err)) => err,
+                Err(err) => AgentError::Worker(err.to_string()),
+            };
+            error!(?err, "action pipeline terminated");
+            return Err(err);
+        }
+        res = &mut metrics_task => {
             if let Err(err) = res {
-                error!(?err, "action pipeline terminated");
+                error!(?err, "metrics endpoint terminated");
                 return Err(err);
             }
         }
-        _ = signal::ctrl_c() => {
+        _ = shutdown_signal() => {
             info!("shutdown signal received");
         }
     }
 
+    // Readiness drops first so load balancers stop routing here; liveness
+    // stays up until the drain is over.
+    health.set_not_serving::<ActionsServer>().await;
+    let _ = shutdown_tx.send(true);
+    let cancelled = executor.shutdown(shutdown_timeout).await;
+    info!(cancelled, "evaluations drained");
+    match tokio::time::timeout(HANDLER_GRACE, pipeline_task).await {
+        Ok(Ok(Err(err))) => warn!(?err, "action pipeline failed while draining"),
+        Ok(Err(err)) => warn!(?err, "action pipeline task failed"),
+        Err(_) => warn!("action pipeline did not drain in time"),
+        Ok(Ok(Ok(()))) => {}
+    }
+    match tokio::time::timeout(HANDLER_GRACE, grpc_task).await {
+        Ok(Ok(Err(err))) => warn!(?err, "gRPC server failed while draining"),
+        Ok(Err(err)) => warn!(?err, "gRPC server task failed"),
+        Err(_) => warn!("gRPC calls did not finish in time"),
+        Ok(Ok(Ok(()))) => {}
+    }
+    if let Err(err) = nats.flush().await {
+        warn!(?err, "unable to flush NATS publishes");
+    }
+    health
+        .set_service_status("", ServingStatus::NotServing)
+        .await;
+    telemetry::shutdown();
+    info!("eco-agent stopped");
     Ok(())
 }
 
+/// Resolves on ctrl-c or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(err) => {
+                warn!(?err, "unable to listen for SIGTERM");
+                let _ = signal::ctrl_c().await;
+                return;
+            }
+        };
+        tokio::select! {
+            _ = signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = signal::ctrl_c().await;
+}
+
+fn executor_config_from_env() -> Result<ExecutorConfig, AgentError> {
+    let mut config = ExecutorConfig::default();
+    if let Ok(value) = env::var("AGENT_WORKERS") {
+        config.workers = value
+            .parse()
+            .map_err(|err| AgentError::InvalidConfig(format!("invalid AGENT_WORKERS: {err}")))?;
+        if config.workers == 0 {
+            return Err(AgentError::InvalidConfig(
+                "AGENT_WORKERS must be at least 1".to_string(),
+            ));
+        }
+    }
+    if let Ok(value) = env::var("AGENT_QUEUE_CAPACITY") {
+        config.queue_capacity = value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_QUEUE_CAPACITY: {err}"))
+        })?;
+    }
+    if let Ok(value) = env::var("AGENT_EVALUATION_TIMEOUT_SECS") {
+        let secs: u64 = value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_EVALUATION_TIMEOUT_SECS: {err}"))
+        })?;
+        if secs == 0 {
+            return Err(AgentError::InvalidConfig(
+                "AGENT_EVALUATION_TIMEOUT_SECS must be at least 1".to_string(),
+            ));
+        }
+        config.evaluation_timeout = Duration::from_secs(secs);
+    }
+    Ok(config)
+}
+
+/// Tokens are checked against `AGENT_JWT_SECRET`, falling back to the
+/// gateway's `ETHOS_JWT_SECRET` and then to the gateway's development default.
+/// `AGENT_ACTION_PERMISSIONS` overrides the access per action kind, e.g.
+/// `qpp.evaluate=everyone`, and `AGENT_ADMINS` lists the subjects, separated
+/// by commas, that `admins` access lets through.
+fn authenticator_from_env() -> Result<Authenticator, AgentError> {
+    let secret = env::var("AGENT_JWT_SECRET")
+        .or_else(|_| env::var("ETHOS_JWT_SECRET"))
+        .unwrap_or_else(|_| {
+            warn!("no JWT secret configured, using the insecure development secret");
+            DEV_JWT_SECRET.to_string()
+        });
+    let permissions = match env::var("AGENT_ACTION_PERMISSIONS") {
+        Ok(spec) => Permissions::parse(&spec)?,
+        Err(_) => Permissions::default(),
+    };
+    let admins = env::var("AGENT_ADMINS").unwrap_or_default();
+    let permissions = permissions.with_admins(
+        admins
+            .split(',')
+            .map(str::trim)
+            .filter(|admin| !admin.is_empty())
+            .map(str::to_string),
+    );
+    Ok(Authenticator::new(secret, permissions))
+}
+
+/// Loads the gesture map named by `AGENT_GESTURE_MAP` and starts watching it
+/// for changes; without it the built-in mapping is used.
+fn gesture_mapper_from_env() -> Result<GestureMapper, AgentError> {
+    let Ok(path) = env::var("AGENT_GESTURE_MAP") else {
+        return Ok(GestureMapper::new(GestureMap::builtin()));
+    };
+    let interval = match env::var("AGENT_GESTURE_RELOAD_SECS") {
+        Ok(value) => {
+            let secs: u64 = value.parse().map_err(|err| {
+                AgentError::InvalidConfig(format!("invalid AGENT_GESTURE_RELOAD_SECS: {err}"))
+            })?;
+            Duration::from_secs(secs.max(1))
+        }
+        Err(_) => DEFAULT_GESTURE_RELOAD_INTERVAL,
+    };
+    let mapper = GestureMapper::from_file(&path)?;
+    info!(%path, "gesture map loaded");
+    tokio::spawn(mapper.clone().watch(interval));
+    Ok(mapper)
+}
+
+fn retention_from_env() -> Result<RetentionPolicy, AgentError> {
+    let mut policy = RetentionPolicy::default();
+    if let Ok(value) = env::var("AGENT_JOB_RETENTION_SECS") {
+        let secs: u64 = value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_JOB_RETENTION_SECS: {err}"))
+        })?;
+        policy.max_age = Duration::from_secs(secs);
+    }
+    if let Ok(value) = env::var("AGENT_JOB_RETENTION_MAX") {
+        policy.max_jobs = value.parse().map_err(|err| {
+            AgentError::InvalidConfig(format!("invalid AGENT_JOB_RETENTION_MAX: {err}"))
+        })?;
+    }
+    Ok(policy)
+}
+
+async fn run_retention(executor: JobExecutor, policy: RetentionPolicy) {
+    let mut interval = tokio::time::interval(RETENTION_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match executor.prune(&policy).await {
+            Ok(0) => {}
+            Ok(removed) => info!(removed, "pruned expired jobs"),
+            Err(err) => warn!(?err, "job retention sweep failed"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests;
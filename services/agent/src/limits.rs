@@ -0,0 +1,401 @@
+use crate::auth::{EVALUATE_ACTION, GESTURE_ACTION};
+use crate::error::AgentError;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+/// Requester and kind pairs tracked at once. Requesters are caller supplied
+/// on the NATS path, so the table must not grow without bound; once it is
+/// full of entries that still limit someone, the least recently refilled
+/// ones are dropped to make room, so a flood of made-up names can neither
+/// lock anyone out nor grow the table.
+const DEFAULT_MAX_TRACKED: usize = 100_000;
+/// A full table drops this fraction of its entries at once, so the scan is
+/// paid once per many new requesters.
+const EVICTED_SHARE: usize = 10;
+/// How often entries that no longer limit anything are swept out.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limits applied to one action kind, per requester.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    /// Tokens added to the bucket per second.
+    pub rate: f64,
+    /// Bucket size, i.e. how many actions may arrive at once.
+    pub burst: f64,
+    /// Actions allowed per UTC day, if capped.
+    pub daily: Option<u64>,
+}
+
+impl Limit {
+    fn parse(spec: &str) -> Result<Option<Self>, AgentError> {
+        let spec = spec.trim();
+        if spec == "unlimited" {
+            return Ok(None);
+        }
+        let mut limit = Limit {
+            rate: 1.0,
+            burst: 1.0,
+            daily: None,
+        };
+        for field in spec.split(',') {
+            let (name, value) = field.split_once(':').ok_or_else(|| {
+                AgentError::InvalidConfig(format!("invalid rate limit field {field}"))
+            })?;
+            let invalid =
+                |err: &dyn std::fmt::Display| AgentError::InvalidConfig(format!("{field}: {err}"));
+            match name.trim() {
+                "rate" => limit.rate = value.trim().parse().map_err(|err| invalid(&err))?,
+                "burst" => limit.burst = value.trim().parse().map_err(|err| invalid(&err))?,
+                "daily" => limit.daily = Some(value.trim().parse().map_err(|err| invalid(&err))?),
+                other => {
+                    return Err(AgentError::InvalidConfig(format!(
+                        "unknown rate limit field {other}"
+                    )))
+                }
+            }
+        }
+        if !(limit.rate > 0.0 && limit.burst >= 1.0) {
+            return Err(AgentError::InvalidConfig(format!(
+                "rate limit {spec} needs a positive rate and a burst of at least 1"
+            )));
+        }
+        Ok(Some(limit))
+    }
+}
+
+struct Usage {
+    tokens: f64,
+    refilled_at: Instant,
+    day: u64,
+    used_today: u64,
+}
+
+impl Usage {
+    /// Whether dropping the entry changes nothing: a fresh one would start
+    /// with the same full bucket and, for capped kinds, the same quota.
+    fn is_spent(&self, limit: &Limit, now: Instant, day: u64) -> bool {
+        if self.day < day {
+            return true;
+        }
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        let refilled = self.tokens + elapsed.as_secs_f64() * limit.rate >= limit.burst;
+        refilled && (limit.daily.is_none() || self.used_today == 0)
+    }
+}
+
+struct UsageTable {
+    entries: HashMap<(String, String), Usage>,
+    swept_at: Option<Instant>,
+}
+
+/// Token-bucket rate limits and daily quotas keyed by requester and action
+/// kind. Kinds without a limit are not restricted. Clones share usage.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limits: Arc<HashMap<String, Limit>>,
+    usage: Arc<Mutex<UsageTable>>,
+    max_tracked: usize,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        let mut limits = HashMap::new();
+        limits.insert(
+            EVALUATE_ACTION.to_string(),
+            Limit {
+                rate: 2.0,
+                burst: 10.0,
+                daily: Some(5_000),
+            },
+        );
+        limits.insert(
+            GESTURE_ACTION.to_string(),
+            Limit {
+                rate: 1.0,
+                burst: 5.0,
+                daily: Some(2_000),
+            },
+        );
+        Self::new(limits)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<String, Limit>) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            usage: Arc::new(Mutex::new(UsageTable {
+                entries: HashMap::new(),
+                swept_at: None,
+            })),
+            max_tracked: DEFAULT_MAX_TRACKED,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_max_tracked(mut self, max_tracked: usize) -> Self {
+        self.max_tracked = max_tracked;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn unlimited() -> Self {
+        Self::new(HashMap::new())
+    }
+
+    /// Parses overrides such as
+    /// `qpp.evaluate=rate:2,burst:10,daily:500;gesture.recognize=unlimited`
+    /// on top of the defaults.
+    pub fn parse(spec: &str) -> Result<Self, AgentError> {
+        let mut limits = Self::default().limits.as_ref().clone();
+        for entry in spec.split(';').filter(|entry| !entry.trim().is_empty()) {
+            let (kind, limit) = entry.split_once('=').ok_or_else(|| {
+                AgentError::InvalidConfig(format!("invalid rate limit entry {entry}"))
+            })?;
+            match Limit::parse(limit)? {
+                Some(limit) => limits.insert(kind.trim().to_string(), limit),
+                None => limits.remove(kind.trim()),
+            };
+        }
+        Ok(Self::new(limits))
+    }
+
+    /// Records one `kind` action by `requester`, or rejects it with
+    /// [`AgentError::RateLimited`] or [`AgentError::QuotaExceeded`].
+    pub fn check(&self, requester: &str, kind: &str) -> Result<(), AgentError> {
+        let day = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / SECONDS_PER_DAY;
+        self.check_at(requester, kind, Instant::now(), day)
+    }
+
+    fn check_at(
+        &self,
+        requester: &str,
+        kind: &str,
+        now: Instant,
+        day: u64,
+    ) -> Result<(), AgentError> {
+        let Some(limit) = self.limits.get(kind) else {
+            return Ok(());
+        };
+        let mut usage = self
+            .usage
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = (requester.to_string(), kind.to_string());
+        let full = usage.entries.len() >= self.max_tracked;
+        let sweep_due = usage
+            .swept_at
+            .is_none_or(|swept_at| now.saturating_duration_since(swept_at) >= SWEEP_INTERVAL);
+        if sweep_due || (full && !usage.entries.contains_key(&key)) {
+            self.sweep(&mut usage, now, day);
+        }
+        if usage.entries.len() >= self.max_tracked && !usage.entries.contains_key(&key) {
+            evict_least_recent(&mut usage);
+        }
+        let entry = usage.entries.entry(key).or_insert(Usage {
+            tokens: limit.burst,
+            refilled_at: now,
+            day,
+            used_today: 0,
+        });
+        let elapsed = now.saturating_duration_since(entry.refilled_at);
+        entry.tokens = (entry.tokens + elapsed.as_secs_f64() * limit.rate).min(limit.burst);
+        entry.refilled_at = now;
+        if entry.day != day {
+            entry.day = day;
+            entry.used_today = 0;
+        }
+        if limit.daily.is_some_and(|daily| entry.used_today >= daily) {
+            return Err(AgentError::QuotaExceeded(format!("{requester} on {kind}")));
+        }
+        if entry.tokens < 1.0 {
+            return Err(AgentError::RateLimited(format!("{requester} on {kind}")));
+        }
+        entry.tokens -= 1.0;
+        entry.used_today += 1;
+        Ok(())
+    }
+
+    /// Drops entries from earlier days and entries whose bucket has refilled
+    /// without any quota used.
+    fn sweep(&self, usage: &mut UsageTable, now: Instant, day: u64) {
+        let limits = &self.limits;
+        usage.entries.retain(|(_, kind), entry| {
+            limits
+                .get(kind)
+                .is_some_and(|limit| !entry.is_spent(limit, now, day))
+        });
+        usage.swept_at = Some(now);
+    }
+}
+
+/// Drops the least recently refilled tenth of the entries. Their requesters
+/// start over with a full bucket and quota, which only happens while the
+/// table is flooded.
+fn evict_least_recent(usage: &mut UsageTable) {
+    let count = (usage.entries.len() / EVICTED_SHARE).max(1);
+    let mut refilled: Vec<Instant> = usage
+        .entries
+        .values()
+        .map(|entry| entry.refilled_at)
+        .collect();
+    let cutoff = *refilled.select_nth_unstable(count - 1).1;
+    let mut left = count;
+    usage.entries.retain(|_, entry| {
+        let evict = left > 0 && entry.refilled_at <= cutoff;
+        left -= usize::from(evict);
+        !evict
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(daily: Option<u64>) -> RateLimiter {
+        let mut limits = HashMap::new();
+        limits.insert(
+            EVALUATE_ACTION.to_string(),
+            Limit {
+                rate: 1.0,
+                burst: 2.0,
+                daily,
+            },
+        );
+        RateLimiter::new(limits)
+    }
+
+    #[test]
+    fn buckets_refill_over_time_per_requester() {
+        let limiter = limiter(None);
+        let start = Instant::now();
+        for _ in 0..2 {
+            limiter
+                .check_at("alice", EVALUATE_ACTION, start, 0)
+                .expect("within burst");
+        }
+        let err = limiter
+            .check_at("alice", EVALUATE_ACTION, start, 0)
+            .expect_err("bucket empty");
+        assert!(matches!(err, AgentError::RateLimited(_)));
+        limiter
+            .check_at("bob", EVALUATE_ACTION, start, 0)
+            .expect("separate bucket");
+        limiter
+            .check_at("alice", GESTURE_ACTION, start, 0)
+            .expect("kind without a limit");
+        limiter
+            .check_at("alice", EVALUATE_ACTION, start + Duration::from_secs(1), 0)
+            .expect("refilled");
+    }
+
+    #[test]
+    fn daily_quotas_reset_on_the_next_day() {
+        let limiter = limiter(Some(3));
+        let mut now = Instant::now();
+        for _ in 0..3 {
+            now += Duration::from_secs(10);
+            limiter
+                .check_at("alice", EVALUATE_ACTION, now, 7)
+                .expect("within quota");
+        }
+        let err = limiter
+            .check_at("alice", EVALUATE_ACTION, now + Duration::from_secs(10), 7)
+            .expect_err("quota used up");
+        assert!(matches!(err, AgentError::QuotaExceeded(_)));
+        limiter
+            .check_at("alice", EVALUATE_ACTION, now + Duration::from_secs(20), 8)
+            .expect("new day");
+    }
+
+    #[test]
+    fn idle_entries_are_evicted_and_the_table_is_bounded() {
+        let limiter = limiter(None).with_max_tracked(2);
+        let start = Instant::now();
+        for requester in ["alice", "bob"] {
+            limiter
+                .check_at(requester, EVALUATE_ACTION, start, 0)
+                .expect("tracked");
+        }
+        limiter
+            .check_at("alice", EVALUATE_ACTION, start, 0)
+            .expect("known requesters keep their bucket");
+
+        // Once both buckets refilled the entries limit nothing and make room.
+        let later = start + Duration::from_secs(2);
+        limiter
+            .check_at("mallory", EVALUATE_ACTION, later, 0)
+            .expect("room after eviction");
+        let usage = limiter.usage.lock().unwrap();
+        assert_eq!(usage.entries.len(), 1);
+    }
+
+    #[test]
+    fn entries_with_quota_used_today_survive_sweeps() {
+        let limiter = limiter(Some(1));
+        let start = Instant::now();
+        limiter
+            .check_at("alice", EVALUATE_ACTION, start, 3)
+            .expect("within quota");
+        let later = start + SWEEP_INTERVAL * 2;
+        let err = limiter
+            .check_at("alice", EVALUATE_ACTION, later, 3)
+            .expect_err("quota kept across the sweep");
+        assert!(matches!(err, AgentError::QuotaExceeded(_)));
+        limiter
+            .check_at("alice", EVALUATE_ACTION, later, 4)
+            .expect("new day");
+    }
+
+    #[test]
+    fn a_full_table_evicts_the_least_recently_refilled_entries() {
+        let limiter = limiter(Some(5)).with_max_tracked(2);
+        let start = Instant::now();
+        limiter
+            .check_at("alice", EVALUATE_ACTION, start, 3)
+            .expect("tracked");
+        let later = start + Duration::from_secs(1);
+        limiter
+            .check_at("bob", EVALUATE_ACTION, later, 3)
+            .expect("tracked");
+        limiter
+            .check_at("carol", EVALUATE_ACTION, later, 3)
+            .expect("room made for a new requester");
+
+        let usage = limiter.usage.lock().unwrap();
+        assert_eq!(usage.entries.len(), 2);
+        let tracked = |requester: &str| {
+            usage
+                .entries
+                .contains_key(&(requester.to_string(), EVALUATE_ACTION.to_string()))
+        };
+        assert!(!tracked("alice"));
+        assert!(tracked("bob") && tracked("carol"));
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_defaults() {
+        let limiter = RateLimiter::parse(
+            "qpp.evaluate=rate:0.5,burst:3,daily:100; gesture.recognize=unlimited",
+        )
+        .expect("parse");
+        assert_eq!(
+            limiter.limits.get(EVALUATE_ACTION),
+            Some(&Limit {
+                rate: 0.5,
+                burst: 3.0,
+                daily: Some(100),
+            })
+        );
+        assert!(!limiter.limits.contains_key(GESTURE_ACTION));
+        assert!(RateLimiter::parse("qpp.evaluate=rate:0").is_err());
+        assert!(RateLimiter::parse("qpp.evaluate=speed:1").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::AgentError;
+use crate::jobs::JobExecutor;
+use axum::extract::State;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::info;
+
+/// Buckets in seconds, from a single fast gate up to the default evaluation
+/// timeout.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Prometheus metrics of the agent, shared by the whole process.
+pub struct Metrics {
+    registry: Registry,
+    job_queue_seconds: HistogramVec,
+    job_run_seconds: HistogramVec,
+    queue_depth: IntGauge,
+    running_jobs: IntGauge,
+    action_failures: IntCounterVec,
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("eco_agent".to_string()), None)
+            .expect("valid metrics prefix");
+        let job_queue_seconds = HistogramVec::new(
+            HistogramOpts::new("job_queue_seconds", "Time jobs spend waiting for a worker")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["model"],
+        )
+        .expect("valid histogram");
+        let job_run_seconds = HistogramVec::new(
+            HistogramOpts::new("job_run_seconds", "Time workers spend running a job")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["model", "state"],
+        )
+        .expect("valid histogram");
+        let queue_depth =
+            IntGauge::new("queue_depth", "Jobs waiting for a worker").expect("valid gauge");
+        let running_jobs = IntGauge::new("running_jobs", "Jobs being run").expect("valid gauge");
+        let action_failures = IntCounterVec::new(
+            Opts::new(
+                "action_failures_total",
+                "Actions that were rejected or failed",
+            ),
+            &["kind", "reason"],
+        )
+        .expect("valid counter");
+        for collector in [
+            Box::new(job_queue_seconds.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(job_run_seconds.clone()),
+            Box::new(queue_depth.clone()),
+            Box::new(running_jobs.clone()),
+            Box::new(action_failures.clone()),
+        ] {
+            registry.register(collector).expect("unique metric");
+        }
+        Self {
+            registry,
+            job_queue_seconds,
+            job_run_seconds,
+            queue_depth,
+            running_jobs,
+            action_failures,
+        }
+    }
+
+    pub fn observe_queue_wait(&self, model: &str, waited: Duration) {
+        self.job_queue_seconds
+            .with_label_values(&[model])
+            .observe(waited.as_secs_f64());
+    }
+
+    pub fn observe_run(&self, model: &str, state: &str, took: Duration) {
+        self.job_run_seconds
+            .with_label_values(&[model, state])
+            .observe(took.as_secs_f64());
+    }
+
+    pub fn record_failure(&self, kind: &str, err: &AgentError) {
+        self.action_failures
+            .with_label_values(&[kind, failure_reason(err)])
+            .inc();
+    }
+
+    /// Renders every metric in the Prometheus text format, sampling the
+    /// executor gauges first.
+    pub fn render(&self, executor: &JobExecutor) -> String {
+        self.queue_depth.set(executor.queue_depth() as i64);
+        self.running_jobs.set(executor.running_jobs() as i64);
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding");
+        String::from_utf8(buffer).expect("metrics are UTF-8")
+    }
+}
+
+fn failure_reason(err: &AgentError) -> &'static str {
+    match err {
+        AgentError::RateLimited(_) => "rate_limited",
+        AgentError::QuotaExceeded(_) => "quota_exceeded",
+        AgentError::QueueFull(_) => "queue_full",
+        AgentError::Timeout(_) => "timeout",
+        AgentError::Cancelled(_) => "cancelled",
+        AgentError::ShuttingDown => "shutting_down",
+        AgentError::Panic(_) => "panic",
+        AgentError::Bridge(_) => "backend",
+        AgentError::Expression(_) | AgentError::InvalidAction(_) | AgentError::UnknownModel(_) => {
+            "invalid"
+        }
+        _ => "internal",
+    }
+}
+
+/// Serves `GET /metrics` until the server fails.
+pub async fn serve(addr: SocketAddr, executor: JobExecutor) -> Result<(), AgentError> {
+    let app = Router::new()
+        .route(
+            "/metrics",
+            get(|State(executor): State<JobExecutor>| async move { metrics().render(&executor) }),
+        )
+        .with_state(executor);
+    info!(%addr, "metrics endpoint listening");
+    axum::Server::try_bind(&addr)
+        .map_err(|err| AgentError::Metrics(format!("unable to bind {addr}: {err}")))?
+        .serve(app.into_make_service())
+        .await
+        .map_err(|err| AgentError::Metrics(err.to_string()))
+}
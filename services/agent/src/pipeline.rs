@@ -1,54 +1,104 @@
+use crate::auth::EVALUATE_ACTION;
 use crate::error::AgentError;
 use crate::jobs::{EvaluateJob, JobExecutor};
+use crate::limits::RateLimiter;
+use crate::metrics::metrics;
 use crate::publisher::{ActionOutcome, ActionResultPublisher};
+use crate::queue::JobPriority;
+use crate::store::JobState;
+use crate::telemetry;
 use async_nats::Client as NatsClient;
 use futures::StreamExt;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// How long an action id is remembered for deduplication by default.
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(300);
 
-#[derive(Clone)]
 pub struct ActionPipeline<P: ActionResultPublisher> {
     client: NatsClient,
     processor: ActionProcessor<P>,
 }
 
+// Manual impls: deriving would require `P: Clone`, but the publisher is
+// shared through an `Arc`.
+impl<P: ActionResultPublisher> Clone for ActionPipeline<P> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            processor: self.processor.clone(),
+        }
+    }
+}
+
 impl<P: ActionResultPublisher> ActionPipeline<P> {
-    pub fn new(client: NatsClient, executor: JobExecutor, publisher: Arc<P>) -> Self {
-        let processor = ActionProcessor::new(executor, publisher);
+    pub fn new(client: NatsClient, processor: ActionProcessor<P>) -> Self {
         Self { client, processor }
     }
 
-    pub async fn run(&self) -> Result<(), AgentError> {
+    /// Handles `eco.action.*` messages until `shutdown` flips to `true`.
+    /// Shutting down unsubscribes, still handles the messages that were
+    /// already delivered, and returns once every handler has finished and
+    /// the outcomes have been flushed to the server.
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) -> Result<(), AgentError> {
         let mut sub = self.client.subscribe("eco.action.*").await?;
         info!("action pipeline subscribed to eco.action.*");
-        while let Some(msg) = sub.next().await {
-            if let Err(err) = self.process_message(&msg.subject, &msg.payload).await {
-                error!(%msg.subject, ?err, "failed to process action message");
-            }
+        let mut handlers = JoinSet::new();
+        let mut draining = false;
+        loop {
+            let msg = tokio::select! {
+                msg = sub.next() => msg,
+                _ = async { shutdown.wait_for(|stop| *stop).await.map(|_| ()) }, if !draining => {
+                    info!("action pipeline draining");
+                    draining = true;
+                    sub.unsubscribe()
+                        .await
+                        .map_err(|err| AgentError::Nats(Box::new(err)))?;
+                    continue;
+                }
+                // Reap finished handlers so the set does not grow unbounded.
+                Some(_) = handlers.join_next(), if !handlers.is_empty() => continue,
+            };
+            let Some(msg) = msg else { break };
+            // Each message gets its own task so a long evaluation does not
+            // hold up cancellations or other actions.
+            let pipeline = self.clone();
+            handlers.spawn(async move {
+                let reply = msg.reply.as_deref();
+                if let Err(err) = pipeline
+                    .process_message(&msg.subject, reply, msg.headers.as_ref(), &msg.payload)
+                    .await
+                {
+                    error!(%msg.subject, ?err, "failed to process action message");
+                }
+            });
         }
+        while handlers.join_next().await.is_some() {}
+        self.client
+            .flush()
+            .await
+            .map_err(|err| AgentError::Nats(Box::new(err)))?;
+        info!("action pipeline drained");
         Ok(())
     }
 
-    pub async fn process_message(&self, subject: &str, payload: &[u8]) -> Result<(), AgentError> {
-        match subject.strip_prefix("eco.action.") {
-            Some("cast") => {
-                let event: ActionEvent = serde_json::from_slice(payload)?;
-                self.processor.handle_action_event(event).await?;
-            }
-            Some("evaluate") => {
-                let event: EvaluateEvent = serde_json::from_slice(payload)?;
-                self.processor.handle_evaluate_event(event).await?;
-            }
-            Some(other) => {
-                warn!(%other, "unsupported action subject");
-            }
-            None => {
-                warn!(%subject, "unexpected subject" );
-            }
-        }
-        Ok(())
+    pub async fn process_message(
+        &self,
+        subject: &str,
+        reply: Option<&str>,
+        headers: Option<&async_nats::HeaderMap>,
+        payload: &[u8],
+    ) -> Result<(), AgentError> {
+        self.processor
+            .process_message(subject, reply, headers, payload)
+            .await
     }
 }
 
@@ -67,11 +117,13 @@ impl ActionEvent {
         match self.kind.as_str() {
             "qpp.evaluate" => {
                 let payload: EvaluatePayload = serde_json::from_value(self.payload)?;
+                let priority = parse_priority(payload.priority.as_deref())?;
                 Ok(Some(ActionCommand::Evaluate(EvaluateJob {
                     id: self.id,
                     expression: payload.expression,
                     requested_by,
                     model: payload.model,
+                    priority,
                 })))
             }
             other => {
@@ -87,6 +139,8 @@ struct EvaluatePayload {
     expression: String,
     #[serde(default)]
     model: Option<String>,
+    #[serde(default)]
+    priority: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,6 +151,48 @@ struct EvaluateEvent {
     requested_by: Option<String>,
     #[serde(default)]
     model: Option<String>,
+    #[serde(default)]
+    priority: Option<String>,
+}
+
+/// The `id` and `requested_by` of a request that could not be handled, read
+/// as far as the payload allows so the failure reply can still be matched to
+/// it. Falls back to the subject when no id is readable.
+fn rejected_request(subject: &str, payload: &[u8]) -> (String, String) {
+    let value = serde_json::from_slice::<Value>(payload).ok();
+    let field = |name: &str| {
+        value
+            .as_ref()
+            .and_then(|value| value.get(name))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    (
+        field("id").unwrap_or_else(|| subject.to_string()),
+        field("requested_by").unwrap_or_else(|| "nats".to_string()),
+    )
+}
+
+fn parse_priority(value: Option<&str>) -> Result<JobPriority, AgentError> {
+    match value {
+        None => Ok(JobPriority::default()),
+        Some(value) => JobPriority::parse(value)
+            .ok_or_else(|| AgentError::InvalidAction(format!("unknown priority `{value}`"))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelEvent {
+    id: String,
+}
+
+/// Reply sent for `eco.action.cancel` requests.
+#[derive(Clone, Debug, Serialize)]
+pub struct CancelOutcome {
+    pub id: String,
+    pub cancelled: bool,
+    pub state: Option<String>,
+    pub message: String,
 }
 
 #[derive(Clone, Debug)]
@@ -104,56 +200,323 @@ pub enum ActionCommand {
     Evaluate(EvaluateJob),
 }
 
+impl ActionCommand {
+    fn id(&self) -> &str {
+        match self {
+            ActionCommand::Evaluate(job) => &job.id,
+        }
+    }
+
+    /// Action kind used for permissions and rate limits.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ActionCommand::Evaluate(_) => EVALUATE_ACTION,
+        }
+    }
+
+    pub fn requested_by(&self) -> &str {
+        match self {
+            ActionCommand::Evaluate(job) => &job.requested_by,
+        }
+    }
+}
+
+/// Action ids seen within the deduplication window, with who asked and the
+/// outcome of each once it is known.
+struct RecentActions {
+    window: Duration,
+    entries: HashMap<String, RecentAction>,
+}
+
 #[derive(Clone)]
+struct RecentAction {
+    seen_at: Instant,
+    requested_by: String,
+    outcome: watch::Receiver<Option<ActionOutcome>>,
+}
+
+enum Claim {
+    New(watch::Sender<Option<ActionOutcome>>),
+    Duplicate(RecentAction),
+}
+
+impl RecentActions {
+    fn claim(&mut self, id: &str, requested_by: &str) -> Claim {
+        let now = Instant::now();
+        let window = self.window;
+        self.entries
+            .retain(|_, action| now.duration_since(action.seen_at) < window);
+        if let Some(action) = self.entries.get(id) {
+            return Claim::Duplicate(action.clone());
+        }
+        let (sender, receiver) = watch::channel(None);
+        self.entries.insert(
+            id.to_string(),
+            RecentAction {
+                seen_at: now,
+                requested_by: requested_by.to_string(),
+                outcome: receiver,
+            },
+        );
+        Claim::New(sender)
+    }
+
+    /// Forgets an action that was turned away before it ran, so a retry
+    /// under the same id is handled afresh.
+    fn release(&mut self, id: &str) {
+        self.entries.remove(id);
+    }
+}
+
 pub struct ActionProcessor<P: ActionResultPublisher> {
     executor: JobExecutor,
     publisher: Arc<P>,
+    limits: RateLimiter,
+    recent: Arc<Mutex<RecentActions>>,
+}
+
+impl<P: ActionResultPublisher> Clone for ActionProcessor<P> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: self.executor.clone(),
+            publisher: self.publisher.clone(),
+            limits: self.limits.clone(),
+            recent: self.recent.clone(),
+        }
+    }
 }
 
 impl<P: ActionResultPublisher> ActionProcessor<P> {
-    pub fn new(executor: JobExecutor, publisher: Arc<P>) -> Self {
+    pub fn new(
+        executor: JobExecutor,
+        publisher: Arc<P>,
+        limits: RateLimiter,
+        dedup_window: Duration,
+    ) -> Self {
         Self {
             executor,
             publisher,
+            limits,
+            recent: Arc::new(Mutex::new(RecentActions {
+                window: dedup_window,
+                entries: HashMap::new(),
+            })),
         }
     }
 
-    pub async fn handle_action_event(&self, event: ActionEvent) -> Result<(), AgentError> {
+    /// Handles one `eco.action.*` message. When the message carries a reply
+    /// inbox the outcome is also sent there, including for malformed actions.
+    /// The work is traced under the trace context found in the headers.
+    pub async fn process_message(
+        &self,
+        subject: &str,
+        reply: Option<&str>,
+        headers: Option<&async_nats::HeaderMap>,
+        payload: &[u8],
+    ) -> Result<(), AgentError> {
+        let span = info_span!("action.process", %subject);
+        span.set_parent(telemetry::nats_context(headers));
+        self.handle_message(subject, reply, payload)
+            .instrument(span)
+            .await
+    }
+
+    async fn handle_message(
+        &self,
+        subject: &str,
+        reply: Option<&str>,
+        payload: &[u8],
+    ) -> Result<(), AgentError> {
+        let handled = match subject.strip_prefix("eco.action.") {
+            Some("cast") => match serde_json::from_slice::<ActionEvent>(payload) {
+                Ok(event) => self.handle_action_event(event, reply).await,
+                Err(err) => Err(err.into()),
+            },
+            Some("evaluate") => match serde_json::from_slice::<EvaluateEvent>(payload) {
+                Ok(event) => self.handle_evaluate_event(event, reply).await,
+                Err(err) => Err(err.into()),
+            },
+            Some("cancel") => match serde_json::from_slice::<CancelEvent>(payload) {
+                Ok(event) => self.handle_cancel_event(event, reply).await,
+                Err(err) => Err(err.into()),
+            },
+            Some(other) => {
+                warn!(%other, "unsupported action subject");
+                Ok(())
+            }
+            None => {
+                warn!(%subject, "unexpected subject");
+                Ok(())
+            }
+        };
+        if let (Err(err), Some(inbox)) = (&handled, reply) {
+            let (id, requested_by) = rejected_request(subject, payload);
+            let outcome =
+                ActionOutcome::failure(id, requested_by, format!("invalid action: {err}"));
+            self.reply(inbox, &outcome).await?;
+        }
+        handled
+    }
+
+    pub async fn handle_action_event(
+        &self,
+        event: ActionEvent,
+        reply: Option<&str>,
+    ) -> Result<(), AgentError> {
         if let Some(command) = event.into_command()? {
-            let outcome = self.execute(command).await;
-            self.publish(outcome).await?;
+            self.dispatch(command, reply).await?;
         }
         Ok(())
     }
 
-    async fn handle_evaluate_event(&self, event: EvaluateEvent) -> Result<(), AgentError> {
+    async fn handle_evaluate_event(
+        &self,
+        event: EvaluateEvent,
+        reply: Option<&str>,
+    ) -> Result<(), AgentError> {
         let job = EvaluateJob {
             id: event.id,
             expression: event.expression,
             requested_by: event.requested_by.unwrap_or_else(|| "nats".to_string()),
             model: event.model,
+            priority: parse_priority(event.priority.as_deref())?,
+        };
+        self.dispatch(ActionCommand::Evaluate(job), reply).await
+    }
+
+    async fn handle_cancel_event(
+        &self,
+        event: CancelEvent,
+        reply: Option<&str>,
+    ) -> Result<(), AgentError> {
+        let outcome = match self.executor.cancel(&event.id).await {
+            Ok(record) => {
+                let cancelled = record.state == JobState::Cancelled;
+                let message = if cancelled {
+                    "job cancelled".to_string()
+                } else {
+                    format!("job already {}", record.state.as_str())
+                };
+                CancelOutcome {
+                    id: event.id,
+                    cancelled,
+                    state: Some(record.state.as_str().to_string()),
+                    message,
+                }
+            }
+            Err(AgentError::JobNotFound(_)) => CancelOutcome {
+                id: event.id,
+                cancelled: false,
+                state: None,
+                message: "job not found".to_string(),
+            },
+            Err(err) => return Err(err),
         };
-        let outcome = self.execute(ActionCommand::Evaluate(job)).await;
-        self.publish(outcome).await?;
+        info!(id = %outcome.id, cancelled = outcome.cancelled, "cancel requested");
+        if let Some(inbox) = reply {
+            self.publisher
+                .reply(inbox, serde_json::to_vec(&outcome)?)
+                .await?;
+        }
         Ok(())
     }
 
-    async fn execute(&self, command: ActionCommand) -> ActionOutcome {
+    /// Runs a command unless its id was already seen within the dedup
+    /// window. A duplicate that asks for a reply gets the original outcome.
+    /// Only actions whose job was queued are remembered; one rejected before
+    /// that may be retried under the same id.
+    async fn dispatch(
+        &self,
+        command: ActionCommand,
+        reply: Option<&str>,
+    ) -> Result<(), AgentError> {
+        let claim = self
+            .lock_recent()
+            .claim(command.id(), command.requested_by());
+        match claim {
+            Claim::Duplicate(mut original) => {
+                debug!(id = %command.id(), "duplicate action ignored");
+                if let Some(inbox) = reply {
+                    let outcome = match original.outcome.wait_for(Option::is_some).await {
+                        Ok(outcome) => outcome.clone(),
+                        Err(_) => None,
+                    };
+                    let outcome = outcome.unwrap_or_else(|| {
+                        ActionOutcome::failure(
+                            command.id().to_string(),
+                            original.requested_by,
+                            "original action did not complete".to_string(),
+                        )
+                    });
+                    self.reply(inbox, &outcome).await?;
+                }
+                Ok(())
+            }
+            Claim::New(sender) => {
+                let id = command.id().to_string();
+                let outcome = match self.execute(command).await {
+                    Ok(outcome) => outcome,
+                    Err(rejection) => {
+                        self.lock_recent().release(&id);
+                        rejection
+                    }
+                };
+                sender.send_replace(Some(outcome.clone()));
+                if let Some(inbox) = reply {
+                    self.reply(inbox, &outcome).await?;
+                }
+                self.publish(outcome).await
+            }
+        }
+    }
+
+    /// The outcome of running the command, or `Err` with the failure to
+    /// report when it was rejected before its job was queued.
+    async fn execute(&self, command: ActionCommand) -> Result<ActionOutcome, ActionOutcome> {
+        if let Err(err) = self.limits.check(command.requested_by(), command.kind()) {
+            warn!(?err, id = %command.id(), "action rejected");
+            metrics().record_failure(command.kind(), &err);
+            return Err(ActionOutcome::failure(
+                command.id().to_string(),
+                command.requested_by().to_string(),
+                format!("action rejected: {err}"),
+            ));
+        }
         match command {
             ActionCommand::Evaluate(job) => match self.executor.evaluate(job.clone()).await {
-                Ok(result) => ActionOutcome::success(job, result),
+                Ok(result) => Ok(ActionOutcome::success(job, result)),
                 Err(err) => {
                     warn!(?err, "evaluation failed");
-                    ActionOutcome::failure(
+                    metrics().record_failure(EVALUATE_ACTION, &err);
+                    let rejected = err.rejected_before_recording();
+                    let outcome = ActionOutcome::failure(
                         job.id,
                         job.requested_by,
                         format!("evaluation failed: {err}"),
-                    )
+                    );
+                    if rejected {
+                        Err(outcome)
+                    } else {
+                        Ok(outcome)
+                    }
                 }
             },
         }
     }
 
+    fn lock_recent(&self) -> MutexGuard<'_, RecentActions> {
+        self.recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn reply(&self, inbox: &str, outcome: &ActionOutcome) -> Result<(), AgentError> {
+        debug!(id = %outcome.id, %inbox, "replying with outcome");
+        self.publisher
+            .reply(inbox, serde_json::to_vec(outcome)?)
+            .await
+    }
+
     async fn publish(&self, outcome: ActionOutcome) -> Result<(), AgentError> {
         debug!(id = %outcome.id, accepted = outcome.accepted, "publishing outcome");
         self.publisher.publish(&outcome).await
@@ -1,6 +1,7 @@
 use crate::error::AgentError;
 use crate::jobs::{EvaluateJob, EvaluationResult};
 use crate::proto::actions::{ActionAck, EvaluateResponse};
+use crate::telemetry;
 use async_trait::async_trait;
 use serde::Serialize;
 use std::sync::Arc;
@@ -78,6 +79,9 @@ fn current_timestamp() -> i64 {
 #[async_trait]
 pub trait ActionResultPublisher: Send + Sync + 'static {
     async fn publish(&self, outcome: &ActionOutcome) -> Result<(), AgentError>;
+
+    /// Sends a JSON payload straight to the reply inbox of a NATS request.
+    async fn reply(&self, inbox: &str, payload: Vec<u8>) -> Result<(), AgentError>;
 }
 
 #[derive(Clone)]
@@ -93,10 +97,20 @@ impl NatsPublisher {
 
 #[async_trait]
 impl ActionResultPublisher for NatsPublisher {
+    #[tracing::instrument(name = "outcome.publish", skip_all, fields(id = %outcome.id))]
     async fn publish(&self, outcome: &ActionOutcome) -> Result<(), AgentError> {
         let subject = format!("eco.action.result.{}", outcome.id);
         let payload = serde_json::to_vec(outcome)?;
-        self.client.publish(subject, payload.into()).await?;
+        self.client
+            .publish_with_headers(subject, telemetry::nats_headers(), payload.into())
+            .await?;
+        Ok(())
+    }
+
+    async fn reply(&self, inbox: &str, payload: Vec<u8>) -> Result<(), AgentError> {
+        self.client
+            .publish_with_headers(inbox.to_string(), telemetry::nats_headers(), payload.into())
+            .await?;
         Ok(())
     }
 }
@@ -104,14 +118,27 @@ impl ActionResultPublisher for NatsPublisher {
 #[derive(Clone, Default)]
 pub struct MockPublisher {
     pub results: Arc<tokio::sync::Mutex<Vec<ActionOutcome>>>,
+    pub replies: Arc<tokio::sync::Mutex<Vec<(String, serde_json::Value)>>>,
+    /// `traceparent` header each result would have been published with.
+    pub trace_parents: Arc<tokio::sync::Mutex<Vec<Option<String>>>>,
 }
 
 #[async_trait]
 impl ActionResultPublisher for MockPublisher {
     async fn publish(&self, outcome: &ActionOutcome) -> Result<(), AgentError> {
         debug!(id = %outcome.id, accepted = outcome.accepted, "mock publish");
+        let trace_parent = telemetry::nats_headers()
+            .get("traceparent")
+            .map(|value| value.to_string());
+        self.trace_parents.lock().await.push(trace_parent);
         let mut guard = self.results.lock().await;
         guard.push(outcome.clone());
         Ok(())
     }
+
+    async fn reply(&self, inbox: &str, payload: Vec<u8>) -> Result<(), AgentError> {
+        let value = serde_json::from_slice(&payload)?;
+        self.replies.lock().await.push((inbox.to_string(), value));
+        Ok(())
+    }
 }
@@ -1,3 +1,8 @@
+use crate::backend::{QuantumBackend, QuantumEvaluation};
+use crate::circuit::Circuit;
+use crate::error::BridgeError;
+use std::ops::ControlFlow;
+
 #[cxx::bridge]
 mod ffi {
     unsafe extern "C++" {
@@ -7,7 +12,10 @@ mod ffi {
         type QuantumResult;
 
         #[namespace = "eco::qpp"]
-        fn evaluate_expression(source: &CxxString) -> UniquePtr<QuantumResult>;
+        fn evaluate_expression(source: &CxxString) -> Result<UniquePtr<QuantumResult>>;
+
+        #[namespace = "eco::qpp"]
+        fn evaluate_steps(source: &CxxString) -> Result<UniquePtr<CxxVector<f64>>>;
 
         #[namespace = "eco::qpp"]
         fn qpp_energy(result: &QuantumResult) -> f64;
@@ -17,18 +25,55 @@ mod ffi {
     }
 }
 
-pub struct QuantumEvaluation {
-    pub energy: f64,
-    pub fidelity: f64,
+/// Evaluates circuits with the C++ Q++ runtime.
+pub struct CppBackend;
+
+impl QuantumBackend for CppBackend {
+    fn name(&self) -> &'static str {
+        "cpp-qpp"
+    }
+
+    fn evaluate(&self, circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError> {
+        evaluate_expression(&circuit.to_string())
+    }
+
+    /// The runtime simulates the whole circuit in one call and returns every
+    /// snapshot at once; `step` sees them afterwards.
+    fn evaluate_steps(
+        &self,
+        circuit: &Circuit,
+        step: &mut dyn FnMut(QuantumEvaluation) -> ControlFlow<()>,
+    ) -> Result<QuantumEvaluation, BridgeError> {
+        cxx::let_cxx_string!(cxx_source = circuit.to_string());
+        let steps = ffi::evaluate_steps(&cxx_source)?;
+        let steps = steps.as_ref().ok_or(BridgeError::NullResult)?;
+        let mut last = None;
+        for pair in steps.as_slice().chunks_exact(2) {
+            let evaluation = checked(pair[0], pair[1])?;
+            last = Some(evaluation);
+            if step(evaluation).is_break() {
+                break;
+            }
+        }
+        last.ok_or(BridgeError::NullResult)
+    }
 }
 
-pub fn evaluate_expression(source: &str) -> Result<QuantumEvaluation, cxx::Exception> {
+/// Evaluates a Q++ expression through the C++ runtime. Exceptions thrown by
+/// the runtime and null results come back as `BridgeError`s instead of
+/// unwinding into Rust.
+pub fn evaluate_expression(source: &str) -> Result<QuantumEvaluation, BridgeError> {
     cxx::let_cxx_string!(cxx_source = source);
-    let result = ffi::evaluate_expression(&cxx_source);
-    let result_ref = result
-        .as_ref()
-        .expect("quantum evaluation returned null pointer");
-    let energy = ffi::qpp_energy(result_ref);
-    let fidelity = ffi::qpp_fidelity(result_ref);
+    let result = ffi::evaluate_expression(&cxx_source)?;
+    let result_ref = result.as_ref().ok_or(BridgeError::NullResult)?;
+    checked(ffi::qpp_energy(result_ref), ffi::qpp_fidelity(result_ref))
+}
+
+fn checked(energy: f64, fidelity: f64) -> Result<QuantumEvaluation, BridgeError> {
+    if !energy.is_finite() || !fidelity.is_finite() {
+        return Err(BridgeError::Numerical(format!(
+            "non-finite result (energy {energy}, fidelity {fidelity})"
+        )));
+    }
     Ok(QuantumEvaluation { energy, fidelity })
 }
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl JobPriority {
+    const ALL_DESCENDING: [JobPriority; 3] =
+        [JobPriority::High, JobPriority::Normal, JobPriority::Low];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(JobPriority::Low),
+            "normal" | "" => Some(JobPriority::Normal),
+            "high" => Some(JobPriority::High),
+            _ => None,
+        }
+    }
+}
+
+/// Bounded queue that serves higher priorities first and rotates between
+/// requesters within a priority so one caller cannot starve the others.
+pub struct FairQueue<T> {
+    capacity: usize,
+    len: usize,
+    levels: HashMap<JobPriority, Level<T>>,
+}
+
+struct Level<T> {
+    rotation: VecDeque<String>,
+    pending: HashMap<String, VecDeque<T>>,
+}
+
+impl<T> Default for Level<T> {
+    fn default() -> Self {
+        Self {
+            rotation: VecDeque::new(),
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl<T> FairQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            len: 0,
+            levels: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Queues an item, handing it back when the queue is already full.
+    pub fn push(&mut self, requester: &str, priority: JobPriority, item: T) -> Result<(), T> {
+        if self.len >= self.capacity {
+            return Err(item);
+        }
+        let level = self.levels.entry(priority).or_default();
+        let pending = level.pending.entry(requester.to_string()).or_default();
+        if pending.is_empty() {
+            level.rotation.push_back(requester.to_string());
+        }
+        pending.push_back(item);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        for priority in JobPriority::ALL_DESCENDING {
+            let Some(level) = self.levels.get_mut(&priority) else {
+                continue;
+            };
+            while let Some(requester) = level.rotation.pop_front() {
+                let Some(pending) = level.pending.get_mut(&requester) else {
+                    continue;
+                };
+                let Some(item) = pending.pop_front() else {
+                    level.pending.remove(&requester);
+                    continue;
+                };
+                if pending.is_empty() {
+                    level.pending.remove(&requester);
+                } else {
+                    level.rotation.push_back(requester);
+                }
+                self.len -= 1;
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    /// Removes every queued item matching `predicate` and returns them.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut removed = Vec::new();
+        for level in self.levels.values_mut() {
+            for pending in level.pending.values_mut() {
+                let mut index = 0;
+                while index < pending.len() {
+                    if predicate(&pending[index]) {
+                        removed.extend(pending.remove(index));
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+            level.pending.retain(|_, pending| !pending.is_empty());
+            let pending = &level.pending;
+            level
+                .rotation
+                .retain(|requester| pending.contains_key(requester));
+        }
+        self.len -= removed.len();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_priorities_are_served_first() {
+        let mut queue = FairQueue::new(8);
+        queue.push("a", JobPriority::Low, 1).unwrap();
+        queue.push("a", JobPriority::High, 2).unwrap();
+        queue.push("a", JobPriority::Normal, 3).unwrap();
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn requesters_take_turns_within_a_priority() {
+        let mut queue = FairQueue::new(8);
+        for item in 0..3 {
+            queue.push("flood", JobPriority::Normal, item).unwrap();
+        }
+        queue.push("quiet", JobPriority::Normal, 10).unwrap();
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(order, vec![0, 10, 1, 2]);
+    }
+
+    #[test]
+    fn full_queue_rejects_and_remove_frees_capacity() {
+        let mut queue = FairQueue::new(2);
+        queue.push("a", JobPriority::Normal, 1).unwrap();
+        queue.push("b", JobPriority::Normal, 2).unwrap();
+        assert_eq!(queue.push("c", JobPriority::High, 3), Err(3));
+
+        assert_eq!(queue.remove_where(|item| *item == 1), vec![1]);
+        assert_eq!(queue.len(), 1);
+        queue.push("c", JobPriority::High, 3).unwrap();
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), Some(2));
+    }
+}
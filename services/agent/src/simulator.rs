@@ -0,0 +1,319 @@
+use crate::backend::{QuantumBackend, QuantumEvaluation};
+use crate::circuit::{Circuit, Gate, Operation};
+use crate::error::BridgeError;
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::ops::{Add, ControlFlow, Mul};
+
+const NORM_TOLERANCE: f64 = 1e-9;
+
+/// Pure-Rust statevector simulator. It follows the same gate conventions and
+/// observables as the C++ runtime in `sdks/cpp-qpp`.
+pub struct StatevectorBackend;
+
+impl QuantumBackend for StatevectorBackend {
+    fn name(&self) -> &'static str {
+        "statevector"
+    }
+
+    fn evaluate(&self, circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError> {
+        let mut state = Statevector::new(qubit_count(circuit));
+        for operation in &circuit.operations {
+            state.apply(operation);
+        }
+        state.observe()
+    }
+
+    fn evaluate_steps(
+        &self,
+        circuit: &Circuit,
+        step: &mut dyn FnMut(QuantumEvaluation) -> ControlFlow<()>,
+    ) -> Result<QuantumEvaluation, BridgeError> {
+        let mut state = Statevector::new(qubit_count(circuit));
+        let mut last = state.observe()?;
+        for operation in &circuit.operations {
+            state.apply(operation);
+            last = state.observe()?;
+            if step(last).is_break() {
+                break;
+            }
+        }
+        Ok(last)
+    }
+}
+
+fn qubit_count(circuit: &Circuit) -> usize {
+    circuit
+        .operations
+        .iter()
+        .flat_map(|operation| operation.qubits.iter())
+        .max()
+        .map_or(1, |qubit| *qubit as usize + 1)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+    const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn from_polar(phase: f64) -> Self {
+        Self::new(phase.cos(), phase.sin())
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+type Matrix = [[Complex; 2]; 2];
+
+fn single_qubit_matrix(gate: Gate, params: &[f64]) -> Matrix {
+    let angle = params.first().copied().unwrap_or_default();
+    let (c, s) = ((angle / 2.0).cos(), (angle / 2.0).sin());
+    let real = |value: f64| Complex::new(value, 0.0);
+    match gate {
+        Gate::H => [
+            [real(FRAC_1_SQRT_2), real(FRAC_1_SQRT_2)],
+            [real(FRAC_1_SQRT_2), real(-FRAC_1_SQRT_2)],
+        ],
+        Gate::X => [[Complex::ZERO, Complex::ONE], [Complex::ONE, Complex::ZERO]],
+        Gate::Y => [
+            [Complex::ZERO, Complex::new(0.0, -1.0)],
+            [Complex::I, Complex::ZERO],
+        ],
+        Gate::Z => [[Complex::ONE, Complex::ZERO], [Complex::ZERO, real(-1.0)]],
+        Gate::S => [[Complex::ONE, Complex::ZERO], [Complex::ZERO, Complex::I]],
+        Gate::T => [
+            [Complex::ONE, Complex::ZERO],
+            [
+                Complex::ZERO,
+                Complex::from_polar(std::f64::consts::FRAC_PI_4),
+            ],
+        ],
+        Gate::Rx => [
+            [real(c), Complex::new(0.0, -s)],
+            [Complex::new(0.0, -s), real(c)],
+        ],
+        Gate::Ry => [[real(c), real(-s)], [real(s), real(c)]],
+        Gate::Rz => [
+            [Complex::from_polar(-angle / 2.0), Complex::ZERO],
+            [Complex::ZERO, Complex::from_polar(angle / 2.0)],
+        ],
+        Gate::Cnot | Gate::Cz | Gate::Swap | Gate::Measure => {
+            unreachable!("{} is not a single-qubit unitary", gate.as_str())
+        }
+    }
+}
+
+struct Statevector {
+    amplitudes: Vec<Complex>,
+}
+
+impl Statevector {
+    fn new(qubits: usize) -> Self {
+        let mut amplitudes = vec![Complex::ZERO; 1 << qubits];
+        amplitudes[0] = Complex::ONE;
+        Self { amplitudes }
+    }
+
+    fn apply(&mut self, operation: &Operation) {
+        let qubits = &operation.qubits;
+        match operation.gate {
+            Gate::Cnot => self.cnot(qubits[0], qubits[1]),
+            Gate::Cz => self.cz(qubits[0], qubits[1]),
+            Gate::Swap => self.swap(qubits[0], qubits[1]),
+            Gate::Measure => self.measure(qubits[0]),
+            gate => self.single(qubits[0], single_qubit_matrix(gate, &operation.params)),
+        }
+    }
+
+    fn single(&mut self, qubit: u32, matrix: Matrix) {
+        let bit = 1usize << qubit;
+        for index in 0..self.amplitudes.len() {
+            if index & bit != 0 {
+                continue;
+            }
+            let a0 = self.amplitudes[index];
+            let a1 = self.amplitudes[index | bit];
+            self.amplitudes[index] = matrix[0][0] * a0 + matrix[0][1] * a1;
+            self.amplitudes[index | bit] = matrix[1][0] * a0 + matrix[1][1] * a1;
+        }
+    }
+
+    fn cnot(&mut self, control: u32, target: u32) {
+        let (control, target) = (1usize << control, 1usize << target);
+        for index in 0..self.amplitudes.len() {
+            if index & control != 0 && index & target == 0 {
+                self.amplitudes.swap(index, index | target);
+            }
+        }
+    }
+
+    fn cz(&mut self, first: u32, second: u32) {
+        let mask = (1usize << first) | (1usize << second);
+        for (index, amplitude) in self.amplitudes.iter_mut().enumerate() {
+            if index & mask == mask {
+                *amplitude = amplitude.scale(-1.0);
+            }
+        }
+    }
+
+    fn swap(&mut self, first: u32, second: u32) {
+        let (first, second) = (1usize << first, 1usize << second);
+        for index in 0..self.amplitudes.len() {
+            if index & first != 0 && index & second == 0 {
+                self.amplitudes.swap(index, index ^ first ^ second);
+            }
+        }
+    }
+
+    /// Collapses the qubit onto its more likely outcome (`0` on a tie) so
+    /// evaluations stay deterministic.
+    fn measure(&mut self, qubit: u32) {
+        let bit = 1usize << qubit;
+        let mut one = 0.0;
+        for (index, amplitude) in self.amplitudes.iter().enumerate() {
+            if index & bit != 0 {
+                one += amplitude.norm_sqr();
+            }
+        }
+        let (keep_set, probability) = if one > 0.5 {
+            (true, one)
+        } else {
+            (false, 1.0 - one)
+        };
+        let factor = 1.0 / probability.sqrt();
+        for (index, amplitude) in self.amplitudes.iter_mut().enumerate() {
+            *amplitude = if (index & bit != 0) == keep_set {
+                amplitude.scale(factor)
+            } else {
+                Complex::ZERO
+            };
+        }
+    }
+
+    fn observe(&self) -> Result<QuantumEvaluation, BridgeError> {
+        let mut norm = 0.0;
+        let mut energy = 0.0;
+        let mut fidelity: f64 = 0.0;
+        for (index, amplitude) in self.amplitudes.iter().enumerate() {
+            let probability = amplitude.norm_sqr();
+            norm += probability;
+            energy += probability * index.count_ones() as f64;
+            fidelity = fidelity.max(probability);
+        }
+        if !energy.is_finite() || !fidelity.is_finite() || (norm - 1.0).abs() > NORM_TOLERANCE {
+            return Err(BridgeError::Numerical(format!(
+                "state lost normalization (norm {norm})"
+            )));
+        }
+        Ok(QuantumEvaluation { energy, fidelity })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> QuantumEvaluation {
+        let circuit = Circuit::parse(source).expect("parse");
+        StatevectorBackend.evaluate(&circuit).expect("evaluate")
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn bell_state_has_two_equally_likely_outcomes() {
+        let result = evaluate("H(q0); CNOT(q0, q1)");
+        assert_close(result.energy, 1.0);
+        assert_close(result.fidelity, 0.5);
+    }
+
+    #[test]
+    fn gates_match_their_textbook_action() {
+        assert_close(evaluate("X(q2)").energy, 1.0);
+        assert_close(evaluate("H(q0); Z(q0); H(q0)").energy, 1.0);
+        assert_close(evaluate("X(q0); SWAP(q0, q1); MEASURE(q1)").energy, 1.0);
+        assert_close(evaluate("RY(1.5707963267948966, q0)").fidelity, 0.5);
+        let rx = evaluate("RX(1, q0)");
+        assert_close(rx.energy, (0.5f64).sin().powi(2));
+        // phases alone do not change the observables
+        assert_close(evaluate("H(q0); S(q0); T(q0); RZ(0.3, q0)").energy, 0.5);
+    }
+
+    #[test]
+    fn measurement_collapses_deterministically() {
+        let result = evaluate("RY(2, q0); MEASURE(q0)");
+        assert_close(result.energy, 1.0);
+        assert_close(result.fidelity, 1.0);
+    }
+
+    #[test]
+    fn steps_match_evaluating_each_prefix() {
+        let circuit =
+            Circuit::parse("H(q0); CNOT(q0, q2); RX(0.7, q1); MEASURE(q0)").expect("parse");
+        let mut steps = Vec::new();
+        let last = StatevectorBackend
+            .evaluate_steps(&circuit, &mut |evaluation| {
+                steps.push(evaluation);
+                ControlFlow::Continue(())
+            })
+            .expect("evaluate");
+        assert_eq!(steps.len(), circuit.operations.len());
+        for (len, step) in (1..=circuit.operations.len()).zip(&steps) {
+            let prefix = Circuit {
+                operations: circuit.operations[..len].to_vec(),
+            };
+            let expected = StatevectorBackend.evaluate(&prefix).expect("evaluate");
+            assert_close(step.energy, expected.energy);
+            assert_close(step.fidelity, expected.fidelity);
+        }
+        assert_close(last.energy, steps[3].energy);
+
+        let mut seen = 0;
+        StatevectorBackend
+            .evaluate_steps(&circuit, &mut |_| {
+                seen += 1;
+                ControlFlow::Break(())
+            })
+            .expect("evaluate");
+        assert_eq!(seen, 1);
+    }
+}
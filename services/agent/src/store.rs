@@ -0,0 +1,359 @@
+use crate::error::AgentError;
+use crate::jobs::{EvaluateJob, EvaluationResult};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    expression TEXT NOT NULL,
+    requested_by TEXT NOT NULL,
+    model TEXT,
+    state TEXT NOT NULL,
+    created_at_ms INTEGER NOT NULL,
+    started_at_ms INTEGER,
+    finished_at_ms INTEGER,
+    energy REAL,
+    fidelity REAL,
+    result_model TEXT,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS jobs_created_at_idx ON jobs (created_at_ms);
+CREATE INDEX IF NOT EXISTS jobs_requested_by_idx ON jobs (requested_by);
+";
+
+/// Recorded on jobs that were still queued or running when the agent stopped.
+pub const INTERRUPTED_ERROR: &str = "agent stopped before the job finished";
+
+const JOB_COLUMNS: &str = "id, expression, requested_by, model, state, created_at_ms, \
+     started_at_ms, finished_at_ms, energy, fidelity, result_model, error";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(JobState::Queued),
+            "running" => Some(JobState::Running),
+            "succeeded" => Some(JobState::Succeeded),
+            "failed" => Some(JobState::Failed),
+            "cancelled" => Some(JobState::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct JobRecord {
+    pub id: String,
+    pub expression: String,
+    pub requested_by: String,
+    pub model: Option<String>,
+    pub state: JobState,
+    pub created_at_ms: i64,
+    pub started_at_ms: Option<i64>,
+    pub finished_at_ms: Option<i64>,
+    pub result: Option<EvaluationResult>,
+    pub error: Option<String>,
+}
+
+impl JobRecord {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let state: String = row.get("state")?;
+        let energy: Option<f64> = row.get("energy")?;
+        let fidelity: Option<f64> = row.get("fidelity")?;
+        let result_model: Option<String> = row.get("result_model")?;
+        let result = match (energy, fidelity, result_model) {
+            (Some(energy), Some(fidelity), Some(model)) => Some(EvaluationResult {
+                energy,
+                fidelity,
+                model,
+            }),
+            _ => None,
+        };
+        Ok(Self {
+            id: row.get("id")?,
+            expression: row.get("expression")?,
+            requested_by: row.get("requested_by")?,
+            model: row.get("model")?,
+            state: JobState::parse(&state).unwrap_or(JobState::Failed),
+            created_at_ms: row.get("created_at_ms")?,
+            started_at_ms: row.get("started_at_ms")?,
+            finished_at_ms: row.get("finished_at_ms")?,
+            result,
+            error: row.get("error")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct JobQuery {
+    pub requested_by: Option<String>,
+    pub state: Option<JobState>,
+    pub limit: usize,
+}
+
+/// Controls how long finished jobs are kept in the store.
+#[derive(Clone, Debug)]
+pub struct RetentionPolicy {
+    pub max_age: Duration,
+    pub max_jobs: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            max_jobs: 10_000,
+        }
+    }
+}
+
+/// SQLite-backed record of every job's lifecycle.
+#[derive(Clone)]
+pub struct JobStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl JobStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AgentError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).map_err(|err| {
+                    AgentError::InvalidConfig(format!(
+                        "unable to create job store directory {}: {err}",
+                        parent.display()
+                    ))
+                })?;
+            }
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, AgentError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, AgentError> {
+        conn.execute_batch(SCHEMA)?;
+        let store = Self {
+            conn: Arc::new(Mutex::new(conn)),
+        };
+        store.fail_interrupted()?;
+        Ok(store)
+    }
+
+    /// Fails jobs a previous process left queued or running. Their queue
+    /// slots and workers died with it, so nothing would ever finish them.
+    fn fail_interrupted(&self) -> Result<usize, AgentError> {
+        let updated = self.conn().execute(
+            "UPDATE jobs SET state = ?1, finished_at_ms = ?2, error = ?3 \
+             WHERE state IN (?4, ?5)",
+            params![
+                JobState::Failed.as_str(),
+                current_timestamp(),
+                INTERRUPTED_ERROR,
+                JobState::Queued.as_str(),
+                JobState::Running.as_str()
+            ],
+        )?;
+        Ok(updated)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records a new job as queued. Fails with `AgentError::DuplicateJob`
+    /// when the id is already taken, so earlier history is never replaced.
+    pub fn enqueue(&self, job: &EvaluateJob) -> Result<(), AgentError> {
+        let inserted = self.conn().execute(
+            "INSERT INTO jobs (id, expression, requested_by, model, state, created_at_ms) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                job.id,
+                job.expression,
+                job.requested_by,
+                job.model,
+                JobState::Queued.as_str(),
+                current_timestamp()
+            ],
+        );
+        match inserted {
+            Ok(_) => Ok(()),
+            Err(rusqlite::Error::SqliteFailure(err, _))
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Err(AgentError::DuplicateJob(job.id.clone()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Drops a job that never made it into the queue, so its id can be
+    /// submitted again.
+    pub fn discard(&self, id: &str) -> Result<(), AgentError> {
+        self.conn().execute(
+            "DELETE FROM jobs WHERE id = ?1 AND state = ?2",
+            params![id, JobState::Queued.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Moves a queued job to running. Returns `false` when the job is no longer
+    /// queued, e.g. because it was cancelled while waiting.
+    pub fn start(&self, id: &str) -> Result<bool, AgentError> {
+        let updated = self.conn().execute(
+            "UPDATE jobs SET state = ?2, started_at_ms = ?3 WHERE id = ?1 AND state = ?4",
+            params![
+                id,
+                JobState::Running.as_str(),
+                current_timestamp(),
+                JobState::Queued.as_str()
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Records the result of a running job. Returns `false` when the job is
+    /// no longer running, e.g. because it was cancelled as it finished.
+    pub fn complete(&self, id: &str, result: &EvaluationResult) -> Result<bool, AgentError> {
+        let updated = self.conn().execute(
+            "UPDATE jobs SET state = ?2, finished_at_ms = ?3, energy = ?4, fidelity = ?5, \
+             result_model = ?6 WHERE id = ?1 AND state = ?7",
+            params![
+                id,
+                JobState::Succeeded.as_str(),
+                current_timestamp(),
+                result.energy,
+                result.fidelity,
+                result.model,
+                JobState::Running.as_str()
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn fail(&self, id: &str, error: &str) -> Result<(), AgentError> {
+        self.conn().execute(
+            "UPDATE jobs SET state = ?2, finished_at_ms = ?3, error = ?4 \
+             WHERE id = ?1 AND state IN (?5, ?6)",
+            params![
+                id,
+                JobState::Failed.as_str(),
+                current_timestamp(),
+                error,
+                JobState::Queued.as_str(),
+                JobState::Running.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Cancels a queued or running job. Jobs that already finished are
+    /// returned unchanged so the caller can report their state.
+    pub fn cancel(&self, id: &str) -> Result<JobRecord, AgentError> {
+        self.conn().execute(
+            "UPDATE jobs SET state = ?2, finished_at_ms = ?3 WHERE id = ?1 AND state IN (?4, ?5)",
+            params![
+                id,
+                JobState::Cancelled.as_str(),
+                current_timestamp(),
+                JobState::Queued.as_str(),
+                JobState::Running.as_str()
+            ],
+        )?;
+        self.get(id)?
+            .ok_or_else(|| AgentError::JobNotFound(id.to_string()))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<JobRecord>, AgentError> {
+        let record = self
+            .conn()
+            .query_row(
+                &format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = ?1"),
+                params![id],
+                JobRecord::from_row,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    pub fn list(&self, query: &JobQuery) -> Result<Vec<JobRecord>, AgentError> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM jobs \
+             WHERE (?1 IS NULL OR requested_by = ?1) AND (?2 IS NULL OR state = ?2) \
+             ORDER BY created_at_ms DESC, id ASC LIMIT ?3"
+        ))?;
+        let rows = statement.query_map(
+            params![
+                query.requested_by,
+                query.state.map(|state| state.as_str()),
+                query.limit as i64
+            ],
+            JobRecord::from_row,
+        )?;
+        let records = rows.collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// Deletes finished jobs that fall outside the retention policy and
+    /// returns their ids.
+    pub fn prune(&self, policy: &RetentionPolicy) -> Result<Vec<String>, AgentError> {
+        let cutoff = current_timestamp() - policy.max_age.as_millis() as i64;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let removed = {
+            let mut statement = tx.prepare(
+                "DELETE FROM jobs WHERE state IN (?1, ?2, ?3) AND (created_at_ms < ?4 OR id IN ( \
+                     SELECT id FROM jobs WHERE state IN (?1, ?2, ?3) \
+                     ORDER BY created_at_ms DESC, id ASC LIMIT -1 OFFSET ?5)) \
+                 RETURNING id",
+            )?;
+            let rows = statement.query_map(
+                params![
+                    JobState::Succeeded.as_str(),
+                    JobState::Failed.as_str(),
+                    JobState::Cancelled.as_str(),
+                    cutoff,
+                    policy.max_jobs as i64
+                ],
+                |row| row.get::<_, String>(0),
+            )?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        tx.commit()?;
+        Ok(removed)
+    }
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
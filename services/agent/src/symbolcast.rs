@@ -1,5 +1,6 @@
 use crate::error::AgentError;
 use crate::proto::symbolcast::{symbol_cast_client::SymbolCastClient, Gesture, PointerEvent};
+use crate::telemetry;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio_stream::iter;
@@ -31,10 +32,12 @@ impl GrpcSymbolCastInvoker {
 
 #[async_trait]
 impl SymbolCastInvoker for GrpcSymbolCastInvoker {
+    #[tracing::instrument(name = "symbolcast.recognize", skip_all, fields(events = events.len()))]
     async fn recognize(&self, events: Vec<PointerEvent>) -> Result<Gesture, AgentError> {
         let mut client = self.client().await?;
-        let stream = iter(events.into_iter());
-        let response = client.recognize(Request::new(stream)).await?.into_inner();
+        let mut request = Request::new(iter(events));
+        telemetry::inject_metadata(request.metadata_mut());
+        let response = client.recognize(request).await?.into_inner();
         Ok(response)
     }
 }
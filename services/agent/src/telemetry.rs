@@ -0,0 +1,140 @@
+use crate::error::AgentError;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, TracerProvider};
+use opentelemetry_sdk::Resource;
+use std::env;
+use tonic::codegen::http;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tracing::{info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+const SERVICE_NAME: &str = "eco-agent";
+
+/// Installs the `tracing` subscriber: logs go to stdout and spans are turned
+/// into OpenTelemetry spans. Spans are exported over OTLP when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set; otherwise they only carry trace
+/// context from callers to the services the agent talks to.
+pub fn init() -> Result<(), AgentError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let config = sdktrace::config().with_resource(Resource::new(vec![KeyValue::new(
+        "service.name",
+        SERVICE_NAME,
+    )]));
+    let tracer = if env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+            .with_trace_config(config)
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .map_err(|err| AgentError::InvalidConfig(format!("OTLP exporter: {err}")))?
+    } else {
+        let provider = TracerProvider::builder().with_config(config).build();
+        let tracer = provider.tracer(SERVICE_NAME);
+        global::set_tracer_provider(provider);
+        tracer
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|err| AgentError::InvalidConfig(format!("tracing subscriber: {err}")))
+}
+
+/// Flushes spans that are still buffered for export.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Span wrapping one incoming gRPC call, continuing the caller's trace when
+/// the request carries W3C trace context. Used as the server `trace_fn`.
+pub fn grpc_request_span(request: &http::Request<()>) -> Span {
+    let span = info_span!(
+        "grpc.request",
+        otel.name = %request.uri().path(),
+        rpc.system = "grpc",
+    );
+    span.set_parent(global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HttpHeaders(request.headers()))
+    }));
+    span
+}
+
+/// Trace context carried in the headers of a NATS message.
+pub fn nats_context(headers: Option<&async_nats::HeaderMap>) -> Context {
+    match headers {
+        Some(headers) => {
+            global::get_text_map_propagator(|propagator| propagator.extract(&NatsHeaders(headers)))
+        }
+        None => Context::new(),
+    }
+}
+
+/// NATS headers carrying the trace context of the current span.
+pub fn nats_headers() -> async_nats::HeaderMap {
+    let mut headers = async_nats::HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut NatsHeadersMut(&mut headers))
+    });
+    headers
+}
+
+/// Adds the trace context of the current span to outgoing gRPC metadata.
+pub fn inject_metadata(metadata: &mut MetadataMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut MetadataInjector(metadata))
+    });
+}
+
+struct HttpHeaders<'a>(&'a http::HeaderMap);
+
+impl Extractor for HttpHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+struct NatsHeaders<'a>(&'a async_nats::HeaderMap);
+
+impl Extractor for NatsHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.iter().map(|(key, _)| key.as_ref()).collect()
+    }
+}
+
+struct NatsHeadersMut<'a>(&'a mut async_nats::HeaderMap);
+
+impl Injector for NatsHeadersMut<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key, value.as_str());
+    }
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value.as_str()),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
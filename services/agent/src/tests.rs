@@ -1,19 +1,88 @@
+use super::auth::{Authenticator, Claims, Permissions};
+use super::backend::{Backends, QuantumBackend, QuantumEvaluation};
+use super::circuit::Circuit;
+use super::error::{AgentError, BridgeError};
 use super::frequency::FrequencyHub;
+use super::gestures::{GestureMap, GestureMapper};
 use super::grpc_service::ActionGrpcService;
-use super::jobs::{job_from_gesture, JobExecutor};
-use super::pipeline::{ActionCommand, ActionEvent, ActionProcessor};
+use super::jobs::{EvaluateJob, ExecutorConfig, JobExecutor};
+use super::limits::{Limit, RateLimiter};
+use super::pipeline::{ActionCommand, ActionEvent, ActionProcessor, DEFAULT_DEDUP_WINDOW};
+use super::proto::actions::{
+    eco_actions_server::EcoActions, CancelJobRequest, EvaluateRequest, FrequencyStreamRequest,
+    GetJobRequest, JobState as ProtoJobState, ListJobsRequest,
+};
 use super::proto::symbolcast::Gesture;
 use super::publisher::MockPublisher;
+use super::queue::JobPriority;
+use super::simulator::StatevectorBackend;
+use super::store::{JobQuery, JobState, JobStore, RetentionPolicy, INTERRUPTED_ERROR};
 use super::symbolcast::MockSymbolCastInvoker;
+use futures::StreamExt;
 use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
+use tonic::{Code, Request};
+
+fn test_executor(frequency: &FrequencyHub) -> JobExecutor {
+    JobExecutor::new(
+        frequency.clone(),
+        JobStore::in_memory().expect("job store"),
+        Backends::builtin(),
+        ExecutorConfig::default(),
+    )
+}
+
+const TEST_SECRET: &str = "test-secret";
+
+fn test_auth() -> Authenticator {
+    Authenticator::new(TEST_SECRET, Permissions::default())
+}
+
+/// Wraps a message in a request carrying a gateway token for `sub`.
+fn authorized<T>(message: T, sub: &str, is_guest: bool) -> Request<T> {
+    let claims = Claims {
+        sub: sub.to_string(),
+        email: format!("{sub}@example.com"),
+        display_name: None,
+        is_guest,
+        exp: 4_102_444_800,
+    };
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+    )
+    .expect("encode token");
+    let mut request = Request::new(message);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {token}").parse().expect("metadata value"),
+    );
+    request
+}
+
+fn test_job(id: &str, requested_by: &str) -> EvaluateJob {
+    EvaluateJob {
+        id: id.to_string(),
+        expression: "H(q0)".to_string(),
+        requested_by: requested_by.to_string(),
+        model: None,
+        priority: JobPriority::Normal,
+    }
+}
 
 #[tokio::test]
 async fn pipeline_publishes_results_for_cast_events() {
-    let frequency = FrequencyHub::new(8);
-    let executor = JobExecutor::new(frequency.clone());
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
     let publisher = Arc::new(MockPublisher::default());
-    let processor = ActionProcessor::new(executor, publisher.clone());
+    let processor = ActionProcessor::new(
+        executor,
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
 
     let event = ActionEvent {
         id: "job-1".to_string(),
@@ -23,7 +92,7 @@ async fn pipeline_publishes_results_for_cast_events() {
     };
 
     processor
-        .handle_action_event(event)
+        .handle_action_event(event, None)
         .await
         .expect("process event");
 
@@ -36,10 +105,179 @@ async fn pipeline_publishes_results_for_cast_events() {
     );
 }
 
+#[tokio::test]
+async fn requests_get_replies_and_duplicates_run_once() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let publisher = Arc::new(MockPublisher::default());
+    let processor = ActionProcessor::new(
+        executor,
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+    let payload = serde_json::to_vec(&json!({ "id": "once", "expression": "X(q0)" })).unwrap();
+
+    for inbox in ["_INBOX.first", "_INBOX.second"] {
+        processor
+            .process_message("eco.action.evaluate", Some(inbox), None, &payload)
+            .await
+            .expect("process request");
+    }
+    processor
+        .process_message("eco.action.evaluate", None, None, &payload)
+        .await
+        .expect("process duplicate");
+
+    assert_eq!(publisher.results.lock().await.len(), 1, "evaluated once");
+    let replies = publisher.replies.lock().await;
+    assert_eq!(replies.len(), 2);
+    for ((inbox, reply), expected) in replies.iter().zip(["_INBOX.first", "_INBOX.second"]) {
+        assert_eq!(inbox, expected);
+        assert_eq!(reply["id"], "once");
+        assert_eq!(reply["accepted"], true);
+        assert_eq!(reply["energy"], 1.0);
+    }
+}
+
+#[tokio::test]
+async fn malformed_requests_get_a_failure_reply() {
+    let frequency = FrequencyHub::new(8, 16);
+    let publisher = Arc::new(MockPublisher::default());
+    let processor = ActionProcessor::new(
+        test_executor(&frequency),
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+
+    let result = processor
+        .process_message("eco.action.cast", Some("_INBOX.bad"), None, b"{not json")
+        .await;
+
+    assert!(matches!(result, Err(AgentError::Serde(_))));
+
+    // A readable request is answered under its own id and requester.
+    let payload = json!({
+        "id": "cast-7",
+        "kind": "qpp.evaluate",
+        "payload": {},
+        "requested_by": "alice",
+    });
+    let result = processor
+        .process_message(
+            "eco.action.cast",
+            Some("_INBOX.missing"),
+            None,
+            &serde_json::to_vec(&payload).unwrap(),
+        )
+        .await;
+    assert!(result.is_err());
+
+    let replies = publisher.replies.lock().await;
+    assert_eq!(replies[0].1["accepted"], false);
+    assert_eq!(replies[0].1["id"], "eco.action.cast");
+    assert_eq!(replies[1].1["id"], "cast-7");
+    assert_eq!(replies[1].1["requested_by"], "alice");
+}
+
+/// Takes long enough per step for a test to cancel it mid-run.
+struct SlowBackend;
+
+impl QuantumBackend for SlowBackend {
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+
+    fn evaluate(&self, circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError> {
+        std::thread::sleep(Duration::from_millis(200));
+        StatevectorBackend.evaluate(circuit)
+    }
+}
+
+#[tokio::test]
+async fn cancel_subject_aborts_running_jobs() {
+    let frequency = FrequencyHub::new(8, 16);
+    let mut backends = Backends::builtin();
+    backends.register(Arc::new(SlowBackend));
+    let executor = JobExecutor::new(
+        frequency.clone(),
+        JobStore::in_memory().expect("job store"),
+        backends,
+        ExecutorConfig::default(),
+    );
+    let publisher = Arc::new(MockPublisher::default());
+    let processor = ActionProcessor::new(
+        executor.clone(),
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+
+    let running = tokio::spawn({
+        let processor = processor.clone();
+        async move {
+            let payload = json!({
+                "id": "long",
+                "expression": "H(q0); H(q1); H(q2); H(q3); H(q4)",
+                "model": "slow",
+            });
+            processor
+                .process_message(
+                    "eco.action.evaluate",
+                    Some("_INBOX.long"),
+                    None,
+                    &serde_json::to_vec(&payload).unwrap(),
+                )
+                .await
+        }
+    });
+    for _ in 0..100 {
+        let record = executor.store().get("long").expect("lookup");
+        if record.is_some_and(|record| record.state == JobState::Running) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    processor
+        .process_message(
+            "eco.action.cancel",
+            Some("_INBOX.cancel"),
+            None,
+            br#"{"id":"long"}"#,
+        )
+        .await
+        .expect("cancel");
+    tokio::time::timeout(Duration::from_secs(1), running)
+        .await
+        .expect("job aborted promptly")
+        .expect("task")
+        .expect("process request");
+
+    let replies = publisher.replies.lock().await;
+    let reply = |inbox: &str| {
+        replies
+            .iter()
+            .find(|(candidate, _)| candidate == inbox)
+            .map(|(_, reply)| reply.clone())
+            .expect("reply sent")
+    };
+    assert_eq!(reply("_INBOX.cancel")["cancelled"], true);
+    assert_eq!(reply("_INBOX.cancel")["state"], "cancelled");
+    let outcome = reply("_INBOX.long");
+    assert_eq!(outcome["accepted"], false);
+    assert!(outcome["message"].as_str().unwrap().contains("cancelled"));
+    let record = executor.store().get("long").expect("lookup").expect("job");
+    assert_eq!(record.state, JobState::Cancelled);
+    let marker = frequency.latest("long").await.expect("marker");
+    assert_eq!(marker.completion, Some(JobState::Cancelled));
+}
+
 #[tokio::test]
 async fn gesture_recognition_triggers_evaluation() {
-    let frequency = FrequencyHub::new(8);
-    let executor = JobExecutor::new(frequency.clone());
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
     let publisher = Arc::new(MockPublisher::default());
     let symbolcast = MockSymbolCastInvoker::default();
     {
@@ -55,14 +293,22 @@ async fn gesture_recognition_triggers_evaluation() {
         publisher.clone(),
         frequency.clone(),
         Arc::new(symbolcast),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
     );
 
-    let job = job_from_gesture(&Gesture {
-        id: "triangle".to_string(),
-        label: "Triangle".to_string(),
-        confidence: 0.94,
-    })
-    .expect("gesture job");
+    let job = GestureMap::builtin()
+        .job_for(
+            None,
+            &Gesture {
+                id: "triangle".to_string(),
+                label: "Triangle".to_string(),
+                confidence: 0.94,
+            },
+        )
+        .expect("render circuit")
+        .expect("gesture job");
 
     let outcome = service
         .run_evaluation_from_command(ActionCommand::Evaluate(job.clone()))
@@ -79,3 +325,961 @@ async fn gesture_recognition_triggers_evaluation() {
     assert_eq!(published.len(), 1, "published outcome for gesture job");
     assert!(published[0].accepted);
 }
+
+#[tokio::test]
+async fn executor_records_job_lifecycle() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+
+    executor
+        .evaluate(test_job("job-1", "tester"))
+        .await
+        .expect("evaluation");
+
+    let record = executor
+        .store()
+        .get("job-1")
+        .expect("store lookup")
+        .expect("job recorded");
+    assert_eq!(record.state, JobState::Succeeded);
+    assert_eq!(record.requested_by, "tester");
+    assert!(record.started_at_ms.is_some());
+    assert!(record.finished_at_ms >= record.started_at_ms);
+    let result = record.result.expect("result stored");
+    assert!(result.energy > 0.0);
+}
+
+#[cfg(feature = "cpp-qpp")]
+#[test]
+fn bridge_failures_become_typed_errors() {
+    use super::qpp_bridge;
+
+    let parse = qpp_bridge::evaluate_expression("H(q0").err();
+    assert!(matches!(parse, Some(BridgeError::Parse(_))));
+    let gate = qpp_bridge::evaluate_expression("H(q0); TOFFOLI(q0, q1, q2)").err();
+    assert!(matches!(gate, Some(BridgeError::UnsupportedGate(gate)) if gate == "TOFFOLI"));
+    let numerical = qpp_bridge::evaluate_expression("RX(inf, q0)").err();
+    assert!(matches!(numerical, Some(BridgeError::Numerical(_))));
+}
+
+#[tokio::test]
+async fn invalid_expressions_are_rejected_before_queueing() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let mut job = test_job("invalid", "tester");
+    job.expression = "H(q0);\nTOFFOLI(q0, q1, q2)".to_string();
+
+    let err = executor.evaluate(job).await.expect_err("rejected");
+    let AgentError::Expression(err) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(err.position.line, 2);
+    assert!(executor.store().get("invalid").expect("lookup").is_none());
+
+    let mut job = test_job("canonical", "tester");
+    job.expression = "H( q0 )\nCNOT(q0,q1)".to_string();
+    executor.evaluate(job).await.expect("evaluation");
+    let record = executor
+        .store()
+        .get("canonical")
+        .expect("lookup")
+        .expect("job recorded");
+    assert_eq!(record.expression, "H(q0); CNOT(q0, q1)");
+}
+
+#[tokio::test]
+async fn invalid_evaluate_requests_are_invalid_arguments() {
+    let frequency = FrequencyHub::new(8, 16);
+    let publisher = Arc::new(MockPublisher::default());
+    let service = ActionGrpcService::new(
+        test_executor(&frequency),
+        publisher.clone(),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
+    );
+    let request = |expression: &str, model: &str| {
+        authorized(
+            EvaluateRequest {
+                job_id: String::new(),
+                expression: expression.to_string(),
+                requested_by: String::new(),
+                model: model.to_string(),
+                priority: 0,
+            },
+            "alice",
+            false,
+        )
+    };
+
+    let err = service
+        .evaluate(request("H(q0);\nTOFFOLI(q0, q1, q2)", ""))
+        .await
+        .expect_err("invalid expression");
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert!(err.message().contains("at 2:1"), "{}", err.message());
+
+    let err = service
+        .evaluate(request("H(q0)", "no-such-model"))
+        .await
+        .expect_err("unknown model");
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert!(err.message().contains("no-such-model"), "{}", err.message());
+    assert!(publisher.results.lock().await.is_empty());
+}
+
+#[cfg(feature = "cpp-qpp")]
+#[test]
+fn backends_agree_on_energy_and_fidelity() {
+    use super::qpp_bridge::CppBackend;
+    use super::simulator::StatevectorBackend;
+
+    let circuits = [
+        "H(q0)",
+        "H(q0); CNOT(q0, q1); Z(q1)",
+        "RX(0.3, q0); RY(-1.2, q1); CZ(q0, q1); T(q1); RZ(2.5, q0)",
+        "X(q0); H(q2); SWAP(q0, q2); S(q1); Y(q1); MEASURE(q2)",
+    ];
+    for source in circuits {
+        let circuit = Circuit::parse(source).expect("parse");
+        let cpp = CppBackend.evaluate(&circuit).expect("cpp backend");
+        let rust = StatevectorBackend.evaluate(&circuit).expect("rust backend");
+        assert!((cpp.energy - rust.energy).abs() < 1e-9, "{source}");
+        assert!((cpp.fidelity - rust.fidelity).abs() < 1e-9, "{source}");
+
+        let mut cpp_steps = Vec::new();
+        CppBackend
+            .evaluate_steps(&circuit, &mut |step| {
+                cpp_steps.push(step);
+                std::ops::ControlFlow::Continue(())
+            })
+            .expect("cpp backend");
+        let mut rust_steps = Vec::new();
+        StatevectorBackend
+            .evaluate_steps(&circuit, &mut |step| {
+                rust_steps.push(step);
+                std::ops::ControlFlow::Continue(())
+            })
+            .expect("rust backend");
+        assert_eq!(cpp_steps.len(), circuit.operations.len(), "{source}");
+        for (cpp, rust) in cpp_steps.iter().zip(&rust_steps) {
+            assert!((cpp.energy - rust.energy).abs() < 1e-9, "{source}");
+            assert!((cpp.fidelity - rust.fidelity).abs() < 1e-9, "{source}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn model_selects_the_backend() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+
+    let mut job = test_job("simulated", "tester");
+    job.model = Some("statevector".to_string());
+    let result = executor.evaluate(job).await.expect("evaluation");
+    assert_eq!(result.model, "statevector");
+    assert!((result.energy - 0.5).abs() < 1e-12);
+
+    let mut job = test_job("unknown", "tester");
+    job.model = Some("analog".to_string());
+    let err = executor.evaluate(job).await.expect_err("unknown model");
+    assert!(matches!(err, AgentError::UnknownModel(model) if model == "analog"));
+}
+
+struct PanickingBackend;
+
+impl QuantumBackend for PanickingBackend {
+    fn name(&self) -> &'static str {
+        "panicking"
+    }
+
+    fn evaluate(&self, _circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError> {
+        panic!("backend exploded")
+    }
+}
+
+#[tokio::test]
+async fn panicking_backends_fail_only_their_job() {
+    let frequency = FrequencyHub::new(8, 16);
+    let mut backends = Backends::builtin();
+    backends.register(Arc::new(PanickingBackend));
+    let executor = JobExecutor::new(
+        frequency,
+        JobStore::in_memory().expect("job store"),
+        backends,
+        ExecutorConfig {
+            workers: 1,
+            ..ExecutorConfig::default()
+        },
+    );
+
+    let mut job = test_job("boom", "tester");
+    job.model = Some("panicking".to_string());
+    let err = executor.evaluate(job).await.expect_err("panic reported");
+    assert!(matches!(err, AgentError::Panic(message) if message == "backend exploded"));
+    let record = executor
+        .store()
+        .get("boom")
+        .expect("lookup")
+        .expect("job recorded");
+    assert_eq!(record.state, JobState::Failed);
+
+    // the single worker survived the panic
+    executor
+        .evaluate(test_job("after", "tester"))
+        .await
+        .expect("evaluation");
+}
+
+/// Cancels the job it evaluates from inside the backend, as a cancel that
+/// lands just as the evaluation finishes would.
+struct CancelledMidRunBackend(JobStore);
+
+impl QuantumBackend for CancelledMidRunBackend {
+    fn name(&self) -> &'static str {
+        "cancelled-mid-run"
+    }
+
+    fn evaluate(&self, circuit: &Circuit) -> Result<QuantumEvaluation, BridgeError> {
+        self.0.cancel("raced").expect("cancel");
+        StatevectorBackend.evaluate(circuit)
+    }
+}
+
+#[tokio::test]
+async fn cancels_racing_completion_are_not_reported_as_success() {
+    let frequency = FrequencyHub::new(8, 16);
+    let store = JobStore::in_memory().expect("job store");
+    let mut backends = Backends::builtin();
+    backends.register(Arc::new(CancelledMidRunBackend(store.clone())));
+    let executor = JobExecutor::new(frequency, store, backends, ExecutorConfig::default());
+
+    let mut job = test_job("raced", "tester");
+    job.model = Some("cancelled-mid-run".to_string());
+    let err = executor.evaluate(job).await.expect_err("cancel wins");
+    assert!(matches!(err, AgentError::Cancelled(id) if id == "raced"));
+    let record = executor
+        .store()
+        .get("raced")
+        .expect("lookup")
+        .expect("job recorded");
+    assert_eq!(record.state, JobState::Cancelled);
+    assert!(record.result.is_none());
+}
+
+#[tokio::test]
+async fn cancelled_jobs_are_not_evaluated() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let store = executor.store().clone();
+    store
+        .enqueue(&test_job("job-2", "tester"))
+        .expect("enqueue");
+
+    let cancelled = store.cancel("job-2").expect("cancel");
+    assert_eq!(cancelled.state, JobState::Cancelled);
+    assert!(
+        !store.start("job-2").expect("start"),
+        "cancelled job stays put"
+    );
+    assert!(store.cancel("missing").is_err());
+}
+
+#[tokio::test]
+async fn retention_prunes_oldest_finished_jobs() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    for index in 0..3 {
+        executor
+            .evaluate(test_job(&format!("job-{index}"), "tester"))
+            .await
+            .expect("evaluation");
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    executor
+        .store()
+        .enqueue(&test_job("queued", "tester"))
+        .expect("enqueue");
+
+    let policy = RetentionPolicy {
+        max_jobs: 1,
+        ..Default::default()
+    };
+    let removed = executor.prune(&policy).await.expect("prune");
+    assert_eq!(removed, 2);
+    assert!(frequency.latest("job-0").await.is_none());
+
+    let remaining = executor
+        .store()
+        .list(&JobQuery {
+            limit: 10,
+            ..Default::default()
+        })
+        .expect("list");
+    let ids: Vec<_> = remaining.iter().map(|job| job.id.as_str()).collect();
+    assert_eq!(ids, vec!["queued", "job-2"]);
+}
+
+#[test]
+fn reopening_the_store_fails_interrupted_jobs_and_keeps_history() {
+    let dir = std::env::temp_dir().join(format!(
+        "eco-agent-store-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    let path = dir.join("jobs.sqlite3");
+    {
+        let store = JobStore::open(&path).expect("job store");
+        store
+            .enqueue(&test_job("queued", "tester"))
+            .expect("enqueue");
+        store
+            .enqueue(&test_job("running", "tester"))
+            .expect("enqueue");
+        assert!(store.start("running").expect("start"));
+        let err = store
+            .enqueue(&test_job("running", "someone-else"))
+            .expect_err("id already taken");
+        assert!(matches!(err, AgentError::DuplicateJob(ref id) if id == "running"));
+    }
+
+    let store = JobStore::open(&path).expect("reopened store");
+    for id in ["queued", "running"] {
+        let record = store.get(id).expect("get").expect("job kept");
+        assert_eq!(record.state, JobState::Failed);
+        assert_eq!(record.requested_by, "tester");
+        assert_eq!(record.error.as_deref(), Some(INTERRUPTED_ERROR));
+        assert!(record.finished_at_ms.is_some());
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn job_status_rpcs_expose_the_store() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let service = ActionGrpcService::new(
+        executor.clone(),
+        Arc::new(MockPublisher::default()),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
+    );
+    executor
+        .evaluate(test_job("done", "alice"))
+        .await
+        .expect("evaluation");
+    executor
+        .store()
+        .enqueue(&test_job("waiting", "bob"))
+        .expect("enqueue");
+
+    let job = service
+        .get_job(authorized(
+            GetJobRequest {
+                job_id: "done".to_string(),
+            },
+            "alice",
+            false,
+        ))
+        .await
+        .expect("get job")
+        .into_inner();
+    assert_eq!(job.state, ProtoJobState::Succeeded as i32);
+    assert!(job.result.is_some());
+
+    let listed = service
+        .list_jobs(authorized(
+            ListJobsRequest {
+                requested_by: "bob".to_string(),
+                state: ProtoJobState::Unspecified as i32,
+                limit: 0,
+            },
+            "alice",
+            false,
+        ))
+        .await
+        .expect("list jobs")
+        .into_inner();
+    assert_eq!(listed.jobs.len(), 1);
+    assert_eq!(
+        listed.jobs[0].job_id, "done",
+        "non-admins only list their own"
+    );
+
+    let err = service
+        .cancel_job(authorized(
+            CancelJobRequest {
+                job_id: "waiting".to_string(),
+            },
+            "alice",
+            false,
+        ))
+        .await
+        .expect_err("only the owner may cancel");
+    assert_eq!(err.code(), Code::PermissionDenied);
+
+    let cancelled = service
+        .cancel_job(authorized(
+            CancelJobRequest {
+                job_id: "waiting".to_string(),
+            },
+            "bob",
+            false,
+        ))
+        .await
+        .expect("cancel job")
+        .into_inner();
+    assert_eq!(cancelled.state, ProtoJobState::Cancelled as i32);
+
+    let err = service
+        .cancel_job(authorized(
+            CancelJobRequest {
+                job_id: "done".to_string(),
+            },
+            "alice",
+            false,
+        ))
+        .await
+        .expect_err("finished job cannot be cancelled");
+    assert_eq!(err.code(), Code::FailedPrecondition);
+
+    let err = service
+        .get_job(authorized(
+            GetJobRequest {
+                job_id: "unknown".to_string(),
+            },
+            "alice",
+            true,
+        ))
+        .await
+        .expect_err("unknown job");
+    assert_eq!(err.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn jobs_are_private_to_their_requester_unless_read_by_an_admin() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let service = ActionGrpcService::new(
+        executor.clone(),
+        Arc::new(MockPublisher::default()),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        Authenticator::new(
+            TEST_SECRET,
+            Permissions::default().with_admins(["root".to_string()]),
+        ),
+        RateLimiter::unlimited(),
+    );
+    executor
+        .evaluate(test_job("alices", "alice"))
+        .await
+        .expect("evaluation");
+    let get = |sub: &str| {
+        authorized(
+            GetJobRequest {
+                job_id: "alices".to_string(),
+            },
+            sub,
+            false,
+        )
+    };
+    let list = |sub: &str| {
+        authorized(
+            ListJobsRequest {
+                requested_by: "alice".to_string(),
+                state: ProtoJobState::Unspecified as i32,
+                limit: 0,
+            },
+            sub,
+            false,
+        )
+    };
+
+    let err = service
+        .get_job(get("bob"))
+        .await
+        .expect_err("not bob's job");
+    assert_eq!(err.code(), Code::PermissionDenied);
+    let err = service
+        .stream_frequencies(authorized(
+            FrequencyStreamRequest {
+                job_id: "alices".to_string(),
+            },
+            "bob",
+            false,
+        ))
+        .await
+        .err()
+        .expect("not bob's job");
+    assert_eq!(err.code(), Code::PermissionDenied);
+    let listed = service.list_jobs(list("bob")).await.expect("list");
+    assert!(listed.into_inner().jobs.is_empty());
+
+    service.get_job(get("alice")).await.expect("own job");
+    service
+        .get_job(get("root"))
+        .await
+        .expect("admin reads any job");
+    let listed = service.list_jobs(list("root")).await.expect("list");
+    assert_eq!(listed.into_inner().jobs[0].job_id, "alices");
+}
+
+#[tokio::test]
+async fn full_queue_rejects_with_resource_exhausted() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = JobExecutor::new(
+        frequency.clone(),
+        JobStore::in_memory().expect("job store"),
+        Backends::builtin(),
+        ExecutorConfig {
+            workers: 0,
+            queue_capacity: 1,
+            ..ExecutorConfig::default()
+        },
+    );
+    let service = ActionGrpcService::new(
+        executor.clone(),
+        Arc::new(MockPublisher::default()),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
+    );
+
+    let waiting = tokio::spawn({
+        let executor = executor.clone();
+        async move { executor.evaluate(test_job("first", "alice")).await }
+    });
+    while executor.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let rendered = super::metrics::metrics().render(&executor);
+    assert!(rendered.contains("eco_agent_queue_depth 1"), "{rendered}");
+
+    let err = service
+        .evaluate(authorized(
+            EvaluateRequest {
+                job_id: "second".to_string(),
+                expression: "H(q0)".to_string(),
+                requested_by: String::new(),
+                model: String::new(),
+                priority: 0,
+            },
+            "bob",
+            false,
+        ))
+        .await
+        .expect_err("queue is full");
+    assert_eq!(err.code(), Code::ResourceExhausted);
+    let rejected = executor.store().get("second").expect("store lookup");
+    assert!(rejected.is_none(), "rejected job leaves no record");
+
+    executor.cancel("first").await.expect("cancel queued job");
+    assert_eq!(executor.queue_depth(), 0);
+    let outcome = waiting.await.expect("join");
+    assert!(matches!(outcome, Err(AgentError::Cancelled(_))));
+}
+
+#[tokio::test]
+async fn rejected_actions_can_be_retried_under_the_same_id() {
+    let executor = JobExecutor::new(
+        FrequencyHub::new(8, 16),
+        JobStore::in_memory().expect("job store"),
+        Backends::builtin(),
+        ExecutorConfig {
+            workers: 0,
+            queue_capacity: 1,
+            ..ExecutorConfig::default()
+        },
+    );
+    let publisher = Arc::new(MockPublisher::default());
+    let processor = ActionProcessor::new(
+        executor.clone(),
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+    let payload = |id: &str, requested_by: &str| {
+        serde_json::to_vec(
+            &json!({ "id": id, "expression": "X(q0)", "requested_by": requested_by }),
+        )
+        .unwrap()
+    };
+    let spawn_request = |id: &str, requested_by: &str, reply: Option<&'static str>| {
+        let processor = processor.clone();
+        let payload = payload(id, requested_by);
+        tokio::spawn(async move {
+            processor
+                .process_message("eco.action.evaluate", reply, None, &payload)
+                .await
+        })
+    };
+
+    let first = spawn_request("first", "alice", None);
+    while executor.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+    processor
+        .process_message(
+            "eco.action.evaluate",
+            Some("_INBOX.full"),
+            None,
+            &payload("retried", "alice"),
+        )
+        .await
+        .expect("rejection replied");
+    assert_eq!(publisher.replies.lock().await[0].1["accepted"], false);
+
+    executor.cancel("first").await.expect("cancel first");
+    first.await.expect("join").expect("first handled");
+    let retried = spawn_request("retried", "alice", None);
+    while executor.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+    executor.cancel("retried").await.expect("cancel retried");
+    retried
+        .await
+        .expect("join")
+        .expect("retry queued and handled");
+
+    // A duplicate of an action that never finished answers for its requester.
+    let abandoned = spawn_request("abandoned", "bob", None);
+    while executor.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+    let duplicate = spawn_request("abandoned", "mallory", Some("_INBOX.duplicate"));
+    tokio::task::yield_now().await;
+    abandoned.abort();
+    duplicate.await.expect("join").expect("duplicate replied");
+    let replies = publisher.replies.lock().await;
+    let (inbox, reply) = replies.last().expect("duplicate reply");
+    assert_eq!(inbox, "_INBOX.duplicate");
+    assert_eq!(reply["accepted"], false);
+    assert_eq!(reply["requested_by"], "bob");
+}
+
+#[tokio::test]
+async fn late_subscribers_replay_the_frequency_series() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let service = ActionGrpcService::new(
+        executor.clone(),
+        Arc::new(MockPublisher::default()),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
+    );
+    let mut job = test_job("series", "tester");
+    job.expression = "H(q0); CNOT(q0, q1); Z(q1)".to_string();
+    executor.evaluate(job).await.expect("evaluation");
+
+    let stream = service
+        .stream_frequencies(authorized(
+            FrequencyStreamRequest {
+                job_id: "series".to_string(),
+            },
+            "tester",
+            true,
+        ))
+        .await
+        .expect("stream")
+        .into_inner();
+    let updates: Vec<_> = stream.map(|update| update.expect("update")).collect().await;
+
+    // one sample when the job starts, one per statement, then the marker
+    assert_eq!(updates.len(), 5);
+    let sequences: Vec<_> = updates.iter().map(|update| update.sequence).collect();
+    assert_eq!(sequences, vec![0, 1, 2, 3, 4]);
+    assert!(updates[..4].iter().all(|update| !update.complete));
+    assert!(updates[1].frequency < updates[3].frequency);
+    let marker = updates.last().expect("marker");
+    assert!(marker.complete);
+    assert_eq!(marker.state, ProtoJobState::Succeeded as i32);
+}
+
+#[tokio::test]
+async fn frequency_history_is_bounded_per_job() {
+    let frequency = FrequencyHub::new(8, 2);
+    for step in 0..4 {
+        frequency.publish("job", step as f64, 0.5).await;
+    }
+    frequency.complete("job", JobState::Failed).await;
+    frequency.publish("job", 9.0, 0.5).await;
+
+    let (replay, _receiver) = frequency.subscribe_job("job").await;
+    let sequences: Vec<_> = replay.iter().map(|sample| sample.sequence).collect();
+    assert_eq!(
+        sequences,
+        vec![3, 4],
+        "oldest samples dropped, none after completion"
+    );
+    assert_eq!(replay[1].completion, Some(JobState::Failed));
+    assert_eq!(replay[1].frequency, 3.0);
+}
+
+#[tokio::test]
+async fn grpc_calls_require_a_permitted_gateway_token() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let service = ActionGrpcService::new(
+        executor.clone(),
+        Arc::new(MockPublisher::default()),
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        RateLimiter::unlimited(),
+    );
+    let request = || EvaluateRequest {
+        job_id: String::new(),
+        expression: "X(q0)".to_string(),
+        requested_by: "spoofed".to_string(),
+        model: String::new(),
+        priority: 0,
+    };
+
+    let err = service
+        .evaluate(Request::new(request()))
+        .await
+        .expect_err("missing token");
+    assert_eq!(err.code(), Code::Unauthenticated);
+
+    let mut forged = Request::new(request());
+    forged.metadata_mut().insert(
+        "authorization",
+        "Bearer not-a-token".parse().expect("metadata value"),
+    );
+    let err = service.evaluate(forged).await.expect_err("invalid token");
+    assert_eq!(err.code(), Code::Unauthenticated);
+
+    let err = service
+        .evaluate(authorized(request(), "guest-1", true))
+        .await
+        .expect_err("guests may not evaluate");
+    assert_eq!(err.code(), Code::PermissionDenied);
+
+    let response = service
+        .evaluate(authorized(request(), "alice", false))
+        .await
+        .expect("member evaluation")
+        .into_inner();
+    let record = executor
+        .store()
+        .get(&response.job_id)
+        .expect("store lookup")
+        .expect("job recorded");
+    assert_eq!(record.requested_by, "alice");
+}
+
+#[tokio::test]
+async fn over_limit_requesters_are_rejected_on_both_paths() {
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let publisher = Arc::new(MockPublisher::default());
+    let limits = RateLimiter::new(
+        [(
+            "qpp.evaluate".to_string(),
+            Limit {
+                rate: 0.001,
+                burst: 1.0,
+                daily: None,
+            },
+        )]
+        .into(),
+    );
+    let processor = ActionProcessor::new(
+        executor.clone(),
+        publisher.clone(),
+        limits.clone(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+    for id in ["first", "second"] {
+        let payload = serde_json::to_vec(
+            &json!({ "id": id, "expression": "X(q0)", "requested_by": "alice" }),
+        )
+        .unwrap();
+        processor
+            .process_message("eco.action.evaluate", None, None, &payload)
+            .await
+            .expect("process request");
+    }
+    {
+        let results = publisher.results.lock().await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].accepted);
+        assert!(!results[1].accepted, "second request over the limit");
+        assert!(results[1].message.contains("rate limit"));
+    }
+
+    let service = ActionGrpcService::new(
+        executor,
+        publisher,
+        frequency,
+        Arc::new(MockSymbolCastInvoker::default()),
+        GestureMapper::new(GestureMap::builtin()),
+        test_auth(),
+        limits,
+    );
+    let request = || EvaluateRequest {
+        job_id: String::new(),
+        expression: "X(q0)".to_string(),
+        requested_by: String::new(),
+        model: String::new(),
+        priority: 0,
+    };
+    let err = service
+        .evaluate(authorized(request(), "alice", false))
+        .await
+        .expect_err("alice is limited across paths");
+    assert_eq!(err.code(), Code::ResourceExhausted);
+    service
+        .evaluate(authorized(request(), "bob", false))
+        .await
+        .expect("other requesters are unaffected");
+}
+
+#[tokio::test]
+async fn traces_follow_actions_from_nats_headers_to_the_outcome() {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporterBuilder;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    opentelemetry::global::set_text_map_propagator(
+        opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+    );
+    let exporter = InMemorySpanExporterBuilder::new().build();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let frequency = FrequencyHub::new(8, 16);
+    let executor = test_executor(&frequency);
+    let publisher = Arc::new(MockPublisher::default());
+    let processor = ActionProcessor::new(
+        executor.clone(),
+        publisher.clone(),
+        RateLimiter::unlimited(),
+        DEFAULT_DEDUP_WINDOW,
+    );
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+    let mut headers = async_nats::HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        format!("00-{trace_id}-00f067aa0ba902b7-01").as_str(),
+    );
+    for (id, expression) in [("traced", "H(q0)"), ("broken", "H(q0")] {
+        let payload = serde_json::to_vec(&json!({ "id": id, "expression": expression })).unwrap();
+        processor
+            .process_message("eco.action.evaluate", None, Some(&headers), &payload)
+            .await
+            .expect("process request");
+    }
+
+    let trace_parents = publisher.trace_parents.lock().await;
+    assert_eq!(trace_parents.len(), 2);
+    assert!(trace_parents[0]
+        .as_deref()
+        .is_some_and(|parent| parent.contains(trace_id)));
+
+    provider.force_flush();
+    let spans = exporter.get_finished_spans().expect("finished spans");
+    for name in ["action.process", "job.evaluate", "job.execute"] {
+        let span = spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("{name} span exported"));
+        assert_eq!(span.span_context.trace_id().to_string(), trace_id);
+    }
+
+    let rendered = super::metrics::metrics().render(&executor);
+    assert!(rendered.lines().any(|line| {
+        line.starts_with("eco_agent_job_run_seconds_count") && line.contains("state=\"succeeded\"")
+    }));
+    assert!(rendered
+        .contains("eco_agent_action_failures_total{kind=\"qpp.evaluate\",reason=\"invalid\"}"));
+    assert!(rendered.contains("eco_agent_queue_depth 0"));
+}
+
+/// An executor with one worker and the slow backend registered.
+fn single_slow_worker() -> JobExecutor {
+    let mut backends = Backends::builtin();
+    backends.register(Arc::new(SlowBackend));
+    JobExecutor::new(
+        FrequencyHub::new(8, 16),
+        JobStore::in_memory().expect("job store"),
+        backends,
+        ExecutorConfig {
+            workers: 1,
+            ..ExecutorConfig::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn shutdown_drains_jobs_until_the_deadline() {
+    let executor = single_slow_worker();
+    let spawn_job = |id: &str, expression: &str| {
+        let executor = executor.clone();
+        let mut job = test_job(id, "alice");
+        job.expression = expression.to_string();
+        job.model = Some("slow".to_string());
+        tokio::spawn(async move { executor.evaluate(job).await })
+    };
+
+    let quick = spawn_job("quick", "H(q0)");
+    while executor.running_jobs() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(executor.shutdown(Duration::from_secs(5)).await, 0);
+    assert!(quick.await.expect("join").is_ok(), "running job finished");
+
+    let err = executor
+        .evaluate(test_job("late", "alice"))
+        .await
+        .expect_err("no new work after shutdown");
+    assert!(matches!(err, AgentError::ShuttingDown));
+
+    let executor = single_slow_worker();
+    let spawn_job = |id: &str| {
+        let executor = executor.clone();
+        let mut job = test_job(id, "alice");
+        job.expression = "H(q0); H(q1); H(q2); H(q3); H(q4)".to_string();
+        job.model = Some("slow".to_string());
+        tokio::spawn(async move { executor.evaluate(job).await })
+    };
+    let running = spawn_job("running");
+    while executor.running_jobs() == 0 {
+        tokio::task::yield_now().await;
+    }
+    let queued = spawn_job("queued");
+    while executor.queue_depth() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(executor.shutdown(Duration::from_millis(50)).await, 2);
+    for task in [running, queued] {
+        let outcome = task.await.expect("join");
+        assert!(matches!(outcome, Err(AgentError::Cancelled(_))));
+    }
+    for id in ["running", "queued"] {
+        let record = executor.store().get(id).expect("lookup").expect("job");
+        assert_eq!(record.state, JobState::Cancelled);
+    }
+}
@@ -0,0 +1,26 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("manifest dir"));
+    let proto_dir = manifest_dir
+        .parent()
+        .expect("services dir")
+        .parent()
+        .expect("repo root")
+        .join("proto");
+
+    tonic_build::configure()
+        .build_server(true)
+        .build_client(false)
+        .compile(
+            &[proto_dir.join("search.proto")],
+            std::slice::from_ref(&proto_dir),
+        )
+        .expect("compile search proto");
+
+    println!(
+        "cargo:rerun-if-changed={}",
+        proto_dir.join("search.proto").display()
+    );
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use deadpool_postgres::Pool;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Claims of the session tokens issued by ethos-gateway.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub email: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub is_guest: bool,
+    pub exp: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("invalid authorization token")]
+    InvalidToken,
+    #[error("failed to load guild memberships: {0}")]
+    Directory(String),
+}
+
+impl AuthError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthError::Directory(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Whose search this is. Anonymous viewers only see public documents;
+/// signed-in viewers also see documents they own or that are shared with
+/// one of their guilds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Viewer {
+    pub user_id: Option<String>,
+    pub guild_ids: Vec<String>,
+}
+
+impl Viewer {
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+}
+
+/// Guilds a user belongs to.
+#[async_trait]
+pub trait GuildDirectory: Send + Sync {
+    async fn guilds_of(&self, user_id: &str) -> Result<Vec<String>, AuthError>;
+}
+
+/// Reads memberships from the gateway database.
+pub struct PostgresGuildDirectory {
+    pool: Pool,
+}
+
+impl PostgresGuildDirectory {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl GuildDirectory for PostgresGuildDirectory {
+    async fn guilds_of(&self, user_id: &str) -> Result<Vec<String>, AuthError> {
+        // Guest and legacy subjects are not users rows, so they have no guilds.
+        let Ok(user_id) = Uuid::parse_str(user_id) else {
+            return Ok(Vec::new());
+        };
+        let client = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| AuthError::Directory(err.to_string()))?;
+        let rows = client
+            .query(
+                "SELECT guild_id FROM memberships WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await
+            .map_err(|err| AuthError::Directory(err.to_string()))?;
+        rows.iter()
+            .map(|row| {
+                row.try_get::<_, Uuid>("guild_id")
+                    .map(|id| id.to_string())
+                    .map_err(|err| AuthError::Directory(err.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Fixed memberships, used when no database is configured.
+#[derive(Clone, Default)]
+pub struct StaticGuildDirectory {
+    memberships: HashMap<String, Vec<String>>,
+}
+
+impl StaticGuildDirectory {
+    #[cfg(test)]
+    pub fn with_member(mut self, user_id: &str, guild_id: &str) -> Self {
+        self.memberships
+            .entry(user_id.to_string())
+            .or_default()
+            .push(guild_id.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl GuildDirectory for StaticGuildDirectory {
+    async fn guilds_of(&self, user_id: &str) -> Result<Vec<String>, AuthError> {
+        Ok(self.memberships.get(user_id).cloned().unwrap_or_default())
+    }
+}
+
+/// Turns the `authorization` header of a search into a [`Viewer`].
+#[derive(Clone)]
+pub struct ViewerResolver {
+    secret: Arc<String>,
+    guilds: Arc<dyn GuildDirectory>,
+}
+
+impl ViewerResolver {
+    pub fn new(secret: impl Into<String>, guilds: Arc<dyn GuildDirectory>) -> Self {
+        Self {
+            secret: Arc::new(secret.into()),
+            guilds,
+        }
+    }
+
+    /// Requests without a header are anonymous; a header that does not hold
+    /// a valid gateway token is rejected instead of being downgraded.
+    pub async fn resolve(&self, authorization: Option<&str>) -> Result<Viewer, AuthError> {
+        let Some(authorization) = authorization else {
+            return Ok(Viewer::anonymous());
+        };
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .ok_or(AuthError::InvalidToken)?;
+        let claims = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret.as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        )
+        .map_err(|_| AuthError::InvalidToken)?
+        .claims;
+        let guild_ids = self.guilds.guilds_of(&claims.sub).await?;
+        Ok(Viewer {
+            user_id: Some(claims.sub),
+            guild_ids,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    #[tokio::test]
+    async fn tokens_resolve_to_members_of_their_guilds() {
+        let guilds = StaticGuildDirectory::default().with_member("member", "guild-1");
+        let viewers = ViewerResolver::new("secret", Arc::new(guilds));
+        let claims = Claims {
+            sub: "member".to_string(),
+            email: "member@example.com".to_string(),
+            display_name: None,
+            is_guest: false,
+            exp: 4_102_444_800,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("token");
+
+        let viewer = viewers
+            .resolve(Some(&format!("Bearer {token}")))
+            .await
+            .expect("viewer");
+        assert_eq!(viewer.user_id.as_deref(), Some("member"));
+        assert_eq!(viewer.guild_ids, vec!["guild-1".to_string()]);
+        assert_eq!(
+            viewers.resolve(None).await.expect("anonymous"),
+            Viewer::anonymous()
+        );
+        assert!(matches!(
+            viewers.resolve(Some(&token)).await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use search_schema::{primary_language, POD_ENTITY_TYPE};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::warn;
+
+use crate::auth::{AuthError, ViewerResolver};
+use crate::paging::SortMode;
+use crate::proto::search::search_server::Search;
+use crate::proto::search::{QueryRequest, WorldCard};
+use crate::search::{SearchError, SearchHit, SearchIndex, SearchMode, SearchRequest};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+/// Cards buffered ahead of a slow client before the search pauses.
+const STREAM_BUFFER: usize = 8;
+
+/// `eco.search.Search` over the Tantivy index written by eco-indexer.
+pub struct SearchService {
+    index: Arc<SearchIndex>,
+    viewers: ViewerResolver,
+}
+
+impl SearchService {
+    pub fn new(index: Arc<SearchIndex>, viewers: ViewerResolver) -> Self {
+        Self { index, viewers }
+    }
+}
+
+#[tonic::async_trait]
+impl Search for SearchService {
+    type QueryStream = ReceiverStream<Result<WorldCard, Status>>;
+
+    /// Streams matching worlds, best first, while the index is still being
+    /// read. A blank query lists worlds. `limit` defaults to 10 and is capped
+    /// at 50. Worlds written in the language of `locale` rank above the
+    /// rest; an empty `locale` states no preference. Callers without a
+    /// gateway token only see public worlds.
+    async fn query(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<Self::QueryStream>, Status> {
+        let authorization = match request.metadata().get("authorization") {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .map_err(|_| Status::unauthenticated("invalid authorization"))?,
+            ),
+            None => None,
+        };
+        let viewer = self
+            .viewers
+            .resolve(authorization)
+            .await
+            .map_err(|err| match err {
+                AuthError::InvalidToken => Status::unauthenticated(err.to_string()),
+                AuthError::Directory(_) => Status::unavailable(err.to_string()),
+            })?;
+        let QueryRequest {
+            query,
+            locale,
+            limit,
+        } = request.into_inner();
+        let language = if locale.trim().is_empty() {
+            None
+        } else {
+            let language = primary_language(&locale)
+                .ok_or_else(|| Status::invalid_argument(format!("invalid locale `{locale}`")))?;
+            Some(language)
+        };
+        let limit = match limit {
+            0 => DEFAULT_LIMIT,
+            limit => (limit as usize).min(MAX_LIMIT),
+        };
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let index = self.index.clone();
+        tokio::task::spawn_blocking(move || {
+            let entity_types = [POD_ENTITY_TYPE.to_string()];
+            let request = SearchRequest {
+                viewer: &viewer,
+                query: &query,
+                limit,
+                entity_types: &entity_types,
+                tags: &[],
+                visibilities: &[],
+                sort: SortMode::Relevance,
+                cursor: None,
+                mode: SearchMode::Keyword,
+                neighbours: &[],
+                language: language.as_deref(),
+            };
+            let result = index.stream(request, |hit| tx.blocking_send(Ok(world_card(hit))).is_ok());
+            if let Err(err) = result {
+                warn!(?err, "world search failed");
+                let _ = tx.blocking_send(Err(search_status(err)));
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// The index does not record scenes or portals yet, so those stay empty.
+fn world_card(hit: SearchHit) -> WorldCard {
+    WorldCard {
+        id: hit.entity_id,
+        name: hit.title.unwrap_or_default(),
+        summary: hit.description.unwrap_or_default(),
+        entry_scene: String::new(),
+        portals: Vec::new(),
+    }
+}
+
+fn search_status(err: SearchError) -> Status {
+    match err {
+        SearchError::Query(err) => Status::invalid_argument(err.to_string()),
+        err => Status::internal(err.to_string()),
+    }
+}
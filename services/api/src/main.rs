@@ -1,22 +1,50 @@
+mod auth;
+mod grpc;
+mod paging;
+mod proto;
 mod search;
+mod semantic;
 mod verification;
 
-use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use auth::Viewer;
+use auth::{GuildDirectory, PostgresGuildDirectory, StaticGuildDirectory, ViewerResolver};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::{routing::get, routing::post, Json, Router};
-use search::{FacetSummary, SearchError, SearchHit, SearchIndex, SearchRequest};
+use axum_extra::extract::Query;
+use grpc::SearchService;
+use paging::SortMode;
+use proto::search::search_server::SearchServer;
+use search::{
+    FacetSummary, SearchError, SearchHit, SearchIndex, SearchMode, SearchRequest, Suggestion,
+};
+use semantic::SemanticSearch;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::net::TcpListener;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
-use verification::{DeliveryChannel, LoggingCodeSender, VerificationConfig, VerificationService};
+use vector_search::{VectorConfig, VectorError};
+use verification::{
+    ChannelCodeSender, CodeSender, CodeTemplates, DeliveryChannel, DeliveryConfigError,
+    HttpSmsCodeSender, LoggingCodeSender, MemoryVerificationStore, PostgresVerificationStore,
+    ProofIssuer, SmsConfig, SmtpCodeSender, SmtpConfig, VerificationConfig, VerificationService,
+    VerificationStore, DEFAULT_PROOF_TTL,
+};
+
+/// How often the index pointer is checked for a newly published version.
+const POINTER_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 #[derive(Clone)]
 struct AppState {
     search: Arc<SearchIndex>,
+    viewers: ViewerResolver,
+    /// `None` when vector search is disabled.
+    semantic: Option<SemanticSearch>,
     verification: VerificationService,
 }
 
@@ -30,6 +58,14 @@ enum ApiError {
     Io(#[from] std::io::Error),
     #[error("server error: {0}")]
     Server(#[from] hyper::Error),
+    #[error("gRPC server error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("invalid database configuration: {0}")]
+    Database(#[from] deadpool_postgres::CreatePoolError),
+    #[error("vector search initialisation failed: {0}")]
+    Vector(#[from] VectorError),
+    #[error(transparent)]
+    Delivery(#[from] DeliveryConfigError),
 }
 
 #[derive(Debug, Serialize)]
@@ -45,23 +81,82 @@ async fn main() -> Result<(), ApiError> {
         .init();
 
     let index_path = std::env::var("ECO_INDEX_PATH").unwrap_or_default();
-    let search = SearchIndex::open(index_path)?;
+    let fuzzy_distance = std::env::var("ECO_SEARCH_FUZZY_DISTANCE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(search::DEFAULT_FUZZY_DISTANCE);
+    let search = SearchIndex::open(index_path)?.with_fuzzy_distance(fuzzy_distance);
+    let vectors = VectorConfig::from_env()?;
+    let vectors_dir = search.version_path().unwrap_or_default();
+    let semantic = vectors
+        .open_store(&vectors_dir)
+        .await?
+        .map(|store| SemanticSearch::new(Arc::new(vectors.embedder()), store));
+    if semantic.is_none() {
+        warn!("vector search disabled; semantic and hybrid queries are rejected");
+    }
+    let pool = match std::env::var("ETHOS_DATABASE_URL").or_else(|_| std::env::var("DATABASE_URL"))
+    {
+        Ok(url) => {
+            let mut pg_config = deadpool_postgres::Config::new();
+            pg_config.url = Some(url);
+            Some(pg_config.create_pool(None, tokio_postgres::NoTls)?)
+        }
+        Err(_) => None,
+    };
+    let configured_jwt_secret = std::env::var("ECO_API_JWT_SECRET")
+        .or_else(|_| std::env::var("ETHOS_JWT_SECRET"))
+        .ok();
+    let jwt_secret = configured_jwt_secret.clone().unwrap_or_else(|| {
+        warn!(
+            "ECO_API_JWT_SECRET and ETHOS_JWT_SECRET are unset; using the development secret \
+             and disabling passwordless login proofs"
+        );
+        "insecure-dev-secret".to_string()
+    });
     let verification_secret =
         std::env::var("ECO_VERIFICATION_SECRET").unwrap_or_else(|_| "local-dev-secret".to_string());
-    let verification_sender = Arc::new(LoggingCodeSender::default());
-    let verification = VerificationService::new(
-        VerificationConfig::default(),
+    let verification_config = VerificationConfig::default();
+    let verification_sender = code_sender(&verification_config)?;
+    let verification_store: Arc<dyn VerificationStore> = match &pool {
+        Some(pool) => Arc::new(PostgresVerificationStore::new(pool.clone())),
+        None => {
+            warn!("no database configured; verification codes are kept in memory");
+            Arc::new(MemoryVerificationStore::default())
+        }
+    };
+    let mut verification = VerificationService::new(
+        verification_config,
         verification_secret.into_bytes(),
         verification_sender,
-    );
+    )
+    .with_store(verification_store);
+    // Signed with the secret ethos-gateway checks its own tokens with, so
+    // never with the well-known development fallback.
+    if let Some(secret) = &configured_jwt_secret {
+        verification = verification.with_proofs(ProofIssuer::new(secret, DEFAULT_PROOF_TTL));
+    }
+    let guilds: Arc<dyn GuildDirectory> = match &pool {
+        Some(pool) => Arc::new(PostgresGuildDirectory::new(pool.clone())),
+        None => {
+            warn!("no database configured; guild-shared documents stay hidden");
+            Arc::new(StaticGuildDirectory::default())
+        }
+    };
+    let viewers = ViewerResolver::new(jwt_secret, guilds);
+    let search = Arc::new(search);
+    spawn_version_follower(search.clone(), semantic.clone(), vectors_dir);
     let state = AppState {
-        search: Arc::new(search),
+        search: search.clone(),
+        viewers: viewers.clone(),
+        semantic,
         verification,
     };
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/query", get(query))
+        .route("/suggest", get(suggest))
         .route("/auth/verification-code", post(request_code))
         .route("/auth/verify", post(verify_code))
         .with_state(state);
@@ -69,12 +164,88 @@ async fn main() -> Result<(), ApiError> {
     let addr: SocketAddr = std::env::var("ECO_API_ADDR")
         .unwrap_or_else(|_| "127.0.0.1:8080".to_string())
         .parse()?;
+    let grpc_addr: SocketAddr = std::env::var("ECO_API_GRPC_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:8090".to_string())
+        .parse()?;
     let listener = TcpListener::bind(addr).await?;
     info!(%addr, "eco-api listening");
-    axum::serve(listener, app.into_make_service()).await?;
+    let grpc = tonic::transport::Server::builder()
+        .accept_http1(true)
+        .add_service(tonic_web::enable(SearchServer::new(SearchService::new(
+            search, viewers,
+        ))))
+        .serve(grpc_addr);
+    info!(%grpc_addr, "eco-api gRPC listening");
+    tokio::try_join!(
+        async {
+            axum::serve(listener, app.into_make_service())
+                .await
+                .map_err(ApiError::from)
+        },
+        async { grpc.await.map_err(ApiError::from) },
+    )?;
     Ok(())
 }
 
+/// Switches queries to index versions the indexer publishes, together with
+/// their vector store. A vector store that fails to open is retried on the
+/// next poll.
+fn spawn_version_follower(
+    search: Arc<SearchIndex>,
+    semantic: Option<SemanticSearch>,
+    mut vectors_dir: PathBuf,
+) {
+    tokio::spawn(async move {
+        let mut poll = tokio::time::interval(POINTER_POLL_INTERVAL);
+        loop {
+            poll.tick().await;
+            let index = search.clone();
+            if let Err(err) = tokio::task::spawn_blocking(move || index.follow_pointer()).await {
+                warn!(?err, "following the published index version failed");
+                continue;
+            }
+            let (Some(semantic), Some(dir)) = (&semantic, search.version_path()) else {
+                continue;
+            };
+            if dir == vectors_dir {
+                continue;
+            }
+            match semantic.follow(&dir).await {
+                Ok(()) => vectors_dir = dir,
+                Err(err) => warn!(
+                    ?err,
+                    ?dir,
+                    "vector store of the published index version cannot be opened"
+                ),
+            }
+        }
+    });
+}
+
+/// Delivers codes over SMTP and the SMS gateway configured through the
+/// environment; a channel without one only logs that a code was sent.
+fn code_sender(config: &VerificationConfig) -> Result<Arc<dyn CodeSender>, ApiError> {
+    let mut templates = CodeTemplates::builtin(config.ttl);
+    if let Ok(dir) = std::env::var("ECO_VERIFICATION_TEMPLATES") {
+        templates = templates.load_dir(std::path::Path::new(&dir))?;
+    }
+    let email: Arc<dyn CodeSender> = match SmtpConfig::from_env()? {
+        Some(smtp) => Arc::new(SmtpCodeSender::new(smtp, templates.clone())?),
+        None => {
+            warn!("ECO_SMTP_HOST is unset; email verification codes are only logged");
+            Arc::new(LoggingCodeSender)
+        }
+    };
+    let sms: Arc<dyn CodeSender> = match SmsConfig::from_env()? {
+        Some(sms) => Arc::new(HttpSmsCodeSender::new(sms, templates)?),
+        None => {
+            warn!("ECO_SMS_URL is unset; SMS verification codes are only logged");
+            Arc::new(LoggingCodeSender)
+        }
+    };
+    Ok(Arc::new(ChannelCodeSender::new(email, sms)))
+}
+
 async fn health() -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok",
@@ -86,6 +257,8 @@ async fn health() -> Json<HealthResponse> {
 struct QueryResponse {
     hits: Vec<SearchHit>,
     facets: FacetSummary,
+    total_hits: usize,
+    next_cursor: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -100,32 +273,70 @@ struct QueryParams {
     tag: Vec<String>,
     #[serde(default)]
     visibility: Vec<String>,
+    #[serde(default)]
+    sort: SortMode,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    mode: SearchMode,
 }
 
+/// Searches the documents the caller may see. Without an `authorization`
+/// header only public documents match; `visibility` only narrows that set.
+/// Pages are walked by passing `next_cursor` back as `cursor` with the same
+/// query and `sort`. `mode=semantic` ranks by embedding similarity and
+/// `mode=hybrid` fuses that with keyword ranking; both need vector search
+/// and only sort by relevance.
 async fn query(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(params): Query<QueryParams>,
 ) -> Result<Json<QueryResponse>, (StatusCode, String)> {
+    let viewer = resolve_viewer(&state, &headers).await?;
     let QueryParams {
         q,
         limit,
         entity_types,
         tag,
         visibility,
+        sort,
+        cursor,
+        mode,
     } = params;
     let query_text = q.unwrap_or_default();
     let limit = limit.unwrap_or(10).clamp(1, 50);
+    let neighbours = match (mode, &state.semantic) {
+        (SearchMode::Keyword, _) => Vec::new(),
+        (_, None) => {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "vector search is not configured".to_string(),
+            ))
+        }
+        (_, Some(semantic)) => semantic.neighbours(&query_text).await.map_err(|err| {
+            warn!(?err, "vector search failed");
+            (StatusCode::SERVICE_UNAVAILABLE, err.to_string())
+        })?,
+    };
     let request = SearchRequest {
+        viewer: &viewer,
         query: &query_text,
         limit,
         entity_types: entity_types.as_slice(),
         tags: tag.as_slice(),
         visibilities: visibility.as_slice(),
+        sort,
+        cursor: cursor.as_deref(),
+        mode,
+        neighbours: &neighbours,
+        language: None,
     };
     match state.search.search(request) {
         Ok(results) => Ok(Json(QueryResponse {
             hits: results.hits,
             facets: results.facets,
+            total_hits: results.total_hits,
+            next_cursor: results.next_cursor,
         })),
         Err(err) => {
             let status = err.status_code();
@@ -135,12 +346,66 @@ async fn query(
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct SuggestResponse {
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SuggestParams {
+    #[serde(default)]
+    q: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Search-as-you-type completions from titles and tags the caller may see.
+async fn suggest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SuggestParams>,
+) -> Result<Json<SuggestResponse>, (StatusCode, String)> {
+    let viewer = resolve_viewer(&state, &headers).await?;
+    let prefix = params.q.unwrap_or_default();
+    let limit = params.limit.unwrap_or(8).clamp(1, 20);
+    match state.search.suggest(&viewer, &prefix, limit) {
+        Ok(suggestions) => Ok(Json(SuggestResponse { suggestions })),
+        Err(err) => {
+            let status = err.status_code();
+            warn!(?err, "suggest query failed");
+            Err((status, err.to_string()))
+        }
+    }
+}
+
+async fn resolve_viewer(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Viewer, (StatusCode, String)> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .map(|value| value.to_str())
+        .transpose()
+        .map_err(|_| {
+            (
+                StatusCode::UNAUTHORIZED,
+                "invalid authorization".to_string(),
+            )
+        })?;
+    state
+        .viewers
+        .resolve(authorization)
+        .await
+        .map_err(|err| (err.status_code(), err.to_string()))
+}
+
 #[derive(Debug, Deserialize)]
 struct VerificationCodeRequest {
     identifier: String,
     channel: DeliveryChannel,
-    #[serde(default = "default_locale")]
-    locale: String,
+    /// Without one, a resend keeps the locale of the code it replaces.
+    #[serde(default)]
+    locale: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -149,17 +414,17 @@ struct VerificationCodeResponse {
     retry_after: u64,
 }
 
-fn default_locale() -> String {
-    "en-US".to_string()
-}
-
 async fn request_code(
     State(state): State<AppState>,
     Json(payload): Json<VerificationCodeRequest>,
 ) -> Result<Json<VerificationCodeResponse>, (StatusCode, String)> {
     match state
         .verification
-        .issue_code(&payload.identifier, payload.channel, &payload.locale)
+        .issue_code(
+            &payload.identifier,
+            payload.channel,
+            payload.locale.as_deref(),
+        )
         .await
     {
         Ok(outcome) => Ok(Json(VerificationCodeResponse {
@@ -184,6 +449,9 @@ struct VerifyCodeRequest {
 struct VerifyCodeResponse {
     verified: bool,
     remaining_attempts: u32,
+    /// Exchanged for a session at ethos-gateway's `/auth/passwordless`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof_token: Option<String>,
 }
 
 async fn verify_code(
@@ -198,6 +466,7 @@ async fn verify_code(
         Ok(outcome) => Ok(Json(VerifyCodeResponse {
             verified: outcome.verified,
             remaining_attempts: outcome.remaining_attempts,
+            proof_token: outcome.proof_token,
         })),
         Err(err) => {
             let status = err.status_code();
@@ -212,12 +481,33 @@ mod tests {
     use axum::body::to_bytes;
     use axum::body::Body;
     use axum::http::Request;
-    use tantivy::{
-        doc,
-        schema::{Facet, STORED, STRING, TEXT},
-    };
+    use search_schema::SearchSchema;
+    use tantivy::{doc, schema::Facet};
     use tower::util::ServiceExt;
 
+    const TEST_SECRET: &str = "test-jwt-secret";
+
+    fn test_viewers() -> ViewerResolver {
+        ViewerResolver::new(TEST_SECRET, Arc::new(StaticGuildDirectory::default()))
+    }
+
+    fn bearer(sub: &str) -> String {
+        let claims = auth::Claims {
+            sub: sub.to_string(),
+            email: format!("{sub}@example.com"),
+            display_name: None,
+            is_guest: false,
+            exp: 4_102_444_800,
+        };
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+        )
+        .expect("token");
+        format!("Bearer {token}")
+    }
+
     #[tokio::test]
     async fn health_route() {
         let Json(resp) = health().await;
@@ -229,10 +519,105 @@ mod tests {
         let search_index = build_test_search_index();
         let state = AppState {
             search: Arc::new(search_index),
+            viewers: test_viewers(),
+            semantic: None,
+            verification: VerificationService::new(
+                VerificationConfig::default(),
+                b"test-secret".to_vec(),
+                Arc::new(LoggingCodeSender),
+            ),
+        };
+        let app = Router::new().route("/query", get(query)).with_state(state);
+        let send = |uri: &str, authorization: Option<String>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            app.clone()
+                .oneshot(request.body(Body::empty()).expect("request"))
+        };
+        let hits = |response: axum::response::Response| async move {
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = to_bytes(response.into_body(), 1024 * 1024)
+                .await
+                .expect("body bytes");
+            let payload: QueryResponse =
+                serde_json::from_slice(&bytes).expect("deserialize response");
+            assert!(!payload.facets.entity_type.is_empty());
+            payload.hits
+        };
+
+        let anonymous = hits(send("/query?q=community", None).await.expect("response")).await;
+        assert_eq!(anonymous.len(), 1);
+        assert_eq!(anonymous[0].doc_id, "pod:1");
+
+        let owner = hits(
+            send("/query?q=community", Some(bearer("creator")))
+                .await
+                .expect("response"),
+        )
+        .await;
+        assert_eq!(owner.len(), 2);
+
+        let widened = send("/query?q=community&visibility=private", None)
+            .await
+            .expect("response");
+        assert_eq!(widened.status(), StatusCode::OK);
+        let bytes = to_bytes(widened.into_body(), 1024 * 1024)
+            .await
+            .expect("body bytes");
+        let payload: QueryResponse = serde_json::from_slice(&bytes).expect("deserialize response");
+        assert!(payload.hits.is_empty());
+
+        let forged = send("/query?q=community", Some("Bearer forged".to_string()))
+            .await
+            .expect("response");
+        assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+        let hybrid = send("/query?q=community&mode=hybrid", None)
+            .await
+            .expect("response");
+        assert_eq!(hybrid.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn semantic_route_ranks_vector_store_neighbours() {
+        use vector_search::{
+            Embedder, HashingEmbedder, LocalVectorStore, VectorPoint, VectorStore,
+        };
+
+        let embedder = Arc::new(HashingEmbedder::default());
+        let store = Arc::new(
+            LocalVectorStore::open(
+                std::env::temp_dir()
+                    .join(format!("eco-api-vectors-{}", uuid::Uuid::new_v4()))
+                    .join("vectors.bin"),
+            )
+            .expect("vector store"),
+        );
+        store
+            .upsert(
+                [
+                    ("pod:1", "Community Pod. A vibrant community hub"),
+                    ("quest:3", "Community Quest. A private quest"),
+                ]
+                .into_iter()
+                .map(|(doc_id, text)| VectorPoint {
+                    doc_id: doc_id.to_string(),
+                    vector: embedder.embed(text),
+                })
+                .collect(),
+            )
+            .await
+            .expect("upsert");
+        let state = AppState {
+            search: Arc::new(build_test_search_index()),
+            viewers: test_viewers(),
+            semantic: Some(SemanticSearch::new(embedder, store)),
             verification: VerificationService::new(
                 VerificationConfig::default(),
                 b"test-secret".to_vec(),
-                Arc::new(LoggingCodeSender::default()),
+                Arc::new(LoggingCodeSender),
             ),
         };
         let app = Router::new().route("/query", get(query)).with_state(state);
@@ -240,82 +625,163 @@ mod tests {
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/query?q=community")
+                    .uri("/query?q=vibrant%20hubs&mode=semantic")
                     .body(Body::empty())
                     .expect("request"),
             )
             .await
             .expect("response");
-
         assert_eq!(response.status(), StatusCode::OK);
         let bytes = to_bytes(response.into_body(), 1024 * 1024)
             .await
             .expect("body bytes");
         let payload: QueryResponse = serde_json::from_slice(&bytes).expect("deserialize response");
-        assert_eq!(payload.hits.len(), 2);
-        assert!(!payload.facets.entity_type.is_empty());
+        let doc_ids: Vec<_> = payload.hits.iter().map(|hit| hit.doc_id.as_str()).collect();
+        assert_eq!(doc_ids, ["pod:1"]);
+    }
+
+    #[tokio::test]
+    async fn suggest_route_completes_visible_titles() {
+        let state = AppState {
+            search: Arc::new(build_test_search_index()),
+            viewers: test_viewers(),
+            semantic: None,
+            verification: VerificationService::new(
+                VerificationConfig::default(),
+                b"test-secret".to_vec(),
+                Arc::new(LoggingCodeSender),
+            ),
+        };
+        let app = Router::new()
+            .route("/suggest", get(suggest))
+            .with_state(state);
+        let send = |authorization: Option<String>| {
+            let mut request = Request::builder().uri("/suggest?q=qu");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            app.clone()
+                .oneshot(request.body(Body::empty()).expect("request"))
+        };
+
+        let mut texts = Vec::new();
+        for authorization in [None, Some(bearer("creator"))] {
+            let response = send(authorization).await.expect("response");
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = to_bytes(response.into_body(), 1024 * 1024)
+                .await
+                .expect("body bytes");
+            let payload: SuggestResponse =
+                serde_json::from_slice(&bytes).expect("deserialize response");
+            texts.push(
+                payload
+                    .suggestions
+                    .into_iter()
+                    .map(|suggestion| suggestion.text)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(texts, [vec![], vec!["Community Quest".to_string()]]);
+    }
+
+    #[tokio::test]
+    async fn search_service_streams_world_cards() {
+        use crate::proto::search::search_server::Search;
+        use crate::proto::search::QueryRequest;
+        use tokio_stream::StreamExt;
+
+        let service = SearchService::new(Arc::new(build_test_search_index()), test_viewers());
+        let response = service
+            .query(tonic::Request::new(QueryRequest {
+                query: "community".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .expect("query");
+        let cards: Vec<_> = response
+            .into_inner()
+            .collect::<Result<_, _>>()
+            .await
+            .expect("cards");
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].id, "1");
+        assert_eq!(cards[0].name, "Community Pod");
+        assert_eq!(cards[0].summary, "A public pod");
+    }
+
+    #[tokio::test]
+    async fn search_service_accepts_locales_and_rejects_malformed_ones() {
+        use crate::proto::search::search_server::Search;
+        use crate::proto::search::QueryRequest;
+        use tokio_stream::StreamExt;
+
+        let service = SearchService::new(Arc::new(build_test_search_index()), test_viewers());
+        let query = |locale: &str| QueryRequest {
+            query: "community".to_string(),
+            locale: locale.to_string(),
+            ..Default::default()
+        };
+        let response = service
+            .query(tonic::Request::new(query("pt_BR")))
+            .await
+            .expect("query");
+        let cards: Vec<_> = response
+            .into_inner()
+            .collect::<Result<_, _>>()
+            .await
+            .expect("cards");
+        assert_eq!(cards.len(), 1);
+
+        let status = service
+            .query(tonic::Request::new(query("portuguese")))
+            .await
+            .expect_err("malformed locale");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
     }
 
     fn build_test_search_index() -> SearchIndex {
-        let mut builder = tantivy::schema::Schema::builder();
-        let doc_id = builder.add_text_field("doc_id", STRING | STORED);
-        let entity_id = builder.add_text_field("entity_id", STRING | STORED);
-        let entity_type = builder.add_text_field("entity_type", STRING | STORED);
-        let entity_type_facet = builder.add_facet_field(
-            "entity_type_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let _owner_id = builder.add_text_field("owner_id", STRING | STORED);
-        let title = builder.add_text_field("title", TEXT | STORED);
-        let description = builder.add_text_field("description", TEXT | STORED);
-        let visibility = builder.add_text_field("visibility", STRING | STORED);
-        let visibility_facet = builder.add_facet_field(
-            "visibility_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let tags = builder.add_text_field("tags", TEXT | STORED);
-        let tag_facet = builder.add_facet_field(
-            "tag_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let status = builder.add_text_field("status", STRING | STORED);
-        let kind = builder.add_text_field("kind", STRING | STORED);
-        let content = builder.add_text_field("content", TEXT);
-        let schema = builder.build();
-        let index = tantivy::Index::create_in_ram(schema);
+        let fields = SearchSchema::build();
+        let index = fields.create_in_ram();
         let mut writer = index.writer(50_000_000).expect("writer");
         writer
             .add_document(doc!(
-                doc_id => "pod:1",
-                entity_id => "1",
-                entity_type => "pod",
-                entity_type_facet => Facet::from("/type/pod"),
-                title => "Community Pod",
-                description => "A public pod",
-                visibility => "public",
-                visibility_facet => Facet::from("/visibility/public"),
-                tags => "community",
-                tag_facet => Facet::from("/tag/community"),
-                status => "published",
-                kind => "pod_snapshot",
-                content => "community pod",
+                fields.doc_id => "pod:1",
+                fields.entity_id => "1",
+                fields.entity_type => "pod",
+                fields.entity_type_facet => Facet::from("/type/pod"),
+                fields.title => "Community Pod",
+                fields.title_suggest => "community pod",
+                fields.title_suggest => "pod",
+                fields.description => "A public pod",
+                fields.visibility => "public",
+                fields.visibility_facet => Facet::from("/visibility/public"),
+                fields.tags => "community",
+                fields.tag_suggest => "community",
+                fields.tag_facet => Facet::from("/tag/community"),
+                fields.status => "published",
+                fields.kind => "pod_snapshot",
+                fields.content => "community pod",
             ))
             .expect("add pod");
         writer
             .add_document(doc!(
-                doc_id => "quest:2",
-                entity_id => "2",
-                entity_type => "quest",
-                entity_type_facet => Facet::from("/type/quest"),
-                title => "Community Quest",
-                description => "A private challenge",
-                visibility => "private",
-                visibility_facet => Facet::from("/visibility/private"),
-                tags => "private",
-                tag_facet => Facet::from("/tag/private"),
-                status => "draft",
-                kind => "quest",
-                content => "community quest",
+                fields.doc_id => "quest:2",
+                fields.entity_id => "2",
+                fields.entity_type => "quest",
+                fields.entity_type_facet => Facet::from("/type/quest"),
+                fields.title => "Community Quest",
+                fields.title_suggest => "community quest",
+                fields.title_suggest => "quest",
+                fields.description => "A private challenge",
+                fields.owner_id => "creator",
+                fields.visibility => "private",
+                fields.visibility_facet => Facet::from("/visibility/private"),
+                fields.tags => "private",
+                fields.tag_suggest => "private",
+                fields.tag_facet => Facet::from("/tag/private"),
+                fields.status => "draft",
+                fields.kind => "quest",
+                fields.content => "community quest",
             ))
             .expect("add quest");
         writer.commit().expect("commit");
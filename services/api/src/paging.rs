@@ -0,0 +1,211 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use search_schema::{DOC_ID_FIELD, TITLE_SORT_FIELD, UPDATED_AT_FIELD};
+use serde::{Deserialize, Serialize};
+use tantivy::collector::TopDocs;
+use tantivy::columnar::StrColumn;
+use tantivy::{DocId, Score, SegmentReader};
+
+/// Order of search results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// Best BM25 score first.
+    #[default]
+    Relevance,
+    /// Most recently updated first; documents without a date come last.
+    Newest,
+    /// Alphabetical by title; untitled documents come last.
+    Title,
+}
+
+/// Value a hit is ranked on under one [`SortMode`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortValue {
+    Relevance(Score),
+    Newest(i64),
+    Title(Option<String>),
+}
+
+impl SortValue {
+    fn mode(&self) -> SortMode {
+        match self {
+            SortValue::Relevance(_) => SortMode::Relevance,
+            SortValue::Newest(_) => SortMode::Newest,
+            SortValue::Title(_) => SortMode::Title,
+        }
+    }
+}
+
+/// Greater means ranked earlier.
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (SortValue::Relevance(a), SortValue::Relevance(b)) => a.partial_cmp(b),
+            (SortValue::Newest(a), SortValue::Newest(b)) => a.partial_cmp(b),
+            (SortValue::Title(a), SortValue::Title(b)) => Some(match (a, b) {
+                (Some(a), Some(b)) => b.cmp(a),
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                (None, None) => Ordering::Equal,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Position of a hit in the result order. Ties on the sort value are broken
+/// by `doc_id`, so every hit has a distinct key and a cursor names exactly
+/// where the next page starts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageKey {
+    value: SortValue,
+    doc_id: String,
+}
+
+impl PartialOrd for PageKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.value.partial_cmp(&other.value)? {
+            Ordering::Equal => Some(other.doc_id.cmp(&self.doc_id)),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl PageKey {
+    /// Key of a hit ranked by `score` outside the Tantivy collector, as in
+    /// semantic and hybrid search.
+    pub fn relevance(score: Score, doc_id: String) -> Self {
+        Self {
+            value: SortValue::Relevance(score),
+            doc_id,
+        }
+    }
+
+    pub fn doc_id(&self) -> &str {
+        &self.doc_id
+    }
+
+    /// Opaque token handed to clients as `next_cursor`.
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("page keys serialize"))
+    }
+
+    /// Decodes a cursor, rejecting tokens issued for another sort mode.
+    pub fn decode(cursor: &str, sort: SortMode) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let key: PageKey = serde_json::from_slice(&bytes).ok()?;
+        (key.value.mode() == sort).then_some(key)
+    }
+}
+
+/// A collected hit: its page key plus the BM25 score reported to clients.
+#[derive(Clone, Debug)]
+pub struct Ranked {
+    pub key: PageKey,
+    pub score: Score,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+/// Collects the `limit` best hits ranked after `after`. Hits at or before
+/// the cursor are scored `None` and dropped by the caller, so collectors run
+/// alongside still see the whole result set.
+pub fn page_collector(
+    sort: SortMode,
+    after: Option<PageKey>,
+    limit: usize,
+) -> impl tantivy::collector::Collector<Fruit = Vec<(Option<Ranked>, tantivy::DocAddress)>> {
+    let after = Arc::new(after);
+    TopDocs::with_limit(limit).tweak_score(move |segment: &SegmentReader| {
+        let fast_fields = segment.fast_fields();
+        let doc_ids = fast_fields.str(DOC_ID_FIELD).ok().flatten();
+        let titles = match sort {
+            SortMode::Title => fast_fields.str(TITLE_SORT_FIELD).ok().flatten(),
+            _ => None,
+        };
+        let dates = match sort {
+            SortMode::Newest => fast_fields.date(UPDATED_AT_FIELD).ok(),
+            _ => None,
+        };
+        let after = after.clone();
+        move |doc: DocId, score: Score| {
+            let value = match sort {
+                SortMode::Relevance => SortValue::Relevance(score),
+                SortMode::Newest => SortValue::Newest(
+                    dates
+                        .as_ref()
+                        .and_then(|column| column.first(doc))
+                        .map(|date| date.into_timestamp_secs())
+                        .unwrap_or(i64::MIN),
+                ),
+                SortMode::Title => {
+                    SortValue::Title(titles.as_ref().and_then(|column| read_str(column, doc)))
+                }
+            };
+            let key = PageKey {
+                value,
+                doc_id: doc_ids
+                    .as_ref()
+                    .and_then(|column| read_str(column, doc))
+                    .unwrap_or_default(),
+            };
+            match after.as_ref() {
+                Some(after) if key.partial_cmp(after) != Some(Ordering::Less) => None,
+                _ => Some(Ranked { key, score }),
+            }
+        }
+    })
+}
+
+pub(crate) fn read_str(column: &StrColumn, doc: DocId) -> Option<String> {
+    let ord = column.term_ords(doc).next()?;
+    let mut value = String::new();
+    column
+        .ord_to_str(ord, &mut value)
+        .ok()?
+        .then_some(value)
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: SortValue, doc_id: &str) -> PageKey {
+        PageKey {
+            value,
+            doc_id: doc_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn keys_order_titles_ascending_and_round_trip_as_cursors() {
+        let alpha = key(SortValue::Title(Some("alpha".to_string())), "pod:2");
+        let beta = key(SortValue::Title(Some("beta".to_string())), "pod:1");
+        let untitled = key(SortValue::Title(None), "pod:0");
+        assert!(alpha > beta);
+        assert!(beta > untitled);
+        let tie = key(SortValue::Relevance(1.5), "pod:1");
+        assert!(tie > key(SortValue::Relevance(1.5), "pod:2"));
+
+        let cursor = beta.encode();
+        assert_eq!(PageKey::decode(&cursor, SortMode::Title), Some(beta));
+        assert_eq!(PageKey::decode(&cursor, SortMode::Newest), None);
+        assert_eq!(PageKey::decode("not a cursor", SortMode::Title), None);
+    }
+}
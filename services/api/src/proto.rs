@@ -0,0 +1,3 @@
+pub mod search {
+    tonic::include_proto!("eco.search");
+}
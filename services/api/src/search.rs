@@ -1,17 +1,44 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use axum::http::StatusCode;
+use search_schema::{
+    IndexLayout, SearchSchema, DOC_ID_FIELD, ENTITY_TYPE_FACET_FIELD, GUILD_FACET_FIELD,
+    SUGGEST_MAX_CHARS, SUGGEST_MIN_CHARS, TAG_FACET_FIELD, VISIBILITY_FACET_FIELD,
+};
 use serde::{Deserialize, Serialize};
-use tantivy::collector::{FacetCollector, FacetCounts, MultiCollector, TopDocs};
-use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery};
-use tantivy::schema::{Facet, Field, IndexRecordOption, Schema, Value as TantivyValue};
-use tantivy::{Document, Index, IndexReader, ReloadPolicy, Term};
+use tantivy::collector::{Count, FacetCollector, FacetCounts, MultiCollector, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, Occur, Query, QueryParser, TermQuery,
+    TermSetQuery,
+};
+use tantivy::schema::{Facet, Field, IndexRecordOption, Value as TantivyValue};
+use tantivy::Score;
+use tantivy::{
+    DocAddress, Document, Index, IndexReader, ReloadPolicy, Searcher, SnippetGenerator, Term,
+};
 use thiserror::Error;
+use tracing::{info, warn};
+use vector_search::Neighbour;
+
+use crate::auth::Viewer;
+use crate::paging::{self, PageKey, SortMode};
+use crate::semantic::reciprocal_rank_fusion;
 
 const DEFAULT_INDEX_PATH: &str = "./.tmp/index";
-const ENTITY_TYPE_FACET_FIELD: &str = "entity_type_facet";
-const TAG_FACET_FIELD: &str = "tag_facet";
-const VISIBILITY_FACET_FIELD: &str = "visibility_facet";
+const PUBLIC_VISIBILITY: &str = "public";
+/// Edit distance allowed between query terms and indexed terms by default.
+pub const DEFAULT_FUZZY_DISTANCE: u8 = 1;
+/// Largest edit distance Tantivy builds Levenshtein automata for.
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+/// Exact matches outrank matches that only exist through a typo.
+const EXACT_MATCH_BOOST: Score = 2.0;
+/// Added to the score of documents in the requested language.
+const LANGUAGE_BOOST: Score = 2.0;
+const SNIPPET_MAX_CHARS: usize = 160;
+/// Keyword hits fused with the semantic neighbours in hybrid mode.
+const FUSION_DEPTH: usize = 100;
 
 #[derive(Debug, Error)]
 pub enum SearchError {
@@ -19,106 +46,97 @@ pub enum SearchError {
     Index(#[from] tantivy::TantivyError),
     #[error("query parse error: {0}")]
     Query(#[from] tantivy::query::QueryParserError),
-    #[error("index schema missing field `{0}`")]
-    MissingField(&'static str),
+    #[error(transparent)]
+    Schema(#[from] search_schema::SchemaError),
+    #[error("index field `{0}` is not a fast field")]
+    NotFast(&'static str),
+    #[error("invalid or expired cursor")]
+    InvalidCursor,
+    #[error("semantic and hybrid results can only be sorted by relevance")]
+    UnsupportedSort,
 }
 
 impl SearchError {
     pub fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+        match self {
+            SearchError::Query(_) | SearchError::InvalidCursor | SearchError::UnsupportedSort => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct SearchIndex {
-    index: Index,
-    reader: IndexReader,
-    fields: SearchFields,
+    /// Where published versions are looked up; `None` for a fixed index.
+    layout: Option<IndexLayout>,
+    active: Arc<RwLock<ActiveIndex>>,
+    fields: SearchSchema,
+    fuzzy_distance: u8,
 }
 
-#[derive(Clone)]
-struct SearchFields {
-    doc_id: Field,
-    entity_id: Field,
-    entity_type: Field,
-    entity_type_facet: Field,
-    owner_id: Field,
-    title: Field,
-    description: Field,
-    visibility: Field,
-    visibility_facet: Field,
-    tags: Field,
-    tag_facet: Field,
-    status: Field,
-    kind: Field,
-    content: Field,
+/// The index version queries currently run against.
+struct ActiveIndex {
+    version: Option<String>,
+    index: Index,
+    reader: IndexReader,
 }
 
-impl SearchFields {
-    fn resolve(schema: &Schema) -> Result<Self, SearchError> {
+impl ActiveIndex {
+    fn open(version: Option<String>, index: Index) -> Result<Self, SearchError> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
         Ok(Self {
-            doc_id: schema
-                .get_field("doc_id")
-                .map_err(|_| SearchError::MissingField("doc_id"))?,
-            entity_id: schema
-                .get_field("entity_id")
-                .map_err(|_| SearchError::MissingField("entity_id"))?,
-            entity_type: schema
-                .get_field("entity_type")
-                .map_err(|_| SearchError::MissingField("entity_type"))?,
-            entity_type_facet: schema
-                .get_field("entity_type_facet")
-                .map_err(|_| SearchError::MissingField("entity_type_facet"))?,
-            owner_id: schema
-                .get_field("owner_id")
-                .map_err(|_| SearchError::MissingField("owner_id"))?,
-            title: schema
-                .get_field("title")
-                .map_err(|_| SearchError::MissingField("title"))?,
-            description: schema
-                .get_field("description")
-                .map_err(|_| SearchError::MissingField("description"))?,
-            visibility: schema
-                .get_field("visibility")
-                .map_err(|_| SearchError::MissingField("visibility"))?,
-            visibility_facet: schema
-                .get_field("visibility_facet")
-                .map_err(|_| SearchError::MissingField("visibility_facet"))?,
-            tags: schema
-                .get_field("tags")
-                .map_err(|_| SearchError::MissingField("tags"))?,
-            tag_facet: schema
-                .get_field("tag_facet")
-                .map_err(|_| SearchError::MissingField("tag_facet"))?,
-            status: schema
-                .get_field("status")
-                .map_err(|_| SearchError::MissingField("status"))?,
-            kind: schema
-                .get_field("kind")
-                .map_err(|_| SearchError::MissingField("kind"))?,
-            content: schema
-                .get_field("content")
-                .map_err(|_| SearchError::MissingField("content"))?,
+            version,
+            index,
+            reader,
         })
     }
+}
 
-    fn query_fields(&self) -> Vec<Field> {
-        vec![self.title, self.description, self.tags, self.content]
-    }
+/// How query text is matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// BM25 over the indexed text, with typo tolerance.
+    #[default]
+    Keyword,
+    /// Nearest embeddings only.
+    Semantic,
+    /// Keyword and semantic rankings merged by reciprocal rank fusion.
+    Hybrid,
 }
 
 pub struct SearchRequest<'a> {
+    pub viewer: &'a Viewer,
     pub query: &'a str,
     pub limit: usize,
     pub entity_types: &'a [String],
     pub tags: &'a [String],
     pub visibilities: &'a [String],
+    pub sort: SortMode,
+    /// `next_cursor` of the previous page, if any.
+    pub cursor: Option<&'a str>,
+    pub mode: SearchMode,
+    /// Vector store neighbours of the query text, closest first. Only read
+    /// in semantic and hybrid mode.
+    pub neighbours: &'a [Neighbour],
+    /// Primary language subtag; documents in it rank above the rest. Only
+    /// read in keyword mode.
+    pub language: Option<&'a str>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResults {
     pub hits: Vec<SearchHit>,
     pub facets: FacetSummary,
+    /// Matches across all pages.
+    pub total_hits: usize,
+    /// Pass back as `cursor` to fetch the following page; `None` on the last.
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -134,6 +152,27 @@ pub struct SearchHit {
     pub tags: Vec<String>,
     pub status: Option<String>,
     pub kind: Option<String>,
+    /// Unix seconds of the last change to the entity.
+    pub updated_at: Option<i64>,
+    /// HTML fragment of the description, or else the title, with the
+    /// matched terms wrapped in `<b>`. Absent when nothing was highlighted.
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Title,
+    Tag,
+}
+
+/// Completion offered while the user is typing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub text: String,
+    pub kind: SuggestionKind,
+    /// Document the title belongs to; tags span documents.
+    pub doc_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -141,6 +180,8 @@ pub struct FacetSummary {
     pub entity_type: Vec<FacetBucket>,
     pub tag: Vec<FacetBucket>,
     pub visibility: Vec<FacetBucket>,
+    /// Guilds the matched documents belong to or are shared with.
+    pub guild: Vec<FacetBucket>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -150,6 +191,8 @@ pub struct FacetBucket {
 }
 
 impl SearchIndex {
+    /// Opens the published version under the index root `path`. Versions
+    /// published later are picked up by [`SearchIndex::follow_pointer`].
     pub fn open(path: impl AsRef<Path>) -> Result<Self, SearchError> {
         let path = path.as_ref();
         let index_path = if path.as_os_str().is_empty() {
@@ -157,48 +200,172 @@ impl SearchIndex {
         } else {
             path.to_path_buf()
         };
-        let index = Index::open_in_dir(index_path)?;
-        let reader = index
-            .reader_builder()
-            .reload_policy(ReloadPolicy::OnCommit)
-            .try_into()?;
-        let schema = index.schema();
-        let fields = SearchFields::resolve(&schema)?;
+        // Only an index of the current schema version has the fields
+        // `SearchSchema::build` hands out.
+        let layout = IndexLayout::new(index_path);
+        let (version, index) = layout.open_current()?;
+        let active = ActiveIndex::open(Some(version), index)?;
         Ok(Self {
-            index,
-            reader,
-            fields,
+            layout: Some(layout),
+            active: Arc::new(RwLock::new(active)),
+            fields: SearchSchema::build(),
+            fuzzy_distance: DEFAULT_FUZZY_DISTANCE,
         })
     }
 
+    /// Sets the edit distance tolerated per query term; 0 disables fuzzy
+    /// matching. Values above [`MAX_FUZZY_DISTANCE`] are capped.
+    pub fn with_fuzzy_distance(mut self, distance: u8) -> Self {
+        self.fuzzy_distance = distance.min(MAX_FUZZY_DISTANCE);
+        self
+    }
+
     pub fn search(&self, request: SearchRequest<'_>) -> Result<SearchResults, SearchError> {
-        if request.query.trim().is_empty()
-            && request.entity_types.is_empty()
-            && request.tags.is_empty()
-            && request.visibilities.is_empty()
-        {
+        // Without query text there is nothing to embed; filters alone list
+        // documents the same way in every mode.
+        if request.mode != SearchMode::Keyword && !request.query.trim().is_empty() {
+            return self.fused_search(request);
+        }
+        let after = request
+            .cursor
+            .map(|cursor| PageKey::decode(cursor, request.sort).ok_or(SearchError::InvalidCursor))
+            .transpose()?;
+        let Some(full_query) = self.build_query(&request)? else {
             return Ok(SearchResults {
                 hits: Vec::new(),
                 facets: FacetSummary::default(),
+                total_hits: 0,
+                next_cursor: None,
             });
-        }
+        };
 
-        self.reader.reload()?;
-        let searcher = self.reader.searcher();
-        let mut parser = QueryParser::for_index(&self.index, self.fields.query_fields());
-        parser.set_conjunction_by_default();
+        let searcher = self.searcher()?;
+        let mut collector = MultiCollector::new();
+        let limit = request.limit.max(1);
+        // One extra hit tells whether another page follows.
+        let page_handle =
+            collector.add_collector(paging::page_collector(request.sort, after, limit + 1));
+        let count_handle = collector.add_collector(Count);
+        let mut type_collector = FacetCollector::for_field(ENTITY_TYPE_FACET_FIELD);
+        type_collector.add_facet(Facet::from("/type"));
+        let mut tag_collector = FacetCollector::for_field(TAG_FACET_FIELD);
+        tag_collector.add_facet(Facet::from("/tag"));
+        let mut visibility_collector = FacetCollector::for_field(VISIBILITY_FACET_FIELD);
+        visibility_collector.add_facet(Facet::from("/visibility"));
+        let mut guild_collector = FacetCollector::for_field(GUILD_FACET_FIELD);
+        guild_collector.add_facet(Facet::from("/guild"));
+        let type_handle = collector.add_collector(type_collector);
+        let tag_handle = collector.add_collector(tag_collector);
+        let visibility_handle = collector.add_collector(visibility_collector);
+        let guild_handle = collector.add_collector(guild_collector);
 
-        let normalized_types = normalize_filters(request.entity_types);
-        let normalized_tags = normalize_filters(request.tags);
-        let normalized_visibility = normalize_filters(request.visibilities);
+        let mut fruits = searcher.search(&*full_query, &collector)?;
+        let mut page: Vec<_> = page_handle
+            .extract(&mut fruits)
+            .into_iter()
+            .filter_map(|(ranked, address)| ranked.map(|ranked| (ranked, address)))
+            .collect();
+        let total_hits = count_handle.extract(&mut fruits);
+        let type_counts = type_handle.extract(&mut fruits);
+        let tag_counts = tag_handle.extract(&mut fruits);
+        let visibility_counts = visibility_handle.extract(&mut fruits);
+        let guild_counts = guild_handle.extract(&mut fruits);
+
+        let next_cursor = if page.len() > limit {
+            page.truncate(limit);
+            page.last().map(|(ranked, _)| ranked.key.encode())
+        } else {
+            None
+        };
+        let snippets = Snippets::new(&searcher, &*full_query, &self.fields)?;
+        let hits = page
+            .into_iter()
+            .map(|(ranked, address)| {
+                self.load_hit(&searcher, ranked.score, address, Some(&snippets))
+            })
+            .collect::<Result<Vec<_>, tantivy::TantivyError>>()?;
+
+        let facets = FacetSummary {
+            entity_type: facet_buckets(&type_counts, "/type"),
+            tag: facet_buckets(&tag_counts, "/tag"),
+            visibility: facet_buckets(&visibility_counts, "/visibility"),
+            guild: facet_buckets(&guild_counts, "/guild"),
+        };
+
+        Ok(SearchResults {
+            hits,
+            facets,
+            total_hits,
+            next_cursor,
+        })
+    }
+
+    /// Runs the same query as [`SearchIndex::search`] without facets and
+    /// hands each hit to `emit` as soon as its stored fields are loaded, best
+    /// first. Stops early once `emit` returns `false`.
+    pub fn stream(
+        &self,
+        request: SearchRequest<'_>,
+        mut emit: impl FnMut(SearchHit) -> bool,
+    ) -> Result<(), SearchError> {
+        let Some(full_query) = self.build_query(&request)? else {
+            return Ok(());
+        };
+
+        let searcher = self.searcher()?;
+        let top_docs = searcher.search(&*full_query, &TopDocs::with_limit(request.limit.max(1)))?;
+        for (score, address) in top_docs {
+            if !emit(self.load_hit(&searcher, score, address, None)?) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Combines the parsed query text with the filters, or `None` when the
+    /// request has neither.
+    fn build_query(
+        &self,
+        request: &SearchRequest<'_>,
+    ) -> Result<Option<Box<dyn Query>>, SearchError> {
+        if request.query.trim().is_empty()
+            && request.entity_types.is_empty()
+            && request.tags.is_empty()
+            && request.visibilities.is_empty()
+        {
+            return Ok(None);
+        }
 
         let base_query: Box<dyn Query> = if request.query.trim().is_empty() {
             Box::new(AllQuery)
         } else {
-            Box::new(parser.parse_query(request.query)?)
+            self.text_query(request.query)?
         };
+        let mut clauses = vec![(Occur::Must, base_query)];
+        if let Some(language) = request.language {
+            let in_language = TermQuery::new(
+                Term::from_field_text(self.fields.language, language),
+                IndexRecordOption::Basic,
+            );
+            clauses.push((
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(Box::new(in_language), LANGUAGE_BOOST)),
+            ));
+        }
+        clauses.extend(self.filters(request));
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
 
-        let mut filters: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    /// Clauses restricting a query to what the request may return.
+    fn filters(&self, request: &SearchRequest<'_>) -> Vec<(Occur, Box<dyn Query>)> {
+        let normalized_types = normalize_filters(request.entity_types);
+        let normalized_tags = normalize_filters(request.tags);
+        let normalized_visibility = normalize_filters(request.visibilities);
+
+        // The access clause always applies, so the client filters below can
+        // only narrow what the viewer may see.
+        let mut filters: Vec<(Occur, Box<dyn Query>)> =
+            vec![(Occur::Must, self.access_filter(request.viewer))];
         if let Some(filter) = build_terms_filter(self.fields.entity_type, &normalized_types) {
             filters.push((Occur::Must, filter));
         }
@@ -208,96 +375,406 @@ impl SearchIndex {
         if let Some(filter) = build_terms_filter(self.fields.visibility, &normalized_visibility) {
             filters.push((Occur::Must, filter));
         }
+        filters
+    }
 
-        let full_query: Box<dyn Query> = if filters.is_empty() {
-            base_query
-        } else {
-            let mut clauses = vec![(Occur::Must, base_query)];
-            clauses.extend(filters.into_iter());
-            Box::new(BooleanQuery::new(clauses))
+    /// Semantic and hybrid search. Neighbours go through the same access
+    /// and client filters as keyword hits, then the surviving rankings are
+    /// fused. The whole fused list is built per request, so cursors are
+    /// positions in it and totals and facets cover exactly what pages walk.
+    fn fused_search(&self, request: SearchRequest<'_>) -> Result<SearchResults, SearchError> {
+        if request.sort != SortMode::Relevance {
+            return Err(SearchError::UnsupportedSort);
+        }
+        let after = request
+            .cursor
+            .map(|cursor| {
+                PageKey::decode(cursor, SortMode::Relevance).ok_or(SearchError::InvalidCursor)
+            })
+            .transpose()?;
+
+        let searcher = self.searcher()?;
+        let mut addresses = HashMap::new();
+        let mut nearby = vec![(
+            Occur::Must,
+            doc_id_set(
+                self.fields.doc_id,
+                request.neighbours.iter().map(|neighbour| &neighbour.doc_id),
+            ),
+        )];
+        nearby.extend(self.filters(&request));
+        let allowed = searcher.search(
+            &BooleanQuery::new(nearby),
+            &TopDocs::with_limit(request.neighbours.len().max(1)),
+        )?;
+        for (_, address) in allowed {
+            addresses.insert(doc_id_at(&searcher, address)?, address);
+        }
+        let semantic: Vec<&Neighbour> = request
+            .neighbours
+            .iter()
+            .filter(|neighbour| addresses.contains_key(&neighbour.doc_id))
+            .collect();
+
+        let keyword_query = self
+            .build_query(&request)?
+            .expect("requests with query text build a query");
+        let mut fused = match request.mode {
+            SearchMode::Hybrid => {
+                let mut keyword = Vec::new();
+                for (_, address) in
+                    searcher.search(&*keyword_query, &TopDocs::with_limit(FUSION_DEPTH))?
+                {
+                    let doc_id = doc_id_at(&searcher, address)?;
+                    addresses.insert(doc_id.clone(), address);
+                    keyword.push(doc_id);
+                }
+                let semantic = semantic
+                    .iter()
+                    .map(|neighbour| neighbour.doc_id.clone())
+                    .collect();
+                reciprocal_rank_fusion(&[keyword, semantic])
+            }
+            _ => semantic
+                .iter()
+                .map(|neighbour| (neighbour.doc_id.clone(), neighbour.score))
+                .collect(),
         };
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
-        let mut collector = MultiCollector::new();
-        let limit = request.limit.max(1);
-        let top_docs_handle = collector.add_collector(TopDocs::with_limit(limit));
+        let total_hits = fused.len();
+        let mut facets = MultiCollector::new();
         let mut type_collector = FacetCollector::for_field(ENTITY_TYPE_FACET_FIELD);
         type_collector.add_facet(Facet::from("/type"));
         let mut tag_collector = FacetCollector::for_field(TAG_FACET_FIELD);
         tag_collector.add_facet(Facet::from("/tag"));
         let mut visibility_collector = FacetCollector::for_field(VISIBILITY_FACET_FIELD);
         visibility_collector.add_facet(Facet::from("/visibility"));
-        let type_handle = collector.add_collector(type_collector);
-        let tag_handle = collector.add_collector(tag_collector);
-        let visibility_handle = collector.add_collector(visibility_collector);
-
-        let mut fruits = searcher.search(&*full_query, &collector)?;
-        let top_docs = top_docs_handle.extract(&mut fruits);
-        let type_counts = type_handle.extract(&mut fruits);
-        let tag_counts = tag_handle.extract(&mut fruits);
-        let visibility_counts = visibility_handle.extract(&mut fruits);
+        let mut guild_collector = FacetCollector::for_field(GUILD_FACET_FIELD);
+        guild_collector.add_facet(Facet::from("/guild"));
+        let type_handle = facets.add_collector(type_collector);
+        let tag_handle = facets.add_collector(tag_collector);
+        let visibility_handle = facets.add_collector(visibility_collector);
+        let guild_handle = facets.add_collector(guild_collector);
+        let matched = doc_id_set(self.fields.doc_id, fused.iter().map(|(doc_id, _)| doc_id));
+        let mut fruits = searcher.search(&*matched, &facets)?;
+        let facets = FacetSummary {
+            entity_type: facet_buckets(&type_handle.extract(&mut fruits), "/type"),
+            tag: facet_buckets(&tag_handle.extract(&mut fruits), "/tag"),
+            visibility: facet_buckets(&visibility_handle.extract(&mut fruits), "/visibility"),
+            guild: facet_buckets(&guild_handle.extract(&mut fruits), "/guild"),
+        };
 
-        let hits = top_docs
+        let limit = request.limit.max(1);
+        let mut page: Vec<(PageKey, f32)> = fused
             .into_iter()
-            .map(|(score, address)| {
-                let doc = searcher.doc(address)?;
-                Ok(SearchHit {
-                    score,
-                    doc_id: extract_first(&doc, self.fields.doc_id).unwrap_or_default(),
-                    entity_id: extract_first(&doc, self.fields.entity_id).unwrap_or_default(),
-                    entity_type: extract_first(&doc, self.fields.entity_type).unwrap_or_default(),
-                    title: extract_first(&doc, self.fields.title),
-                    description: extract_first(&doc, self.fields.description),
-                    owner_id: extract_first(&doc, self.fields.owner_id),
-                    visibility: extract_first(&doc, self.fields.visibility),
-                    tags: extract_all(&doc, self.fields.tags),
-                    status: extract_first(&doc, self.fields.status),
-                    kind: extract_first(&doc, self.fields.kind),
-                })
+            .map(|(doc_id, score)| (PageKey::relevance(score, doc_id), score))
+            .filter(|(key, _)| after.as_ref().is_none_or(|after| key < after))
+            .take(limit + 1)
+            .collect();
+        let next_cursor = if page.len() > limit {
+            page.truncate(limit);
+            page.last().map(|(key, _)| key.encode())
+        } else {
+            None
+        };
+        let snippets = Snippets::new(&searcher, &*keyword_query, &self.fields)?;
+        let hits = page
+            .into_iter()
+            .map(|(key, score)| {
+                let address = addresses[key.doc_id()];
+                self.load_hit(&searcher, score, address, Some(&snippets))
             })
             .collect::<Result<Vec<_>, tantivy::TantivyError>>()?;
 
-        let facets = FacetSummary {
-            entity_type: facet_buckets(&type_counts, "/type"),
-            tag: facet_buckets(&tag_counts, "/tag"),
-            visibility: facet_buckets(&visibility_counts, "/visibility"),
+        Ok(SearchResults {
+            hits,
+            facets,
+            total_hits,
+            next_cursor,
+        })
+    }
+
+    /// Parses the query text strictly and, unless fuzzy matching is off,
+    /// also with every term allowed `fuzzy_distance` edits. Fuzzy matches
+    /// carry a constant score, so the boosted BM25 clause keeps exact
+    /// matches on top.
+    fn text_query(&self, text: &str) -> Result<Box<dyn Query>, SearchError> {
+        let index = self.read_active().index.clone();
+        let mut parser = QueryParser::for_index(&index, self.fields.query_fields());
+        parser.set_conjunction_by_default();
+        let exact = parser.parse_query(text)?;
+        if self.fuzzy_distance == 0 {
+            return Ok(exact);
+        }
+        for field in self.fields.query_fields() {
+            parser.set_field_fuzzy(field, false, self.fuzzy_distance, true);
+        }
+        let fuzzy = parser.parse_query(text)?;
+        Ok(Box::new(BooleanQuery::new(vec![
+            (
+                Occur::Should,
+                Box::new(BoostQuery::new(exact, EXACT_MATCH_BOOST)),
+            ),
+            (Occur::Should, fuzzy),
+        ])))
+    }
+
+    /// Titles and tags visible to `viewer` that have a word starting with
+    /// `prefix`, best matching documents first.
+    pub fn suggest(
+        &self,
+        viewer: &Viewer,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>, SearchError> {
+        let prefix = prefix.trim().to_lowercase();
+        if prefix.chars().count() < SUGGEST_MIN_CHARS {
+            return Ok(Vec::new());
+        }
+        // Longer prefixes are not indexed; matches are re-checked below.
+        let indexed_prefix: String = prefix.chars().take(SUGGEST_MAX_CHARS).collect();
+        let matches = BooleanQuery::new(vec![
+            term_clause(self.fields.title_suggest, &indexed_prefix),
+            term_clause(self.fields.tag_suggest, &indexed_prefix),
+        ]);
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(matches) as Box<dyn Query>),
+            (Occur::Must, self.access_filter(viewer)),
+        ]);
+
+        let searcher = self.searcher()?;
+        let limit = limit.max(1);
+        // Matching documents can repeat a suggestion or only match on a
+        // longer word, so keep paging until the limit is filled.
+        let page_size = limit * 2;
+        let mut offset = 0;
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+        loop {
+            let page =
+                searcher.search(&query, &TopDocs::with_limit(page_size).and_offset(offset))?;
+            let exhausted = page.len() < page_size;
+            offset += page.len();
+            for (_, address) in page {
+                let doc = searcher.doc(address)?;
+                let mut candidates = Vec::new();
+                if let Some(title) = extract_first(&doc, self.fields.title) {
+                    candidates.push((title, SuggestionKind::Title));
+                }
+                candidates.extend(
+                    extract_all(&doc, self.fields.tags)
+                        .into_iter()
+                        .map(|tag| (tag, SuggestionKind::Tag)),
+                );
+                for (text, kind) in candidates {
+                    if !has_word_prefix(&text, &prefix) || !seen.insert((kind, text.to_lowercase()))
+                    {
+                        continue;
+                    }
+                    let doc_id = match kind {
+                        SuggestionKind::Title => extract_first(&doc, self.fields.doc_id),
+                        SuggestionKind::Tag => None,
+                    };
+                    suggestions.push(Suggestion { text, kind, doc_id });
+                    if suggestions.len() == limit {
+                        return Ok(suggestions);
+                    }
+                }
+            }
+            if exhausted {
+                break;
+            }
+        }
+        Ok(suggestions)
+    }
+
+    /// Matches public documents, the viewer's own documents and documents
+    /// shared with the viewer's guilds.
+    fn access_filter(&self, viewer: &Viewer) -> Box<dyn Query> {
+        let mut clauses = vec![term_clause(self.fields.visibility, PUBLIC_VISIBILITY)];
+        if let Some(user_id) = &viewer.user_id {
+            clauses.push(term_clause(self.fields.owner_id, user_id));
+        }
+        for guild_id in &viewer.guild_ids {
+            clauses.push(term_clause(self.fields.guild_ids, guild_id));
+        }
+        Box::new(BooleanQuery::new(clauses))
+    }
+
+    fn load_hit(
+        &self,
+        searcher: &Searcher,
+        score: f32,
+        address: DocAddress,
+        snippets: Option<&Snippets>,
+    ) -> Result<SearchHit, tantivy::TantivyError> {
+        let doc = searcher.doc(address)?;
+        let snippet = snippets.and_then(|snippets| snippets.render(&doc));
+        Ok(SearchHit {
+            score,
+            doc_id: extract_first(&doc, self.fields.doc_id).unwrap_or_default(),
+            entity_id: extract_first(&doc, self.fields.entity_id).unwrap_or_default(),
+            entity_type: extract_first(&doc, self.fields.entity_type).unwrap_or_default(),
+            title: extract_first(&doc, self.fields.title),
+            description: extract_first(&doc, self.fields.description),
+            owner_id: extract_first(&doc, self.fields.owner_id),
+            visibility: extract_first(&doc, self.fields.visibility),
+            tags: extract_all(&doc, self.fields.tags),
+            status: extract_first(&doc, self.fields.status),
+            kind: extract_first(&doc, self.fields.kind),
+            updated_at: doc
+                .get_first(self.fields.updated_at)
+                .and_then(|value| value.as_date())
+                .map(|date| date.into_timestamp_secs()),
+            snippet,
+        })
+    }
+
+    /// A searcher over the published version. Published versions never
+    /// change, so there is nothing to reload.
+    fn searcher(&self) -> Result<Searcher, SearchError> {
+        Ok(self.read_active().reader.searcher())
+    }
+
+    /// Directory of the version queries run against; `None` for a fixed
+    /// index.
+    pub fn version_path(&self) -> Option<PathBuf> {
+        let layout = self.layout.as_ref()?;
+        let active = self.read_active();
+        active
+            .version
+            .as_deref()
+            .map(|version| layout.version_path(version))
+    }
+
+    /// Switches to the version `CURRENT` names if the indexer published
+    /// another one and returns its directory. A version that fails to open
+    /// is logged and the previous one keeps serving. Reads the file system,
+    /// so async callers run it on a blocking thread.
+    pub fn follow_pointer(&self) -> Option<PathBuf> {
+        let layout = self.layout.as_ref()?;
+        let current = match layout.current() {
+            Ok(current) => current?,
+            Err(err) => {
+                warn!(?err, "reading the published index version failed");
+                return None;
+            }
+        };
+        if self.read_active().version.as_deref() == Some(current.as_str()) {
+            return None;
+        }
+        let next = match layout
+            .open_version(&current)
+            .map_err(SearchError::from)
+            .and_then(|index| ActiveIndex::open(Some(current.clone()), index))
+        {
+            Ok(next) => next,
+            Err(err) => {
+                warn!(?err, version = %current, "published index version cannot be opened");
+                return None;
+            }
         };
+        let mut active = self
+            .active
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        info!(from = ?active.version, to = %current, "switched to published index version");
+        *active = next;
+        Some(layout.version_path(&current))
+    }
 
-        Ok(SearchResults { hits, facets })
+    fn read_active(&self) -> std::sync::RwLockReadGuard<'_, ActiveIndex> {
+        self.active
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 
     #[cfg(test)]
     pub(crate) fn from_index(index: Index) -> Result<Self, SearchError> {
-        let reader = index
-            .reader_builder()
-            .reload_policy(ReloadPolicy::Manual)
-            .try_into()?;
-        let schema = index.schema();
-        let fields = SearchFields::resolve(&schema)?;
+        let active = ActiveIndex::open(None, index)?;
         Ok(Self {
-            index,
-            reader,
-            fields,
+            layout: None,
+            active: Arc::new(RwLock::new(active)),
+            fields: SearchSchema::build(),
+            fuzzy_distance: DEFAULT_FUZZY_DISTANCE,
         })
     }
 }
 
+/// Highlights query terms in stored descriptions, falling back to titles.
+struct Snippets {
+    description: SnippetGenerator,
+    title: SnippetGenerator,
+}
+
+impl Snippets {
+    fn new(
+        searcher: &Searcher,
+        query: &dyn Query,
+        fields: &SearchSchema,
+    ) -> Result<Self, tantivy::TantivyError> {
+        let mut description = SnippetGenerator::create(searcher, query, fields.description)?;
+        description.set_max_num_chars(SNIPPET_MAX_CHARS);
+        let mut title = SnippetGenerator::create(searcher, query, fields.title)?;
+        title.set_max_num_chars(SNIPPET_MAX_CHARS);
+        Ok(Self { description, title })
+    }
+
+    fn render(&self, doc: &Document) -> Option<String> {
+        [&self.description, &self.title]
+            .into_iter()
+            .map(|generator| generator.snippet_from_doc(doc))
+            .find(|snippet| !snippet.highlighted().is_empty())
+            .map(|snippet| snippet.to_html())
+    }
+}
+
+/// Whether a word of `text` (or the whole text) starts with `prefix`, the
+/// same word-suffix rule the indexer applies to `title_suggest`.
+fn has_word_prefix(text: &str, prefix: &str) -> bool {
+    let text = text.to_lowercase();
+    text.starts_with(prefix)
+        || text
+            .match_indices(char::is_whitespace)
+            .any(|(at, _)| text[at..].trim_start().starts_with(prefix))
+}
+
+/// Matches the documents with any of `doc_ids`.
+fn doc_id_set<'a>(field: Field, doc_ids: impl Iterator<Item = &'a String>) -> Box<dyn Query> {
+    Box::new(TermSetQuery::new(
+        doc_ids.map(|doc_id| Term::from_field_text(field, doc_id)),
+    ))
+}
+
+fn doc_id_at(searcher: &Searcher, address: DocAddress) -> Result<String, SearchError> {
+    let column = searcher
+        .segment_reader(address.segment_ord)
+        .fast_fields()
+        .str(DOC_ID_FIELD)?
+        .ok_or(SearchError::NotFast(DOC_ID_FIELD))?;
+    Ok(paging::read_str(&column, address.doc_id).unwrap_or_default())
+}
+
 fn build_terms_filter(field: Field, values: &[String]) -> Option<Box<dyn Query>> {
     if values.is_empty() {
         return None;
     }
-    let clauses: Vec<(Occur, Box<dyn Query>)> = values
+    let clauses = values
         .iter()
-        .map(|value| {
-            let term = Term::from_field_text(field, value);
-            (
-                Occur::Should,
-                Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>,
-            )
-        })
+        .map(|value| term_clause(field, value))
         .collect();
     Some(Box::new(BooleanQuery::new(clauses)))
 }
 
+fn term_clause(field: Field, value: &str) -> (Occur, Box<dyn Query>) {
+    let term = Term::from_field_text(field, value);
+    (
+        Occur::Should,
+        Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+    )
+}
+
 fn normalize_filters(values: &[String]) -> Vec<String> {
     let mut normalized: Vec<String> = values
         .iter()
@@ -342,60 +819,23 @@ fn extract_all(doc: &Document, field: Field) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tantivy::schema::{SchemaBuilder, STORED, STRING, TEXT};
-    use tantivy::{doc, Index};
-
-    fn build_test_index() -> (Index, SearchFields) {
-        let mut builder = SchemaBuilder::default();
-        let doc_id = builder.add_text_field("doc_id", STRING | STORED);
-        let entity_id = builder.add_text_field("entity_id", STRING | STORED);
-        let entity_type = builder.add_text_field("entity_type", STRING | STORED);
-        let entity_type_facet = builder.add_facet_field(
-            "entity_type_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let owner_id = builder.add_text_field("owner_id", STRING | STORED);
-        let title = builder.add_text_field("title", TEXT | STORED);
-        let description = builder.add_text_field("description", TEXT | STORED);
-        let visibility = builder.add_text_field("visibility", STRING | STORED);
-        let visibility_facet = builder.add_facet_field(
-            "visibility_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let tags = builder.add_text_field("tags", TEXT | STORED);
-        let tag_facet = builder.add_facet_field(
-            "tag_facet",
-            tantivy::schema::FacetOptions::default().set_stored(),
-        );
-        let status = builder.add_text_field("status", STRING | STORED);
-        let kind = builder.add_text_field("kind", STRING | STORED);
-        let content = builder.add_text_field("content", TEXT);
-        let schema = builder.build();
-        let index = Index::create_in_ram(schema.clone());
-        let fields = SearchFields {
-            doc_id,
-            entity_id,
-            entity_type,
-            entity_type_facet,
-            owner_id,
-            title,
-            description,
-            visibility,
-            visibility_facet,
-            tags,
-            tag_facet,
-            status,
-            kind,
-            content,
-        };
-        (index, fields)
+    use tantivy::{doc, DateTime, Index};
+
+    fn build_test_index() -> (Index, SearchSchema) {
+        let fields = SearchSchema::build();
+        (fields.create_in_ram(), fields)
     }
 
-    fn index_sample_docs(index: &Index, fields: &SearchFields) {
+    fn index_sample_docs(index: &Index, fields: &SearchSchema) {
         let mut writer = index.writer(50_000_000).expect("writer");
         writer
             .add_document(doc!(
                 fields.doc_id => "pod:1",
+                fields.title_suggest => "community pod",
+                fields.title_suggest => "pod",
+                fields.tag_suggest => "community",
+                fields.title_sort => "community pod",
+                fields.updated_at => DateTime::from_timestamp_secs(300),
                 fields.entity_id => "1",
                 fields.entity_type => "pod",
                 fields.entity_type_facet => Facet::from("/type/pod"),
@@ -413,6 +853,12 @@ mod tests {
         writer
             .add_document(doc!(
                 fields.doc_id => "artifact:2",
+                fields.title_suggest => "community artifact",
+                fields.title_suggest => "artifact",
+                fields.tag_suggest => "community",
+                fields.tag_suggest => "build",
+                fields.title_sort => "community artifact",
+                fields.updated_at => DateTime::from_timestamp_secs(100),
                 fields.entity_id => "2",
                 fields.entity_type => "artifact",
                 fields.entity_type_facet => Facet::from("/type/artifact"),
@@ -431,11 +877,17 @@ mod tests {
         writer
             .add_document(doc!(
                 fields.doc_id => "quest:3",
+                fields.title_suggest => "community challenge",
+                fields.title_suggest => "challenge",
+                fields.tag_suggest => "private",
+                fields.title_sort => "community challenge",
+                fields.updated_at => DateTime::from_timestamp_secs(200),
                 fields.entity_id => "3",
                 fields.entity_type => "quest",
                 fields.entity_type_facet => Facet::from("/type/quest"),
                 fields.title => "Community Challenge",
                 fields.description => "A private challenge",
+                fields.owner_id => "creator",
                 fields.visibility => "private",
                 fields.visibility_facet => Facet::from("/visibility/private"),
                 fields.tags => "private",
@@ -445,20 +897,147 @@ mod tests {
                 fields.content => "challenge community",
             ))
             .expect("add quest");
+        writer
+            .add_document(doc!(
+                fields.doc_id => "artifact:4",
+                fields.title_suggest => "community plans",
+                fields.title_suggest => "plans",
+                fields.title_sort => "community plans",
+                fields.updated_at => DateTime::from_timestamp_secs(400),
+                fields.entity_id => "4",
+                fields.entity_type => "artifact",
+                fields.entity_type_facet => Facet::from("/type/artifact"),
+                fields.owner_id => "guildmate",
+                fields.title => "Community Plans",
+                fields.visibility => "private",
+                fields.visibility_facet => Facet::from("/visibility/private"),
+                fields.kind => "document",
+                fields.content => "community plans",
+                fields.guild_ids => "guild-1",
+                fields.guild_facet => Facet::from("/guild/guild-1"),
+            ))
+            .expect("add shared artifact");
         writer.commit().expect("commit");
     }
 
+    /// Publishes a version holding one public document titled `title`.
+    fn publish_version(layout: &IndexLayout, title: &str) {
+        let fields = SearchSchema::build();
+        let (version, index) = layout.create_version(&fields).expect("create");
+        let mut writer = index.writer(15_000_000).expect("writer");
+        writer
+            .add_document(doc!(
+                fields.doc_id => format!("pod:{title}"),
+                fields.title => title,
+                fields.visibility => "public",
+            ))
+            .expect("add");
+        search_schema::commit(&mut writer).expect("commit");
+        layout.publish(&version).expect("publish");
+    }
+
+    fn titles(search_index: &SearchIndex, query: &str) -> Vec<String> {
+        let anonymous = Viewer::default();
+        let request = SearchRequest {
+            viewer: &anonymous,
+            query,
+            limit: 10,
+            entity_types: &[],
+            tags: &[],
+            visibilities: &[],
+            sort: SortMode::Relevance,
+            cursor: None,
+            mode: SearchMode::Keyword,
+            neighbours: &[],
+            language: None,
+        };
+        let results = search_index.search(request).expect("results");
+        results
+            .hits
+            .into_iter()
+            .filter_map(|hit| hit.title)
+            .collect()
+    }
+
+    #[test]
+    fn open_rejects_indexes_of_another_schema_version() {
+        let dir = std::env::temp_dir().join(format!("eco-api-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let layout = IndexLayout::new(&dir);
+        assert!(matches!(
+            SearchIndex::open(&dir),
+            Err(SearchError::Schema(
+                search_schema::SchemaError::NoCurrentVersion(_)
+            ))
+        ));
+
+        let (version, index) = layout
+            .create_version(&SearchSchema::build())
+            .expect("create");
+        let mut writer = index.writer(15_000_000).expect("writer");
+        writer.commit().expect("unversioned commit");
+        layout.publish(&version).expect("publish");
+        assert!(matches!(
+            SearchIndex::open(&dir),
+            Err(SearchError::Schema(
+                search_schema::SchemaError::VersionMismatch { found: None, .. }
+            ))
+        ));
+
+        search_schema::commit(&mut writer).expect("commit");
+        assert!(SearchIndex::open(&dir).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn queries_switch_to_newly_published_versions() {
+        let dir = std::env::temp_dir().join(format!("eco-api-versions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let layout = IndexLayout::new(&dir);
+        publish_version(&layout, "Garden");
+        let search_index = SearchIndex::open(&dir).expect("open");
+        assert_eq!(titles(&search_index, "garden"), ["Garden"]);
+        assert_eq!(search_index.follow_pointer(), None);
+
+        publish_version(&layout, "Orchard");
+        assert_eq!(titles(&search_index, "garden"), ["Garden"]);
+        let switched = search_index.follow_pointer().expect("switched");
+        assert_eq!(search_index.version_path(), Some(switched));
+        assert_eq!(titles(&search_index, "orchard"), ["Orchard"]);
+        assert!(titles(&search_index, "garden").is_empty());
+
+        // A pointer to a missing version leaves the live one serving.
+        std::fs::write(dir.join("CURRENT"), "000000000000000").expect("pointer");
+        assert_eq!(search_index.follow_pointer(), None);
+        assert_eq!(titles(&search_index, "orchard"), ["Orchard"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn viewer(user_id: &str, guild_ids: &[&str]) -> Viewer {
+        Viewer {
+            user_id: Some(user_id.to_string()),
+            guild_ids: guild_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
     #[test]
     fn search_returns_facets_and_hits() {
         let (index, fields) = build_test_index();
         index_sample_docs(&index, &fields);
         let search_index = SearchIndex::from_index(index).expect("search index");
+        let creator = viewer("creator", &[]);
         let request = SearchRequest {
+            viewer: &creator,
             query: "community",
             limit: 10,
             entity_types: &[],
             tags: &[],
             visibilities: &[],
+            sort: SortMode::Relevance,
+            cursor: None,
+            mode: SearchMode::Keyword,
+            neighbours: &[],
+            language: None,
         };
         let results = search_index.search(request).expect("results");
         assert_eq!(results.hits.len(), 3);
@@ -482,12 +1061,19 @@ mod tests {
         let (index, fields) = build_test_index();
         index_sample_docs(&index, &fields);
         let search_index = SearchIndex::from_index(index).expect("search index");
+        let creator = viewer("creator", &[]);
         let request = SearchRequest {
+            viewer: &creator,
             query: "community",
             limit: 10,
-            entity_types: &vec!["quest".to_string()],
+            entity_types: &["quest".to_string()],
             tags: &[],
-            visibilities: &vec!["private".to_string()],
+            visibilities: &["private".to_string()],
+            sort: SortMode::Relevance,
+            cursor: None,
+            mode: SearchMode::Keyword,
+            neighbours: &[],
+            language: None,
         };
         let results = search_index.search(request).expect("results");
         assert_eq!(results.hits.len(), 1);
@@ -495,4 +1081,392 @@ mod tests {
         assert_eq!(hit.entity_type, "quest");
         assert_eq!(hit.visibility.as_deref(), Some("private"));
     }
+
+    #[test]
+    fn private_documents_need_an_owner_or_a_guild_member() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let doc_ids = |viewer: &Viewer, visibilities: &[String]| {
+            let mut ids: Vec<_> = search_index
+                .search(SearchRequest {
+                    viewer,
+                    query: "community",
+                    limit: 10,
+                    entity_types: &[],
+                    tags: &[],
+                    visibilities,
+                    sort: SortMode::Relevance,
+                    cursor: None,
+                    mode: SearchMode::Keyword,
+                    neighbours: &[],
+                    language: None,
+                })
+                .expect("results")
+                .hits
+                .into_iter()
+                .map(|hit| hit.doc_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let public = vec!["artifact:2".to_string(), "pod:1".to_string()];
+        assert_eq!(doc_ids(&Viewer::anonymous(), &[]), public);
+        assert_eq!(doc_ids(&viewer("stranger", &["guild-2"]), &[]), public);
+        assert!(doc_ids(&Viewer::anonymous(), &["private".to_string()]).is_empty());
+        assert_eq!(
+            doc_ids(&viewer("member", &["guild-1"]), &["private".to_string()]),
+            vec!["artifact:4".to_string()]
+        );
+        assert_eq!(
+            doc_ids(&viewer("creator", &[]), &["private".to_string()]),
+            vec!["quest:3".to_string()]
+        );
+
+        let member = viewer("member", &["guild-1"]);
+        let shared = search_index
+            .search(SearchRequest {
+                viewer: &member,
+                query: "plans",
+                limit: 10,
+                entity_types: &[],
+                tags: &[],
+                visibilities: &[],
+                sort: SortMode::Relevance,
+                cursor: None,
+                mode: SearchMode::Keyword,
+                neighbours: &[],
+                language: None,
+            })
+            .expect("results");
+        assert_eq!(shared.facets.guild.len(), 1);
+        assert_eq!(shared.facets.guild[0].value, "guild-1");
+    }
+
+    #[test]
+    fn cursors_walk_sorted_pages_with_stable_totals_and_facets() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let creator = viewer("creator", &[]);
+        let walk = |sort: SortMode| {
+            let mut doc_ids = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let results = search_index
+                    .search(SearchRequest {
+                        viewer: &creator,
+                        query: "community",
+                        limit: 2,
+                        entity_types: &[],
+                        tags: &[],
+                        visibilities: &[],
+                        sort,
+                        cursor: cursor.as_deref(),
+                        mode: SearchMode::Keyword,
+                        neighbours: &[],
+                        language: None,
+                    })
+                    .expect("results");
+                assert_eq!(results.total_hits, 3);
+                assert_eq!(results.facets.entity_type.len(), 3);
+                doc_ids.extend(results.hits.into_iter().map(|hit| hit.doc_id));
+                match results.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => return doc_ids,
+                }
+            }
+        };
+
+        assert_eq!(walk(SortMode::Title), ["artifact:2", "quest:3", "pod:1"]);
+        assert_eq!(walk(SortMode::Newest), ["pod:1", "quest:3", "artifact:2"]);
+        let mut relevance = walk(SortMode::Relevance);
+        relevance.sort();
+        assert_eq!(relevance, ["artifact:2", "pod:1", "quest:3"]);
+
+        let title_cursor = search_index
+            .search(SearchRequest {
+                viewer: &creator,
+                query: "community",
+                limit: 1,
+                entity_types: &[],
+                tags: &[],
+                visibilities: &[],
+                sort: SortMode::Title,
+                cursor: None,
+                mode: SearchMode::Keyword,
+                neighbours: &[],
+                language: None,
+            })
+            .expect("results")
+            .next_cursor
+            .expect("more pages");
+        let mismatched = search_index.search(SearchRequest {
+            viewer: &creator,
+            query: "community",
+            limit: 1,
+            entity_types: &[],
+            tags: &[],
+            visibilities: &[],
+            sort: SortMode::Newest,
+            cursor: Some(&title_cursor),
+            mode: SearchMode::Keyword,
+            neighbours: &[],
+            language: None,
+        });
+        assert!(matches!(mismatched, Err(SearchError::InvalidCursor)));
+    }
+
+    #[test]
+    fn typos_match_within_the_edit_distance_and_hits_carry_snippets() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let anonymous = Viewer::anonymous();
+        let request = |query| SearchRequest {
+            viewer: &anonymous,
+            query,
+            limit: 10,
+            entity_types: &[],
+            tags: &[],
+            visibilities: &[],
+            sort: SortMode::Relevance,
+            cursor: None,
+            mode: SearchMode::Keyword,
+            neighbours: &[],
+            language: None,
+        };
+
+        let typo = search_index.search(request("comunity")).expect("results");
+        assert_eq!(typo.total_hits, 2);
+        let vibrant = search_index.search(request("vibrant")).expect("results");
+        assert_eq!(
+            vibrant.hits[0].snippet.as_deref(),
+            Some("A <b>vibrant</b> community hub")
+        );
+
+        let strict = search_index.with_fuzzy_distance(0);
+        let typo = strict.search(request("comunity")).expect("results");
+        assert_eq!(typo.total_hits, 0);
+    }
+
+    #[test]
+    fn unparsable_queries_are_bad_requests() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let err = search_index
+            .search(SearchRequest {
+                viewer: &Viewer::anonymous(),
+                query: "nosuchfield:community",
+                limit: 10,
+                entity_types: &[],
+                tags: &[],
+                visibilities: &[],
+                sort: SortMode::Relevance,
+                cursor: None,
+                mode: SearchMode::Keyword,
+                neighbours: &[],
+                language: None,
+            })
+            .expect_err("unknown field");
+        assert!(matches!(err, SearchError::Query(_)));
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn documents_in_the_requested_language_rank_first() {
+        let (index, fields) = build_test_index();
+        let mut writer = index.writer(15_000_000).expect("writer");
+        for (id, title, language) in [
+            ("1", "Garden Walk", Some("en")),
+            ("2", "Garden Tour", Some("pt")),
+            ("3", "Garden Maze", None),
+        ] {
+            let mut document = doc!(
+                fields.doc_id => format!("pod:{id}"),
+                fields.entity_type => "pod",
+                fields.title => title,
+                fields.visibility => "public",
+            );
+            if let Some(language) = language {
+                document.add_text(fields.language, language);
+            }
+            writer.add_document(document).expect("add pod");
+        }
+        writer.commit().expect("commit");
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let entity_types = ["pod".to_string()];
+        let first = |query: &str, language: Option<&str>| {
+            let results = search_index
+                .search(SearchRequest {
+                    viewer: &Viewer::anonymous(),
+                    query,
+                    limit: 10,
+                    entity_types: &entity_types,
+                    tags: &[],
+                    visibilities: &[],
+                    sort: SortMode::Relevance,
+                    cursor: None,
+                    mode: SearchMode::Keyword,
+                    neighbours: &[],
+                    language,
+                })
+                .expect("results");
+            results.hits[0].title.clone().expect("title")
+        };
+
+        assert_eq!(first("garden", Some("pt")), "Garden Tour");
+        assert_eq!(first("garden", Some("en")), "Garden Walk");
+        assert_eq!(first("", Some("pt")), "Garden Tour");
+        // The boost ranks; it does not filter.
+        assert_eq!(first("maze", Some("pt")), "Garden Maze");
+    }
+
+    #[test]
+    fn suggestions_complete_visible_titles_and_tags() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let texts = |viewer: &Viewer, prefix: &str, limit: usize| {
+            search_index
+                .suggest(viewer, prefix, limit)
+                .expect("suggestions")
+                .into_iter()
+                .map(|suggestion| (suggestion.kind, suggestion.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            texts(&Viewer::anonymous(), "Bu", 5),
+            [(SuggestionKind::Tag, "build".to_string())]
+        );
+        assert!(texts(&Viewer::anonymous(), "pla", 5).is_empty());
+        assert_eq!(
+            texts(&viewer("member", &["guild-1"]), "pla", 5),
+            [(SuggestionKind::Title, "Community Plans".to_string())]
+        );
+        let community = texts(&Viewer::anonymous(), "comm", 10);
+        assert_eq!(community.len(), 3);
+        assert!(community.contains(&(SuggestionKind::Tag, "community".to_string())));
+        assert_eq!(texts(&Viewer::anonymous(), "comm", 2).len(), 2);
+        assert!(texts(&Viewer::anonymous(), "c", 5).is_empty());
+    }
+
+    #[test]
+    fn suggestions_page_past_repeated_matches() {
+        let (index, fields) = build_test_index();
+        let mut writer = index.writer(50_000_000).expect("writer");
+        for id in 0..6 {
+            writer
+                .add_document(doc!(
+                    fields.doc_id => format!("artifact:{id}"),
+                    fields.title_suggest => "community",
+                    fields.tag_suggest => "community",
+                    fields.title => "Community",
+                    fields.visibility => "public",
+                    fields.tags => "community",
+                ))
+                .expect("add repeated artifact");
+        }
+        writer
+            .add_document(doc!(
+                fields.doc_id => "artifact:commons",
+                fields.title_suggest => "commons",
+                fields.title => "Commons",
+                fields.visibility => "public",
+            ))
+            .expect("add commons");
+        writer.commit().expect("commit");
+        let search_index = SearchIndex::from_index(index).expect("search index");
+
+        let suggestions = search_index
+            .suggest(&Viewer::anonymous(), "comm", 3)
+            .expect("suggestions");
+        assert_eq!(suggestions.len(), 3);
+        assert!(suggestions
+            .iter()
+            .any(|suggestion| suggestion.doc_id.as_deref() == Some("artifact:commons")));
+    }
+
+    #[test]
+    fn semantic_neighbours_are_filtered_for_the_viewer_and_fused_with_keywords() {
+        let (index, fields) = build_test_index();
+        index_sample_docs(&index, &fields);
+        let search_index = SearchIndex::from_index(index).expect("search index");
+        let neighbours: Vec<Neighbour> = [
+            ("artifact:4", 0.9),
+            ("quest:3", 0.8),
+            ("artifact:2", 0.7),
+            ("pod:1", 0.2),
+        ]
+        .into_iter()
+        .map(|(doc_id, score)| Neighbour {
+            doc_id: doc_id.to_string(),
+            score,
+        })
+        .collect();
+        let member = viewer("member", &["guild-1"]);
+        let anonymous = Viewer::anonymous();
+        let request = |viewer, query, mode, limit, cursor| SearchRequest {
+            viewer,
+            query,
+            limit,
+            entity_types: &[],
+            tags: &[],
+            visibilities: &[],
+            sort: SortMode::Relevance,
+            cursor,
+            mode,
+            neighbours: &neighbours,
+            language: None,
+        };
+        let doc_ids = |results: &SearchResults| {
+            results
+                .hits
+                .iter()
+                .map(|hit| hit.doc_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let semantic = search_index
+            .search(request(&member, "garden", SearchMode::Semantic, 10, None))
+            .expect("results");
+        assert_eq!(doc_ids(&semantic), ["artifact:4", "artifact:2", "pod:1"]);
+        assert_eq!(semantic.hits[0].score, 0.9);
+        let keyword = search_index
+            .search(request(&member, "garden", SearchMode::Keyword, 10, None))
+            .expect("results");
+        assert_eq!(keyword.total_hits, 0);
+
+        // "vibrant" only matches pod:1, which both rankings agree on.
+        let first = search_index
+            .search(request(&anonymous, "vibrant", SearchMode::Hybrid, 1, None))
+            .expect("results");
+        assert_eq!(doc_ids(&first), ["pod:1"]);
+        assert_eq!(first.total_hits, 2);
+        assert_eq!(first.facets.entity_type.len(), 2);
+        assert_eq!(
+            first.hits[0].snippet.as_deref(),
+            Some("A <b>vibrant</b> community hub")
+        );
+        let second = search_index
+            .search(request(
+                &anonymous,
+                "vibrant",
+                SearchMode::Hybrid,
+                1,
+                first.next_cursor.as_deref(),
+            ))
+            .expect("results");
+        assert_eq!(doc_ids(&second), ["artifact:2"]);
+        assert_eq!(second.next_cursor, None);
+
+        let sorted = search_index.search(SearchRequest {
+            sort: SortMode::Newest,
+            ..request(&anonymous, "vibrant", SearchMode::Hybrid, 10, None)
+        });
+        assert!(matches!(sorted, Err(SearchError::UnsupportedSort)));
+    }
 }
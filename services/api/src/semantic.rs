@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use vector_search::{Embedder, Neighbour, VectorError, VectorStore};
+
+/// Nearest documents fetched per query before access rules and filters are
+/// applied, so a page can still be filled after some are dropped.
+const CANDIDATES: usize = 100;
+/// Damping constant of reciprocal rank fusion; 60 is the value from the
+/// original paper and keeps a single top rank from dominating.
+const RRF_K: f32 = 60.0;
+
+/// Finds documents near the query text in the vector store the indexer
+/// filled for the published index version.
+#[derive(Clone)]
+pub struct SemanticSearch {
+    embedder: Arc<dyn Embedder>,
+    store: Arc<RwLock<Arc<dyn VectorStore>>>,
+}
+
+impl SemanticSearch {
+    pub fn new(embedder: Arc<dyn Embedder>, store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            embedder,
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+
+    /// Switches to the store of the index version in `dir`, as the keyword
+    /// index does once it follows a newly published version.
+    pub async fn follow(&self, dir: &Path) -> Result<(), VectorError> {
+        let next = self.store().follow(dir).await?;
+        *self
+            .store
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = next;
+        Ok(())
+    }
+
+    fn store(&self) -> Arc<dyn VectorStore> {
+        self.store
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Closest documents first. Text with nothing to embed has no
+    /// neighbours.
+    pub async fn neighbours(&self, query: &str) -> Result<Vec<Neighbour>, VectorError> {
+        let vector = self.embedder.embed(query);
+        if vector.iter().all(|value| *value == 0.0) {
+            return Ok(Vec::new());
+        }
+        self.store().search(&vector, CANDIDATES).await
+    }
+}
+
+/// Merges ranked `doc_id` lists by summing `1 / (k + rank)` per list, so
+/// documents that rank well in several lists rise and raw scores of
+/// different scales never need to be compared. Best first; ties by `doc_id`.
+pub fn reciprocal_rank_fusion(lists: &[Vec<String>]) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    for list in lists {
+        for (rank, doc_id) in list.iter().enumerate() {
+            *scores.entry(doc_id).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let mut fused: Vec<(String, f32)> = scores
+        .into_iter()
+        .map(|(doc_id, score)| (doc_id.to_string(), score))
+        .collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn fusion_rewards_documents_found_by_both_retrievers() {
+        let keyword = ids(&["pod:1", "quest:3", "artifact:2"]);
+        let semantic = ids(&["artifact:2", "quest:3"]);
+        let fused: Vec<String> = reciprocal_rank_fusion(&[keyword, semantic])
+            .into_iter()
+            .map(|(doc_id, _)| doc_id)
+            .collect();
+        assert_eq!(fused, ids(&["artifact:2", "quest:3", "pod:1"]));
+        assert!(reciprocal_rank_fusion(&[]).is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::templates::CodeTemplates;
+use super::{CodeSendError, CodeSender, DeliveryChannel, DeliveryConfigError};
+
+/// How the connection to the SMTP server is secured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain text, for local relays and test servers only.
+    None,
+    /// Upgrades the connection with STARTTLS, usually on port 587.
+    StartTls,
+    /// Connects over TLS, usually on port 465.
+    Tls,
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Mailbox the codes are sent from, such as `E-CO <no-reply@example.com>`.
+    pub from: String,
+    pub timeout: Duration,
+}
+
+impl SmtpConfig {
+    /// Reads `ECO_SMTP_*`; `None` when `ECO_SMTP_HOST` is unset.
+    pub fn from_env() -> Result<Option<Self>, DeliveryConfigError> {
+        let Ok(host) = std::env::var("ECO_SMTP_HOST") else {
+            return Ok(None);
+        };
+        let port = match std::env::var("ECO_SMTP_PORT") {
+            Ok(port) => Some(port.parse().map_err(|_| {
+                DeliveryConfigError(format!("ECO_SMTP_PORT `{port}` is not a port"))
+            })?),
+            Err(_) => None,
+        };
+        let security = match std::env::var("ECO_SMTP_SECURITY")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "starttls" => SmtpSecurity::StartTls,
+            "tls" => SmtpSecurity::Tls,
+            "none" => SmtpSecurity::None,
+            other => {
+                return Err(DeliveryConfigError(format!(
+                    "unknown ECO_SMTP_SECURITY `{other}`"
+                )))
+            }
+        };
+        let from = std::env::var("ECO_SMTP_FROM").map_err(|_| {
+            DeliveryConfigError("ECO_SMTP_FROM must be set with ECO_SMTP_HOST".to_string())
+        })?;
+        Ok(Some(Self {
+            host,
+            port,
+            security,
+            username: std::env::var("ECO_SMTP_USERNAME").ok(),
+            password: std::env::var("ECO_SMTP_PASSWORD").ok(),
+            from,
+            timeout: Duration::from_secs(10),
+        }))
+    }
+}
+
+/// Emails codes through an SMTP server.
+pub struct SmtpCodeSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    templates: CodeTemplates,
+}
+
+impl SmtpCodeSender {
+    pub fn new(config: SmtpConfig, templates: CodeTemplates) -> Result<Self, DeliveryConfigError> {
+        let from = config.from.parse().map_err(|err| {
+            DeliveryConfigError(format!("invalid sender `{}`: {err}", config.from))
+        })?;
+        let tls = match config.security {
+            SmtpSecurity::None => Tls::None,
+            security => {
+                let parameters = TlsParameters::new(config.host.clone())
+                    .map_err(|err| DeliveryConfigError(format!("SMTP TLS: {err}")))?;
+                if security == SmtpSecurity::Tls {
+                    Tls::Wrapper(parameters)
+                } else {
+                    Tls::Required(parameters)
+                }
+            }
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            .tls(tls)
+            .timeout(Some(config.timeout));
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (config.username, config.password) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from,
+            templates,
+        })
+    }
+}
+
+#[async_trait]
+impl CodeSender for SmtpCodeSender {
+    async fn send_code(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError> {
+        let to: Mailbox = identifier
+            .parse()
+            .map_err(|err| CodeSendError::Rejected(format!("invalid email address: {err}")))?;
+        let message = self.templates.render(channel, locale, code);
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body)
+            .map_err(|err| CodeSendError::Rejected(err.to_string()))?;
+        self.transport.send(email).await.map_err(|err| {
+            if err.is_permanent() {
+                CodeSendError::Rejected(err.to_string())
+            } else {
+                CodeSendError::Transient(err.to_string())
+            }
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Accepts one SMTP session and returns the DATA it received.
+    async fn fake_smtp_server(listener: TcpListener, reject_recipient: bool) -> String {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 fake ESMTP\r\n").await.unwrap();
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.expect("read") {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    write.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let command = line.to_ascii_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250 fake\r\n"
+            } else if command.starts_with("RCPT") && reject_recipient {
+                b"550 no such user\r\n"
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if command.starts_with("QUIT") {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    async fn sender(reject_recipient: bool) -> (SmtpCodeSender, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener, reject_recipient));
+        let sender = SmtpCodeSender::new(
+            SmtpConfig {
+                host: "127.0.0.1".to_string(),
+                port: Some(port),
+                security: SmtpSecurity::None,
+                username: None,
+                password: None,
+                from: "E-CO <no-reply@example.com>".to_string(),
+                timeout: Duration::from_secs(5),
+            },
+            CodeTemplates::builtin(Duration::from_secs(600)),
+        )
+        .expect("sender");
+        (sender, server)
+    }
+
+    #[tokio::test]
+    async fn codes_are_emailed_in_the_requested_locale() {
+        let (sender, server) = sender(false).await;
+        sender
+            .send_code(DeliveryChannel::Email, "ada@example.com", "123456", "es-ES")
+            .await
+            .expect("sent");
+        drop(sender);
+        let data = server.await.expect("server");
+        assert!(data.contains("To: ada@example.com"));
+        assert!(data.contains("123456"));
+        assert!(data.contains("Subject: Tu "), "{data}");
+    }
+
+    #[tokio::test]
+    async fn rejected_recipients_fail_as_rejected() {
+        let (sender, _server) = sender(true).await;
+        let err = sender
+            .send_code(
+                DeliveryChannel::Email,
+                "nobody@example.com",
+                "123456",
+                "en-US",
+            )
+            .await
+            .expect_err("rejected");
+        assert!(matches!(err, CodeSendError::Rejected(_)), "{err:?}");
+    }
+}
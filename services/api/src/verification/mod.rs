@@ -0,0 +1,792 @@
+mod email;
+mod postgres;
+mod proof;
+mod sms;
+mod store;
+mod templates;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+pub use email::{SmtpCodeSender, SmtpConfig};
+pub use postgres::PostgresVerificationStore;
+pub use proof::{ProofIssuer, DEFAULT_PROOF_TTL};
+pub use sms::{HttpSmsCodeSender, SmsConfig};
+pub use store::{CodeRecord, IssuedCode, MemoryVerificationStore, VerificationStore};
+pub use templates::{CodeTemplates, DEFAULT_LOCALE};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryChannel {
+    Email,
+    Sms,
+}
+
+impl DeliveryChannel {
+    /// The form of `identifier` codes and proofs are keyed by, which
+    /// ethos-gateway looks accounts up by too: a trimmed, lowercased email
+    /// address or an E.164 phone number. `None` when it is neither.
+    pub fn normalize(self, identifier: &str) -> Option<String> {
+        let identifier = identifier.trim();
+        match self {
+            DeliveryChannel::Email => {
+                let email = identifier.to_lowercase();
+                let (local, domain) = email.split_once('@')?;
+                (!local.is_empty() && !domain.is_empty()).then_some(email)
+            }
+            DeliveryChannel::Sms => normalize_phone(identifier),
+        }
+    }
+}
+
+/// `+` and 8 to 15 digits, the first not zero. Spaces, dots, dashes and
+/// parentheses are dropped and a leading `00` stands for `+`.
+fn normalize_phone(phone: &str) -> Option<String> {
+    let compact: String = phone
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-' | '(' | ')'))
+        .collect();
+    let digits = compact
+        .strip_prefix('+')
+        .or_else(|| compact.strip_prefix("00"))?;
+    let valid = (8..=15).contains(&digits.len())
+        && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && !digits.starts_with('0');
+    valid.then(|| format!("+{digits}"))
+}
+
+impl fmt::Display for DeliveryChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryChannel::Email => write!(f, "email"),
+            DeliveryChannel::Sms => write!(f, "sms"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VerificationConfig {
+    pub code_length: usize,
+    pub ttl: Duration,
+    pub resend_interval: Duration,
+    pub max_attempts: u32,
+    /// How long no new code is issued after the attempts ran out.
+    pub lockout: Duration,
+    /// Tries per code before delivery counts as failed.
+    pub delivery_attempts: u32,
+    /// Wait before the first retry; it doubles after every further one.
+    pub delivery_backoff: Duration,
+    /// Longest time delivery may take, retries included, since the request
+    /// waits for it.
+    pub delivery_deadline: Duration,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            code_length: 6,
+            ttl: Duration::from_secs(600),
+            resend_interval: Duration::from_secs(30),
+            max_attempts: 5,
+            lockout: Duration::from_secs(900),
+            delivery_attempts: 3,
+            delivery_backoff: Duration::from_millis(500),
+            delivery_deadline: Duration::from_secs(15),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    #[error("not a valid {0} identifier")]
+    InvalidIdentifier(DeliveryChannel),
+    #[error("verification requests are rate limited; retry in {0:?}")]
+    RateLimited(Duration),
+    #[error("unable to deliver verification code: {0}")]
+    DeliveryFailed(String),
+    #[error("verification request not found")]
+    NotFound,
+    #[error("verification code expired; request a new one")]
+    Expired,
+    #[error("verification already completed")]
+    AlreadyVerified,
+    #[error("maximum verification attempts exceeded")]
+    MaxAttempts,
+    #[error("verification code does not match (remaining attempts: {0})")]
+    CodeMismatch(u32),
+    #[error("verification store unavailable: {0}")]
+    Store(String),
+    #[error("unable to sign verification proof: {0}")]
+    Proof(String),
+}
+
+impl VerificationError {
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+        match self {
+            VerificationError::InvalidIdentifier(_) => StatusCode::BAD_REQUEST,
+            VerificationError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            VerificationError::DeliveryFailed(_) => StatusCode::BAD_GATEWAY,
+            VerificationError::NotFound => StatusCode::NOT_FOUND,
+            VerificationError::Expired => StatusCode::GONE,
+            VerificationError::AlreadyVerified => StatusCode::CONFLICT,
+            VerificationError::MaxAttempts => StatusCode::TOO_MANY_REQUESTS,
+            VerificationError::CodeMismatch(_) => StatusCode::UNAUTHORIZED,
+            VerificationError::Store(_) => StatusCode::SERVICE_UNAVAILABLE,
+            VerificationError::Proof(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerificationKey {
+    pub identifier: String,
+    pub channel: DeliveryChannel,
+}
+
+impl VerificationKey {
+    /// The key of `identifier` in its normalized form.
+    pub fn new(identifier: &str, channel: DeliveryChannel) -> Result<Self, VerificationError> {
+        let identifier = channel
+            .normalize(identifier)
+            .ok_or(VerificationError::InvalidIdentifier(channel))?;
+        Ok(Self {
+            identifier,
+            channel,
+        })
+    }
+}
+
+impl PartialEq for VerificationKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.identifier == other.identifier && self.channel == other.channel
+    }
+}
+
+impl Eq for VerificationKey {}
+
+impl Hash for VerificationKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identifier.hash(state);
+        self.channel.hash(state);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeIssueOutcome {
+    pub expires_in: Duration,
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyOutcome {
+    pub verified: bool,
+    pub remaining_attempts: u32,
+    /// Single-use proof of the verification for ethos-gateway's
+    /// passwordless login; `None` without a [`ProofIssuer`].
+    pub proof_token: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum CodeSendError {
+    /// Worth retrying, as after a timeout or an unavailable provider.
+    #[error("{0}")]
+    Transient(String),
+    /// The provider refused the message; retrying will not help.
+    #[error("rejected: {0}")]
+    Rejected(String),
+}
+
+#[derive(Debug, Error)]
+#[error("invalid delivery configuration: {0}")]
+pub struct DeliveryConfigError(pub String);
+
+#[async_trait]
+pub trait CodeSender: Send + Sync {
+    async fn send_code(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError>;
+}
+
+#[derive(Clone, Default)]
+pub struct LoggingCodeSender;
+
+#[async_trait]
+impl CodeSender for LoggingCodeSender {
+    async fn send_code(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        _code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError> {
+        info!(
+            %channel,
+            %identifier,
+            %locale,
+            "dispatching verification code"
+        );
+        Ok(())
+    }
+}
+
+/// Sends each code through the sender of its channel.
+pub struct ChannelCodeSender {
+    email: Arc<dyn CodeSender>,
+    sms: Arc<dyn CodeSender>,
+}
+
+impl ChannelCodeSender {
+    pub fn new(email: Arc<dyn CodeSender>, sms: Arc<dyn CodeSender>) -> Self {
+        Self { email, sms }
+    }
+}
+
+#[async_trait]
+impl CodeSender for ChannelCodeSender {
+    async fn send_code(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError> {
+        let sender = match channel {
+            DeliveryChannel::Email => &self.email,
+            DeliveryChannel::Sms => &self.sms,
+        };
+        sender.send_code(channel, identifier, code, locale).await
+    }
+}
+
+#[derive(Clone)]
+pub struct VerificationService {
+    config: VerificationConfig,
+    secret: Arc<[u8]>,
+    store: Arc<dyn VerificationStore>,
+    sender: Arc<dyn CodeSender>,
+    proofs: Option<ProofIssuer>,
+}
+
+impl VerificationService {
+    /// Keeps codes in memory until [`VerificationService::with_store`]
+    /// names another store.
+    pub fn new(config: VerificationConfig, secret: Vec<u8>, sender: Arc<dyn CodeSender>) -> Self {
+        Self {
+            config,
+            secret: secret.into(),
+            store: Arc::new(MemoryVerificationStore::default()),
+            sender,
+            proofs: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: Arc<dyn VerificationStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Returns a proof token with every successful verification.
+    pub fn with_proofs(mut self, proofs: ProofIssuer) -> Self {
+        self.proofs = Some(proofs);
+        self
+    }
+
+    /// Sends a new code in `locale`; without one, in the locale of the code
+    /// it replaces.
+    pub async fn issue_code(
+        &self,
+        identifier: &str,
+        channel: DeliveryChannel,
+        locale: Option<&str>,
+    ) -> Result<CodeIssueOutcome, VerificationError> {
+        let key = VerificationKey::new(identifier, channel)?;
+        let identifier = key.identifier.as_str();
+        let code = self.generate_code();
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let issued = IssuedCode {
+            code_hash: self.compute_hash(&salt, &code),
+            salt,
+            locale: locale.map(str::to_string),
+        };
+        // Stored before sending, so concurrent requests on other replicas
+        // hit the resend interval instead of sending a second code.
+        let record = self.store.issue(&key, issued, &self.config).await?;
+
+        let delivered = tokio::time::timeout(
+            self.config.delivery_deadline,
+            self.deliver(channel, identifier, &code, &record.locale),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(CodeSendError::Transient(format!(
+                "not delivered within {:?}",
+                self.config.delivery_deadline
+            )))
+        });
+        if let Err(err) = delivered {
+            self.store.revoke(&key, &salt).await?;
+            return Err(VerificationError::DeliveryFailed(err.to_string()));
+        }
+
+        Ok(CodeIssueOutcome {
+            expires_in: self.config.ttl,
+            retry_after: self.config.resend_interval,
+        })
+    }
+
+    /// Sends `code`, retrying transient failures with exponential backoff.
+    async fn deliver(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError> {
+        let mut backoff = self.config.delivery_backoff;
+        let mut attempt = 1;
+        loop {
+            match self
+                .sender
+                .send_code(channel, identifier, code, locale)
+                .await
+            {
+                Err(CodeSendError::Transient(err)) if attempt < self.config.delivery_attempts => {
+                    warn!(%channel, attempt, %err, "verification code delivery failed; retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub async fn verify_code(
+        &self,
+        identifier: &str,
+        channel: DeliveryChannel,
+        code: &str,
+    ) -> Result<VerifyOutcome, VerificationError> {
+        let key = VerificationKey::new(identifier, channel)?;
+        let matches =
+            |record: &CodeRecord| self.compute_hash(&record.salt, code) == record.code_hash;
+        let mut outcome = self.store.verify(&key, &matches, &self.config).await?;
+        if let Some(proofs) = &self.proofs {
+            outcome.proof_token = Some(proofs.issue(&key)?);
+        }
+        Ok(outcome)
+    }
+
+    fn generate_code(&self) -> String {
+        let mut rng = rand::thread_rng();
+        let dist = Uniform::new_inclusive(0u8, 9);
+        (0..self.config.code_length)
+            .map(|_| char::from(b'0' + dist.sample(&mut rng)))
+            .collect()
+    }
+
+    fn compute_hash(&self, salt: &[u8; 16], code: &str) -> Vec<u8> {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC can take key of any size");
+        mac.update(salt);
+        mac.update(code.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct TestSender {
+        last_code: Mutex<Option<String>>,
+        last_locale: Mutex<Option<String>>,
+    }
+
+    #[async_trait]
+    impl CodeSender for TestSender {
+        async fn send_code(
+            &self,
+            _channel: DeliveryChannel,
+            _identifier: &str,
+            code: &str,
+            locale: &str,
+        ) -> Result<(), CodeSendError> {
+            *self.last_code.lock().unwrap() = Some(code.to_string());
+            *self.last_locale.lock().unwrap() = Some(locale.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn issue_and_verify_code() {
+        let sender = Arc::new(TestSender::default());
+        let service = VerificationService::new(
+            VerificationConfig::default(),
+            b"secret".to_vec(),
+            sender.clone(),
+        );
+
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect("issue code");
+        let code = sender
+            .last_code
+            .lock()
+            .unwrap()
+            .clone()
+            .expect("captured code");
+
+        let result = service
+            .verify_code("user@example.com", DeliveryChannel::Email, &code)
+            .await
+            .expect("verify code");
+        assert!(result.verified);
+        assert!(result.remaining_attempts < VerificationConfig::default().max_attempts);
+    }
+
+    #[test]
+    fn identifiers_are_normalized_per_channel() {
+        let email = DeliveryChannel::Email;
+        assert_eq!(
+            email.normalize("  Ada@Example.COM ").as_deref(),
+            Some("ada@example.com")
+        );
+        assert_eq!(email.normalize("ada"), None);
+        assert_eq!(email.normalize("@example.com"), None);
+
+        let sms = DeliveryChannel::Sms;
+        for phone in ["+1 (555) 010-0100", "0015550100100", " +1.555.010.0100 "] {
+            assert_eq!(sms.normalize(phone).as_deref(), Some("+15550100100"));
+        }
+        for phone in ["5550100100", "+1555", "+0155501001", "+1555abc0100"] {
+            assert_eq!(sms.normalize(phone), None, "{phone}");
+        }
+    }
+
+    #[tokio::test]
+    async fn codes_are_keyed_by_the_normalized_identifier() {
+        let sender = Arc::new(TestSender::default());
+        let service = VerificationService::new(
+            VerificationConfig::default(),
+            b"secret".to_vec(),
+            sender.clone(),
+        );
+        service
+            .issue_code(" Ada@Example.com", DeliveryChannel::Email, None)
+            .await
+            .expect("code issued");
+        let code = sender.last_code.lock().unwrap().clone().expect("code");
+        service
+            .verify_code("ada@example.com", DeliveryChannel::Email, &code)
+            .await
+            .expect("verified");
+        let err = service
+            .issue_code("not an address", DeliveryChannel::Email, None)
+            .await
+            .expect_err("invalid");
+        assert!(matches!(err, VerificationError::InvalidIdentifier(_)));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_is_enforced() {
+        let sender = Arc::new(TestSender::default());
+        let config = VerificationConfig {
+            resend_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let service = VerificationService::new(config.clone(), b"secret".to_vec(), sender);
+
+        service
+            .issue_code("+15550123", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect("first code");
+        let err = service
+            .issue_code("+15550123", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect_err("rate limited");
+
+        match err {
+            VerificationError::RateLimited(duration) => {
+                assert!(duration <= config.resend_interval);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn mismatched_code_counts_attempts() {
+        let sender = Arc::new(TestSender::default());
+        let config = VerificationConfig {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let service = VerificationService::new(config, b"secret".to_vec(), sender);
+
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect("code issued");
+
+        let err = service
+            .verify_code("user@example.com", DeliveryChannel::Email, "000000")
+            .await
+            .expect_err("mismatch");
+        matches!(err, VerificationError::CodeMismatch(_));
+
+        let err = service
+            .verify_code("user@example.com", DeliveryChannel::Email, "000000")
+            .await
+            .expect_err("max attempts");
+        matches!(err, VerificationError::MaxAttempts);
+    }
+
+    #[tokio::test]
+    async fn lockout_blocks_new_codes_until_it_ends() {
+        let sender = Arc::new(TestSender::default());
+        let config = VerificationConfig {
+            max_attempts: 1,
+            resend_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        let service = VerificationService::new(config, b"secret".to_vec(), sender.clone());
+
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect("code issued");
+        let code = sender.last_code.lock().unwrap().clone().expect("code");
+        let err = service
+            .verify_code("user@example.com", DeliveryChannel::Email, "not-the-code")
+            .await
+            .expect_err("locked");
+        assert!(matches!(err, VerificationError::MaxAttempts));
+        // The right code no longer helps once the attempts ran out.
+        let err = service
+            .verify_code("user@example.com", DeliveryChannel::Email, &code)
+            .await
+            .expect_err("still locked");
+        assert!(matches!(err, VerificationError::MaxAttempts));
+        let err = service
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect_err("locked out");
+        assert!(matches!(err, VerificationError::RateLimited(retry) if retry > Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn resends_without_a_locale_keep_the_previous_one() {
+        let sender = Arc::new(TestSender::default());
+        let config = VerificationConfig {
+            resend_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        let service = VerificationService::new(config, b"secret".to_vec(), sender.clone());
+        let last_locale = || sender.last_locale.lock().unwrap().clone();
+
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, None)
+            .await
+            .expect("first code");
+        assert_eq!(last_locale().as_deref(), Some(DEFAULT_LOCALE));
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("es-ES"))
+            .await
+            .expect("code in spanish");
+        service
+            .issue_code("user@example.com", DeliveryChannel::Email, None)
+            .await
+            .expect("resend");
+        assert_eq!(last_locale().as_deref(), Some("es-ES"));
+    }
+
+    #[tokio::test]
+    async fn expired_codes_are_removed() {
+        let sender = Arc::new(TestSender::default());
+        let config = VerificationConfig {
+            ttl: Duration::ZERO,
+            ..Default::default()
+        };
+        let service = VerificationService::new(config, b"secret".to_vec(), sender.clone());
+
+        service
+            .issue_code("+15550100", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect("code issued");
+        let code = sender.last_code.lock().unwrap().clone().expect("code");
+        let err = service
+            .verify_code("+15550100", DeliveryChannel::Sms, &code)
+            .await
+            .expect_err("expired");
+        assert!(matches!(err, VerificationError::Expired));
+        let err = service
+            .verify_code("+15550100", DeliveryChannel::Sms, &code)
+            .await
+            .expect_err("removed");
+        assert!(matches!(err, VerificationError::NotFound));
+    }
+
+    /// Fails the first `failures` sends with `error`.
+    struct FlakySender {
+        failures: u32,
+        error: fn() -> CodeSendError,
+        calls: Mutex<u32>,
+    }
+
+    impl FlakySender {
+        fn new(failures: u32, error: fn() -> CodeSendError) -> Self {
+            Self {
+                failures,
+                error,
+                calls: Mutex::new(0),
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            *self.calls.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl CodeSender for FlakySender {
+        async fn send_code(
+            &self,
+            _channel: DeliveryChannel,
+            _identifier: &str,
+            _code: &str,
+            _locale: &str,
+        ) -> Result<(), CodeSendError> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            if *calls <= self.failures {
+                Err((self.error)())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn unavailable() -> CodeSendError {
+        CodeSendError::Transient("provider unavailable".to_string())
+    }
+
+    fn quick_retries() -> VerificationConfig {
+        VerificationConfig {
+            delivery_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_delivery_failures_are_retried() {
+        let sender = Arc::new(FlakySender::new(2, unavailable));
+        let service = VerificationService::new(quick_retries(), b"secret".to_vec(), sender.clone());
+        service
+            .issue_code("+15550100", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect("delivered on the third try");
+        assert_eq!(sender.calls(), 3);
+
+        let sender = Arc::new(FlakySender::new(u32::MAX, || {
+            CodeSendError::Rejected("invalid number".to_string())
+        }));
+        let service = VerificationService::new(quick_retries(), b"secret".to_vec(), sender.clone());
+        let err = service
+            .issue_code("+15550100", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect_err("rejected");
+        assert!(matches!(err, VerificationError::DeliveryFailed(_)));
+        assert_eq!(sender.calls(), 1);
+    }
+
+    /// Never finishes sending.
+    struct HangingSender;
+
+    #[async_trait]
+    impl CodeSender for HangingSender {
+        async fn send_code(
+            &self,
+            _channel: DeliveryChannel,
+            _identifier: &str,
+            _code: &str,
+            _locale: &str,
+        ) -> Result<(), CodeSendError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn delivery_gives_up_at_the_deadline() {
+        let store: Arc<dyn VerificationStore> = Arc::new(MemoryVerificationStore::default());
+        let config = VerificationConfig {
+            delivery_deadline: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let service = VerificationService::new(config, b"secret".to_vec(), Arc::new(HangingSender))
+            .with_store(store.clone());
+        let err = service
+            .issue_code("+15550100", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect_err("timed out");
+        assert!(matches!(err, VerificationError::DeliveryFailed(_)));
+
+        // The undelivered code was revoked, so a retry is not rate limited.
+        let working = VerificationService::new(
+            VerificationConfig::default(),
+            b"secret".to_vec(),
+            Arc::new(TestSender::default()),
+        )
+        .with_store(store);
+        working
+            .issue_code("+15550100", DeliveryChannel::Sms, Some("en-US"))
+            .await
+            .expect("not rate limited");
+    }
+
+    #[tokio::test]
+    async fn undelivered_codes_do_not_count_against_the_resend_interval() {
+        let store: Arc<dyn VerificationStore> = Arc::new(MemoryVerificationStore::default());
+        let sender = Arc::new(FlakySender::new(u32::MAX, unavailable));
+        let failing = VerificationService::new(quick_retries(), b"secret".to_vec(), sender.clone())
+            .with_store(store.clone());
+        let err = failing
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect_err("undelivered");
+        assert!(matches!(err, VerificationError::DeliveryFailed(_)));
+        assert_eq!(sender.calls(), quick_retries().delivery_attempts);
+
+        let working = VerificationService::new(
+            VerificationConfig::default(),
+            b"secret".to_vec(),
+            Arc::new(TestSender::default()),
+        )
+        .with_store(store);
+        working
+            .issue_code("user@example.com", DeliveryChannel::Email, Some("en-US"))
+            .await
+            .expect("not rate limited");
+    }
+}
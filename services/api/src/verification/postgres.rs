@@ -0,0 +1,323 @@
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use deadpool_postgres::{Pool, Transaction};
+use tokio_postgres::Row;
+use tracing::warn;
+
+use super::store::{attempt, check_issue, CodeRecord, IssuedCode, VerificationStore};
+use super::{VerificationConfig, VerificationError, VerificationKey, VerifyOutcome};
+
+/// Codes kept in the `verification_codes` table of the gateway database,
+/// created by ethos-gateway's migrations. Every operation runs in a
+/// transaction holding an advisory lock on its identifier and channel, and
+/// takes the time from the database, so all replicas apply the same rules
+/// to the same clock.
+pub struct PostgresVerificationStore {
+    pool: Pool,
+}
+
+impl PostgresVerificationStore {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl VerificationStore for PostgresVerificationStore {
+    async fn issue(
+        &self,
+        key: &VerificationKey,
+        code: IssuedCode,
+        config: &VerificationConfig,
+    ) -> Result<CodeRecord, VerificationError> {
+        let mut client = self.pool.get().await.map_err(store_error)?;
+        let tx = client.transaction().await.map_err(store_error)?;
+        let now = lock(&tx, key).await?;
+        let existing = load(&tx, key).await?;
+        check_issue(existing.as_ref(), config, now)?;
+        let record = CodeRecord::new(code, existing.as_ref(), config, now);
+        save(&tx, key, &record).await?;
+        tx.commit().await.map_err(store_error)?;
+        // Outside the transaction, so the lock on `key` is not held while
+        // other identifiers are cleaned up; a failure only delays that.
+        if let Err(err) = prune(&client, config).await {
+            warn!(%err, "pruning verification codes failed");
+        }
+        Ok(record)
+    }
+
+    async fn revoke(
+        &self,
+        key: &VerificationKey,
+        salt: &[u8; 16],
+    ) -> Result<(), VerificationError> {
+        let client = self.pool.get().await.map_err(store_error)?;
+        client
+            .execute(
+                "DELETE FROM verification_codes \
+                 WHERE identifier = $1 AND channel = $2 AND salt = $3",
+                &[&key.identifier, &key.channel.to_string(), &salt.as_slice()],
+            )
+            .await
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn verify(
+        &self,
+        key: &VerificationKey,
+        matches: &(dyn for<'r> Fn(&'r CodeRecord) -> bool + Send + Sync),
+        config: &VerificationConfig,
+    ) -> Result<VerifyOutcome, VerificationError> {
+        let mut client = self.pool.get().await.map_err(store_error)?;
+        let tx = client.transaction().await.map_err(store_error)?;
+        let now = lock(&tx, key).await?;
+        let mut record = load(&tx, key).await?.ok_or(VerificationError::NotFound)?;
+        let matched = matches(&record);
+        let result = attempt(&mut record, matched, config, now);
+        if matches!(result, Err(VerificationError::Expired)) {
+            tx.execute(
+                "DELETE FROM verification_codes WHERE identifier = $1 AND channel = $2",
+                &[&key.identifier, &key.channel.to_string()],
+            )
+            .await
+            .map_err(store_error)?;
+        } else {
+            save(&tx, key, &record).await?;
+        }
+        tx.commit().await.map_err(store_error)?;
+        result
+    }
+}
+
+/// Deletes the rows [`super::store::prunable`] describes.
+async fn prune(
+    client: &deadpool_postgres::Client,
+    config: &VerificationConfig,
+) -> Result<u64, VerificationError> {
+    client
+        .execute(
+            "DELETE FROM verification_codes \
+             WHERE expires_at <= now() \
+             AND last_sent_at <= now() - make_interval(secs => $1) \
+             AND (locked_until IS NULL OR locked_until <= now())",
+            &[&config.resend_interval.as_secs_f64()],
+        )
+        .await
+        .map_err(store_error)
+}
+
+/// Serializes operations on `key` until the transaction ends and returns
+/// the database time once the lock is held.
+async fn lock(
+    tx: &Transaction<'_>,
+    key: &VerificationKey,
+) -> Result<SystemTime, VerificationError> {
+    let lock_key = format!("verification:{}:{}", key.channel, key.identifier);
+    let row = tx
+        .query_one(
+            "SELECT clock_timestamp() FROM pg_advisory_xact_lock(hashtextextended($1, 0))",
+            &[&lock_key],
+        )
+        .await
+        .map_err(store_error)?;
+    row.try_get(0).map_err(store_error)
+}
+
+async fn load(
+    tx: &Transaction<'_>,
+    key: &VerificationKey,
+) -> Result<Option<CodeRecord>, VerificationError> {
+    let row = tx
+        .query_opt(
+            "SELECT code_hash, salt, locale, attempts, used, locked_until, last_sent_at, \
+             expires_at FROM verification_codes WHERE identifier = $1 AND channel = $2",
+            &[&key.identifier, &key.channel.to_string()],
+        )
+        .await
+        .map_err(store_error)?;
+    row.as_ref().map(record_from_row).transpose()
+}
+
+async fn save(
+    tx: &Transaction<'_>,
+    key: &VerificationKey,
+    record: &CodeRecord,
+) -> Result<(), VerificationError> {
+    let attempts = i32::try_from(record.attempts).unwrap_or(i32::MAX);
+    tx.execute(
+        "INSERT INTO verification_codes \
+         (identifier, channel, code_hash, salt, locale, attempts, used, locked_until, \
+          last_sent_at, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+         ON CONFLICT (identifier, channel) DO UPDATE SET \
+         code_hash = EXCLUDED.code_hash, salt = EXCLUDED.salt, locale = EXCLUDED.locale, \
+         attempts = EXCLUDED.attempts, used = EXCLUDED.used, \
+         locked_until = EXCLUDED.locked_until, last_sent_at = EXCLUDED.last_sent_at, \
+         expires_at = EXCLUDED.expires_at",
+        &[
+            &key.identifier,
+            &key.channel.to_string(),
+            &record.code_hash,
+            &record.salt.as_slice(),
+            &record.locale,
+            &attempts,
+            &record.used,
+            &record.locked_until,
+            &record.last_sent_at,
+            &record.expires_at,
+        ],
+    )
+    .await
+    .map_err(store_error)?;
+    Ok(())
+}
+
+fn record_from_row(row: &Row) -> Result<CodeRecord, VerificationError> {
+    let salt: Vec<u8> = row.try_get("salt").map_err(store_error)?;
+    let attempts: i32 = row.try_get("attempts").map_err(store_error)?;
+    Ok(CodeRecord {
+        code_hash: row.try_get("code_hash").map_err(store_error)?,
+        salt: salt
+            .try_into()
+            .map_err(|_| VerificationError::Store("stored salt is not 16 bytes".to_string()))?,
+        locale: row.try_get("locale").map_err(store_error)?,
+        attempts: u32::try_from(attempts).unwrap_or_default(),
+        used: row.try_get("used").map_err(store_error)?,
+        locked_until: row.try_get("locked_until").map_err(store_error)?,
+        last_sent_at: row.try_get("last_sent_at").map_err(store_error)?,
+        expires_at: row.try_get("expires_at").map_err(store_error)?,
+    })
+}
+
+fn store_error(err: impl std::fmt::Display) -> VerificationError {
+    VerificationError::Store(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::verification::{DeliveryChannel, LoggingCodeSender, VerificationService};
+
+    /// The gateway migration that creates the table.
+    const MIGRATION: &str =
+        include_str!("../../../ethos-gateway/migrations/0021_create_verification_codes.sql");
+
+    /// A pool on `ETHOS_TEST_DATABASE_URL` with the table in place; `None`
+    /// when no test database is configured.
+    async fn test_pool() -> Option<Pool> {
+        let url = std::env::var("ETHOS_TEST_DATABASE_URL").ok()?;
+        let mut config = deadpool_postgres::Config::new();
+        config.url = Some(url);
+        let pool = config
+            .create_pool(None, tokio_postgres::NoTls)
+            .expect("pool");
+        let (up, _) = MIGRATION
+            .split_once("-- migrate:down")
+            .expect("migration sections");
+        pool.get()
+            .await
+            .expect("connection")
+            .batch_execute(up)
+            .await
+            .expect("create table");
+        Some(pool)
+    }
+
+    #[tokio::test]
+    async fn replicas_sharing_the_table_share_limits_and_prune_stale_codes() {
+        let Some(pool) = test_pool().await else {
+            eprintln!("ETHOS_TEST_DATABASE_URL is unset; skipping");
+            return;
+        };
+        let config = VerificationConfig {
+            resend_interval: Duration::from_secs(60),
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let store: Arc<dyn VerificationStore> =
+            Arc::new(PostgresVerificationStore::new(pool.clone()));
+        let replica = || {
+            VerificationService::new(
+                config.clone(),
+                b"secret".to_vec(),
+                Arc::new(LoggingCodeSender),
+            )
+            .with_store(store.clone())
+        };
+        let (first, second) = (replica(), replica());
+        let identifier = format!("{}@example.com", uuid::Uuid::new_v4());
+        let email = DeliveryChannel::Email;
+
+        let client = pool.get().await.expect("connection");
+        let stale = format!("stale-{}", uuid::Uuid::new_v4());
+        let locked = format!("locked-{}", uuid::Uuid::new_v4());
+        client
+            .execute(
+                "INSERT INTO verification_codes \
+                 (identifier, channel, code_hash, salt, locale, locked_until, last_sent_at, \
+                  expires_at) \
+                 VALUES ($1, 'email', '', '', 'en', NULL, now() - interval '2 hours', \
+                         now() - interval '1 hour'), \
+                        ($2, 'email', '', '', 'en', now() + interval '1 hour', \
+                         now() - interval '2 hours', now() - interval '1 hour')",
+                &[&stale, &locked],
+            )
+            .await
+            .expect("seed rows");
+
+        first
+            .issue_code(&identifier, email, Some("en-US"))
+            .await
+            .expect("first code");
+        let err = second
+            .issue_code(&identifier, email, Some("en-US"))
+            .await
+            .expect_err("resend interval");
+        assert!(matches!(err, VerificationError::RateLimited(_)));
+
+        let err = first
+            .verify_code(&identifier, email, "not-the-code")
+            .await
+            .expect_err("mismatch");
+        assert!(matches!(err, VerificationError::CodeMismatch(1)));
+        let err = second
+            .verify_code(&identifier, email, "not-the-code")
+            .await
+            .expect_err("attempts shared");
+        assert!(matches!(err, VerificationError::MaxAttempts));
+        let err = first
+            .issue_code(&identifier, email, Some("en-US"))
+            .await
+            .expect_err("locked out");
+        assert!(
+            matches!(err, VerificationError::RateLimited(retry) if retry > Duration::from_secs(60))
+        );
+
+        let remaining: Vec<String> = client
+            .query(
+                "SELECT identifier FROM verification_codes WHERE identifier = ANY($1)",
+                &[&vec![stale.clone(), locked.clone(), identifier.clone()]],
+            )
+            .await
+            .expect("remaining rows")
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        assert!(!remaining.contains(&stale));
+        assert!(remaining.contains(&locked));
+        assert!(remaining.contains(&identifier));
+
+        client
+            .execute(
+                "DELETE FROM verification_codes WHERE identifier = ANY($1)",
+                &[&vec![locked, identifier]],
+            )
+            .await
+            .expect("clean up");
+    }
+}
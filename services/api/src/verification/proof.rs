@@ -0,0 +1,94 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{VerificationError, VerificationKey};
+
+/// Audience ethos-gateway's passwordless login accepts; session tokens
+/// carry none, so neither kind passes for the other.
+pub const PROOF_AUDIENCE: &str = "ethos-gateway/passwordless";
+
+/// How long a proof can be exchanged for a session.
+pub const DEFAULT_PROOF_TTL: Duration = Duration::from_secs(300);
+
+/// Claims of a proof that `sub` received a code over `channel`. The gateway
+/// accepts each `jti` once.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofClaims {
+    pub sub: String,
+    pub channel: String,
+    pub jti: String,
+    pub aud: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+/// Signs proofs with the JWT secret shared with ethos-gateway.
+#[derive(Clone)]
+pub struct ProofIssuer {
+    key: EncodingKey,
+    ttl: Duration,
+}
+
+impl ProofIssuer {
+    pub fn new(secret: &str, ttl: Duration) -> Self {
+        Self {
+            key: EncodingKey::from_secret(secret.as_bytes()),
+            ttl,
+        }
+    }
+
+    pub fn issue(&self, key: &VerificationKey) -> Result<String, VerificationError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let claims = ProofClaims {
+            sub: key.identifier.clone(),
+            channel: key.channel.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            aud: PROOF_AUDIENCE.to_string(),
+            iat: now.as_secs(),
+            exp: (now + self.ttl).as_secs(),
+        };
+        encode(&Header::default(), &claims, &self.key)
+            .map_err(|err| VerificationError::Proof(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::DeliveryChannel;
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+
+    #[test]
+    fn proofs_name_the_verified_identifier_for_the_gateway_only() {
+        let issuer = ProofIssuer::new("shared-secret", DEFAULT_PROOF_TTL);
+        let key = VerificationKey {
+            identifier: "ada@example.com".to_string(),
+            channel: DeliveryChannel::Email,
+        };
+        let first = issuer.issue(&key).expect("proof");
+        let second = issuer.issue(&key).expect("proof");
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[PROOF_AUDIENCE]);
+        let decoding = DecodingKey::from_secret(b"shared-secret");
+        let claims = decode::<ProofClaims>(&first, &decoding, &validation)
+            .expect("valid proof")
+            .claims;
+        assert_eq!(claims.sub, "ada@example.com");
+        assert_eq!(claims.channel, "email");
+        assert_eq!(claims.exp - claims.iat, DEFAULT_PROOF_TTL.as_secs());
+        let other = decode::<ProofClaims>(&second, &decoding, &validation)
+            .expect("valid proof")
+            .claims;
+        assert_ne!(claims.jti, other.jti);
+
+        // Without the audience, as when decoding a session token, it fails.
+        let session_validation = Validation::new(Algorithm::HS256);
+        assert!(decode::<ProofClaims>(&first, &decoding, &session_validation).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+
+use super::templates::CodeTemplates;
+use super::{CodeSendError, CodeSender, DeliveryChannel, DeliveryConfigError};
+
+#[derive(Clone, Debug)]
+pub struct SmsConfig {
+    /// Endpoint the messages are posted to.
+    pub url: String,
+    /// Sent as a bearer token when set.
+    pub token: Option<String>,
+    /// Sender number or alphanumeric ID.
+    pub from: String,
+    pub timeout: Duration,
+}
+
+impl SmsConfig {
+    /// Reads `ECO_SMS_*`; `None` when `ECO_SMS_URL` is unset.
+    pub fn from_env() -> Result<Option<Self>, DeliveryConfigError> {
+        let Ok(url) = std::env::var("ECO_SMS_URL") else {
+            return Ok(None);
+        };
+        let from = std::env::var("ECO_SMS_FROM").map_err(|_| {
+            DeliveryConfigError("ECO_SMS_FROM must be set with ECO_SMS_URL".to_string())
+        })?;
+        Ok(Some(Self {
+            url,
+            token: std::env::var("ECO_SMS_TOKEN").ok(),
+            from,
+            timeout: Duration::from_secs(10),
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SmsMessage<'a> {
+    to: &'a str,
+    from: &'a str,
+    body: &'a str,
+}
+
+/// Texts codes by posting `{to, from, body}` as JSON to an SMS gateway.
+pub struct HttpSmsCodeSender {
+    client: Client,
+    config: SmsConfig,
+    templates: CodeTemplates,
+}
+
+impl HttpSmsCodeSender {
+    pub fn new(config: SmsConfig, templates: CodeTemplates) -> Result<Self, DeliveryConfigError> {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|err| DeliveryConfigError(format!("SMS client: {err}")))?;
+        Ok(Self {
+            client,
+            config,
+            templates,
+        })
+    }
+}
+
+#[async_trait]
+impl CodeSender for HttpSmsCodeSender {
+    async fn send_code(
+        &self,
+        channel: DeliveryChannel,
+        identifier: &str,
+        code: &str,
+        locale: &str,
+    ) -> Result<(), CodeSendError> {
+        let message = self.templates.render(channel, locale, code);
+        let mut request = self.client.post(&self.config.url).json(&SmsMessage {
+            to: identifier,
+            from: &self.config.from,
+            body: &message.body,
+        });
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| CodeSendError::Transient(err.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let detail = response.text().await.unwrap_or_default();
+        let message = format!("SMS gateway answered {status}: {detail}");
+        // Other client errors mean the gateway will not take this message.
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            Err(CodeSendError::Transient(message))
+        } else {
+            Err(CodeSendError::Rejected(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{self, HeaderMap};
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    type Received = Arc<Mutex<Vec<(Option<String>, serde_json::Value)>>>;
+
+    /// Serves a fake SMS gateway answering with `status`.
+    async fn fake_gateway(status: http::StatusCode) -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route(
+                "/messages",
+                post(
+                    move |State(received): State<Received>,
+                          headers: HeaderMap,
+                          Json(body): Json<serde_json::Value>| async move {
+                        let auth = headers
+                            .get("authorization")
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        received.lock().unwrap().push((auth, body));
+                        status
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/messages"), received)
+    }
+
+    fn sender(url: String) -> HttpSmsCodeSender {
+        HttpSmsCodeSender::new(
+            SmsConfig {
+                url,
+                token: Some("sms-token".to_string()),
+                from: "ECO".to_string(),
+                timeout: Duration::from_secs(5),
+            },
+            CodeTemplates::builtin(Duration::from_secs(600)),
+        )
+        .expect("sender")
+    }
+
+    #[tokio::test]
+    async fn codes_are_posted_to_the_gateway() {
+        let (url, received) = fake_gateway(http::StatusCode::ACCEPTED).await;
+        sender(url)
+            .send_code(DeliveryChannel::Sms, "+15550100", "123456", "pt-BR")
+            .await
+            .expect("sent");
+        let received = received.lock().unwrap();
+        let (auth, body) = &received[0];
+        assert_eq!(auth.as_deref(), Some("Bearer sms-token"));
+        assert_eq!(body["to"], "+15550100");
+        assert_eq!(body["from"], "ECO");
+        assert!(body["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("123456 é seu código"));
+    }
+
+    #[tokio::test]
+    async fn gateway_errors_are_retryable_unless_the_message_is_refused() {
+        let (url, _) = fake_gateway(http::StatusCode::SERVICE_UNAVAILABLE).await;
+        let err = sender(url)
+            .send_code(DeliveryChannel::Sms, "+15550100", "123456", "en-US")
+            .await
+            .expect_err("unavailable");
+        assert!(matches!(err, CodeSendError::Transient(_)), "{err:?}");
+
+        let (url, _) = fake_gateway(http::StatusCode::BAD_REQUEST).await;
+        let err = sender(url)
+            .send_code(DeliveryChannel::Sms, "not-a-number", "123456", "en-US")
+            .await
+            .expect_err("refused");
+        assert!(matches!(err, CodeSendError::Rejected(_)), "{err:?}");
+    }
+}
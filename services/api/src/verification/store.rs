@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use super::{
+    VerificationConfig, VerificationError, VerificationKey, VerifyOutcome, DEFAULT_LOCALE,
+};
+
+/// A code waiting to be verified. Only its salted hash is kept.
+#[derive(Clone, Debug)]
+pub struct CodeRecord {
+    pub code_hash: Vec<u8>,
+    pub salt: [u8; 16],
+    pub locale: String,
+    pub attempts: u32,
+    pub used: bool,
+    /// Set once the attempts ran out; no new code is issued before then.
+    pub locked_until: Option<SystemTime>,
+    pub last_sent_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+/// What is known about a code before it is stored.
+#[derive(Clone, Debug)]
+pub struct IssuedCode {
+    pub code_hash: Vec<u8>,
+    pub salt: [u8; 16],
+    /// `None` when the request named none, so a resend keeps the locale of
+    /// the code it replaces.
+    pub locale: Option<String>,
+}
+
+impl CodeRecord {
+    /// The record of `code`, replacing `existing`.
+    pub(super) fn new(
+        code: IssuedCode,
+        existing: Option<&CodeRecord>,
+        config: &VerificationConfig,
+        now: SystemTime,
+    ) -> Self {
+        let locale = code
+            .locale
+            .or_else(|| existing.map(|record| record.locale.clone()))
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        Self {
+            code_hash: code.code_hash,
+            salt: code.salt,
+            locale,
+            attempts: 0,
+            used: false,
+            locked_until: None,
+            last_sent_at: now,
+            expires_at: now + config.ttl,
+        }
+    }
+}
+
+/// Where pending codes live. Implementations run [`issue`] and [`verify`]
+/// atomically per identifier and channel, so replicas sharing a store
+/// enforce one resend interval, attempt budget and lockout. Records that
+/// turned [`prunable`] are dropped while issuing.
+#[async_trait]
+pub trait VerificationStore: Send + Sync {
+    /// Stores `code` as the pending code of `key`, replacing the previous
+    /// one unless it is rate limited or locked out, and returns the stored
+    /// record.
+    async fn issue(
+        &self,
+        key: &VerificationKey,
+        code: IssuedCode,
+        config: &VerificationConfig,
+    ) -> Result<CodeRecord, VerificationError>;
+
+    /// Drops the pending code of `key` if it is still the one issued with
+    /// `salt`, as when it could not be delivered.
+    async fn revoke(&self, key: &VerificationKey, salt: &[u8; 16])
+        -> Result<(), VerificationError>;
+
+    /// Counts one attempt against the pending code of `key`; `matches` tells
+    /// whether the submitted code hashes to the stored one.
+    async fn verify(
+        &self,
+        key: &VerificationKey,
+        matches: &(dyn for<'r> Fn(&'r CodeRecord) -> bool + Send + Sync),
+        config: &VerificationConfig,
+    ) -> Result<VerifyOutcome, VerificationError>;
+}
+
+/// Whether a new code may replace `existing` at `now`.
+pub(super) fn check_issue(
+    existing: Option<&CodeRecord>,
+    config: &VerificationConfig,
+    now: SystemTime,
+) -> Result<(), VerificationError> {
+    let Some(existing) = existing else {
+        return Ok(());
+    };
+    if let Some(locked_until) = existing.locked_until {
+        if now < locked_until {
+            return Err(VerificationError::RateLimited(elapsed(now, locked_until)));
+        }
+        return Ok(());
+    }
+    let since_sent = elapsed(existing.last_sent_at, now);
+    if !existing.used && since_sent < config.resend_interval {
+        return Err(VerificationError::RateLimited(
+            config.resend_interval - since_sent,
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `record` no longer affects anything at `now`: expired, past its
+/// resend interval and not locked out, so the store may drop it.
+pub(super) fn prunable(record: &CodeRecord, config: &VerificationConfig, now: SystemTime) -> bool {
+    now >= record.expires_at
+        && elapsed(record.last_sent_at, now) >= config.resend_interval
+        && record
+            .locked_until
+            .is_none_or(|locked_until| now >= locked_until)
+}
+
+/// Applies one attempt to `record`. The store saves the updated record,
+/// or deletes it when the result is [`VerificationError::Expired`].
+pub(super) fn attempt(
+    record: &mut CodeRecord,
+    matches: bool,
+    config: &VerificationConfig,
+    now: SystemTime,
+) -> Result<VerifyOutcome, VerificationError> {
+    // A locked code stays until its lockout ends, even past its expiry, so
+    // it keeps blocking new codes.
+    if record.locked_until.is_some() {
+        return Err(VerificationError::MaxAttempts);
+    }
+    if now >= record.expires_at {
+        return Err(VerificationError::Expired);
+    }
+    if record.used {
+        return Err(VerificationError::AlreadyVerified);
+    }
+
+    record.attempts += 1;
+    let remaining = config.max_attempts.saturating_sub(record.attempts);
+    if matches {
+        record.used = true;
+        Ok(VerifyOutcome {
+            verified: true,
+            remaining_attempts: remaining,
+            proof_token: None,
+        })
+    } else if remaining == 0 {
+        record.locked_until = Some(now + config.lockout);
+        Err(VerificationError::MaxAttempts)
+    } else {
+        Err(VerificationError::CodeMismatch(remaining))
+    }
+}
+
+/// Time from `earlier` to `later`, zero if the clock went backwards.
+fn elapsed(earlier: SystemTime, later: SystemTime) -> Duration {
+    later.duration_since(earlier).unwrap_or_default()
+}
+
+/// Codes kept in process memory; they are lost on restart and not shared
+/// between replicas.
+#[derive(Default)]
+pub struct MemoryVerificationStore {
+    records: RwLock<HashMap<VerificationKey, CodeRecord>>,
+}
+
+#[async_trait]
+impl VerificationStore for MemoryVerificationStore {
+    async fn issue(
+        &self,
+        key: &VerificationKey,
+        code: IssuedCode,
+        config: &VerificationConfig,
+    ) -> Result<CodeRecord, VerificationError> {
+        let now = SystemTime::now();
+        let mut records = self.records.write().await;
+        records.retain(|_, record| !prunable(record, config, now));
+        let existing = records.get(key);
+        check_issue(existing, config, now)?;
+        let record = CodeRecord::new(code, existing, config, now);
+        records.insert(key.clone(), record.clone());
+        Ok(record)
+    }
+
+    async fn revoke(
+        &self,
+        key: &VerificationKey,
+        salt: &[u8; 16],
+    ) -> Result<(), VerificationError> {
+        let mut records = self.records.write().await;
+        if records.get(key).is_some_and(|record| &record.salt == salt) {
+            records.remove(key);
+        }
+        Ok(())
+    }
+
+    async fn verify(
+        &self,
+        key: &VerificationKey,
+        matches: &(dyn for<'r> Fn(&'r CodeRecord) -> bool + Send + Sync),
+        config: &VerificationConfig,
+    ) -> Result<VerifyOutcome, VerificationError> {
+        let mut records = self.records.write().await;
+        let record = records.get_mut(key).ok_or(VerificationError::NotFound)?;
+        let matched = matches(record);
+        let result = attempt(record, matched, config, SystemTime::now());
+        if matches!(result, Err(VerificationError::Expired)) {
+            records.remove(key);
+        }
+        result
+    }
+}
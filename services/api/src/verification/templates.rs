@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use super::{DeliveryChannel, DeliveryConfigError};
+
+/// Locale of codes requested without one, and of codes requested in a
+/// locale without templates.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Subject and body of one verification message. `{code}` and `{minutes}`
+/// are replaced when rendering; SMS messages have no subject.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct MessageTemplate {
+    subject: Option<String>,
+    body: String,
+}
+
+impl MessageTemplate {
+    fn new(subject: Option<&str>, body: &str) -> Self {
+        Self {
+            subject: subject.map(str::to_string),
+            body: body.to_string(),
+        }
+    }
+}
+
+/// A message ready to send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedMessage {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Verification messages per channel and locale.
+#[derive(Clone, Debug)]
+pub struct CodeTemplates {
+    templates: HashMap<(DeliveryChannel, String), MessageTemplate>,
+    /// How long codes stay valid, for `{minutes}`.
+    ttl: Duration,
+}
+
+impl CodeTemplates {
+    /// The built-in English, Spanish, French and Portuguese messages.
+    pub fn builtin(ttl: Duration) -> Self {
+        let mut templates = Self {
+            templates: HashMap::new(),
+            ttl,
+        };
+        let email = [
+            (
+                "en-US",
+                "Your E-CO verification code",
+                "Your verification code is {code}.\n\nIt expires in {minutes} minutes. \
+                 If you did not request it, you can ignore this email.",
+            ),
+            (
+                "es-ES",
+                "Tu código de verificación de E-CO",
+                "Tu código de verificación es {code}.\n\nCaduca en {minutes} minutos. \
+                 Si no lo has solicitado, puedes ignorar este correo.",
+            ),
+            (
+                "fr-FR",
+                "Votre code de vérification E-CO",
+                "Votre code de vérification est {code}.\n\nIl expire dans {minutes} minutes. \
+                 Si vous ne l'avez pas demandé, vous pouvez ignorer cet e-mail.",
+            ),
+            (
+                "pt-BR",
+                "Seu código de verificação do E-CO",
+                "Seu código de verificação é {code}.\n\nEle expira em {minutes} minutos. \
+                 Se você não o solicitou, ignore este e-mail.",
+            ),
+        ];
+        for (locale, subject, body) in email {
+            templates.insert(
+                DeliveryChannel::Email,
+                locale,
+                MessageTemplate::new(Some(subject), body),
+            );
+        }
+        let sms = [
+            (
+                "en-US",
+                "{code} is your E-CO verification code. It expires in {minutes} minutes.",
+            ),
+            (
+                "es-ES",
+                "{code} es tu código de verificación de E-CO. Caduca en {minutes} minutos.",
+            ),
+            (
+                "fr-FR",
+                "{code} est votre code de vérification E-CO. Il expire dans {minutes} minutes.",
+            ),
+            (
+                "pt-BR",
+                "{code} é seu código de verificação do E-CO. Ele expira em {minutes} minutos.",
+            ),
+        ];
+        for (locale, body) in sms {
+            templates.insert(
+                DeliveryChannel::Sms,
+                locale,
+                MessageTemplate::new(None, body),
+            );
+        }
+        templates
+    }
+
+    /// Adds the templates in `dir`, replacing built-in ones of the same
+    /// locale. Emails are read from `email/<locale>.txt`, whose first line
+    /// is the subject and the rest the body; texts from `sms/<locale>.txt`.
+    pub fn load_dir(mut self, dir: &Path) -> Result<Self, DeliveryConfigError> {
+        for channel in [DeliveryChannel::Email, DeliveryChannel::Sms] {
+            let channel_dir = dir.join(channel.to_string());
+            if !channel_dir.is_dir() {
+                continue;
+            }
+            let entries = std::fs::read_dir(&channel_dir).map_err(|err| {
+                DeliveryConfigError(format!("read {}: {err}", channel_dir.display()))
+            })?;
+            for entry in entries {
+                let path = entry
+                    .map_err(|err| DeliveryConfigError(err.to_string()))?
+                    .path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+                    continue;
+                }
+                let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                let text = std::fs::read_to_string(&path).map_err(|err| {
+                    DeliveryConfigError(format!("read {}: {err}", path.display()))
+                })?;
+                let template = match channel {
+                    DeliveryChannel::Email => {
+                        let (subject, body) = text.split_once('\n').ok_or_else(|| {
+                            DeliveryConfigError(format!(
+                                "{} needs a subject line and a body",
+                                path.display()
+                            ))
+                        })?;
+                        MessageTemplate::new(Some(subject.trim()), body.trim())
+                    }
+                    DeliveryChannel::Sms => MessageTemplate::new(None, text.trim()),
+                };
+                self.insert(channel, locale, template);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds or replaces the template of `channel` in `locale`.
+    fn insert(&mut self, channel: DeliveryChannel, locale: &str, template: MessageTemplate) {
+        self.templates
+            .insert((channel, normalize(locale)), template);
+    }
+
+    /// Renders the message of `channel` in `locale`, falling back to the
+    /// locale's language in any region and then to [`DEFAULT_LOCALE`].
+    pub fn render(&self, channel: DeliveryChannel, locale: &str, code: &str) -> RenderedMessage {
+        let template = self.resolve(channel, locale);
+        let minutes = self.ttl.as_secs().div_ceil(60).to_string();
+        let fill = |text: &str| text.replace("{code}", code).replace("{minutes}", &minutes);
+        RenderedMessage {
+            subject: template.subject.as_deref().map(fill).unwrap_or_default(),
+            body: fill(&template.body),
+        }
+    }
+
+    fn resolve(&self, channel: DeliveryChannel, locale: &str) -> &MessageTemplate {
+        let locale = normalize(locale);
+        if let Some(template) = self.templates.get(&(channel, locale.clone())) {
+            return template;
+        }
+        let language = locale.split('-').next().unwrap_or_default();
+        let same_language = self
+            .templates
+            .iter()
+            .filter(|((candidate, other), _)| {
+                *candidate == channel && other.split('-').next() == Some(language)
+            })
+            .min_by(|(a, _), (b, _)| a.1.cmp(&b.1))
+            .map(|(_, template)| template);
+        same_language
+            .or_else(|| {
+                self.templates
+                    .get(&(channel, DEFAULT_LOCALE.to_ascii_lowercase()))
+            })
+            .expect("the fallback locale has a template for every channel")
+    }
+}
+
+/// `pt_br` and `PT-BR` name the same locale as `pt-BR`.
+fn normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_fall_back_to_the_language_and_then_to_english() {
+        let templates = CodeTemplates::builtin(Duration::from_secs(600));
+
+        let french = templates.render(DeliveryChannel::Email, "fr_FR", "123456");
+        assert_eq!(french.subject, "Votre code de vérification E-CO");
+        assert!(french.body.contains("123456"));
+        assert!(french.body.contains("10 minutes"));
+
+        let canadian = templates.render(DeliveryChannel::Sms, "fr-CA", "123456");
+        assert!(canadian.body.starts_with("123456 est votre code"));
+        assert!(canadian.subject.is_empty());
+
+        let unknown = templates.render(DeliveryChannel::Sms, "ja-JP", "654321");
+        assert_eq!(
+            unknown.body,
+            "654321 is your E-CO verification code. It expires in 10 minutes."
+        );
+    }
+
+    #[test]
+    fn templates_in_a_directory_add_and_replace_locales() {
+        let dir = std::env::temp_dir().join(format!("eco-templates-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("email")).unwrap();
+        std::fs::create_dir_all(dir.join("sms")).unwrap();
+        std::fs::write(
+            dir.join("email/de-DE.txt"),
+            "Ihr E-CO-Bestätigungscode\nIhr Code lautet {code}.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("sms/en-US.txt"), "Code: {code}\n").unwrap();
+
+        let templates = CodeTemplates::builtin(Duration::from_secs(600))
+            .load_dir(&dir)
+            .expect("load");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let german = templates.render(DeliveryChannel::Email, "de-AT", "123456");
+        assert_eq!(german.subject, "Ihr E-CO-Bestätigungscode");
+        assert_eq!(german.body, "Ihr Code lautet 123456.");
+        let english = templates.render(DeliveryChannel::Sms, "en-US", "123456");
+        assert_eq!(english.body, "Code: 123456");
+    }
+}
@@ -0,0 +1,33 @@
+-- migrate:up
+-- eco-indexer LISTENs on eco_search_changes and reindexes the named row.
+CREATE OR REPLACE FUNCTION notify_search_change() RETURNS trigger AS $$
+DECLARE
+    row_id UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        row_id := OLD.id;
+    ELSE
+        row_id := NEW.id;
+    END IF;
+    PERFORM pg_notify(
+        'eco_search_changes',
+        json_build_object('table', TG_TABLE_NAME, 'id', row_id)::text
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS artifacts_search_change ON artifacts;
+CREATE TRIGGER artifacts_search_change
+    AFTER INSERT OR UPDATE OR DELETE ON artifacts
+    FOR EACH ROW EXECUTE FUNCTION notify_search_change();
+
+DROP TRIGGER IF EXISTS quests_search_change ON quests;
+CREATE TRIGGER quests_search_change
+    AFTER INSERT OR UPDATE OR DELETE ON quests
+    FOR EACH ROW EXECUTE FUNCTION notify_search_change();
+
+-- migrate:down
+DROP TRIGGER IF EXISTS quests_search_change ON quests;
+DROP TRIGGER IF EXISTS artifacts_search_change ON artifacts;
+DROP FUNCTION IF EXISTS notify_search_change();
@@ -0,0 +1,37 @@
+-- migrate:up
+ALTER TABLE conversations
+    ADD COLUMN IF NOT EXISTS topic TEXT;
+
+DROP TRIGGER IF EXISTS guilds_search_change ON guilds;
+CREATE TRIGGER guilds_search_change
+    AFTER INSERT OR UPDATE OR DELETE ON guilds
+    FOR EACH ROW EXECUTE FUNCTION notify_search_change();
+
+-- Profiles are built from these columns only; password and contact changes
+-- must not re-index them.
+DROP TRIGGER IF EXISTS users_search_change ON users;
+CREATE TRIGGER users_search_change
+    AFTER INSERT OR DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION notify_search_change();
+
+DROP TRIGGER IF EXISTS users_search_update ON users;
+CREATE TRIGGER users_search_update
+    AFTER UPDATE OF display_name, is_guest, profile ON users
+    FOR EACH ROW
+    WHEN (OLD.display_name IS DISTINCT FROM NEW.display_name
+        OR OLD.is_guest IS DISTINCT FROM NEW.is_guest
+        OR OLD.profile IS DISTINCT FROM NEW.profile)
+    EXECUTE FUNCTION notify_search_change();
+
+DROP TRIGGER IF EXISTS conversations_search_change ON conversations;
+CREATE TRIGGER conversations_search_change
+    AFTER INSERT OR UPDATE OR DELETE ON conversations
+    FOR EACH ROW EXECUTE FUNCTION notify_search_change();
+
+-- migrate:down
+DROP TRIGGER IF EXISTS conversations_search_change ON conversations;
+DROP TRIGGER IF EXISTS users_search_update ON users;
+DROP TRIGGER IF EXISTS users_search_change ON users;
+DROP TRIGGER IF EXISTS guilds_search_change ON guilds;
+ALTER TABLE conversations
+    DROP COLUMN IF EXISTS topic;
@@ -0,0 +1,21 @@
+-- migrate:up
+CREATE TABLE IF NOT EXISTS verification_codes (
+    identifier TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    code_hash BYTEA NOT NULL,
+    salt BYTEA NOT NULL,
+    locale TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    locked_until TIMESTAMPTZ,
+    last_sent_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (identifier, channel)
+);
+
+-- Expired codes are pruned whenever a new one is issued.
+CREATE INDEX IF NOT EXISTS verification_codes_expires_at_idx
+    ON verification_codes (expires_at);
+
+-- migrate:down
+DROP TABLE IF EXISTS verification_codes;
@@ -0,0 +1,24 @@
+-- migrate:up
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS phone_number TEXT UNIQUE;
+
+-- Proofs already exchanged for a session, kept until they expire.
+CREATE TABLE IF NOT EXISTS passwordless_proofs (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS passwordless_proofs_expires_at_idx ON passwordless_proofs(expires_at);
+
+-- Passwordless login looks emails up case-insensitively.
+CREATE INDEX IF NOT EXISTS users_lower_email_idx ON users (lower(email));
+
+-- migrate:down
+DROP INDEX IF EXISTS users_lower_email_idx;
+
+DROP TABLE IF EXISTS passwordless_proofs;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS phone_number;
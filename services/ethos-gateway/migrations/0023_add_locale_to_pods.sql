@@ -0,0 +1,9 @@
+-- migrate:up
+-- BCP 47 tag of the language a pod is written in; search ranks worlds in the
+-- caller's language first.
+ALTER TABLE pods
+    ADD COLUMN IF NOT EXISTS locale TEXT;
+
+-- migrate:down
+ALTER TABLE pods
+    DROP COLUMN IF EXISTS locale;
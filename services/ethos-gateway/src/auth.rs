@@ -84,3 +84,32 @@ pub fn decode_token(secret: &str, token: &str) -> Result<Claims, AuthError> {
     .map_err(|_| AuthError)?;
     Ok(decoded.claims)
 }
+
+/// Audience of the proofs eco-api signs when a verification code is
+/// confirmed.
+pub const PASSWORDLESS_AUDIENCE: &str = "ethos-gateway/passwordless";
+
+/// Proof from eco-api that `sub` received a code over `channel`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofClaims {
+    pub sub: String,
+    pub channel: String,
+    pub jti: String,
+    pub exp: usize,
+}
+
+/// Proofs get no clock leeway: used ones are forgotten once `exp` passes,
+/// so a proof accepted after that could be exchanged again.
+pub fn decode_proof(secret: &str, token: &str) -> Result<ProofClaims, AuthError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    validation.set_audience(&[PASSWORDLESS_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+    let decoded = decode::<ProofClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map_err(|_| AuthError)?;
+    Ok(decoded.claims)
+}
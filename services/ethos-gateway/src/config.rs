@@ -4,6 +4,9 @@ use anyhow::Context;
 use serde::Deserialize;
 
 const DEFAULT_CONFIG_PATH: &str = "packages/config/dist/env.json";
+/// Used when `ETHOS_JWT_SECRET` is unset. Anyone can mint tokens with it, so
+/// passwordless proofs signed with it are refused.
+pub const DEV_JWT_SECRET: &str = "insecure-dev-secret";
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -106,7 +109,7 @@ impl GatewayConfig {
         }
 
         let jwt_secret =
-            env::var("ETHOS_JWT_SECRET").unwrap_or_else(|_| "insecure-dev-secret".to_string());
+            env::var("ETHOS_JWT_SECRET").unwrap_or_else(|_| DEV_JWT_SECRET.to_string());
         let http_addr: SocketAddr = env::var("ETHOS_HTTP_ADDR")
             .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
             .parse()?;
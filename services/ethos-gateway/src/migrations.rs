@@ -93,6 +93,26 @@ const MIGRATIONS: &[Migration] = &[
         "0017_create_refresh_sessions.sql",
         include_str!("../migrations/0017_create_refresh_sessions.sql"),
     ),
+    Migration::new(
+        "0019_notify_search_changes.sql",
+        include_str!("../migrations/0019_notify_search_changes.sql"),
+    ),
+    Migration::new(
+        "0020_search_guilds_profiles_conversations.sql",
+        include_str!("../migrations/0020_search_guilds_profiles_conversations.sql"),
+    ),
+    Migration::new(
+        "0021_create_verification_codes.sql",
+        include_str!("../migrations/0021_create_verification_codes.sql"),
+    ),
+    Migration::new(
+        "0022_passwordless_login.sql",
+        include_str!("../migrations/0022_passwordless_login.sql"),
+    ),
+    Migration::new(
+        "0023_add_locale_to_pods.sql",
+        include_str!("../migrations/0023_add_locale_to_pods.sql"),
+    ),
 ];
 
 const DEMO_SEED: Migration = Migration::new(
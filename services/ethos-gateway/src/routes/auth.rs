@@ -5,7 +5,8 @@ use argon2::{
     Argon2,
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use deadpool_postgres::GenericClient;
 use jsonwebtoken::{encode, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -15,6 +16,7 @@ use uuid::Uuid;
 
 use crate::{
     auth::{self, AuthSession},
+    config::DEV_JWT_SECRET,
     state::AppState,
 };
 
@@ -40,6 +42,14 @@ pub struct GuestLoginRequest {
     pub display_name: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PasswordlessLoginRequest {
+    /// Proof token eco-api returned for a verified email or phone number.
+    pub proof: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshRequest {
     pub session_id: String,
@@ -138,7 +148,7 @@ pub async fn login(
         .verify_password(password.as_bytes(), &parsed_hash)
         .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid credentials"))?;
 
-    let refresh = create_refresh_session(&*client, user.id)
+    let refresh = create_refresh_session(&client, user.id)
         .await
         .map_err(|error| {
             error!(error = ?error, "failed to create refresh session during login");
@@ -220,7 +230,7 @@ pub async fn register(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to register user")
     })?;
 
-    let refresh = create_refresh_session(&*client, user.id)
+    let refresh = create_refresh_session(&client, user.id)
         .await
         .map_err(|error| {
             error!(
@@ -290,7 +300,7 @@ pub async fn guest_login(
         )
     })?;
 
-    let refresh = create_refresh_session(&*client, user.id)
+    let refresh = create_refresh_session(&client, user.id)
         .await
         .map_err(|error| {
             error!(
@@ -308,6 +318,204 @@ pub async fn guest_login(
     Ok(Json(response))
 }
 
+/// Signs in the owner of the email or phone number a proof was issued for,
+/// creating the account on first use. A guest calling with their session
+/// is upgraded in place instead, unless the identifier already has an
+/// account.
+pub async fn passwordless_login(
+    Extension(state): Extension<Arc<AppState>>,
+    guest: Option<AuthSession>,
+    Json(request): Json<PasswordlessLoginRequest>,
+) -> Result<Json<SessionResponse>, (StatusCode, &'static str)> {
+    const FAILED: (StatusCode, &str) =
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to authenticate");
+
+    if state.config.jwt_secret == DEV_JWT_SECRET {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Passwordless login is disabled",
+        ));
+    }
+    if request.proof.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Missing proof"));
+    }
+    let proof = auth::decode_proof(&state.config.jwt_secret, request.proof.trim())
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid proof"))?;
+    let jti =
+        Uuid::parse_str(&proof.jti).map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid proof"))?;
+    let expires_at = Utc
+        .timestamp_opt(proof.exp as i64, 0)
+        .single()
+        .ok_or((StatusCode::UNAUTHORIZED, "Invalid proof"))?;
+    let (column, identifier) = match proof.channel.as_str() {
+        "email" => ("email", normalize_email(&proof.sub)),
+        "sms" => ("phone_number", normalize_phone(&proof.sub)),
+        _ => return Err((StatusCode::UNAUTHORIZED, "Invalid proof")),
+    };
+    let identifier = identifier.ok_or((StatusCode::UNAUTHORIZED, "Invalid proof"))?;
+    // Accounts registered with a password keep the case they were given.
+    let lookup = if column == "email" {
+        "lower(email) = $1 ORDER BY email = $1 DESC, created_at LIMIT 1"
+    } else {
+        "phone_number = $1"
+    };
+    let display_name = request
+        .display_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned);
+
+    let mut client = state.db.get().await.map_err(|error| {
+        error!(error = ?error, "failed to acquire database connection for passwordless login");
+        FAILED
+    })?;
+    let tx = client.transaction().await.map_err(|error| {
+        error!(error = ?error, "failed to start passwordless login");
+        FAILED
+    })?;
+
+    let existing = tx
+        .query_opt(
+            &format!(
+                "SELECT id, email, password_hash, display_name, is_guest FROM users \
+                 WHERE {lookup} FOR UPDATE"
+            ),
+            &[&identifier],
+        )
+        .await
+        .map_err(|error| {
+            error!(error = ?error, "failed to fetch user during passwordless login");
+            FAILED
+        })?;
+    let upgraded = match (&existing, guest.filter(|session| session.is_guest)) {
+        (None, Some(session)) => {
+            let guest_id = Uuid::parse_str(&session.user_id)
+                .map_err(|_| (StatusCode::UNAUTHORIZED, "Unauthorized"))?;
+            // An email proof replaces the placeholder guest address; a phone
+            // proof keeps it.
+            tx.query_opt(
+                &format!(
+                    "UPDATE users SET {column} = $2, is_guest = FALSE, \
+                     display_name = COALESCE($3, display_name) \
+                     WHERE id = $1 AND is_guest \
+                     RETURNING id, email, password_hash, display_name, is_guest"
+                ),
+                &[&guest_id, &identifier, &display_name],
+            )
+            .await
+            .map_err(|error| {
+                error!(error = ?error, "failed to upgrade guest during passwordless login");
+                FAILED
+            })?
+        }
+        _ => None,
+    };
+    let row = match existing.or(upgraded) {
+        Some(row) => row,
+        None => {
+            let mut rng = OsRng;
+            let password = Uuid::new_v4().to_string();
+            let password_hash = Argon2::default()
+                .hash_password(password.as_bytes(), &SaltString::generate(&mut rng))
+                .map_err(|_| FAILED)?
+                .to_string();
+            let user_id = Uuid::new_v4();
+            let (email, phone_number) = if column == "email" {
+                (identifier.clone(), None)
+            } else {
+                (
+                    format!("phone+{}@ethos.local", user_id.simple()),
+                    Some(identifier.as_str()),
+                )
+            };
+            tx.query_one(
+                "INSERT INTO users (id, email, phone_number, password_hash, display_name, is_guest) \
+                 VALUES ($1, $2, $3, $4, $5, FALSE) \
+                 RETURNING id, email, password_hash, display_name, is_guest",
+                &[&user_id, &email, &phone_number, &password_hash, &display_name],
+            )
+            .await
+            .map_err(|error| {
+                if error.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+                    return (StatusCode::CONFLICT, "Account was created concurrently");
+                }
+                error!(error = ?error, "failed to create user during passwordless login");
+                FAILED
+            })?
+        }
+    };
+    let user = DbUser::from_row(&row).map_err(|error| {
+        error!(error = ?error, "failed to parse user record");
+        FAILED
+    })?;
+
+    // Recording the proof last keeps a reused one from committing anything.
+    tx.execute(
+        "DELETE FROM passwordless_proofs WHERE expires_at < NOW()",
+        &[],
+    )
+    .await
+    .map_err(|error| {
+        error!(error = ?error, "failed to prune used passwordless proofs");
+        FAILED
+    })?;
+    let recorded = tx
+        .execute(
+            "INSERT INTO passwordless_proofs (jti, user_id, expires_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (jti) DO NOTHING",
+            &[&jti, &user.id, &expires_at],
+        )
+        .await
+        .map_err(|error| {
+            error!(error = ?error, "failed to record passwordless proof");
+            FAILED
+        })?;
+    if recorded == 0 {
+        return Err((StatusCode::UNAUTHORIZED, "Proof already used"));
+    }
+
+    let refresh = create_refresh_session(&tx, user.id)
+        .await
+        .map_err(|error| {
+            error!(error = ?error, "failed to create refresh session during passwordless login");
+            FAILED
+        })?;
+    tx.commit().await.map_err(|error| {
+        error!(error = ?error, "failed to commit passwordless login");
+        FAILED
+    })?;
+
+    let response = build_session_response(state.as_ref(), &user, None, Some(refresh))?;
+
+    Ok(Json(response))
+}
+
+/// Trimmed and lowercased, as eco-api keys the codes it proves.
+fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    (!local.is_empty() && !domain.is_empty()).then_some(email)
+}
+
+/// E.164 as eco-api writes it: `+` and 8 to 15 digits, the first not zero.
+/// Spaces, dots, dashes and parentheses are dropped and a leading `00`
+/// stands for `+`.
+fn normalize_phone(phone: &str) -> Option<String> {
+    let compact: String = phone
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-' | '(' | ')'))
+        .collect();
+    let digits = compact
+        .strip_prefix('+')
+        .or_else(|| compact.strip_prefix("00"))?;
+    let valid = (8..=15).contains(&digits.len())
+        && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && !digits.starts_with('0');
+    valid.then(|| format!("+{digits}"))
+}
+
 pub async fn refresh(
     Extension(state): Extension<Arc<AppState>>,
     Json(request): Json<RefreshRequest>,
@@ -487,7 +695,7 @@ fn build_session_response(
 }
 
 async fn create_refresh_session(
-    client: &tokio_postgres::Client,
+    client: &impl GenericClient,
     user_id: Uuid,
 ) -> Result<RefreshTokenBundle, tokio_postgres::Error> {
     let session_id = Uuid::new_v4();
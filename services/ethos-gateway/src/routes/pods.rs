@@ -25,6 +25,8 @@ pub struct CreatePodRequest {
     pub title: String,
     #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +35,8 @@ pub struct UpdatePodRequest {
     pub title: Option<String>,
     #[serde(default)]
     pub description: Option<Option<String>>,
+    #[serde(default)]
+    pub locale: Option<Option<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -86,9 +90,15 @@ pub async fn create_pod(
     if body.title.trim().is_empty() {
         return Err((StatusCode::BAD_REQUEST, "Pod title is required"));
     }
-    let pod = pods::create_pod(&state.db, owner_id, body.title, body.description)
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create pod"))?;
+    let pod = pods::create_pod(
+        &state.db,
+        owner_id,
+        body.title,
+        body.description,
+        body.locale,
+    )
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create pod"))?;
     Ok((StatusCode::CREATED, Json(pod)))
 }
 
@@ -128,6 +138,7 @@ pub async fn update_pod(
     let changes = PodChanges {
         title: body.title,
         description: body.description,
+        locale: body.locale,
     };
     let updated = pods::update_pod(&state.db, pod_id, owner_id, changes)
         .await
@@ -20,6 +20,9 @@ pub struct Pod {
     pub title: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// BCP 47 tag of the language the pod is written in, such as `pt-BR`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -31,6 +34,7 @@ impl Pod {
             owner_id: row.try_get("owner_id")?,
             title: row.try_get("title")?,
             description: row.try_get("description")?,
+            locale: row.try_get("locale")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
         })
@@ -59,6 +63,7 @@ struct PodSnapshotMetadata {
 pub struct PodChanges {
     pub title: Option<String>,
     pub description: Option<Option<String>>,
+    pub locale: Option<Option<String>>,
 }
 
 pub async fn list_pods(pool: &Pool, owner_id: Option<Uuid>) -> anyhow::Result<Vec<Pod>> {
@@ -70,7 +75,7 @@ pub async fn list_pods(pool: &Pool, owner_id: Option<Uuid>) -> anyhow::Result<Ve
         Some(owner_id) => {
             client
                 .query(
-                    "SELECT id, owner_id, title, description, created_at, updated_at \
+                    "SELECT id, owner_id, title, description, locale, created_at, updated_at \
                      FROM pods WHERE owner_id = $1 ORDER BY created_at DESC",
                     &[&owner_id],
                 )
@@ -79,7 +84,7 @@ pub async fn list_pods(pool: &Pool, owner_id: Option<Uuid>) -> anyhow::Result<Ve
         None => {
             client
                 .query(
-                    "SELECT id, owner_id, title, description, created_at, updated_at \
+                    "SELECT id, owner_id, title, description, locale, created_at, updated_at \
                      FROM pods ORDER BY created_at DESC",
                     &[],
                 )
@@ -95,7 +100,7 @@ pub async fn get_pod(pool: &Pool, id: Uuid) -> anyhow::Result<Option<Pod>> {
     let client = pool.get().await.context("acquire connection for get_pod")?;
     let row = client
         .query_opt(
-            "SELECT id, owner_id, title, description, created_at, updated_at FROM pods WHERE id = $1",
+            "SELECT id, owner_id, title, description, locale, created_at, updated_at FROM pods WHERE id = $1",
             &[&id],
         )
         .await?;
@@ -110,6 +115,7 @@ pub async fn create_pod(
     owner_id: Uuid,
     title: String,
     description: Option<String>,
+    locale: Option<String>,
 ) -> anyhow::Result<Pod> {
     let client = pool
         .get()
@@ -118,12 +124,14 @@ pub async fn create_pod(
     let id = Uuid::new_v4();
     let normalized_description = normalize_text(description);
     let description_param: Option<&str> = normalized_description.as_deref();
+    let normalized_locale = normalize_text(locale);
+    let locale_param: Option<&str> = normalized_locale.as_deref();
     let row = client
         .query_one(
-            "INSERT INTO pods (id, owner_id, title, description) \
-             VALUES ($1, $2, $3, $4) \
-             RETURNING id, owner_id, title, description, created_at, updated_at",
-            &[&id, &owner_id, &title, &description_param],
+            "INSERT INTO pods (id, owner_id, title, description, locale) \
+             VALUES ($1, $2, $3, $4, $5) \
+             RETURNING id, owner_id, title, description, locale, created_at, updated_at",
+            &[&id, &owner_id, &title, &description_param, &locale_param],
         )
         .await?;
     Pod::from_row(&row).map_err(anyhow::Error::from)
@@ -145,7 +153,7 @@ pub async fn update_pod(
         .context("begin transaction for update_pod")?;
     let row = transaction
         .query_opt(
-            "SELECT id, owner_id, title, description, created_at, updated_at \
+            "SELECT id, owner_id, title, description, locale, created_at, updated_at \
              FROM pods WHERE id = $1 FOR UPDATE",
             &[&id],
         )
@@ -165,15 +173,25 @@ pub async fn update_pod(
     if let Some(description) = changes.description.take() {
         pod.description = normalize_text(description);
     }
+    if let Some(locale) = changes.locale.take() {
+        pod.locale = normalize_text(locale);
+    }
     pod.updated_at = Utc::now();
     let description_param: Option<&str> = pod.description.as_deref();
+    let locale_param: Option<&str> = pod.locale.as_deref();
     let row = transaction
         .query_one(
             "UPDATE pods \
-             SET title = $2, description = $3, updated_at = $4 \
+             SET title = $2, description = $3, locale = $4, updated_at = $5 \
              WHERE id = $1 \
-             RETURNING id, owner_id, title, description, created_at, updated_at",
-            &[&pod.id, &pod.title, &description_param, &pod.updated_at],
+             RETURNING id, owner_id, title, description, locale, created_at, updated_at",
+            &[
+                &pod.id,
+                &pod.title,
+                &description_param,
+                &locale_param,
+                &pod.updated_at,
+            ],
         )
         .await?;
     transaction
@@ -213,6 +213,63 @@ async fn refresh_session(
     (status, payload)
 }
 
+fn sign_proof(config: &GatewayConfig, identifier: &str, channel: &str) -> String {
+    sign_proof_expiring(
+        config,
+        identifier,
+        channel,
+        chrono::Utc::now() + chrono::Duration::minutes(5),
+    )
+}
+
+fn sign_proof_expiring(
+    config: &GatewayConfig,
+    identifier: &str,
+    channel: &str,
+    exp: chrono::DateTime<chrono::Utc>,
+) -> String {
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &json!({
+            "sub": identifier,
+            "channel": channel,
+            "jti": Uuid::new_v4().to_string(),
+            "aud": auth::PASSWORDLESS_AUDIENCE,
+            "exp": exp.timestamp(),
+        }),
+        &jsonwebtoken::EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .unwrap()
+}
+
+async fn post_json(
+    app: &Router,
+    uri: &str,
+    bearer: Option<&str>,
+    payload: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let mut request = HttpRequest::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(token) = bearer {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(payload.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+    )
+}
+
 async fn make_stream_test_state() -> (GatewayConfig, Arc<AppState>, Arc<InMemoryRoomService>) {
     let config = make_config();
     let (app_state, room_service, _) = build_state_with_config(&config, true).await;
@@ -327,6 +384,115 @@ async fn refresh_session_rotates_tokens() {
     assert_ne!(third_refresh_token, next_refresh_token);
 }
 
+#[tokio::test]
+async fn passwordless_login_exchanges_single_use_proofs() {
+    let (config, app_state, _room_service, _publisher) = build_state().await;
+    let app = router(app_state.clone());
+
+    // A guest verifying an unused email keeps their account.
+    let (status, guest) = post_json(&app, "/auth/guest", None, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let guest_token = guest["token"].as_str().unwrap();
+    let email = format!("user+{}@example.com", Uuid::new_v4());
+    let proof = sign_proof(&config, &email, "email");
+    let (status, upgraded) = post_json(
+        &app,
+        "/auth/passwordless",
+        Some(guest_token),
+        json!({ "proof": proof }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(upgraded["user"]["id"], guest["user"]["id"]);
+    assert_eq!(upgraded["user"]["email"], email.as_str());
+    assert_eq!(upgraded["user"]["is_guest"], false);
+    assert!(upgraded["refresh_token"].as_str().is_some());
+
+    let (status, _) = post_json(&app, "/auth/passwordless", None, json!({ "proof": proof })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A fresh proof for the same email signs in to that account.
+    let (status, again) = post_json(
+        &app,
+        "/auth/passwordless",
+        None,
+        json!({ "proof": sign_proof(&config, &email, "email") }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["user"]["id"], guest["user"]["id"]);
+
+    // Identifiers match in their normalized form.
+    let (status, shouted) = post_json(
+        &app,
+        "/auth/passwordless",
+        None,
+        json!({ "proof": sign_proof(&config, &format!(" {} ", email.to_uppercase()), "email") }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(shouted["user"]["id"], guest["user"]["id"]);
+
+    // Expired proofs are refused even within the leeway session tokens get,
+    // since used ones are pruned once they expire and could be replayed.
+    let expired = sign_proof_expiring(
+        &config,
+        &email,
+        "email",
+        chrono::Utc::now() - chrono::Duration::seconds(30),
+    );
+    for _ in 0..2 {
+        let (status, _) = post_json(
+            &app,
+            "/auth/passwordless",
+            None,
+            json!({ "proof": expired }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // Phone numbers get an account of their own.
+    let number = format!("+1555{:07}", Uuid::new_v4().as_u128() % 10_000_000);
+    let (status, phone) = post_json(
+        &app,
+        "/auth/passwordless",
+        None,
+        json!({ "proof": sign_proof(&config, &number, "sms"), "display_name": "Ada" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(phone["user"]["id"], guest["user"]["id"]);
+    assert_eq!(phone["user"]["display_name"], "Ada");
+    assert_eq!(phone["user"]["is_guest"], false);
+    let formatted = format!(
+        "00 1 ({}) {}-{}",
+        &number[2..5],
+        &number[5..8],
+        &number[8..]
+    );
+    let (status, again) = post_json(
+        &app,
+        "/auth/passwordless",
+        None,
+        json!({ "proof": sign_proof(&config, &formatted, "sms") }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["user"]["id"], phone["user"]["id"]);
+
+    // Session tokens are not proofs.
+    let session_token = sign_token(&config, &Uuid::new_v4().to_string(), &email);
+    let (status, _) = post_json(
+        &app,
+        "/auth/passwordless",
+        None,
+        json!({ "proof": session_token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
 #[tokio::test]
 async fn rest_conversation_requires_participation() {
     let (_config, app_state, room_service, _publisher) = build_state().await;
@@ -1071,6 +1237,7 @@ async fn rest_pod_crud_and_publish_endpoints() {
     let create_pod = json!({
         "title": "Launch Pod",
         "description": "Alpha iteration",
+        "locale": "pt-BR",
     });
     let response = app
         .clone()
@@ -1091,6 +1258,7 @@ async fn rest_pod_crud_and_publish_endpoints() {
         .unwrap();
     let pod: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
     let pod_id = pod["id"].as_str().unwrap();
+    assert_eq!(pod["locale"], "pt-BR");
 
     let response = app
         .clone()
@@ -1232,6 +1400,7 @@ async fn rest_pod_crud_and_publish_endpoints() {
         .unwrap();
     let snapshot: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
     assert_eq!(snapshot["pod"]["id"].as_str().unwrap(), pod_id);
+    assert_eq!(snapshot["pod"]["locale"], "pt-BR");
     assert!(snapshot["items"].as_array().unwrap().is_empty());
 
     let response = app
@@ -0,0 +1,343 @@
+//! HTTP admin surface of the indexer: its refresh status, reindexing on
+//! demand and the documents it builds, for debugging why something is not
+//! findable.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use search_schema::{SearchSchema, ENTITY_TYPE_FACET_FIELD};
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{FacetCollector, TopDocs};
+use tantivy::query::{AllQuery, TermQuery};
+use tantivy::schema::{Facet, IndexRecordOption, NamedFieldDocument};
+use tantivy::{Document, Searcher, Term};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::warn;
+
+use crate::{IngestionService, ENTITY_TYPES};
+
+/// Reindex requests waiting behind the one running.
+pub const REINDEX_QUEUE: usize = 8;
+
+/// A reindex requested through the admin API.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reindex {
+    /// Builds a new index version from every row and publishes it.
+    Full,
+    /// Replaces the documents of one entity type in a version forked from
+    /// the live one.
+    EntityType(String),
+}
+
+impl Reindex {
+    /// A full reindex without an entity type.
+    fn parse(entity_type: Option<&str>) -> Result<Self, String> {
+        match entity_type {
+            None => Ok(Reindex::Full),
+            Some(entity_type) if ENTITY_TYPES.contains(&entity_type) => {
+                Ok(Reindex::EntityType(entity_type.to_string()))
+            }
+            Some(other) => Err(format!(
+                "unknown entity type `{other}`, expected one of {}",
+                ENTITY_TYPES.join(", ")
+            )),
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Reindex::Full => "full",
+            Reindex::EntityType(entity_type) => entity_type,
+        }
+    }
+}
+
+/// Outcome of the indexer's latest work.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RefreshStatus {
+    /// When the index last took in database changes successfully.
+    pub last_refresh: Option<DateTime<Utc>>,
+    /// The latest failure, until the step that failed succeeds again.
+    pub last_error: Option<RefreshError>,
+}
+
+impl RefreshStatus {
+    /// Notes that `operation` succeeded, clearing its earlier failure. A
+    /// failure of another step stays, since that step may still be broken.
+    pub fn succeeded(&mut self, operation: &str) {
+        if self
+            .last_error
+            .as_ref()
+            .is_some_and(|error| error.operation == operation)
+        {
+            self.last_error = None;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RefreshError {
+    /// Loop step that failed, such as `changes` or `reconcile`.
+    pub operation: &'static str,
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct AdminState {
+    ingestion: Arc<IngestionService>,
+    reindex: mpsc::Sender<Reindex>,
+}
+
+impl AdminState {
+    pub fn new(ingestion: Arc<IngestionService>, reindex: mpsc::Sender<Reindex>) -> Self {
+        Self { ingestion, reindex }
+    }
+}
+
+/// The admin routes. They check no credentials and `/documents` returns
+/// every stored field of private documents too, so the admin API must only
+/// listen on loopback or a private network and never be routed to from
+/// outside.
+pub fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/reindex", post(reindex))
+        .route("/documents/:doc_id", get(document))
+        .with_state(state)
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    /// Published version new ones are derived from.
+    version: String,
+    last_refresh: Option<DateTime<Utc>>,
+    last_error: Option<RefreshError>,
+    total_documents: u64,
+    /// Documents per entity type.
+    documents: BTreeMap<String, u64>,
+}
+
+async fn status(
+    State(state): State<AdminState>,
+) -> Result<Json<StatusResponse>, (StatusCode, String)> {
+    let (version, searcher) = live_searcher(&state.ingestion).await;
+    let documents = document_counts(&searcher).map_err(internal_error)?;
+    let RefreshStatus {
+        last_refresh,
+        last_error,
+    } = state.ingestion.status();
+    Ok(Json(StatusResponse {
+        version,
+        last_refresh,
+        last_error,
+        total_documents: searcher.num_docs(),
+        documents,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReindexParams {
+    #[serde(rename = "type", default)]
+    entity_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReindexResponse {
+    queued: String,
+}
+
+/// Queues a full reindex, or one of `type` only. It runs after the changes
+/// already queued; `/status` reports its outcome.
+async fn reindex(
+    State(state): State<AdminState>,
+    Query(params): Query<ReindexParams>,
+) -> Result<(StatusCode, Json<ReindexResponse>), (StatusCode, String)> {
+    let reindex = Reindex::parse(params.entity_type.as_deref())
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let queued = reindex.label().to_string();
+    state.reindex.try_send(reindex).map_err(|err| match err {
+        TrySendError::Full(_) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many reindexes are queued".to_string(),
+        ),
+        TrySendError::Closed(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "the indexer is shutting down".to_string(),
+        ),
+    })?;
+    Ok((StatusCode::ACCEPTED, Json(ReindexResponse { queued })))
+}
+
+#[derive(Debug, Serialize)]
+struct DocumentResponse {
+    doc_id: String,
+    version: String,
+    /// Stored fields of the document in the index; `None` when it is not
+    /// indexed.
+    indexed: Option<NamedFieldDocument>,
+    /// Every field of the document the database row produces now, the
+    /// indexed-only ones included; `None` when the row is gone or not
+    /// indexable.
+    produced: Option<NamedFieldDocument>,
+    /// Row `produced` was built from.
+    source: Option<String>,
+}
+
+/// Compares the indexed document with what its row produces now. Returns
+/// private documents regardless of visibility or guild; never expose it.
+async fn document(
+    State(state): State<AdminState>,
+    Path(doc_id): Path<String>,
+) -> Result<Json<DocumentResponse>, (StatusCode, String)> {
+    let schema = &state.ingestion.schema;
+    let (version, searcher) = live_searcher(&state.ingestion).await;
+    let indexed = stored_document(schema, &searcher, &doc_id).map_err(internal_error)?;
+    let produced = state
+        .ingestion
+        .produce(&doc_id)
+        .await
+        .map_err(internal_error)?;
+    if indexed.is_none() && produced.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("`{doc_id}` is neither indexed nor produced by a row"),
+        ));
+    }
+    Ok(Json(DocumentResponse {
+        doc_id,
+        version,
+        indexed: indexed.map(|doc| schema.schema.to_named_doc(&doc)),
+        source: produced.as_ref().map(|entity| entity.source.key()),
+        produced: produced.map(|entity| schema.schema.to_named_doc(&entity.to_document(schema))),
+    }))
+}
+
+async fn live_searcher(ingestion: &IngestionService) -> (String, Searcher) {
+    let active = ingestion.active.lock().await;
+    (active.version.clone(), active.reader.searcher())
+}
+
+fn internal_error(err: impl Into<anyhow::Error>) -> (StatusCode, String) {
+    let err = err.into();
+    warn!(?err, "admin request failed");
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+}
+
+fn document_counts(searcher: &Searcher) -> tantivy::Result<BTreeMap<String, u64>> {
+    let mut collector = FacetCollector::for_field(ENTITY_TYPE_FACET_FIELD);
+    collector.add_facet(Facet::from("/type"));
+    let counts = searcher.search(&AllQuery, &collector)?;
+    Ok(counts
+        .get("/type")
+        .filter_map(|(facet, count)| facet.to_path().last().map(|name| (name.to_string(), count)))
+        .collect())
+}
+
+fn stored_document(
+    schema: &SearchSchema,
+    searcher: &Searcher,
+    doc_id: &str,
+) -> tantivy::Result<Option<Document>> {
+    let query = TermQuery::new(
+        Term::from_field_text(schema.doc_id, doc_id),
+        IndexRecordOption::Basic,
+    );
+    searcher
+        .search(&query, &TopDocs::with_limit(1))?
+        .first()
+        .map(|(_, address)| searcher.doc(*address))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::Source;
+    use crate::SearchEntity;
+
+    fn entity(doc_id: &str, entity_type: &str) -> SearchEntity {
+        SearchEntity {
+            doc_id: doc_id.to_string(),
+            entity_id: doc_id.to_string(),
+            entity_type: entity_type.to_string(),
+            owner_id: None,
+            title: Some("Community garden".to_string()),
+            description: None,
+            visibility: "public".to_string(),
+            tags: Vec::new(),
+            status: None,
+            kind: None,
+            content_fragments: vec!["compost".to_string()],
+            guild_ids: Vec::new(),
+            language: None,
+            updated_at: None,
+            source: Source::Quest(uuid::Uuid::nil()),
+        }
+    }
+
+    #[test]
+    fn documents_are_counted_by_type_and_looked_up_by_doc_id() {
+        let schema = SearchSchema::build();
+        let index = schema.create_in_ram();
+        let mut writer = index.writer(15_000_000).expect("writer");
+        for entity in [
+            entity("quest:1", "quest"),
+            entity("quest:2", "quest"),
+            entity("pod:1", "pod"),
+        ] {
+            writer
+                .add_document(entity.to_document(&schema))
+                .expect("add");
+        }
+        search_schema::commit(&mut writer).expect("commit");
+        let searcher = index.reader().expect("reader").searcher();
+
+        let counts = document_counts(&searcher).expect("counts");
+        assert_eq!(
+            counts,
+            BTreeMap::from([("pod".to_string(), 1), ("quest".to_string(), 2)])
+        );
+        let doc = stored_document(&schema, &searcher, "quest:2")
+            .expect("lookup")
+            .expect("indexed");
+        let named = schema.schema.to_named_doc(&doc);
+        assert!(named.0.contains_key("title"));
+        // Indexed-only fields are not stored.
+        assert!(!named.0.contains_key("content"));
+        assert!(stored_document(&schema, &searcher, "quest:3")
+            .expect("lookup")
+            .is_none());
+    }
+
+    #[test]
+    fn errors_clear_once_the_failed_step_succeeds() {
+        let mut status = RefreshStatus {
+            last_refresh: None,
+            last_error: Some(RefreshError {
+                operation: "reconcile",
+                message: "connection refused".to_string(),
+                at: Utc::now(),
+            }),
+        };
+        status.succeeded("changes");
+        assert!(status.last_error.is_some());
+        status.succeeded("reconcile");
+        assert!(status.last_error.is_none());
+    }
+
+    #[test]
+    fn reindex_requests_name_a_known_entity_type() {
+        assert_eq!(Reindex::parse(None), Ok(Reindex::Full));
+        assert_eq!(
+            Reindex::parse(Some("quest")),
+            Ok(Reindex::EntityType("quest".to_string()))
+        );
+        assert!(Reindex::parse(Some("quests")).is_err());
+    }
+}
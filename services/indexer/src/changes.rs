@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+/// Channel the gateway's `notify_search_change` trigger notifies.
+pub const NOTIFY_CHANNEL: &str = "eco_search_changes";
+/// Subjects of the quest events ethos-gateway publishes.
+pub const QUEST_EVENT_SUBJECTS: &str = "ethos.quests.*";
+
+/// Database row that search documents are built from. A change to the row
+/// replaces every document built from it, which also covers rows that stop
+/// producing documents (a pod snapshot made private, a deleted quest).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// An `artifacts` row; pod snapshots are artifacts too.
+    Artifact(Uuid),
+    Quest(Uuid),
+    Guild(Uuid),
+    /// A `users` row, indexed as a profile when the user opted in.
+    User(Uuid),
+    /// A `conversations` row, indexed for its guild when it has a topic.
+    Conversation(Uuid),
+}
+
+impl Source {
+    pub fn from_table(table: &str, id: Uuid) -> Option<Self> {
+        match table {
+            "artifacts" => Some(Source::Artifact(id)),
+            "quests" => Some(Source::Quest(id)),
+            "guilds" => Some(Source::Guild(id)),
+            "users" => Some(Source::User(id)),
+            "conversations" => Some(Source::Conversation(id)),
+            _ => None,
+        }
+    }
+
+    /// Value of the `source` field on the row's documents.
+    pub fn key(&self) -> String {
+        match self {
+            Source::Artifact(id) => format!("artifact:{id}"),
+            Source::Quest(id) => format!("quest:{id}"),
+            Source::Guild(id) => format!("guild:{id}"),
+            Source::User(id) => format!("user:{id}"),
+            Source::Conversation(id) => format!("conversation:{id}"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RowChange {
+    table: String,
+    id: Uuid,
+}
+
+/// The part of ethos-gateway's `QuestEvent` the indexer needs.
+#[derive(Deserialize)]
+struct QuestEvent {
+    quest_id: Uuid,
+    event: String,
+}
+
+pub fn parse_notification(payload: &str) -> Option<Source> {
+    let change: RowChange = serde_json::from_str(payload).ok()?;
+    Source::from_table(&change.table, change.id)
+}
+
+/// Only `quest.*` events touch the quest row; application events do not.
+pub fn parse_quest_event(payload: &[u8]) -> Option<Source> {
+    let event: QuestEvent = serde_json::from_slice(payload).ok()?;
+    event
+        .event
+        .starts_with("quest.")
+        .then_some(Source::Quest(event.quest_id))
+}
+
+/// Forwards row changes notified on [`NOTIFY_CHANNEL`] until the connection
+/// drops.
+pub async fn listen(database_url: &str, changes: mpsc::Sender<Source>) -> Result<()> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls)
+        .await
+        .context("connect change listener")?;
+    let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+    let forward = tokio::spawn(async move {
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    match parse_notification(notification.payload()) {
+                        Some(source) => {
+                            if changes.send(source).await.is_err() {
+                                break;
+                            }
+                        }
+                        None => debug!(payload = notification.payload(), "ignored notification"),
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    error!(?err, "change listener connection error");
+                    break;
+                }
+            }
+        }
+    });
+    client
+        .batch_execute(&format!("LISTEN {NOTIFY_CHANNEL}"))
+        .await
+        .context("listen for search changes")?;
+    forward.await.context("change listener task")?;
+    Ok(())
+}
+
+/// Forwards quest events published on NATS until the subscription ends.
+pub async fn subscribe_quest_events(nats_url: &str, changes: mpsc::Sender<Source>) -> Result<()> {
+    let client = async_nats::connect(nats_url)
+        .await
+        .context("connect to NATS")?;
+    let mut subscriber = client
+        .subscribe(QUEST_EVENT_SUBJECTS)
+        .await
+        .context("subscribe to quest events")?;
+    while let Some(message) = subscriber.next().await {
+        if let Some(source) = parse_quest_event(&message.payload) {
+            if changes.send(source).await.is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_and_quest_events_name_their_rows() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            parse_notification(&format!(r#"{{"table":"artifacts","id":"{id}"}}"#)),
+            Some(Source::Artifact(id))
+        );
+        assert_eq!(
+            parse_notification(&format!(r#"{{"table":"users","id":"{id}"}}"#)),
+            Some(Source::User(id))
+        );
+        assert_eq!(
+            parse_notification(&format!(r#"{{"table":"conversations","id":"{id}"}}"#)),
+            Some(Source::Conversation(id))
+        );
+        assert_eq!(
+            parse_notification(&format!(r#"{{"table":"orders","id":"{id}"}}"#)),
+            None
+        );
+        let event = |name: &str| {
+            format!(r#"{{"quest_id":"{id}","event":"{name}","data":{{}}}}"#).into_bytes()
+        };
+        assert_eq!(
+            parse_quest_event(&event("quest.deleted")),
+            Some(Source::Quest(id))
+        );
+        assert_eq!(parse_quest_event(&event("application.submitted")), None);
+        assert_eq!(Source::Quest(id).key(), format!("quest:{id}"));
+    }
+}
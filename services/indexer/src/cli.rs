@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use search_schema::IndexLayout;
+
+const USAGE: &str = "usage: eco-indexer [versions | publish <version> | \
+                     export <dir> [version] | import <dir> [--publish]]";
+
+/// What the indexer was started to do. Every command other than `Run`
+/// works on the index directory alone and exits.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Index continuously from the database.
+    Run,
+    /// Lists index versions, marking the live one.
+    Versions,
+    /// Makes an existing version live, as on a rollback.
+    Publish(String),
+    /// Copies a version, the live one by default, into a directory.
+    Export {
+        dir: PathBuf,
+        version: Option<String>,
+    },
+    /// Adds an exported index as a new version.
+    Import { dir: PathBuf, publish: bool },
+}
+
+impl Command {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let args: Vec<String> = args.into_iter().collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let command = match args.as_slice() {
+            [] => Command::Run,
+            ["versions"] => Command::Versions,
+            ["publish", version] => Command::Publish(version.to_string()),
+            ["export", dir] => Command::Export {
+                dir: PathBuf::from(dir),
+                version: None,
+            },
+            ["export", dir, version] => Command::Export {
+                dir: PathBuf::from(dir),
+                version: Some(version.to_string()),
+            },
+            ["import", dir] => Command::Import {
+                dir: PathBuf::from(dir),
+                publish: false,
+            },
+            ["import", dir, "--publish"] => Command::Import {
+                dir: PathBuf::from(dir),
+                publish: true,
+            },
+            _ => bail!(USAGE),
+        };
+        Ok(command)
+    }
+}
+
+/// Runs a command other than [`Command::Run`]. A running indexer follows
+/// versions published here on its next watermark poll.
+pub fn execute(command: Command, layout: &IndexLayout) -> Result<()> {
+    match command {
+        Command::Run => bail!("the indexer service is not an index command"),
+        Command::Versions => {
+            let current = layout.current()?;
+            for version in layout.versions()? {
+                let marker = if current.as_deref() == Some(version.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{marker} {version}");
+            }
+        }
+        Command::Publish(version) => {
+            layout.publish(&version).context("publish index version")?;
+            println!("published {version}");
+        }
+        Command::Export { dir, version } => {
+            let version = match version {
+                Some(version) => version,
+                None => layout
+                    .current()?
+                    .context("no index version has been published")?,
+            };
+            layout
+                .export(&version, &dir)
+                .context("export index version")?;
+            println!("exported {version} to {}", dir.display());
+        }
+        Command::Import { dir, publish } => {
+            let version = layout.import(&dir).context("import index snapshot")?;
+            if publish {
+                layout.publish(&version).context("publish index version")?;
+                println!("imported and published {version}");
+            } else {
+                println!("imported {version}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn commands_parse_from_arguments() {
+        assert_eq!(parse(&[]).unwrap(), Command::Run);
+        assert_eq!(
+            parse(&["publish", "000000000000042"]).unwrap(),
+            Command::Publish("000000000000042".to_string())
+        );
+        assert_eq!(
+            parse(&["export", "/tmp/snapshot"]).unwrap(),
+            Command::Export {
+                dir: PathBuf::from("/tmp/snapshot"),
+                version: None,
+            }
+        );
+        assert_eq!(
+            parse(&["import", "/tmp/snapshot", "--publish"]).unwrap(),
+            Command::Import {
+                dir: PathBuf::from("/tmp/snapshot"),
+                publish: true,
+            }
+        );
+        assert!(parse(&["publish"]).is_err());
+        assert!(parse(&["import", "/tmp/snapshot", "--force"]).is_err());
+    }
+}
@@ -1,30 +1,69 @@
-use std::path::{Path, PathBuf};
+mod admin;
+mod changes;
+mod cli;
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use admin::{RefreshError, RefreshStatus, Reindex};
+use anyhow::{bail, Context, Result};
+use changes::Source;
 use chrono::{DateTime, Utc};
 use quest_status::PUBLIC_QUEST_STATUSES;
+use search_schema::{
+    primary_language, IndexLayout, SearchSchema, ARTIFACT_ENTITY_TYPE, CONVERSATION_ENTITY_TYPE,
+    ENTITY_TYPES, GUILD_ENTITY_TYPE, POD_ENTITY_TYPE, QUEST_ENTITY_TYPE, USER_ENTITY_TYPE,
+};
 use serde::Deserialize;
 use serde_json::Value;
-use tantivy::schema::{Facet, FacetOptions, Field, Schema, STORED, STRING, TEXT};
-use tantivy::{Document, Index, IndexWriter};
-use tokio::sync::Mutex;
+use tantivy::collector::DocSetCollector;
+use tantivy::query::TermQuery;
+use tantivy::schema::{Facet, IndexRecordOption};
+use tantivy::{
+    DateTime as TantivyDateTime, Document, Index, IndexReader, IndexWriter, ReloadPolicy, Searcher,
+    Term,
+};
+use tokio::sync::{mpsc, Mutex};
 use tokio_postgres::{Client, NoTls};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+use vector_search::{
+    Embedder, HashingEmbedder, VectorBackend, VectorConfig, VectorPoint, VectorStore,
+};
 
 const POD_SNAPSHOT_TYPE: &str = "pod_snapshot";
+/// Visibility of documents only members of their `guild_ids` may see.
+const GUILD_VISIBILITY: &str = "guild";
+/// Profile keys whose string lists become a profile's tags.
+const PROFILE_TAG_KEYS: [&str; 3] = ["tags", "interests", "skills"];
 const DEFAULT_INDEX_PATH: &str = "./.tmp/index";
+const WRITER_HEAP_BYTES: usize = 50_000_000;
 const DEFAULT_REFRESH_SECS: u64 = 30;
+const DEFAULT_RECONCILE_SECS: u64 = 3600;
+const DEFAULT_KEEP_VERSIONS: usize = 3;
+const DEFAULT_ADMIN_ADDR: &str = "127.0.0.1:8081";
+/// Row changes queued ahead of the indexer before feeds wait.
+const CHANGE_BUFFER: usize = 1024;
+/// Pause before reconnecting a change feed that failed or ended.
+const FEED_RETRY: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
+    let layout = IndexLayout::new(index_path_from_env());
+    match cli::Command::parse(std::env::args().skip(1))? {
+        cli::Command::Run => run(layout).await,
+        command => cli::execute(command, &layout),
+    }
+}
+
+async fn run(layout: IndexLayout) -> Result<()> {
     let config = IndexerConfig::from_env()?;
     let schema = SearchSchema::build();
-    let index = open_or_create_index(&config.index_path, &schema.schema)?;
-    let writer = Arc::new(Mutex::new(index.writer(50_000_000)?));
 
     let (client, connection) = tokio_postgres::connect(&config.database_url, NoTls)
         .await
@@ -36,35 +75,166 @@ async fn main() -> Result<()> {
     });
     let client = Arc::new(client);
 
-    let ingestion = IngestionService::new(client.clone(), schema.clone(), writer.clone());
+    let vectors = VectorConfig::from_env().context("vector search configuration")?;
+    if vectors.backend == VectorBackend::Disabled {
+        info!("vector search disabled, indexing text only");
+    }
+    let embeddings =
+        (vectors.backend != VectorBackend::Disabled).then(|| EmbeddingStage::new(vectors.clone()));
+
+    // An index of another schema version is never read; until the first
+    // build is published, an empty placeholder stands in for it.
+    let (version, index, transient) = match layout.open_current() {
+        Ok((version, index)) => (version, index, false),
+        Err(err) => {
+            warn!(?err, "no usable published index, building a new version");
+            let (version, index) = layout.create_version(&schema)?;
+            (version, index, true)
+        }
+    };
+    let vectors = vectors
+        .open_store(&layout.version_path(&version))
+        .await
+        .context("open vector store")?;
+    let active =
+        ActiveIndex::open(version, &index, vectors, transient).context("open index version")?;
+
+    let ingestion = Arc::new(IngestionService::new(
+        client.clone(),
+        schema.clone(),
+        layout,
+        config.keep_versions,
+        active,
+        embeddings,
+    ));
     let initial_count = ingestion
-        .refresh_index()
+        .reconcile(true)
         .await
-        .context("initial index refresh")?;
+        .context("initial index reconciliation")?;
+    ingestion.record("reconcile", &Ok(initial_count));
     info!(count = initial_count, "initial indexing complete");
 
-    let mut interval = tokio::time::interval(config.refresh_interval);
+    let (reindex_tx, mut reindex_rx) = mpsc::channel(admin::REINDEX_QUEUE);
+    let listener = tokio::net::TcpListener::bind(config.admin_addr)
+        .await
+        .context("bind admin API")?;
+    info!(addr = %config.admin_addr, "indexer admin API listening");
+    let app = admin::router(admin::AdminState::new(ingestion.clone(), reindex_tx));
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, app.into_make_service()).await {
+            error!(?err, "admin API stopped");
+        }
+    });
+
+    let (changes_tx, mut changes_rx) = mpsc::channel(CHANGE_BUFFER);
+    let database_url = config.database_url.clone();
+    spawn_feed("postgres", changes_tx.clone(), move |changes| {
+        let database_url = database_url.clone();
+        async move { changes::listen(&database_url, changes).await }
+    });
+    if let Some(nats_url) = config.nats_url.clone() {
+        spawn_feed("nats", changes_tx.clone(), move |changes| {
+            let nats_url = nats_url.clone();
+            async move { changes::subscribe_quest_events(&nats_url, changes).await }
+        });
+    }
+    drop(changes_tx);
+
+    let mut watermark = tokio::time::interval(config.refresh_interval);
+    let mut reconcile = tokio::time::interval(config.reconcile_interval);
+    // Both fire immediately; the reconciliation above already covered that.
+    watermark.tick().await;
+    reconcile.tick().await;
     loop {
-        interval.tick().await;
-        match ingestion.refresh_index().await {
-            Ok(count) => info!(count, "index refresh complete"),
-            Err(err) => error!(?err, "index refresh failed"),
+        tokio::select! {
+            Some(source) = changes_rx.recv() => {
+                let mut batch = HashSet::from([source]);
+                while let Ok(source) = changes_rx.try_recv() {
+                    batch.insert(source);
+                }
+                let result = ingestion.apply_changes(&batch).await;
+                ingestion.record("changes", &result);
+                match result {
+                    Ok(count) => debug!(count, "applied search changes"),
+                    Err(err) => error!(?err, "applying search changes failed"),
+                }
+            }
+            // Queued here rather than run by the admin API, so reindexing
+            // never interleaves with change batches.
+            Some(reindex) = reindex_rx.recv() => {
+                let result = ingestion.reindex(&reindex).await;
+                ingestion.record("reindex", &result);
+                match result {
+                    Ok(count) => info!(count, ?reindex, "reindex complete"),
+                    Err(err) => error!(?err, ?reindex, "reindex failed"),
+                }
+            }
+            _ = watermark.tick() => {
+                match ingestion.follow_pointer().await {
+                    Ok(()) => ingestion.lock_status().succeeded("follow"),
+                    Err(err) => {
+                        ingestion.record_error("follow", &err);
+                        error!(?err, "following the published index version failed");
+                    }
+                }
+                let result = ingestion.poll_watermark().await;
+                ingestion.record("watermark", &result);
+                match result {
+                    Ok(count) => debug!(count, "watermark poll complete"),
+                    Err(err) => error!(?err, "watermark poll failed"),
+                }
+            }
+            _ = reconcile.tick() => {
+                let result = ingestion.reconcile(false).await;
+                ingestion.record("reconcile", &result);
+                match result {
+                    Ok(count) => info!(count, "index reconciliation complete"),
+                    Err(err) => error!(?err, "index reconciliation failed"),
+                }
+            }
         }
     }
 }
 
+/// Keeps a change feed running, reconnecting whenever it fails or ends.
+/// Changes missed while it is down are caught by the watermark poll or the
+/// next reconciliation.
+fn spawn_feed<F, Fut>(name: &'static str, changes: mpsc::Sender<Source>, connect: F)
+where
+    F: Fn(mpsc::Sender<Source>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    tokio::spawn(async move {
+        loop {
+            match connect(changes.clone()).await {
+                Ok(()) => warn!(feed = name, "change feed ended, reconnecting"),
+                Err(err) => warn!(feed = name, ?err, "change feed failed, reconnecting"),
+            }
+            if changes.is_closed() {
+                break;
+            }
+            tokio::time::sleep(FEED_RETRY).await;
+        }
+    });
+}
+
 #[derive(Clone)]
 struct IndexerConfig {
-    index_path: PathBuf,
     database_url: String,
+    /// How often rows changed since the last poll are looked up.
     refresh_interval: Duration,
+    /// How often the whole index is compared against the database.
+    reconcile_interval: Duration,
+    nats_url: Option<String>,
+    /// Index versions kept for rollback, the live one included.
+    keep_versions: usize,
+    /// Unauthenticated and serves private documents, so never a public
+    /// address.
+    admin_addr: SocketAddr,
 }
 
 impl IndexerConfig {
     fn from_env() -> Result<Self> {
-        let index_path = std::env::var("ECO_INDEX_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from(DEFAULT_INDEX_PATH));
         let database_url = std::env::var("ETHOS_DATABASE_URL")
             .or_else(|_| std::env::var("DATABASE_URL"))
             .context("DATABASE_URL or ETHOS_DATABASE_URL must be set")?;
@@ -73,77 +243,91 @@ impl IndexerConfig {
             .and_then(|value| value.parse().ok())
             .map(Duration::from_secs)
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_REFRESH_SECS));
+        let reconcile_interval = std::env::var("ECO_INDEX_RECONCILE_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_RECONCILE_SECS));
+        let nats_url = std::env::var("ETHOS_NATS_URL").ok();
+        let keep_versions = std::env::var("ECO_INDEX_KEEP_VERSIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_KEEP_VERSIONS);
+        let admin_addr = std::env::var("ECO_INDEXER_ADMIN_ADDR")
+            .unwrap_or_else(|_| DEFAULT_ADMIN_ADDR.to_string())
+            .parse()
+            .context("ECO_INDEXER_ADMIN_ADDR must be a socket address")?;
 
         Ok(Self {
-            index_path,
             database_url,
             refresh_interval,
+            reconcile_interval,
+            nats_url,
+            keep_versions,
+            admin_addr,
         })
     }
 }
 
-#[derive(Clone)]
-struct SearchSchema {
-    schema: Schema,
-    doc_id: Field,
-    entity_id: Field,
-    entity_type: Field,
-    entity_type_facet: Field,
-    owner_id: Field,
-    title: Field,
-    description: Field,
-    visibility: Field,
-    visibility_facet: Field,
-    tags: Field,
-    tag_facet: Field,
-    status: Field,
-    kind: Field,
-    content: Field,
-}
-
-impl SearchSchema {
-    fn build() -> Self {
-        let mut builder = Schema::builder();
-        let doc_id = builder.add_text_field("doc_id", STRING | STORED);
-        let entity_id = builder.add_text_field("entity_id", STRING | STORED);
-        let entity_type = builder.add_text_field("entity_type", STRING | STORED);
-        let entity_type_facet =
-            builder.add_facet_field("entity_type_facet", FacetOptions::default().set_stored());
-        let owner_id = builder.add_text_field("owner_id", STRING | STORED);
-        let title = builder.add_text_field("title", TEXT | STORED);
-        let description = builder.add_text_field("description", TEXT | STORED);
-        let visibility = builder.add_text_field("visibility", STRING | STORED);
-        let visibility_facet =
-            builder.add_facet_field("visibility_facet", FacetOptions::default().set_stored());
-        let tags = builder.add_text_field("tags", TEXT | STORED);
-        let tag_facet = builder.add_facet_field("tag_facet", FacetOptions::default().set_stored());
-        let status = builder.add_text_field("status", STRING | STORED);
-        let kind = builder.add_text_field("kind", STRING | STORED);
-        let content = builder.add_text_field("content", TEXT);
-        let schema = builder.build();
-        Self {
-            schema,
-            doc_id,
-            entity_id,
-            entity_type,
-            entity_type_facet,
-            owner_id,
-            title,
-            description,
-            visibility,
-            visibility_facet,
-            tags,
-            tag_facet,
-            status,
-            kind,
-            content,
-        }
+/// Root of the versioned index directory, shared with eco-api.
+fn index_path_from_env() -> PathBuf {
+    std::env::var("ECO_INDEX_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_INDEX_PATH))
+}
+
+/// Swaps the documents built from `source` for `current` and returns the
+/// `doc_id`s the row no longer produces.
+fn replace_source(
+    schema: &SearchSchema,
+    writer: &mut IndexWriter,
+    searcher: &Searcher,
+    source: Source,
+    current: &[SearchEntity],
+) -> Result<Vec<String>> {
+    let key = source.key();
+    let removed = doc_ids_of(schema, searcher, Term::from_field_text(schema.source, &key))?
+        .into_iter()
+        .filter(|doc_id| !current.iter().any(|entity| &entity.doc_id == doc_id))
+        .collect();
+    writer.delete_term(Term::from_field_text(schema.source, &key));
+    for entity in current {
+        // Another row may have produced the same doc_id, such as an
+        // older snapshot of a republished pod; the latest write wins.
+        writer.delete_term(Term::from_field_text(schema.doc_id, &entity.doc_id));
+        writer.add_document(entity.to_document(schema))?;
+    }
+    Ok(removed)
+}
+
+/// `doc_id`s of the documents holding `term`, such as the `source` of the
+/// row they were built from.
+fn doc_ids_of(schema: &SearchSchema, searcher: &Searcher, term: Term) -> Result<Vec<String>> {
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let mut doc_ids = Vec::new();
+    for address in searcher.search(&query, &DocSetCollector)? {
+        let doc = searcher.doc(address)?;
+        doc_ids.extend(
+            doc.get_first(schema.doc_id)
+                .and_then(|value| value.as_text())
+                .map(str::to_string),
+        );
     }
+    Ok(doc_ids)
+}
 
-    #[allow(dead_code)]
-    fn query_fields(&self) -> Vec<Field> {
-        vec![self.title, self.description, self.tags, self.content]
+/// Every `doc_id` in the term dictionary. Terms of deleted documents may
+/// linger until segments merge, which only costs a redundant delete.
+fn indexed_doc_ids(schema: &SearchSchema, searcher: &Searcher) -> Result<HashSet<String>> {
+    let mut doc_ids = HashSet::new();
+    for segment in searcher.segment_readers() {
+        let inverted_index = segment.inverted_index(schema.doc_id)?;
+        let mut terms = inverted_index.terms().stream()?;
+        while terms.advance() {
+            doc_ids.insert(String::from_utf8_lossy(terms.key()).into_owned());
+        }
     }
+    Ok(doc_ids)
 }
 
 #[derive(Debug, Clone)]
@@ -159,12 +343,29 @@ struct SearchEntity {
     status: Option<String>,
     kind: Option<String>,
     content_fragments: Vec<String>,
+    /// Guilds whose members may see the entity regardless of its visibility.
+    guild_ids: Vec<String>,
+    /// Primary language subtag of the entity's text, when known.
+    language: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+    source: Source,
 }
 
 impl SearchEntity {
+    /// What the entity is about, without the raw metadata strings that go
+    /// into `content`.
+    fn embedding_text(&self) -> String {
+        let mut parts: Vec<&str> = Vec::new();
+        parts.extend(self.title.as_deref());
+        parts.extend(self.description.as_deref());
+        parts.extend(self.tags.iter().map(String::as_str));
+        parts.join(". ")
+    }
+
     fn to_document(&self, schema: &SearchSchema) -> Document {
         let mut doc = Document::new();
         doc.add_text(schema.doc_id, &self.doc_id);
+        doc.add_text(schema.source, self.source.key());
         doc.add_text(schema.entity_id, &self.entity_id);
         doc.add_text(schema.entity_type, &self.entity_type);
         let type_facet_path = format!("/type/{}", normalize_facet_value(&self.entity_type));
@@ -176,6 +377,10 @@ impl SearchEntity {
         if let Some(title) = &self.title {
             doc.add_text(schema.title, title);
             doc.add_text(schema.content, title);
+            doc.add_text(schema.title_sort, title.trim().to_lowercase());
+            for suffix in word_suffixes(title) {
+                doc.add_text(schema.title_suggest, suffix);
+            }
         }
         if let Some(description) = &self.description {
             doc.add_text(schema.description, description);
@@ -199,6 +404,7 @@ impl SearchEntity {
                 continue;
             }
             doc.add_text(schema.tags, tag);
+            doc.add_text(schema.tag_suggest, tag);
             let tag_facet_path = format!("/tag/{}", normalize_facet_value(tag));
             let facet = Facet::from(tag_facet_path.as_str());
             doc.add_facet(schema.tag_facet, facet);
@@ -210,25 +416,187 @@ impl SearchEntity {
             }
             doc.add_text(schema.content, fragment);
         }
+        for guild_id in &self.guild_ids {
+            doc.add_text(schema.guild_ids, guild_id);
+            let guild_facet_path = format!("/guild/{}", normalize_facet_value(guild_id));
+            doc.add_facet(schema.guild_facet, Facet::from(guild_facet_path.as_str()));
+        }
+        if let Some(language) = &self.language {
+            doc.add_text(schema.language, language);
+        }
+        if let Some(updated_at) = self.updated_at {
+            doc.add_date(
+                schema.updated_at,
+                TantivyDateTime::from_timestamp_secs(updated_at.timestamp()),
+            );
+        }
         doc
     }
 }
 
+/// Embeds search entities and keeps each version's vector store in step
+/// with its Tantivy index.
+struct EmbeddingStage {
+    config: VectorConfig,
+    embedder: HashingEmbedder,
+}
+
+impl EmbeddingStage {
+    fn new(config: VectorConfig) -> Self {
+        Self {
+            embedder: config.embedder(),
+            config,
+        }
+    }
+
+    /// Entities without any text to embed are left out, since every vector
+    /// would be equally far from them.
+    fn points(&self, entities: &[SearchEntity]) -> Vec<VectorPoint> {
+        entities
+            .iter()
+            .filter_map(|entity| {
+                let vector = self.embedder.embed(&entity.embedding_text());
+                vector
+                    .iter()
+                    .any(|value| *value != 0.0)
+                    .then(|| VectorPoint {
+                        doc_id: entity.doc_id.clone(),
+                        vector,
+                    })
+            })
+            .collect()
+    }
+
+    /// Embeds `entities` into `store` and drops the vectors of `removed`
+    /// doc_ids.
+    async fn apply(
+        &self,
+        store: &dyn VectorStore,
+        entities: &[SearchEntity],
+        removed: &[String],
+    ) -> Result<()> {
+        store.upsert(self.points(entities)).await?;
+        store.delete(removed).await?;
+        store.commit().await?;
+        Ok(())
+    }
+}
+
+/// The published index version the indexer derives the next one from.
+/// Published versions are never written to.
+struct ActiveIndex {
+    version: String,
+    reader: IndexReader,
+    vectors: Option<Arc<dyn VectorStore>>,
+    /// Whether the version only carried a change batch, or is the
+    /// placeholder used before the first build, and can go once replaced.
+    transient: bool,
+}
+
+impl ActiveIndex {
+    fn open(
+        version: String,
+        index: &Index,
+        vectors: Option<Arc<dyn VectorStore>>,
+        transient: bool,
+    ) -> Result<Self> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        Ok(Self {
+            version,
+            reader,
+            vectors,
+            transient,
+        })
+    }
+}
+
+/// An unpublished version being written.
+struct Draft {
+    version: String,
+    index: Index,
+    writer: IndexWriter,
+    vectors: Option<Arc<dyn VectorStore>>,
+}
+
+impl Draft {
+    fn open(version: String, index: Index, vectors: Option<Arc<dyn VectorStore>>) -> Result<Self> {
+        let writer = index.writer(WRITER_HEAP_BYTES)?;
+        Ok(Self {
+            version,
+            index,
+            writer,
+            vectors,
+        })
+    }
+}
+
 struct IngestionService {
     client: Arc<Client>,
     schema: SearchSchema,
-    writer: Arc<Mutex<IndexWriter>>,
+    layout: IndexLayout,
+    /// Versions kept on disk for rollback, the live one included.
+    keep_versions: usize,
+    active: Mutex<ActiveIndex>,
+    embeddings: Option<EmbeddingStage>,
+    /// Database time up to which row changes have been looked up.
+    watermark: Mutex<Option<DateTime<Utc>>>,
+    status: std::sync::Mutex<RefreshStatus>,
 }
 
 impl IngestionService {
-    fn new(client: Arc<Client>, schema: SearchSchema, writer: Arc<Mutex<IndexWriter>>) -> Self {
+    fn new(
+        client: Arc<Client>,
+        schema: SearchSchema,
+        layout: IndexLayout,
+        keep_versions: usize,
+        active: ActiveIndex,
+        embeddings: Option<EmbeddingStage>,
+    ) -> Self {
         Self {
             client,
             schema,
-            writer,
+            layout,
+            keep_versions,
+            active: Mutex::new(active),
+            embeddings,
+            watermark: Mutex::new(None),
+            status: std::sync::Mutex::new(RefreshStatus::default()),
         }
     }
 
+    /// Notes the outcome of `operation` for the admin status.
+    fn record<T>(&self, operation: &'static str, result: &Result<T>) {
+        match result {
+            Ok(_) => {
+                let mut status = self.lock_status();
+                status.last_refresh = Some(Utc::now());
+                status.succeeded(operation);
+            }
+            Err(err) => self.record_error(operation, err),
+        }
+    }
+
+    fn record_error(&self, operation: &'static str, err: &anyhow::Error) {
+        self.lock_status().last_error = Some(RefreshError {
+            operation,
+            message: format!("{err:#}"),
+            at: Utc::now(),
+        });
+    }
+
+    fn status(&self) -> RefreshStatus {
+        self.lock_status().clone()
+    }
+
+    fn lock_status(&self) -> std::sync::MutexGuard<'_, RefreshStatus> {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     fn quest_visibility(status: &str) -> &'static str {
         if PUBLIC_QUEST_STATUSES
             .iter()
@@ -240,36 +608,371 @@ impl IngestionService {
         }
     }
 
-    async fn refresh_index(&self) -> Result<usize> {
+    /// Replaces the documents built from each changed row with what the row
+    /// produces now; rows that were deleted or hidden produce nothing. The
+    /// whole batch lands in one new version.
+    async fn apply_changes(&self, sources: &HashSet<Source>) -> Result<usize> {
+        let mut loaded = Vec::with_capacity(sources.len());
+        for source in sources {
+            loaded.push((*source, self.load_source(*source).await?));
+        }
+
+        let searcher = self.active.lock().await.reader.searcher();
+        let mut draft = self.fork_active().await?;
+        let version = draft.version.clone();
+        let written = async {
+            let mut entities = Vec::new();
+            let mut removed = Vec::new();
+            for (source, current) in loaded {
+                removed.extend(replace_source(
+                    &self.schema,
+                    &mut draft.writer,
+                    &searcher,
+                    source,
+                    &current,
+                )?);
+                entities.extend(current);
+            }
+            self.embed(&draft, &entities, &removed).await?;
+            self.publish(draft, true).await?;
+            Ok(entities.len() + removed.len())
+        }
+        .await;
+        self.discard_on_error(&version, written)
+    }
+
+    /// Reindexes rows changed since the last poll. Only quests track
+    /// `updated_at`, so other rows are found here when new; their edits and
+    /// deletions arrive through notifications or the next reconciliation.
+    async fn poll_watermark(&self) -> Result<usize> {
+        let Some(since) = *self.watermark.lock().await else {
+            return Ok(0);
+        };
+        let now = self.database_now().await?;
+        let rows = self
+            .client
+            .query(
+                "SELECT 'artifacts' AS source_table, id FROM artifacts WHERE created_at > $1 \
+                 UNION ALL \
+                 SELECT 'quests' AS source_table, id FROM quests WHERE updated_at > $1 \
+                 UNION ALL \
+                 SELECT 'guilds' AS source_table, id FROM guilds WHERE created_at > $1 \
+                 UNION ALL \
+                 SELECT 'users' AS source_table, id FROM users WHERE created_at > $1 \
+                 UNION ALL \
+                 SELECT 'conversations' AS source_table, id FROM conversations \
+                 WHERE created_at > $1",
+                &[&since],
+            )
+            .await?;
+        let mut sources = HashSet::new();
+        for row in rows {
+            let table: String = row.try_get("source_table")?;
+            let id: uuid::Uuid = row.try_get("id")?;
+            sources.extend(Source::from_table(&table, id));
+        }
+        let count = if sources.is_empty() {
+            0
+        } else {
+            self.apply_changes(&sources).await?
+        };
+        *self.watermark.lock().await = Some(now);
+        Ok(count)
+    }
+
+    /// Builds every indexable row into a new version and publishes it once
+    /// complete, catching anything the change feeds missed; queries keep
+    /// hitting the previous version until then. `clear_vectors` empties a
+    /// vector store shared by every version, as on startup, since it may
+    /// hold vectors no version knows about.
+    async fn reconcile(&self, clear_vectors: bool) -> Result<usize> {
+        let now = self.database_now().await?;
         let entities = self.load_all_entities().await?;
-        let mut writer = self.writer.lock().await;
-        writer.delete_all_documents()?;
-        for entity in &entities {
-            let _ = writer.add_document(entity.to_document(&self.schema));
+        let current: HashSet<&str> = entities
+            .iter()
+            .map(|entity| entity.doc_id.as_str())
+            .collect();
+        // Only a shared store still holds the vectors of the live version.
+        let searcher = self.active.lock().await.reader.searcher();
+        let stale: Vec<String> = indexed_doc_ids(&self.schema, &searcher)?
+            .into_iter()
+            .filter(|doc_id| !current.contains(doc_id.as_str()))
+            .collect();
+
+        let draft = self.create_draft().await?;
+        let version = draft.version.clone();
+        let written = async {
+            for entity in &entities {
+                draft
+                    .writer
+                    .delete_term(Term::from_field_text(self.schema.doc_id, &entity.doc_id));
+                draft
+                    .writer
+                    .add_document(entity.to_document(&self.schema))?;
+            }
+            if clear_vectors {
+                if let Some(store) = &draft.vectors {
+                    store.clear().await?;
+                }
+            }
+            self.embed(&draft, &entities, &stale).await?;
+            self.publish(draft, false).await
         }
-        writer.commit()?;
+        .await;
+        self.discard_on_error(&version, written)?;
+        *self.watermark.lock().await = Some(now);
         Ok(entities.len())
     }
 
+    async fn reindex(&self, reindex: &Reindex) -> Result<usize> {
+        match reindex {
+            Reindex::Full => self.reconcile(false).await,
+            Reindex::EntityType(entity_type) => self.reindex_entity_type(entity_type).await,
+        }
+    }
+
+    /// Replaces every document of `entity_type` in a new version.
+    async fn reindex_entity_type(&self, entity_type: &str) -> Result<usize> {
+        let entities = self.load_entity_type(entity_type).await?;
+        let current: HashSet<&str> = entities
+            .iter()
+            .map(|entity| entity.doc_id.as_str())
+            .collect();
+
+        let searcher = self.active.lock().await.reader.searcher();
+        let type_term = Term::from_field_text(self.schema.entity_type, entity_type);
+        let removed: Vec<String> = doc_ids_of(&self.schema, &searcher, type_term.clone())?
+            .into_iter()
+            .filter(|doc_id| !current.contains(doc_id.as_str()))
+            .collect();
+        let draft = self.fork_active().await?;
+        let version = draft.version.clone();
+        let written = async {
+            draft.writer.delete_term(type_term);
+            for entity in &entities {
+                draft
+                    .writer
+                    .delete_term(Term::from_field_text(self.schema.doc_id, &entity.doc_id));
+                draft
+                    .writer
+                    .add_document(entity.to_document(&self.schema))?;
+            }
+            self.embed(&draft, &entities, &removed).await?;
+            self.publish(draft, true).await?;
+            Ok(entities.len())
+        }
+        .await;
+        self.discard_on_error(&version, written)
+    }
+
+    /// Starts a version holding the documents and vectors of the active one.
+    async fn fork_active(&self) -> Result<Draft> {
+        let (origin, vectors) = {
+            let active = self.active.lock().await;
+            (active.version.clone(), active.vectors.clone())
+        };
+        let (version, index) = self.layout.fork_version(&origin)?;
+        let dir = self.layout.version_path(&version);
+        let draft = async {
+            let vectors = match vectors {
+                Some(store) => Some(store.fork(&dir).await?),
+                None => None,
+            };
+            Draft::open(version.clone(), index, vectors)
+        }
+        .await;
+        self.discard_on_error(&version, draft)
+    }
+
+    /// Starts an empty version.
+    async fn create_draft(&self) -> Result<Draft> {
+        let (version, index) = self.layout.create_version(&self.schema)?;
+        let dir = self.layout.version_path(&version);
+        let draft = async {
+            let vectors = match &self.embeddings {
+                Some(embeddings) => embeddings.config.open_store(&dir).await?,
+                None => None,
+            };
+            Draft::open(version.clone(), index, vectors)
+        }
+        .await;
+        self.discard_on_error(&version, draft)
+    }
+
+    async fn embed(
+        &self,
+        draft: &Draft,
+        entities: &[SearchEntity],
+        removed: &[String],
+    ) -> Result<()> {
+        if let (Some(embeddings), Some(store)) = (&self.embeddings, &draft.vectors) {
+            embeddings.apply(store.as_ref(), entities, removed).await?;
+        }
+        Ok(())
+    }
+
+    /// Commits `draft`, publishes it and derives later versions from it.
+    /// The version it replaces is deleted when `transient`, and older
+    /// versions beyond the rollback window are pruned.
+    async fn publish(&self, draft: Draft, transient: bool) -> Result<()> {
+        let Draft {
+            version,
+            index,
+            mut writer,
+            vectors,
+        } = draft;
+        search_schema::commit(&mut writer)?;
+        // Merges finishing later would rewrite a published version.
+        writer.wait_merging_threads()?;
+        let next = ActiveIndex::open(version.clone(), &index, vectors, transient)?;
+        self.layout.publish(&version)?;
+        info!(%version, "index version published");
+        let previous = std::mem::replace(&mut *self.active.lock().await, next);
+        self.retire(previous);
+        self.prune_versions()
+    }
+
+    /// Deletes `previous` when it only carried a change batch, or was the
+    /// placeholder used before the first build.
+    fn retire(&self, previous: ActiveIndex) {
+        if !previous.transient {
+            return;
+        }
+        let version = previous.version;
+        if let Err(err) = self.layout.remove_version(&version) {
+            warn!(?err, %version, "removing a replaced index version failed");
+        }
+    }
+
+    /// Deletes the version `version` when `result` failed, so failed
+    /// writes leave nothing behind.
+    fn discard_on_error<T>(&self, version: &str, result: Result<T>) -> Result<T> {
+        if result.is_err() {
+            if let Err(err) = self.layout.remove_version(version) {
+                warn!(?err, %version, "removing an unpublished index version failed");
+            }
+        }
+        result
+    }
+
+    /// Deletes versions beyond the rollback window.
+    fn prune_versions(&self) -> Result<()> {
+        let pruned = self.layout.prune(self.keep_versions)?;
+        if !pruned.is_empty() {
+            info!(?pruned, "old index versions removed");
+        }
+        Ok(())
+    }
+
+    /// Switches to the version `CURRENT` names once it was published from
+    /// elsewhere, as on a rollback or import, and catches up with the
+    /// database in a new version built from scratch.
+    async fn follow_pointer(&self) -> Result<()> {
+        let Some(current) = self.layout.current()? else {
+            return Ok(());
+        };
+        let vectors = {
+            let active = self.active.lock().await;
+            if active.version == current {
+                return Ok(());
+            }
+            active.vectors.clone()
+        };
+        let index = self.layout.open_version(&current)?;
+        let vectors = match vectors {
+            Some(store) => Some(store.follow(&self.layout.version_path(&current)).await?),
+            None => None,
+        };
+        let next = ActiveIndex::open(current.clone(), &index, vectors, false)?;
+        let previous = std::mem::replace(&mut *self.active.lock().await, next);
+        info!(from = %previous.version, to = %current, "following published index version");
+        self.retire(previous);
+        self.reconcile(false).await?;
+        Ok(())
+    }
+
+    async fn database_now(&self) -> Result<DateTime<Utc>> {
+        let row = self.client.query_one("SELECT NOW()", &[]).await?;
+        Ok(row.try_get(0)?)
+    }
+
+    async fn load_source(&self, source: Source) -> Result<Vec<SearchEntity>> {
+        match source {
+            Source::Artifact(id) => {
+                let mut entities = self.load_pods(Some(id)).await?;
+                entities.extend(self.load_artifacts(Some(id)).await?);
+                Ok(entities)
+            }
+            Source::Quest(id) => self.load_quests(Some(id)).await,
+            Source::Guild(id) => self.load_guilds(Some(id)).await,
+            Source::User(id) => self.load_profiles(Some(id)).await,
+            Source::Conversation(id) => self.load_conversations(Some(id)).await,
+        }
+    }
+
     async fn load_all_entities(&self) -> Result<Vec<SearchEntity>> {
         let mut entities = Vec::new();
-        entities.extend(self.load_pods().await?);
-        entities.extend(self.load_artifacts().await?);
-        entities.extend(self.load_quests().await?);
+        entities.extend(self.load_pods(None).await?);
+        entities.extend(self.load_artifacts(None).await?);
+        entities.extend(self.load_quests(None).await?);
+        entities.extend(self.load_guilds(None).await?);
+        entities.extend(self.load_profiles(None).await?);
+        entities.extend(self.load_conversations(None).await?);
         Ok(entities)
     }
 
-    async fn load_pods(&self) -> Result<Vec<SearchEntity>> {
+    /// Loads every entity of one of [`ENTITY_TYPES`].
+    async fn load_entity_type(&self, entity_type: &str) -> Result<Vec<SearchEntity>> {
+        match entity_type {
+            POD_ENTITY_TYPE => self.load_pods(None).await,
+            ARTIFACT_ENTITY_TYPE => self.load_artifacts(None).await,
+            QUEST_ENTITY_TYPE => self.load_quests(None).await,
+            GUILD_ENTITY_TYPE => self.load_guilds(None).await,
+            USER_ENTITY_TYPE => self.load_profiles(None).await,
+            CONVERSATION_ENTITY_TYPE => self.load_conversations(None).await,
+            other => bail!("unknown entity type `{other}`"),
+        }
+    }
+
+    /// The entity the database produces for `doc_id` now; `None` when its
+    /// row is gone or not indexable.
+    async fn produce(&self, doc_id: &str) -> Result<Option<SearchEntity>> {
+        let Some((entity_type, id)) = doc_id.split_once(':') else {
+            return Ok(None);
+        };
+        let Ok(id) = id.parse::<uuid::Uuid>() else {
+            return Ok(None);
+        };
+        let entities = match entity_type {
+            // Pod documents are keyed by the pod, not by its snapshot row.
+            POD_ENTITY_TYPE => self.load_pods(None).await?,
+            ARTIFACT_ENTITY_TYPE => self.load_artifacts(Some(id)).await?,
+            QUEST_ENTITY_TYPE => self.load_quests(Some(id)).await?,
+            GUILD_ENTITY_TYPE => self.load_guilds(Some(id)).await?,
+            USER_ENTITY_TYPE => self.load_profiles(Some(id)).await?,
+            CONVERSATION_ENTITY_TYPE => self.load_conversations(Some(id)).await?,
+            _ => return Ok(None),
+        };
+        // The last one written wins, as in a reconciliation.
+        Ok(entities
+            .into_iter()
+            .rev()
+            .find(|entity| entity.doc_id == doc_id))
+    }
+
+    /// Loads every row, or only the row `only` when given.
+    async fn load_pods(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
         let rows = self
             .client
             .query(
                 "SELECT id, owner_id, metadata FROM artifacts \
-                 WHERE artifact_type = $1",
-                &[&POD_SNAPSHOT_TYPE],
+                 WHERE artifact_type = $1 AND ($2::uuid IS NULL OR id = $2)",
+                &[&POD_SNAPSHOT_TYPE, &only],
             )
             .await?;
         let mut entities = Vec::new();
         for row in rows {
+            let id: uuid::Uuid = row.try_get("id")?;
             let owner_id: uuid::Uuid = row.try_get("owner_id")?;
             let metadata: Value = row.try_get("metadata")?;
             let snapshot: PodSnapshotMetadata =
@@ -295,7 +998,7 @@ impl IngestionService {
             entities.push(SearchEntity {
                 doc_id: format!("pod:{}", snapshot.pod.id),
                 entity_id: snapshot.pod.id.to_string(),
-                entity_type: "pod".to_string(),
+                entity_type: POD_ENTITY_TYPE.to_string(),
                 owner_id: Some(owner_id.to_string()),
                 title: Some(snapshot.pod.title.clone()),
                 description: snapshot.pod.description.clone(),
@@ -304,18 +1007,24 @@ impl IngestionService {
                 status: Some("published".to_string()),
                 kind: Some(POD_SNAPSHOT_TYPE.to_string()),
                 content_fragments: fragments,
+                guild_ids: Vec::new(),
+                language: snapshot.pod.locale.as_deref().and_then(primary_language),
+                updated_at: Some(snapshot.published_at),
+                source: Source::Artifact(id),
             });
         }
         Ok(entities)
     }
 
-    async fn load_artifacts(&self) -> Result<Vec<SearchEntity>> {
+    async fn load_artifacts(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
         let rows = self
             .client
             .query(
-                "SELECT id, owner_id, artifact_type, metadata FROM artifacts \
-                 WHERE artifact_type <> $1",
-                &[&POD_SNAPSHOT_TYPE],
+                "SELECT id, owner_id, artifact_type, metadata, created_at, \
+                 ARRAY(SELECT guild_id FROM memberships WHERE user_id = artifacts.owner_id) \
+                 AS owner_guild_ids \
+                 FROM artifacts WHERE artifact_type <> $1 AND ($2::uuid IS NULL OR id = $2)",
+                &[&POD_SNAPSHOT_TYPE, &only],
             )
             .await?;
         let mut entities = Vec::new();
@@ -324,6 +1033,7 @@ impl IngestionService {
             let owner_id: uuid::Uuid = row.try_get("owner_id")?;
             let artifact_type: String = row.try_get("artifact_type")?;
             let metadata: Value = row.try_get("metadata")?;
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
             let title = metadata
                 .get("title")
                 .and_then(Value::as_str)
@@ -351,11 +1061,13 @@ impl IngestionService {
             }
             fragments.push(artifact_type.clone());
             collect_strings(&metadata, &mut fragments);
+            let owner_guild_ids: Vec<uuid::Uuid> = row.try_get("owner_guild_ids")?;
+            let guild_ids = owner_guilds(collect_tags(metadata.get("guild_ids")), &owner_guild_ids);
 
             entities.push(SearchEntity {
                 doc_id: format!("artifact:{}", id),
                 entity_id: id.to_string(),
-                entity_type: "artifact".to_string(),
+                entity_type: ARTIFACT_ENTITY_TYPE.to_string(),
                 owner_id: Some(owner_id.to_string()),
                 title,
                 description,
@@ -364,17 +1076,22 @@ impl IngestionService {
                 status: None,
                 kind: Some(artifact_type),
                 content_fragments: fragments,
+                guild_ids,
+                language: None,
+                updated_at: Some(created_at),
+                source: Source::Artifact(id),
             });
         }
         Ok(entities)
     }
 
-    async fn load_quests(&self) -> Result<Vec<SearchEntity>> {
+    async fn load_quests(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
         let rows = self
             .client
             .query(
-                "SELECT id, creator_id, title, description, status FROM quests",
-                &[],
+                "SELECT id, creator_id, title, description, status, updated_at FROM quests \
+                 WHERE $1::uuid IS NULL OR id = $1",
+                &[&only],
             )
             .await?;
         let mut entities = Vec::new();
@@ -384,6 +1101,7 @@ impl IngestionService {
             let title: String = row.try_get("title")?;
             let description: Option<String> = row.try_get("description")?;
             let status: String = row.try_get("status")?;
+            let updated_at: DateTime<Utc> = row.try_get("updated_at")?;
             let mut tags = vec![status.clone()];
             normalize_tags(&mut tags);
             dedup(&mut tags);
@@ -396,7 +1114,7 @@ impl IngestionService {
             entities.push(SearchEntity {
                 doc_id: format!("quest:{}", id),
                 entity_id: id.to_string(),
-                entity_type: "quest".to_string(),
+                entity_type: QUEST_ENTITY_TYPE.to_string(),
                 owner_id: Some(creator_id.to_string()),
                 title: Some(title),
                 description,
@@ -405,12 +1123,183 @@ impl IngestionService {
                 status: Some(status),
                 kind: Some("quest".to_string()),
                 content_fragments: fragments,
+                guild_ids: Vec::new(),
+                language: None,
+                updated_at: Some(updated_at),
+                source: Source::Quest(id),
+            });
+        }
+        Ok(entities)
+    }
+
+    /// Guilds are listed to everyone, so every guild is public.
+    async fn load_guilds(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT id, owner_id, name, description, created_at FROM guilds \
+                 WHERE $1::uuid IS NULL OR id = $1",
+                &[&only],
+            )
+            .await?;
+        let mut entities = Vec::new();
+        for row in rows {
+            let id: uuid::Uuid = row.try_get("id")?;
+            let owner_id: uuid::Uuid = row.try_get("owner_id")?;
+            let name: String = row.try_get("name")?;
+            let description: Option<String> = row.try_get("description")?;
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
+            let mut fragments = vec![name.clone()];
+            if let Some(description) = &description {
+                fragments.push(description.clone());
+            }
+
+            entities.push(SearchEntity {
+                doc_id: format!("guild:{}", id),
+                entity_id: id.to_string(),
+                entity_type: GUILD_ENTITY_TYPE.to_string(),
+                owner_id: Some(owner_id.to_string()),
+                title: Some(name),
+                description,
+                visibility: "public".to_string(),
+                tags: Vec::new(),
+                status: None,
+                kind: Some("guild".to_string()),
+                content_fragments: fragments,
+                guild_ids: vec![id.to_string()],
+                language: None,
+                updated_at: Some(created_at),
+                source: Source::Guild(id),
+            });
+        }
+        Ok(entities)
+    }
+
+    async fn load_profiles(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT id, display_name, is_guest, profile, created_at FROM users \
+                 WHERE $1::uuid IS NULL OR id = $1",
+                &[&only],
+            )
+            .await?;
+        let mut entities = Vec::new();
+        for row in rows {
+            let id: uuid::Uuid = row.try_get("id")?;
+            let display_name: Option<String> = row.try_get("display_name")?;
+            let is_guest: bool = row.try_get("is_guest")?;
+            let profile: Value = row.try_get("profile")?;
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
+            if is_guest {
+                continue;
+            }
+            entities.extend(profile_entity(
+                id,
+                display_name.as_deref(),
+                &profile,
+                created_at,
+            ));
+        }
+        Ok(entities)
+    }
+
+    /// Only conversations held in a guild and given a topic are indexed,
+    /// and only the guild's members and its owner may find them.
+    async fn load_conversations(&self, only: Option<uuid::Uuid>) -> Result<Vec<SearchEntity>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT c.id, c.guild_id, g.owner_id, c.topic, c.created_at \
+                 FROM conversations c JOIN guilds g ON g.id = c.guild_id \
+                 WHERE c.topic IS NOT NULL AND ($1::uuid IS NULL OR c.id = $1)",
+                &[&only],
+            )
+            .await?;
+        let mut entities = Vec::new();
+        for row in rows {
+            let id: uuid::Uuid = row.try_get("id")?;
+            let guild_id: uuid::Uuid = row.try_get("guild_id")?;
+            let owner_id: uuid::Uuid = row.try_get("owner_id")?;
+            let topic: String = row.try_get("topic")?;
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
+            let topic = topic.trim().to_string();
+            if topic.is_empty() {
+                continue;
+            }
+
+            entities.push(SearchEntity {
+                doc_id: format!("conversation:{}", id),
+                entity_id: id.to_string(),
+                entity_type: CONVERSATION_ENTITY_TYPE.to_string(),
+                owner_id: Some(owner_id.to_string()),
+                title: Some(topic.clone()),
+                description: None,
+                visibility: GUILD_VISIBILITY.to_string(),
+                tags: Vec::new(),
+                status: None,
+                kind: Some("conversation".to_string()),
+                content_fragments: vec![topic],
+                guild_ids: vec![guild_id.to_string()],
+                language: None,
+                updated_at: Some(created_at),
+                source: Source::Conversation(id),
             });
         }
         Ok(entities)
     }
 }
 
+/// Builds the search document of a profile whose owner set `discoverable`.
+/// Only the display name, `bio` and interest lists are indexed; the rest of
+/// the profile may hold anything the client chose to store.
+fn profile_entity(
+    id: uuid::Uuid,
+    display_name: Option<&str>,
+    profile: &Value,
+    created_at: DateTime<Utc>,
+) -> Option<SearchEntity> {
+    if profile.get("discoverable").and_then(Value::as_bool) != Some(true) {
+        return None;
+    }
+    let name = display_name
+        .map(str::trim)
+        .filter(|name| !name.is_empty())?
+        .to_string();
+    let bio = profile
+        .get("bio")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|bio| !bio.is_empty())
+        .map(str::to_string);
+    let mut tags = Vec::new();
+    for key in PROFILE_TAG_KEYS {
+        tags.extend(collect_tags(profile.get(key)));
+    }
+    normalize_tags(&mut tags);
+    dedup(&mut tags);
+    let mut fragments = vec![name.clone()];
+    fragments.extend(bio.clone());
+
+    Some(SearchEntity {
+        doc_id: format!("user:{}", id),
+        entity_id: id.to_string(),
+        entity_type: USER_ENTITY_TYPE.to_string(),
+        owner_id: Some(id.to_string()),
+        title: Some(name),
+        description: bio,
+        visibility: "public".to_string(),
+        tags,
+        status: None,
+        kind: Some("profile".to_string()),
+        content_fragments: fragments,
+        guild_ids: Vec::new(),
+        language: None,
+        updated_at: Some(created_at),
+        source: Source::User(id),
+    })
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 struct PodSnapshotMetadata {
@@ -429,6 +1318,8 @@ struct PodRecord {
     title: String,
     #[serde(default)]
     description: Option<String>,
+    #[serde(default)]
+    locale: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -445,18 +1336,16 @@ fn default_visibility() -> String {
     "public".to_string()
 }
 
-fn open_or_create_index(path: &Path, schema: &Schema) -> Result<Index> {
-    if path.exists() {
-        match Index::open_in_dir(path) {
-            Ok(index) => return Ok(index),
-            Err(err) => {
-                warn!(?err, "failed to open existing index, rebuilding");
-                std::fs::remove_dir_all(path).context("remove corrupted index directory")?;
-            }
-        }
-    }
-    std::fs::create_dir_all(path).context("create index directory")?;
-    Index::create_in_dir(path, schema.clone()).context("create index")
+/// Edge n-grams of the whole text only match its first word, so every word
+/// gets its own value starting there: "Demo Pod" indexes "demo pod" and "pod".
+fn word_suffixes(text: &str) -> Vec<String> {
+    let text = text.trim().to_lowercase();
+    text.char_indices()
+        .filter(|&(at, c)| {
+            !c.is_whitespace() && (at == 0 || text[..at].ends_with(char::is_whitespace))
+        })
+        .map(|(at, _)| text[at..].to_string())
+        .collect()
 }
 
 fn collect_tags(value: Option<&Value>) -> Vec<String> {
@@ -520,6 +1409,20 @@ fn normalize_facet_value(value: &str) -> String {
     value.trim().to_lowercase().replace(['/', ' '], "-")
 }
 
+/// Keeps the guilds an artifact is shared with that its owner belongs to.
+/// `metadata.guild_ids` is written by clients, so it alone must not let an
+/// owner share into guilds they are not a member of.
+fn owner_guilds(requested: Vec<String>, member_of: &[uuid::Uuid]) -> Vec<String> {
+    let mut guild_ids: Vec<String> = requested
+        .iter()
+        .filter_map(|guild_id| guild_id.parse::<uuid::Uuid>().ok())
+        .filter(|guild_id| member_of.contains(guild_id))
+        .map(|guild_id| guild_id.to_string())
+        .collect();
+    dedup(&mut guild_ids);
+    guild_ids
+}
+
 fn dedup(values: &mut Vec<String>) {
     values.sort_unstable();
     values.dedup();
@@ -537,6 +1440,24 @@ mod tests {
     use super::*;
     use tantivy::schema::Value as TantivyValue;
 
+    #[test]
+    fn artifacts_are_only_shared_with_their_owners_guilds() {
+        let member_of = [
+            uuid::Uuid::parse_str("6f1c1a0e-1d3b-4c8e-9a51-2f0d7c9b4e01").unwrap(),
+            uuid::Uuid::parse_str("0b9a3c1e-7f44-4d2a-8e3c-5a6b7c8d9e02").unwrap(),
+        ];
+        let requested = vec![
+            "6F1C1A0E-1D3B-4C8E-9A51-2F0D7C9B4E01".to_string(),
+            "6f1c1a0e-1d3b-4c8e-9a51-2f0d7c9b4e01".to_string(),
+            "d1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6".to_string(),
+            "not-a-guild".to_string(),
+        ];
+        assert_eq!(
+            owner_guilds(requested, &member_of),
+            ["6f1c1a0e-1d3b-4c8e-9a51-2f0d7c9b4e01"]
+        );
+    }
+
     #[test]
     fn search_entity_produces_expected_facets() {
         let schema = SearchSchema::build();
@@ -552,6 +1473,10 @@ mod tests {
             status: Some("published".to_string()),
             kind: Some("pod_snapshot".to_string()),
             content_fragments: vec!["extra".to_string()],
+            guild_ids: vec!["guild-1".to_string()],
+            language: Some("pt".to_string()),
+            updated_at: Some(Utc::now()),
+            source: Source::Artifact(uuid::Uuid::new_v4()),
         };
         let document = entity.to_document(&schema);
         let type_facets: Vec<_> = document.get_all(schema.entity_type_facet).collect();
@@ -569,6 +1494,16 @@ mod tests {
             })
             .collect();
         assert_eq!(stored_tags.len(), 2);
+        assert_eq!(document.get_all(schema.guild_ids).count(), 1);
+        assert_eq!(document.get_all(schema.guild_facet).count(), 1);
+        assert_eq!(document.get_all(schema.updated_at).count(), 1);
+        assert_eq!(document.get_all(schema.language).count(), 1);
+        assert_eq!(document.get_all(schema.tag_suggest).count(), 2);
+        assert_eq!(word_suffixes("Demo  Pod"), ["demo  pod", "pod"]);
+        assert_eq!(
+            entity.embedding_text(),
+            "Demo Pod. A pod for demos. alpha. beta"
+        );
     }
 
     #[test]
@@ -589,6 +1524,10 @@ mod tests {
             status: Some("approved".to_string()),
             kind: Some("quest".to_string()),
             content_fragments: vec!["approved".to_string()],
+            guild_ids: Vec::new(),
+            language: None,
+            updated_at: None,
+            source: Source::Quest(uuid::Uuid::new_v4()),
         };
 
         let document = entity.to_document(&schema);
@@ -600,4 +1539,132 @@ mod tests {
         };
         assert_eq!(facet_path, "/visibility/public");
     }
+
+    #[test]
+    fn only_discoverable_named_profiles_are_indexed() {
+        let id = uuid::Uuid::new_v4();
+        let profile = serde_json::json!({
+            "discoverable": true,
+            "bio": " Grows tomatoes ",
+            "interests": ["Gardening", "gardening"],
+            "skills": ["Carpentry"],
+            "phone": "555-0100",
+        });
+        let entity = profile_entity(id, Some("Ada"), &profile, Utc::now()).expect("profile");
+        assert_eq!(entity.doc_id, format!("user:{id}"));
+        assert_eq!(entity.visibility, "public");
+        assert_eq!(entity.description.as_deref(), Some("Grows tomatoes"));
+        assert_eq!(entity.tags, ["carpentry", "gardening"]);
+        assert!(!entity
+            .content_fragments
+            .iter()
+            .any(|fragment| fragment.contains("555")));
+
+        let hidden = serde_json::json!({ "bio": "Grows tomatoes" });
+        assert!(profile_entity(id, Some("Ada"), &hidden, Utc::now()).is_none());
+        assert!(profile_entity(id, Some("  "), &profile, Utc::now()).is_none());
+    }
+
+    #[test]
+    fn entities_without_text_get_no_embedding() {
+        let stage = EmbeddingStage::new(VectorConfig {
+            backend: VectorBackend::Local,
+            dimensions: vector_search::DEFAULT_DIMENSIONS,
+        });
+        let entity = |doc_id: &str, title: Option<&str>| SearchEntity {
+            doc_id: doc_id.to_string(),
+            entity_id: doc_id.to_string(),
+            entity_type: "artifact".to_string(),
+            owner_id: None,
+            title: title.map(str::to_string),
+            description: None,
+            visibility: "public".to_string(),
+            tags: Vec::new(),
+            status: None,
+            kind: None,
+            content_fragments: vec!["ignored".to_string()],
+            guild_ids: Vec::new(),
+            language: None,
+            updated_at: None,
+            source: Source::Artifact(uuid::Uuid::new_v4()),
+        };
+
+        let points = stage.points(&[
+            entity("artifact:1", Some("Garden plans")),
+            entity("artifact:2", None),
+        ]);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].doc_id, "artifact:1");
+        assert_eq!(points[0].vector.len(), vector_search::DEFAULT_DIMENSIONS);
+    }
+
+    #[test]
+    fn replacing_a_source_drops_documents_the_row_no_longer_produces() {
+        let schema = SearchSchema::build();
+        let index = schema.create_in_ram();
+        let mut writer = index.writer(15_000_000).expect("writer");
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .expect("reader");
+        let snapshot = Source::Artifact(uuid::Uuid::new_v4());
+        let pod = |doc_id: &str, source: Source| SearchEntity {
+            doc_id: doc_id.to_string(),
+            entity_id: doc_id.to_string(),
+            entity_type: "pod".to_string(),
+            owner_id: None,
+            title: Some("Garden".to_string()),
+            description: None,
+            visibility: "public".to_string(),
+            tags: Vec::new(),
+            status: None,
+            kind: None,
+            content_fragments: Vec::new(),
+            guild_ids: Vec::new(),
+            language: None,
+            updated_at: None,
+            source,
+        };
+
+        let removed = replace_source(
+            &schema,
+            &mut writer,
+            &reader.searcher(),
+            snapshot,
+            &[pod("pod:1", snapshot)],
+        )
+        .expect("replace");
+        assert!(removed.is_empty());
+        search_schema::commit(&mut writer).expect("commit");
+        reader.reload().expect("reload");
+
+        // Republishing the pod under a new snapshot row moves its document.
+        let republished = Source::Artifact(uuid::Uuid::new_v4());
+        replace_source(
+            &schema,
+            &mut writer,
+            &reader.searcher(),
+            republished,
+            &[pod("pod:1", republished)],
+        )
+        .expect("replace");
+        search_schema::commit(&mut writer).expect("commit");
+        reader.reload().expect("reload");
+        assert_eq!(reader.searcher().num_docs(), 1);
+        assert!(indexed_doc_ids(&schema, &reader.searcher())
+            .expect("doc ids")
+            .contains("pod:1"));
+        let snapshot_term = Term::from_field_text(schema.source, &snapshot.key());
+        assert!(doc_ids_of(&schema, &reader.searcher(), snapshot_term)
+            .expect("doc ids")
+            .is_empty());
+
+        let removed = replace_source(&schema, &mut writer, &reader.searcher(), republished, &[])
+            .expect("replace");
+        assert_eq!(removed, ["pod:1"]);
+        search_schema::commit(&mut writer).expect("commit");
+        reader.reload().expect("reload");
+        assert_eq!(reader.searcher().num_docs(), 0);
+    }
 }
@@ -0,0 +1,379 @@
+//! Blue/green layout of the index directory. Every write goes into a fresh
+//! version directory and goes live by rewriting the pointer file, so
+//! readers switch between complete indexes and published versions never
+//! change, which keeps older ones usable for rollback:
+//!
+//! ```text
+//! <root>/CURRENT            name of the live version
+//! <root>/versions/<name>/   one Tantivy index per version
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tantivy::Index;
+
+use crate::{open_index, SchemaError, SearchSchema};
+
+const POINTER_FILE: &str = "CURRENT";
+const VERSIONS_DIR: &str = "versions";
+/// Tantivy's list of the files it wrote, `meta.json` included.
+const MANAGED_FILE: &str = ".managed.json";
+/// Files Tantivy recreates per process and that must not travel with a
+/// snapshot.
+const LOCK_FILES: [&str; 2] = [".tantivy-writer.lock", ".tantivy-meta.lock"];
+/// Copies attempted while the indexer may be merging segments away.
+const EXPORT_ATTEMPTS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub struct IndexLayout {
+    root: PathBuf,
+}
+
+impl IndexLayout {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn version_path(&self, name: &str) -> PathBuf {
+        self.root.join(VERSIONS_DIR).join(name)
+    }
+
+    /// Name of the live version, or `None` before the first publish.
+    pub fn current(&self) -> Result<Option<String>, SchemaError> {
+        match std::fs::read_to_string(self.root.join(POINTER_FILE)) {
+            Ok(name) => Ok(Some(name.trim().to_string())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Version names, oldest first.
+    pub fn versions(&self) -> Result<Vec<String>, SchemaError> {
+        let dir = self.root.join(VERSIONS_DIR);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.extend(entry.file_name().to_str().map(str::to_string));
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Opens the live version, checking its schema version.
+    pub fn open_current(&self) -> Result<(String, Index), SchemaError> {
+        let name = self
+            .current()?
+            .ok_or_else(|| SchemaError::NoCurrentVersion(self.root.clone()))?;
+        let index = self.open_version(&name)?;
+        Ok((name, index))
+    }
+
+    pub fn open_version(&self, name: &str) -> Result<Index, SchemaError> {
+        let path = self.checked_version_path(name)?;
+        open_index(&path)
+    }
+
+    /// Creates an empty index under a new version name, which sorts after
+    /// every existing one.
+    pub fn create_version(&self, schema: &SearchSchema) -> Result<(String, Index), SchemaError> {
+        let name = self.next_version_name()?;
+        let index = schema.create_in_dir(&self.version_path(&name))?;
+        Ok((name, index))
+    }
+
+    /// Creates a new version holding the documents of `name`. Index files
+    /// are hard-linked rather than copied: Tantivy never rewrites a file in
+    /// place, so writing to the new version leaves `name` as it was. Other
+    /// files in the version directory are not carried over.
+    pub fn fork_version(&self, name: &str) -> Result<(String, Index), SchemaError> {
+        let source = self.checked_version_path(name)?;
+        let managed: HashSet<PathBuf> =
+            serde_json::from_slice(&std::fs::read(source.join(MANAGED_FILE))?)?;
+        let next = self.next_version_name()?;
+        let dest = self.version_path(&next);
+        std::fs::create_dir_all(&dest)?;
+        let linked = std::iter::once(PathBuf::from(MANAGED_FILE))
+            .chain(managed)
+            .try_for_each(
+                |file| match std::fs::hard_link(source.join(&file), dest.join(&file)) {
+                    Ok(()) => Ok(()),
+                    // Listed but already garbage collected.
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    Err(err) => Err(err),
+                },
+            );
+        match linked
+            .map_err(SchemaError::from)
+            .and_then(|()| open_index(&dest))
+        {
+            Ok(index) => Ok((next, index)),
+            Err(err) => {
+                let _ = std::fs::remove_dir_all(&dest);
+                Err(err)
+            }
+        }
+    }
+
+    /// Makes `name` the live version. The pointer is replaced by rename, so
+    /// readers see either the old or the new name, and the rename is synced
+    /// before returning.
+    pub fn publish(&self, name: &str) -> Result<(), SchemaError> {
+        self.checked_version_path(name)?;
+        let staging = self.root.join(format!(
+            "{POINTER_FILE}.{}.{:016x}.tmp",
+            std::process::id(),
+            RandomState::new().build_hasher().finish()
+        ));
+        let mut file = std::fs::File::create(&staging)?;
+        let written = file
+            .write_all(name.as_bytes())
+            .and_then(|()| file.sync_all())
+            .and_then(|()| std::fs::rename(&staging, self.root.join(POINTER_FILE)));
+        if let Err(err) = written {
+            let _ = std::fs::remove_file(&staging);
+            return Err(err.into());
+        }
+        sync_dir(&self.root)?;
+        Ok(())
+    }
+
+    /// Deletes a version other than the live one.
+    pub fn remove_version(&self, name: &str) -> Result<(), SchemaError> {
+        let path = self.checked_version_path(name)?;
+        if self.current()?.as_deref() == Some(name) {
+            return Err(SchemaError::LiveVersion(name.to_string()));
+        }
+        std::fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    /// Deletes all but the `keep` newest versions, never the live one, and
+    /// returns the deleted names.
+    pub fn prune(&self, keep: usize) -> Result<Vec<String>, SchemaError> {
+        let current = self.current()?;
+        let versions = self.versions()?;
+        let excess = versions.len().saturating_sub(keep.max(1));
+        let mut removed = Vec::new();
+        for name in versions.into_iter().take(excess) {
+            if current.as_deref() == Some(name.as_str()) {
+                continue;
+            }
+            std::fs::remove_dir_all(self.version_path(&name))?;
+            removed.push(name);
+        }
+        Ok(removed)
+    }
+
+    /// Copies version `name` into the empty or missing directory `dest`,
+    /// which then holds a plain Tantivy index.
+    pub fn export(&self, name: &str, dest: &Path) -> Result<(), SchemaError> {
+        let source = self.checked_version_path(name)?;
+        let mut attempt = 1;
+        loop {
+            let _ = std::fs::remove_dir_all(dest);
+            match copy_index(&source, dest).and_then(|()| open_index(dest).map(drop)) {
+                Ok(()) => return Ok(()),
+                // A merge deleted segments between reading `meta.json` and
+                // copying them; the next attempt sees the merged segment.
+                Err(_) if attempt < EXPORT_ATTEMPTS => attempt += 1,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Copies the exported index in `source` into a new version and returns
+    /// its name. The version is not published.
+    pub fn import(&self, source: &Path) -> Result<String, SchemaError> {
+        open_index(source)?;
+        let name = self.next_version_name()?;
+        let dest = self.version_path(&name);
+        if let Err(err) = copy_index(source, &dest).and_then(|()| open_index(&dest).map(drop)) {
+            let _ = std::fs::remove_dir_all(&dest);
+            return Err(err);
+        }
+        Ok(name)
+    }
+
+    fn checked_version_path(&self, name: &str) -> Result<PathBuf, SchemaError> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let path = self.version_path(name);
+        if !valid || !path.is_dir() {
+            return Err(SchemaError::UnknownVersion(name.to_string()));
+        }
+        Ok(path)
+    }
+
+    /// Millisecond timestamps, zero-padded so names sort by age.
+    fn next_version_name(&self) -> Result<String, SchemaError> {
+        let latest = self.versions()?.pop();
+        let mut millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        loop {
+            let name = format!("{millis:015}");
+            if latest.as_ref().is_none_or(|latest| &name > latest) {
+                return Ok(name);
+            }
+            millis += 1;
+        }
+    }
+}
+
+/// Makes a rename in `dir` durable. Only Unix can open a directory for
+/// syncing.
+fn sync_dir(dir: &Path) -> Result<(), SchemaError> {
+    #[cfg(unix)]
+    std::fs::File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Copies `meta.json` first and then every other index file, so the copy
+/// never references a segment written after it was taken.
+fn copy_index(source: &Path, dest: &Path) -> Result<(), SchemaError> {
+    std::fs::create_dir_all(dest)?;
+    std::fs::copy(source.join("meta.json"), dest.join("meta.json"))?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "meta.json"
+            || LOCK_FILES.iter().any(|lock| name == *lock)
+            || !entry.file_type()?.is_file()
+        {
+            continue;
+        }
+        match std::fs::copy(entry.path(), dest.join(&name)) {
+            Ok(_) => {}
+            // Garbage collected since listing; the open check afterwards
+            // tells whether the copy still needed it.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit;
+
+    fn layout(test: &str) -> IndexLayout {
+        let root =
+            std::env::temp_dir().join(format!("search-schema-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        IndexLayout::new(root)
+    }
+
+    fn build_version(layout: &IndexLayout, schema: &SearchSchema) -> String {
+        let (name, index) = layout.create_version(schema).expect("create");
+        let mut writer = index.writer(15_000_000).expect("writer");
+        writer
+            .add_document(tantivy::doc!(schema.doc_id => name.clone()))
+            .expect("add");
+        commit(&mut writer).expect("commit");
+        name
+    }
+
+    #[test]
+    fn publishing_switches_versions_and_pruning_keeps_the_live_one() {
+        let layout = layout("publish");
+        let schema = SearchSchema::build();
+        assert!(matches!(
+            layout.open_current(),
+            Err(SchemaError::NoCurrentVersion(_))
+        ));
+
+        let names: Vec<_> = (0..4).map(|_| build_version(&layout, &schema)).collect();
+        assert_eq!(layout.versions().expect("versions"), names);
+        layout.publish(&names[0]).expect("publish");
+        assert_eq!(layout.open_current().expect("open").0, names[0]);
+        assert!(matches!(
+            layout.publish("../elsewhere"),
+            Err(SchemaError::UnknownVersion(_))
+        ));
+
+        assert_eq!(layout.prune(2).expect("prune"), names[1..2]);
+        assert_eq!(
+            layout.versions().expect("versions"),
+            [names[0].clone(), names[2].clone(), names[3].clone()]
+        );
+        let _ = std::fs::remove_dir_all(layout.root());
+    }
+
+    #[test]
+    fn forked_versions_leave_their_origin_alone() {
+        let layout = layout("fork");
+        let schema = SearchSchema::build();
+        let origin = build_version(&layout, &schema);
+        layout.publish(&origin).expect("publish");
+
+        let (fork, index) = layout.fork_version(&origin).expect("fork");
+        assert!(fork > origin);
+        let mut writer = index.writer(15_000_000).expect("writer");
+        writer
+            .add_document(tantivy::doc!(schema.doc_id => fork.clone()))
+            .expect("add");
+        commit(&mut writer).expect("commit");
+        writer.wait_merging_threads().expect("merge");
+
+        let count = |name: &str| {
+            let index = layout.open_version(name).expect("open");
+            index.reader().expect("reader").searcher().num_docs()
+        };
+        assert_eq!(count(&origin), 1);
+        assert_eq!(count(&fork), 2);
+
+        layout.publish(&fork).expect("publish fork");
+        let leftovers: Vec<_> = std::fs::read_dir(layout.root())
+            .expect("root")
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+        assert!(matches!(
+            layout.remove_version(&fork),
+            Err(SchemaError::LiveVersion(_))
+        ));
+        layout.remove_version(&origin).expect("remove");
+        assert_eq!(layout.versions().expect("versions"), [fork]);
+        let _ = std::fs::remove_dir_all(layout.root());
+    }
+
+    #[test]
+    fn exported_versions_import_as_new_versions() {
+        let layout = layout("snapshot");
+        let schema = SearchSchema::build();
+        let name = build_version(&layout, &schema);
+        let snapshot = layout.root().join("snapshot");
+
+        layout.export(&name, &snapshot).expect("export");
+        let imported = layout.import(&snapshot).expect("import");
+        assert!(imported > name);
+        let index = layout.open_version(&imported).expect("open import");
+        let reader = index.reader().expect("reader");
+        assert_eq!(reader.searcher().num_docs(), 1);
+        let _ = std::fs::remove_dir_all(layout.root());
+    }
+}
@@ -0,0 +1,324 @@
+//! The Tantivy schema of the eco search index. eco-indexer writes documents
+//! with it and eco-api reads them, and every commit stamps the index with
+//! [`SCHEMA_VERSION`] so neither opens an index built for another layout.
+
+mod layout;
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tantivy::schema::{
+    FacetOptions, Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, FAST, STORED,
+    STRING, TEXT,
+};
+use tantivy::tokenizer::{LowerCaser, NgramTokenizer, TextAnalyzer};
+use tantivy::{Index, IndexWriter, Opstamp, TantivyError};
+use thiserror::Error;
+
+pub use layout::IndexLayout;
+
+/// Bump whenever a field is added, removed or indexed differently; the
+/// indexer then rebuilds the index from scratch.
+pub const SCHEMA_VERSION: u32 = 2;
+
+pub const DOC_ID_FIELD: &str = "doc_id";
+pub const TITLE_SORT_FIELD: &str = "title_sort";
+pub const UPDATED_AT_FIELD: &str = "updated_at";
+pub const ENTITY_TYPE_FACET_FIELD: &str = "entity_type_facet";
+pub const VISIBILITY_FACET_FIELD: &str = "visibility_facet";
+pub const TAG_FACET_FIELD: &str = "tag_facet";
+pub const GUILD_FACET_FIELD: &str = "guild_facet";
+
+/// Values of the `entity_type` field. Published pods are the worlds of the
+/// world directory.
+pub const POD_ENTITY_TYPE: &str = "pod";
+pub const ARTIFACT_ENTITY_TYPE: &str = "artifact";
+pub const QUEST_ENTITY_TYPE: &str = "quest";
+pub const GUILD_ENTITY_TYPE: &str = "guild";
+pub const USER_ENTITY_TYPE: &str = "user";
+pub const CONVERSATION_ENTITY_TYPE: &str = "conversation";
+pub const ENTITY_TYPES: [&str; 6] = [
+    POD_ENTITY_TYPE,
+    ARTIFACT_ENTITY_TYPE,
+    QUEST_ENTITY_TYPE,
+    GUILD_ENTITY_TYPE,
+    USER_ENTITY_TYPE,
+    CONVERSATION_ENTITY_TYPE,
+];
+
+/// Prefix tokenizer of the `*_suggest` fields.
+pub const EDGE_NGRAM_TOKENIZER: &str = "edge_ngram";
+/// Shortest and longest prefixes indexed for suggestions.
+pub const SUGGEST_MIN_CHARS: usize = 2;
+pub const SUGGEST_MAX_CHARS: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("search index I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("search index error: {0}")]
+    Index(#[from] TantivyError),
+    #[error(
+        "index has schema version {}, expected {expected}",
+        found.map_or_else(|| "none".to_string(), |version| version.to_string())
+    )]
+    VersionMismatch { expected: u32, found: Option<u32> },
+    #[error("index fields differ from schema version {0}")]
+    SchemaMismatch(u32),
+    #[error("no index version has been published in {0}")]
+    NoCurrentVersion(PathBuf),
+    #[error("unknown index version `{0}`")]
+    UnknownVersion(String),
+    #[error("index version `{0}` is live")]
+    LiveVersion(String),
+    #[error("unreadable index file list: {0}")]
+    ManagedFiles(#[from] serde_json::Error),
+}
+
+/// Written as the commit payload of every index commit.
+#[derive(Serialize, Deserialize)]
+struct IndexPayload {
+    schema_version: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchSchema {
+    pub schema: Schema,
+    pub doc_id: Field,
+    pub entity_id: Field,
+    pub entity_type: Field,
+    pub entity_type_facet: Field,
+    pub owner_id: Field,
+    pub title: Field,
+    pub description: Field,
+    pub visibility: Field,
+    pub visibility_facet: Field,
+    pub tags: Field,
+    pub tag_facet: Field,
+    pub status: Field,
+    pub kind: Field,
+    pub content: Field,
+    /// Guilds whose members may see the document regardless of its
+    /// visibility.
+    pub guild_ids: Field,
+    pub guild_facet: Field,
+    pub title_sort: Field,
+    pub updated_at: Field,
+    /// Lowercase primary language subtag of the document's text, such as
+    /// `pt` for a world written in `pt-BR`.
+    pub language: Field,
+    /// Every word suffix of the title, split into prefixes.
+    pub title_suggest: Field,
+    pub tag_suggest: Field,
+    /// Database row the document was built from, such as `quest:<id>`.
+    pub source: Field,
+}
+
+impl SearchSchema {
+    pub fn build() -> Self {
+        let mut builder = Schema::builder();
+        let doc_id = builder.add_text_field(DOC_ID_FIELD, STRING | STORED | FAST);
+        let entity_id = builder.add_text_field("entity_id", STRING | STORED);
+        let entity_type = builder.add_text_field("entity_type", STRING | STORED);
+        let entity_type_facet = builder.add_facet_field(ENTITY_TYPE_FACET_FIELD, facet_options());
+        let owner_id = builder.add_text_field("owner_id", STRING | STORED);
+        let title = builder.add_text_field("title", TEXT | STORED);
+        let description = builder.add_text_field("description", TEXT | STORED);
+        let visibility = builder.add_text_field("visibility", STRING | STORED);
+        let visibility_facet = builder.add_facet_field(VISIBILITY_FACET_FIELD, facet_options());
+        let tags = builder.add_text_field("tags", TEXT | STORED);
+        let tag_facet = builder.add_facet_field(TAG_FACET_FIELD, facet_options());
+        let status = builder.add_text_field("status", STRING | STORED);
+        let kind = builder.add_text_field("kind", STRING | STORED);
+        let content = builder.add_text_field("content", TEXT);
+        let guild_ids = builder.add_text_field("guild_ids", STRING | STORED);
+        let guild_facet = builder.add_facet_field(GUILD_FACET_FIELD, facet_options());
+        let title_sort = builder.add_text_field(TITLE_SORT_FIELD, STRING | FAST);
+        let updated_at = builder.add_date_field(UPDATED_AT_FIELD, STORED | FAST);
+        let language = builder.add_text_field("language", STRING | STORED);
+        let title_suggest = builder.add_text_field("title_suggest", suggest_options());
+        let tag_suggest = builder.add_text_field("tag_suggest", suggest_options());
+        let source = builder.add_text_field("source", STRING);
+        Self {
+            schema: builder.build(),
+            doc_id,
+            entity_id,
+            entity_type,
+            entity_type_facet,
+            owner_id,
+            title,
+            description,
+            visibility,
+            visibility_facet,
+            tags,
+            tag_facet,
+            status,
+            kind,
+            content,
+            guild_ids,
+            guild_facet,
+            title_sort,
+            updated_at,
+            language,
+            title_suggest,
+            tag_suggest,
+            source,
+        }
+    }
+
+    /// Fields free-text queries run against.
+    pub fn query_fields(&self) -> Vec<Field> {
+        vec![self.title, self.description, self.tags, self.content]
+    }
+
+    /// Creates an empty index in `path`, which must not hold one yet. It
+    /// carries no version until its first [`commit`].
+    pub fn create_in_dir(&self, path: &Path) -> Result<Index, SchemaError> {
+        std::fs::create_dir_all(path)?;
+        let index = Index::create_in_dir(path, self.schema.clone())?;
+        register_tokenizers(&index);
+        Ok(index)
+    }
+
+    pub fn create_in_ram(&self) -> Index {
+        let index = Index::create_in_ram(self.schema.clone());
+        register_tokenizers(&index);
+        index
+    }
+}
+
+/// Opens the index in `path` if it was committed with [`SCHEMA_VERSION`]
+/// and its fields are the ones [`SearchSchema::build`] defines, which also
+/// catches a schema change that forgot to bump the version.
+pub fn open_index(path: &Path) -> Result<Index, SchemaError> {
+    let index = Index::open_in_dir(path)?;
+    let found = stored_version(&index)?;
+    if found != Some(SCHEMA_VERSION) {
+        return Err(SchemaError::VersionMismatch {
+            expected: SCHEMA_VERSION,
+            found,
+        });
+    }
+    if index.schema() != SearchSchema::build().schema {
+        return Err(SchemaError::SchemaMismatch(SCHEMA_VERSION));
+    }
+    register_tokenizers(&index);
+    Ok(index)
+}
+
+/// Schema version of the last commit; `None` before the first commit and
+/// for indexes from before versioning.
+pub fn stored_version(index: &Index) -> Result<Option<u32>, SchemaError> {
+    Ok(index
+        .load_metas()?
+        .payload
+        .and_then(|payload| serde_json::from_str::<IndexPayload>(&payload).ok())
+        .map(|payload| payload.schema_version))
+}
+
+/// Commits the writer's pending changes, stamped with [`SCHEMA_VERSION`].
+pub fn commit(writer: &mut IndexWriter) -> tantivy::Result<Opstamp> {
+    let payload = serde_json::to_string(&IndexPayload {
+        schema_version: SCHEMA_VERSION,
+    })
+    .expect("payload serializes");
+    let mut prepared = writer.prepare_commit()?;
+    prepared.set_payload(&payload);
+    prepared.commit()
+}
+
+/// Registers the analyzers the schema refers to by name. Indexes opened
+/// through this crate already have them.
+pub fn register_tokenizers(index: &Index) {
+    index.tokenizers().register(
+        EDGE_NGRAM_TOKENIZER,
+        TextAnalyzer::builder(NgramTokenizer::prefix_only(
+            SUGGEST_MIN_CHARS,
+            SUGGEST_MAX_CHARS,
+        ))
+        .filter(LowerCaser)
+        .build(),
+    );
+}
+
+/// Value of the `language` field for a BCP 47 tag: its primary subtag,
+/// lowercased, so `pt_BR` and `pt-PT` both give `pt`. `None` when the tag
+/// does not start with a 2 or 3 letter language.
+pub fn primary_language(locale: &str) -> Option<String> {
+    let language = locale.trim().split(['-', '_']).next()?;
+    if (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(language.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+fn facet_options() -> FacetOptions {
+    FacetOptions::default().set_stored()
+}
+
+fn suggest_options() -> TextOptions {
+    TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(EDGE_NGRAM_TOKENIZER)
+            .set_index_option(IndexRecordOption::Basic),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_stamp_the_schema_version() {
+        let dir = std::env::temp_dir().join(format!("search-schema-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let schema = SearchSchema::build();
+        let index = schema.create_in_dir(&dir).expect("create");
+        let mut writer = index.writer(15_000_000).expect("writer");
+
+        writer.commit().expect("plain commit");
+        assert!(matches!(
+            open_index(&dir),
+            Err(SchemaError::VersionMismatch { found: None, .. })
+        ));
+
+        commit(&mut writer).expect("commit");
+        drop(writer);
+        let reopened = open_index(&dir).expect("open");
+        assert_eq!(
+            stored_version(&reopened).expect("version"),
+            Some(SCHEMA_VERSION)
+        );
+        assert_eq!(reopened.schema(), schema.schema);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn indexes_with_other_fields_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("search-schema-fields-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("dir");
+        let mut builder = Schema::builder();
+        builder.add_text_field(DOC_ID_FIELD, STRING | STORED);
+        let index = Index::create_in_dir(&dir, builder.build()).expect("create");
+        let mut writer = index.writer(15_000_000).expect("writer");
+        commit(&mut writer).expect("commit");
+        drop(writer);
+
+        assert!(matches!(
+            open_index(&dir),
+            Err(SchemaError::SchemaMismatch(SCHEMA_VERSION))
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn locales_reduce_to_their_primary_language() {
+        assert_eq!(primary_language("pt_BR").as_deref(), Some("pt"));
+        assert_eq!(primary_language(" zh-Hant-TW ").as_deref(), Some("zh"));
+        assert_eq!(primary_language("EN").as_deref(), Some("en"));
+        assert_eq!(primary_language(""), None);
+        assert_eq!(primary_language("english"), None);
+    }
+}
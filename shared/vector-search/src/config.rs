@@ -0,0 +1,92 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    HashingEmbedder, LocalVectorStore, VectorError, VectorStore, DEFAULT_DIMENSIONS, VECTOR_FILE,
+};
+
+pub const DEFAULT_QDRANT_COLLECTION: &str = "eco-search";
+
+/// Where embeddings live. eco-indexer and eco-api read the same variables so
+/// the reader opens what the writer wrote.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VectorBackend {
+    Disabled,
+    /// A file in each index version directory, published, rolled back and
+    /// exported together with the version.
+    Local,
+    Qdrant {
+        url: String,
+        collection: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorConfig {
+    pub backend: VectorBackend,
+    pub dimensions: usize,
+}
+
+impl VectorConfig {
+    /// Reads `ECO_VECTOR_BACKEND` (`local`, the default, `qdrant` or `off`),
+    /// `ECO_QDRANT_URL`, `ECO_QDRANT_COLLECTION` and
+    /// `ECO_EMBEDDING_DIMENSIONS`.
+    pub fn from_env() -> Result<Self, VectorError> {
+        let backend = match std::env::var("ECO_VECTOR_BACKEND")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "local" => VectorBackend::Local,
+            "qdrant" => VectorBackend::Qdrant {
+                url: std::env::var("ECO_QDRANT_URL").map_err(|_| {
+                    VectorError::Config("ECO_QDRANT_URL must be set for qdrant".to_string())
+                })?,
+                collection: std::env::var("ECO_QDRANT_COLLECTION")
+                    .unwrap_or_else(|_| DEFAULT_QDRANT_COLLECTION.to_string()),
+            },
+            "off" | "none" => VectorBackend::Disabled,
+            other => {
+                return Err(VectorError::Config(format!(
+                    "unknown ECO_VECTOR_BACKEND `{other}`"
+                )))
+            }
+        };
+        let dimensions = std::env::var("ECO_EMBEDDING_DIMENSIONS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DIMENSIONS);
+        Ok(Self {
+            backend,
+            dimensions,
+        })
+    }
+
+    pub fn embedder(&self) -> HashingEmbedder {
+        HashingEmbedder::new(self.dimensions)
+    }
+
+    /// Opens the store of the index version in `version_dir`; `None` when
+    /// vector search is disabled.
+    pub async fn open_store(
+        &self,
+        version_dir: &Path,
+    ) -> Result<Option<Arc<dyn VectorStore>>, VectorError> {
+        match &self.backend {
+            VectorBackend::Disabled => Ok(None),
+            VectorBackend::Local => Ok(Some(Arc::new(LocalVectorStore::open(
+                version_dir.join(VECTOR_FILE),
+            )?))),
+            #[cfg(feature = "qdrant")]
+            VectorBackend::Qdrant { url, collection } => Ok(Some(Arc::new(
+                crate::QdrantStore::connect(url.clone(), collection.clone(), self.dimensions)
+                    .await?,
+            ))),
+            #[cfg(not(feature = "qdrant"))]
+            VectorBackend::Qdrant { .. } => Err(VectorError::Config(
+                "built without the `qdrant` feature".to_string(),
+            )),
+        }
+    }
+}
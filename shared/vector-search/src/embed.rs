@@ -0,0 +1,110 @@
+/// Width of the vectors produced by [`HashingEmbedder::default`].
+pub const DEFAULT_DIMENSIONS: usize = 256;
+
+/// Weight of a character trigram relative to a whole word.
+const TRIGRAM_WEIGHT: f32 = 0.5;
+
+/// Turns text into a unit-length vector. Documents and queries must be
+/// embedded by the same model for their similarity to mean anything.
+pub trait Embedder: Send + Sync {
+    fn dimensions(&self) -> usize;
+
+    fn embed(&self, text: &str) -> Vec<f32>;
+}
+
+/// Local embedding model that hashes words and character trigrams into a
+/// fixed number of buckets. It needs no model files and always returns the
+/// same vector for the same text, so the indexer, eco-api and tests agree
+/// without coordination. Trigrams let "builder" land near "builders".
+#[derive(Clone, Debug)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn add(&self, vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let bucket = (hash % self.dimensions as u64) as usize;
+        // The top bit picks a sign so unrelated features cancel out on
+        // average instead of piling up in shared buckets.
+        if hash >> 63 == 0 {
+            vector[bucket] += weight;
+        } else {
+            vector[bucket] -= weight;
+        }
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIMENSIONS)
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Text without any letters or digits embeds to the zero vector.
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let text = text.to_lowercase();
+        for word in text.split(|c: char| !c.is_alphanumeric()) {
+            if word.is_empty() {
+                continue;
+            }
+            self.add(&mut vector, word, 1.0);
+            let padded: Vec<char> = format!(" {word} ").chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                self.add(&mut vector, &format!("#{trigram}"), TRIGRAM_WEIGHT);
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+pub(crate) fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in vector {
+            *value /= norm;
+        }
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    #[test]
+    fn embeddings_are_deterministic_unit_vectors_that_reward_shared_words() {
+        let embedder = HashingEmbedder::default();
+        let pod = embedder.embed("Community garden builders");
+        assert_eq!(pod, embedder.embed("community  GARDEN builders"));
+        assert!((cosine(&pod, &pod) - 1.0).abs() < 1e-5);
+
+        let near = embedder.embed("garden builder");
+        let far = embedder.embed("quarterly tax report");
+        assert!(cosine(&pod, &near) > cosine(&pod, &far));
+        assert!(embedder.embed("  ?! ").iter().all(|value| *value == 0.0));
+    }
+}
@@ -0,0 +1,335 @@
+//! Hierarchical navigable small world graph (Malkov & Yashunin) over unit
+//! vectors. Nodes are never unlinked: removing a document tombstones its
+//! node, which keeps routing searches until the graph is rebuilt.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::embed::fnv1a;
+use crate::{Neighbour, VectorError};
+
+/// Links per node above layer 0; layer 0 keeps twice as many.
+const MAX_LINKS: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+const EF_SEARCH: usize = 64;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Hnsw {
+    dimensions: Option<usize>,
+    nodes: Vec<Node>,
+    entry: Option<usize>,
+    #[serde(skip)]
+    live: HashMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Node {
+    doc_id: String,
+    vector: Vec<f32>,
+    /// Neighbour ids per layer, layer 0 first.
+    links: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Candidate {
+    distance: f32,
+    id: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hnsw {
+    /// Restores the `doc_id` lookup after deserializing.
+    pub(crate) fn reindex(&mut self) {
+        self.live = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.deleted)
+            .map(|(id, node)| (node.doc_id.clone(), id))
+            .collect();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    pub(crate) fn contains(&self, doc_id: &str) -> bool {
+        self.live.contains_key(doc_id)
+    }
+
+    pub(crate) fn insert(&mut self, doc_id: String, vector: Vec<f32>) -> Result<(), VectorError> {
+        self.check_dimensions(&vector)?;
+        self.dimensions = Some(vector.len());
+        self.remove(&doc_id);
+        // Too many tombstones make searches walk mostly dead nodes.
+        if self.nodes.len() > 2 * self.live.len() + MAX_LINKS {
+            self.rebuild();
+        }
+
+        let id = self.nodes.len();
+        let level = level_for(&doc_id);
+        self.nodes.push(Node {
+            doc_id: doc_id.clone(),
+            vector,
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.live.insert(doc_id, id);
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            return Ok(());
+        };
+
+        let top = self.nodes[entry].links.len() - 1;
+        let query = self.nodes[id].vector.clone();
+        let mut entry_points = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, layer)[0].id];
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, EF_CONSTRUCTION, layer);
+            let cap = max_links(layer);
+            let links: Vec<usize> = found.iter().take(cap).map(|found| found.id).collect();
+            for &neighbour in &links {
+                self.nodes[neighbour].links[layer].push(id);
+                if self.nodes[neighbour].links[layer].len() > cap {
+                    self.prune(neighbour, layer, cap);
+                }
+            }
+            self.nodes[id].links[layer] = links;
+            entry_points = found.iter().map(|found| found.id).collect();
+        }
+        if level > top {
+            self.entry = Some(id);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, doc_id: &str) {
+        if let Some(id) = self.live.remove(doc_id) {
+            self.nodes[id].deleted = true;
+        }
+    }
+
+    pub(crate) fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+    ) -> Result<Vec<Neighbour>, VectorError> {
+        let Some(entry) = self.entry else {
+            return Ok(Vec::new());
+        };
+        self.check_dimensions(query)?;
+        let mut entry_points = vec![entry];
+        for layer in (1..self.nodes[entry].links.len()).rev() {
+            entry_points = vec![self.search_layer(query, &entry_points, 1, layer)[0].id];
+        }
+        let ef = EF_SEARCH.max(limit * 2);
+        Ok(self
+            .search_layer(query, &entry_points, ef, 0)
+            .into_iter()
+            .filter(|candidate| !self.nodes[candidate.id].deleted)
+            .take(limit)
+            .map(|candidate| Neighbour {
+                doc_id: self.nodes[candidate.id].doc_id.clone(),
+                score: 1.0 - candidate.distance,
+            })
+            .collect())
+    }
+
+    pub(crate) fn check_dimensions(&self, vector: &[f32]) -> Result<(), VectorError> {
+        match self.dimensions {
+            Some(expected) if expected != vector.len() => Err(VectorError::Dimensions {
+                expected,
+                actual: vector.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Best-first search of one layer, returning up to `ef` nodes closest
+    /// first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut frontier = BinaryHeap::new();
+        let mut found = BinaryHeap::new();
+        for &id in entry_points {
+            let candidate = Candidate {
+                distance: self.distance(query, id),
+                id,
+            };
+            frontier.push(Reverse(candidate));
+            found.push(candidate);
+        }
+        while let Some(Reverse(closest)) = frontier.pop() {
+            let furthest = found.peek().map_or(f32::INFINITY, |found| found.distance);
+            if closest.distance > furthest && found.len() >= ef {
+                break;
+            }
+            for &neighbour in self.nodes[closest.id]
+                .links
+                .get(layer)
+                .into_iter()
+                .flatten()
+            {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: self.distance(query, neighbour),
+                    id: neighbour,
+                };
+                let furthest = found.peek().map_or(f32::INFINITY, |found| found.distance);
+                if found.len() < ef || candidate.distance < furthest {
+                    frontier.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    fn prune(&mut self, id: usize, layer: usize, cap: usize) {
+        let vector = self.nodes[id].vector.clone();
+        let mut links: Vec<Candidate> = self.nodes[id].links[layer]
+            .iter()
+            .map(|&neighbour| Candidate {
+                distance: self.distance(&vector, neighbour),
+                id: neighbour,
+            })
+            .collect();
+        links.sort();
+        self.nodes[id].links[layer] = links.into_iter().take(cap).map(|link| link.id).collect();
+    }
+
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        *self = Hnsw {
+            dimensions: self.dimensions,
+            ..Hnsw::default()
+        };
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(node.doc_id, node.vector)
+                .expect("stored vectors share one dimension");
+        }
+    }
+
+    fn distance(&self, query: &[f32], id: usize) -> f32 {
+        let dot: f32 = query
+            .iter()
+            .zip(&self.nodes[id].vector)
+            .map(|(a, b)| a * b)
+            .sum();
+        1.0 - dot
+    }
+}
+
+fn max_links(layer: usize) -> usize {
+    if layer == 0 {
+        MAX_LINKS * 2
+    } else {
+        MAX_LINKS
+    }
+}
+
+/// Draws the node's top layer from the usual exponential distribution, seeded
+/// by the `doc_id` so rebuilding the same documents yields the same graph.
+fn level_for(doc_id: &str) -> usize {
+    let uniform = (fnv1a(doc_id.as_bytes()) >> 11) as f64 / (1u64 << 53) as f64;
+    let level = -(1.0 - uniform).ln() / (MAX_LINKS as f64).ln();
+    level as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::normalize;
+
+    fn vector(seed: u64) -> Vec<f32> {
+        let mut state = seed.wrapping_add(1);
+        let mut vector: Vec<f32> = (0..16)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+            })
+            .collect();
+        normalize(&mut vector);
+        vector
+    }
+
+    fn exact(points: &[(String, Vec<f32>)], query: &[f32], limit: usize) -> Vec<String> {
+        let mut scored: Vec<_> = points
+            .iter()
+            .map(|(doc_id, vector)| {
+                let dot: f32 = query.iter().zip(vector).map(|(a, b)| a * b).sum();
+                (dot, doc_id.clone())
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(limit).map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn graph_search_matches_brute_force_and_skips_removed_documents() {
+        let points: Vec<_> = (0..500).map(|n| (format!("doc:{n}"), vector(n))).collect();
+        let mut graph = Hnsw::default();
+        for (doc_id, vector) in &points {
+            graph
+                .insert(doc_id.clone(), vector.clone())
+                .expect("insert");
+        }
+
+        let query = vector(10_000);
+        let found: Vec<_> = graph
+            .search(&query, 5)
+            .expect("search")
+            .into_iter()
+            .map(|neighbour| neighbour.doc_id)
+            .collect();
+        assert_eq!(found, exact(&points, &query, 5));
+
+        graph.remove(&found[0]);
+        let after: Vec<_> = graph
+            .search(&query, 5)
+            .expect("search")
+            .into_iter()
+            .map(|neighbour| neighbour.doc_id)
+            .collect();
+        assert!(!after.contains(&found[0]));
+        assert_eq!(after[..4], found[1..]);
+        assert_eq!(graph.len(), 499);
+        assert!(matches!(
+            graph.search(&[1.0], 1),
+            Err(VectorError::Dimensions { .. })
+        ));
+    }
+}
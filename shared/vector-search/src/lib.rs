@@ -0,0 +1,81 @@
+//! Vector side of eco search: turning documents into embeddings and finding
+//! the nearest ones again. eco-indexer writes through [`VectorStore`];
+//! eco-api reads through the same trait.
+
+mod config;
+mod embed;
+mod hnsw;
+mod local;
+#[cfg(feature = "qdrant")]
+mod qdrant;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use config::{VectorBackend, VectorConfig, DEFAULT_QDRANT_COLLECTION};
+pub use embed::{Embedder, HashingEmbedder, DEFAULT_DIMENSIONS};
+pub use local::{LocalVectorStore, DEFAULT_RELOAD_INTERVAL, VECTOR_FILE};
+#[cfg(feature = "qdrant")]
+pub use qdrant::QdrantStore;
+
+#[derive(Debug, Error)]
+pub enum VectorError {
+    #[error("vector store I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode vector store: {0}")]
+    Encoding(#[from] serde_json::Error),
+    #[error("vector store file is corrupt: {0}")]
+    Corrupt(String),
+    #[error("invalid vector search configuration: {0}")]
+    Config(String),
+    #[error("vector has {actual} dimensions, the store holds {expected}")]
+    Dimensions { expected: usize, actual: usize },
+    #[cfg(feature = "qdrant")]
+    #[error("qdrant request failed: {0}")]
+    Qdrant(String),
+}
+
+/// Embedding of one search document, keyed like the Tantivy `doc_id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VectorPoint {
+    pub doc_id: String,
+    pub vector: Vec<f32>,
+}
+
+/// A stored document close to the query vector.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Neighbour {
+    pub doc_id: String,
+    /// Cosine similarity; higher is closer.
+    pub score: f32,
+}
+
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Adds the points, replacing any stored under the same `doc_id`.
+    async fn upsert(&self, points: Vec<VectorPoint>) -> Result<(), VectorError>;
+
+    async fn delete(&self, doc_ids: &[String]) -> Result<(), VectorError>;
+
+    async fn clear(&self) -> Result<(), VectorError>;
+
+    /// Makes the writes so far visible to readers.
+    async fn commit(&self) -> Result<(), VectorError>;
+
+    /// Up to `limit` stored documents, closest first.
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<Neighbour>, VectorError>;
+
+    /// A store for the index version in `dir` that starts with this store's
+    /// points; writes to either leave the other alone. Stores shared by
+    /// every version hand out themselves.
+    async fn fork(&self, dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError>;
+
+    /// Opens the store of the published index version in `dir`, reusing
+    /// what this store already loaded when that version was forked from
+    /// this one.
+    async fn follow(&self, dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError>;
+}
@@ -0,0 +1,1163 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+
+use crate::hnsw::Hnsw;
+use crate::{Neighbour, VectorError, VectorPoint, VectorStore};
+
+/// How often a store looks for commits made by another process.
+pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+/// Name of the store's head file inside an index version directory.
+pub const VECTOR_FILE: &str = "vectors.bin";
+
+const MAGIC: &[u8; 8] = b"ECOVEC1\n";
+/// Magic followed by the generation, which changes whenever the file is
+/// rewritten rather than appended to.
+const HEADER_LEN: u64 = 16;
+/// Magic followed by the data file id and the length of it the store holds.
+const HEAD_MAGIC: &[u8; 8] = b"ECOVHD1\n";
+const HEAD_LEN: usize = 24;
+/// Frame kind plus payload length.
+const FRAME_HEADER_LEN: u64 = 5;
+const SNAPSHOT_FRAME: u8 = 0;
+const CHANGES_FRAME: u8 = 1;
+/// Marks where a fork starts diverging from the store it was forked from;
+/// the payload is a random id.
+const FORK_FRAME: u8 = 2;
+const FORK_FRAME_LEN: usize = FRAME_HEADER_LEN as usize + 8;
+const UPSERT: u8 = 0;
+const DELETE: u8 = 1;
+const CLEAR: u8 = 2;
+/// Appended changes may grow to the snapshot's size, or this much for small
+/// stores, before the file is compacted into a fresh snapshot.
+const MIN_LOG_BYTES: u64 = 1 << 20;
+/// Changes kept beside a shared graph before they are folded into a copy
+/// of it: an eighth of the graph, within these bounds.
+const MIN_OVERLAY: usize = 256;
+const MAX_OVERLAY: usize = 8192;
+/// Opening a data file the writer just compacted away starts over from the
+/// head this often.
+const LOAD_ATTEMPTS: usize = 3;
+
+/// HNSW graph kept in memory and saved to a data file: a snapshot of the
+/// graph followed by the changes of each later commit. Commits append their
+/// changes and then move the head file, [`VECTOR_FILE`], past them; readers
+/// in other processes replay only what the head gained since they last
+/// looked, and once the changes outgrow the snapshot they are compacted
+/// into a fresh data file. A fork for another index version hard-links the
+/// data file, appends its own changes past the origin's head and shares the
+/// graph in memory, so forking costs what the fork changes rather than what
+/// the store holds. Readers following to that version do the same.
+pub struct LocalVectorStore {
+    shared: Arc<Shared>,
+    reload_interval: Duration,
+    checked_at: Mutex<Option<Instant>>,
+}
+
+struct Shared {
+    /// The head file.
+    path: PathBuf,
+    state: RwLock<State>,
+}
+
+struct State {
+    graph: Graph,
+    /// Applied to `graph` but not saved yet.
+    pending: Vec<Change>,
+    /// The part of the data file `graph` reflects.
+    saved: Option<Saved>,
+}
+
+/// A graph shared with the stores this one was forked from or followed,
+/// plus the changes made since, searched exhaustively until there are
+/// enough to fold them into a graph of this store's own.
+#[derive(Clone, Default)]
+struct Graph {
+    base: Arc<Hnsw>,
+    upserts: HashMap<String, Vec<f32>>,
+    /// Documents of `base` deleted or replaced since.
+    removed: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Saved {
+    /// Id of the data file.
+    data: u64,
+    generation: u64,
+    /// End of the last frame applied.
+    offset: u64,
+    snapshot_len: u64,
+    /// The last fork frame applied.
+    last_fork: Option<Fork>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fork {
+    offset: u64,
+    id: u64,
+}
+
+/// Contents of a head file: which data file holds the store and how much
+/// of it. Bytes past `len` belong to forks or to a commit in progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Head {
+    data: u64,
+    len: u64,
+}
+
+#[derive(Clone)]
+enum Change {
+    Upsert(VectorPoint),
+    Delete(String),
+    Clear,
+}
+
+impl LocalVectorStore {
+    /// Loads the graph saved at `path`, or starts empty when nothing has
+    /// been committed there yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, VectorError> {
+        let path = path.as_ref().to_path_buf();
+        let state = match load(&path)? {
+            Some((graph, saved)) => State {
+                graph,
+                pending: Vec::new(),
+                saved: Some(saved),
+            },
+            None => State::empty(),
+        };
+        Ok(Self::with_state(path, state, DEFAULT_RELOAD_INTERVAL))
+    }
+
+    fn with_state(path: PathBuf, state: State, reload_interval: Duration) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                path,
+                state: RwLock::new(state),
+            }),
+            reload_interval,
+            checked_at: Mutex::new(None),
+        }
+    }
+
+    pub fn with_reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = interval;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared
+            .state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .graph
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn reload_due(&self) -> bool {
+        let mut checked_at = self
+            .checked_at
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        if checked_at.is_some_and(|at| now.duration_since(at) < self.reload_interval) {
+            return false;
+        }
+        *checked_at = Some(now);
+        true
+    }
+
+    /// Runs file I/O and graph work off the async runtime.
+    async fn blocking<T, F>(&self, work: F) -> Result<T, VectorError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Shared) -> Result<T, VectorError> + Send + 'static,
+    {
+        let shared = self.shared.clone();
+        tokio::task::spawn_blocking(move || work(&shared))
+            .await
+            .map_err(|err| VectorError::Io(std::io::Error::other(err)))?
+    }
+}
+
+impl State {
+    fn empty() -> Self {
+        Self {
+            graph: Graph::default(),
+            pending: Vec::new(),
+            saved: None,
+        }
+    }
+}
+
+impl Graph {
+    fn new(base: Hnsw) -> Self {
+        Self {
+            base: Arc::new(base),
+            ..Self::default()
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.base.len() + self.upserts.len() - self.removed.len()
+    }
+
+    fn insert(&mut self, doc_id: String, vector: Vec<f32>) -> Result<(), VectorError> {
+        self.check_dimensions(&vector)?;
+        if let Some(base) = self.exclusive()? {
+            return base.insert(doc_id, vector);
+        }
+        if self.base.contains(&doc_id) {
+            self.removed.insert(doc_id.clone());
+        }
+        self.upserts.insert(doc_id, vector);
+        self.fold_if_large()
+    }
+
+    fn remove(&mut self, doc_id: &str) -> Result<(), VectorError> {
+        if let Some(base) = self.exclusive()? {
+            base.remove(doc_id);
+            return Ok(());
+        }
+        self.upserts.remove(doc_id);
+        if self.base.contains(doc_id) {
+            self.removed.insert(doc_id.to_string());
+        }
+        self.fold_if_large()
+    }
+
+    fn search(&self, query: &[f32], limit: usize) -> Result<Vec<Neighbour>, VectorError> {
+        self.check_dimensions(query)?;
+        let mut found: Vec<Neighbour> = self
+            .base
+            .search(query, limit + self.removed.len())?
+            .into_iter()
+            .filter(|neighbour| !self.removed.contains(&neighbour.doc_id))
+            .collect();
+        found.extend(self.upserts.iter().map(|(doc_id, vector)| Neighbour {
+            doc_id: doc_id.clone(),
+            score: query.iter().zip(vector).map(|(a, b)| a * b).sum(),
+        }));
+        found.sort_by(|a, b| b.score.total_cmp(&a.score));
+        found.truncate(limit);
+        Ok(found)
+    }
+
+    fn check_dimensions(&self, vector: &[f32]) -> Result<(), VectorError> {
+        self.base.check_dimensions(vector)?;
+        match self.upserts.values().next() {
+            Some(stored) if stored.len() != vector.len() => Err(VectorError::Dimensions {
+                expected: stored.len(),
+                actual: vector.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The graph with every change folded in.
+    fn folded(&mut self) -> Result<&Hnsw, VectorError> {
+        self.fold()?;
+        Ok(&self.base)
+    }
+
+    /// The graph to change in place once no other store shares it.
+    fn exclusive(&mut self) -> Result<Option<&mut Hnsw>, VectorError> {
+        if Arc::get_mut(&mut self.base).is_none() {
+            return Ok(None);
+        }
+        self.fold()?;
+        Ok(Arc::get_mut(&mut self.base))
+    }
+
+    fn fold_if_large(&mut self) -> Result<(), VectorError> {
+        let limit = (self.base.len() / 8).clamp(MIN_OVERLAY, MAX_OVERLAY);
+        if self.upserts.len() + self.removed.len() > limit {
+            self.fold()?;
+        }
+        Ok(())
+    }
+
+    /// Applies the changes to the graph, copying it first if it is shared.
+    fn fold(&mut self) -> Result<(), VectorError> {
+        if self.upserts.is_empty() && self.removed.is_empty() {
+            return Ok(());
+        }
+        let base = Arc::make_mut(&mut self.base);
+        for doc_id in self.removed.drain() {
+            base.remove(&doc_id);
+        }
+        for (doc_id, vector) in self.upserts.drain() {
+            base.insert(doc_id, vector)?;
+        }
+        Ok(())
+    }
+}
+
+impl Shared {
+    fn reload_if_changed(&self) -> Result<(), VectorError> {
+        let Some(head) = read_head(&self.path)? else {
+            return Ok(());
+        };
+        let saved = self
+            .state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .saved;
+        if saved.is_some_and(|saved| saved.data == head.data && saved.offset >= head.len) {
+            return Ok(());
+        }
+        let mut state = self
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        catch_up(&self.path, &mut state, head)
+    }
+
+    /// Starts the files of a fork at `path` and returns the state of a
+    /// store writing there, which shares this store's graph.
+    fn fork(&self, path: &Path) -> Result<State, VectorError> {
+        // Held for writing so that two forks never append to the shared data
+        // file at once.
+        let state = self
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let saved = match state.saved {
+            Some(saved) => Some(fork_files(&self.path, path, saved)?),
+            None => None,
+        };
+        Ok(State {
+            graph: state.graph.clone(),
+            pending: state.pending.clone(),
+            saved,
+        })
+    }
+
+    /// The state of a store reading `path`, starting from this one's graph
+    /// when the file continues this store's file.
+    fn follow(&self, path: &Path) -> Result<State, VectorError> {
+        let Some(head) = read_head(path)? else {
+            return Ok(State::empty());
+        };
+        let ours = self
+            .state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut state = match ours.saved {
+            Some(saved) if continues(&data_path(path, head.data), &saved, head.len)? => State {
+                graph: ours.graph.clone(),
+                pending: Vec::new(),
+                saved: Some(saved),
+            },
+            _ => State::empty(),
+        };
+        drop(ours);
+        catch_up(path, &mut state, head)?;
+        Ok(state)
+    }
+
+    fn commit(&self) -> Result<(), VectorError> {
+        let mut state = self
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.pending.is_empty() && state.saved.is_some() {
+            return Ok(());
+        }
+        let changes = encode_changes(&state.pending);
+        let frame_len = FRAME_HEADER_LEN + changes.len() as u64;
+        let appendable = match state.saved {
+            Some(saved) => {
+                let log_len = saved.offset - HEADER_LEN - saved.snapshot_len;
+                let head = Head {
+                    data: saved.data,
+                    len: saved.offset,
+                };
+                // A fork sharing the data file may have appended past us.
+                read_head(&self.path)? == Some(head)
+                    && std::fs::metadata(data_path(&self.path, saved.data))?.len() == saved.offset
+                    && log_len + frame_len <= saved.snapshot_len.max(MIN_LOG_BYTES)
+            }
+            None => false,
+        };
+
+        if appendable {
+            let mut saved = state.saved.expect("appendable stores were saved");
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(data_path(&self.path, saved.data))?;
+            let mut frame = Vec::with_capacity(frame_len as usize);
+            write_frame(&mut frame, CHANGES_FRAME, &changes);
+            file.write_all(&frame)?;
+            file.sync_data()?;
+            saved.offset += frame_len;
+            write_head(
+                &self.path,
+                Head {
+                    data: saved.data,
+                    len: saved.offset,
+                },
+            )?;
+            state.saved = Some(saved);
+        } else {
+            let previous = state.saved.map(|saved| saved.data);
+            state.saved = Some(self.compact(state.graph.folded()?, previous)?);
+        }
+        state.pending.clear();
+        Ok(())
+    }
+
+    /// Writes a snapshot of `graph` to a fresh data file, points the head at
+    /// it and drops this store's link to the `previous` one.
+    fn compact(&self, graph: &Hnsw, previous: Option<u64>) -> Result<Saved, VectorError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let snapshot = serde_json::to_vec(graph)?;
+        let generation = unique_suffix();
+        let mut contents =
+            Vec::with_capacity((HEADER_LEN + FRAME_HEADER_LEN) as usize + snapshot.len());
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&generation.to_le_bytes());
+        write_frame(&mut contents, SNAPSHOT_FRAME, &snapshot);
+
+        let mut file = File::create(data_path(&self.path, generation))?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        let offset = contents.len() as u64;
+        write_head(
+            &self.path,
+            Head {
+                data: generation,
+                len: offset,
+            },
+        )?;
+        if let Some(previous) = previous {
+            // Forks keep their own links; a reader still opening it retries.
+            let _ = std::fs::remove_file(data_path(&self.path, previous));
+        }
+        Ok(Saved {
+            data: generation,
+            generation,
+            offset,
+            snapshot_len: FRAME_HEADER_LEN + snapshot.len() as u64,
+            last_fork: None,
+        })
+    }
+}
+
+#[async_trait]
+impl VectorStore for LocalVectorStore {
+    async fn upsert(&self, points: Vec<VectorPoint>) -> Result<(), VectorError> {
+        self.blocking(move |shared| {
+            let mut state = shared
+                .state
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            for point in points {
+                state
+                    .graph
+                    .insert(point.doc_id.clone(), point.vector.clone())?;
+                state.pending.push(Change::Upsert(point));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, doc_ids: &[String]) -> Result<(), VectorError> {
+        let mut state = self
+            .shared
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for doc_id in doc_ids {
+            state.graph.remove(doc_id)?;
+            state.pending.push(Change::Delete(doc_id.clone()));
+        }
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), VectorError> {
+        let mut state = self
+            .shared
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.graph = Graph::default();
+        state.pending.push(Change::Clear);
+        Ok(())
+    }
+
+    async fn commit(&self) -> Result<(), VectorError> {
+        self.blocking(Shared::commit).await
+    }
+
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<Neighbour>, VectorError> {
+        let reload = self.reload_due();
+        let vector = vector.to_vec();
+        self.blocking(move |shared| {
+            if reload {
+                shared.reload_if_changed()?;
+            }
+            shared
+                .state
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .graph
+                .search(&vector, limit)
+        })
+        .await
+    }
+
+    async fn fork(&self, dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError> {
+        let path = dir.join(VECTOR_FILE);
+        let reload_interval = self.reload_interval;
+        self.blocking(move |shared| {
+            let state = shared.fork(&path)?;
+            Ok(
+                Arc::new(LocalVectorStore::with_state(path, state, reload_interval))
+                    as Arc<dyn VectorStore>,
+            )
+        })
+        .await
+    }
+
+    async fn follow(&self, dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError> {
+        let path = dir.join(VECTOR_FILE);
+        let reload_interval = self.reload_interval;
+        self.blocking(move |shared| {
+            let state = shared.follow(&path)?;
+            Ok(
+                Arc::new(LocalVectorStore::with_state(path, state, reload_interval))
+                    as Arc<dyn VectorStore>,
+            )
+        })
+        .await
+    }
+}
+
+/// Gives the head at `to` the part of the data file `saved` describes plus
+/// a fork frame. The data file is hard-linked and the frame appended past
+/// `from`'s head, which readers of `from` never look beyond; when another
+/// fork already appended there, that part is copied instead.
+fn fork_files(from: &Path, to: &Path, saved: Saved) -> Result<Saved, VectorError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let source = data_path(from, saved.data);
+    let fork = Fork {
+        offset: saved.offset,
+        id: unique_suffix(),
+    };
+    let linked = std::fs::metadata(&source)?.len() == saved.offset
+        && std::fs::hard_link(&source, data_path(to, saved.data)).is_ok();
+    let data = if linked {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(data_path(to, saved.data))?;
+        file.write_all(&fork_frame(fork.id))?;
+        file.sync_data()?;
+        saved.data
+    } else {
+        let mut copy = File::create(data_path(to, fork.id))?;
+        std::io::copy(&mut File::open(&source)?.take(saved.offset), &mut copy)?;
+        copy.write_all(&fork_frame(fork.id))?;
+        copy.sync_all()?;
+        fork.id
+    };
+    let offset = saved.offset + FORK_FRAME_LEN as u64;
+    write_head(to, Head { data, len: offset })?;
+    Ok(Saved {
+        data,
+        offset,
+        last_fork: Some(fork),
+        ..saved
+    })
+}
+
+/// Brings `state` up to `head`: replays what was appended when the data
+/// file continues what `state` last saw and loads it afresh otherwise.
+fn catch_up(path: &Path, state: &mut State, head: Head) -> Result<(), VectorError> {
+    if let Some(saved) = state.saved {
+        let data = data_path(path, head.data);
+        if continues(&data, &saved, head.len)? {
+            let mut file = File::open(&data)?;
+            file.seek(SeekFrom::Start(saved.offset))?;
+            let mut tail = Vec::new();
+            file.take(head.len - saved.offset).read_to_end(&mut tail)?;
+            let mut last_fork = saved.last_fork;
+            let consumed = replay(&mut state.graph, &tail, saved.offset, &mut last_fork)?;
+            state.saved = Some(Saved {
+                data: head.data,
+                offset: saved.offset + consumed,
+                last_fork,
+                ..saved
+            });
+            return Ok(());
+        }
+    }
+    if let Some((graph, saved)) = load(path)? {
+        *state = State {
+            graph,
+            pending: Vec::new(),
+            saved: Some(saved),
+        };
+    }
+    Ok(())
+}
+
+/// Whether the first `len` bytes of the data file `data` hold everything
+/// `saved` describes: the same generation, at least as many bytes and the
+/// same last fork, so it is the same file or one forked from it since.
+fn continues(data: &Path, saved: &Saved, len: u64) -> Result<bool, VectorError> {
+    let mut file = match File::open(data) {
+        Ok(file) => file,
+        // Compacted away since the head was read.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    if len < saved.offset || read_header(&mut file)? != saved.generation {
+        return Ok(false);
+    }
+    let Some(fork) = saved.last_fork else {
+        return Ok(true);
+    };
+    file.seek(SeekFrom::Start(fork.offset))?;
+    let mut frame = [0; FORK_FRAME_LEN];
+    file.read_exact(&mut frame)?;
+    Ok(frame[..] == fork_frame(fork.id)[..])
+}
+
+/// Applies the frames after the snapshot, which start at `offset` in the
+/// file, and returns how many bytes they took up.
+fn replay(
+    graph: &mut Graph,
+    bytes: &[u8],
+    offset: u64,
+    last_fork: &mut Option<Fork>,
+) -> Result<u64, VectorError> {
+    let mut frames = Frames::new(bytes);
+    loop {
+        let at = offset + frames.consumed();
+        let Some((kind, payload)) = frames.next()? else {
+            break;
+        };
+        match kind {
+            CHANGES_FRAME => {
+                for change in decode_changes(payload)? {
+                    apply(graph, change)?;
+                }
+            }
+            FORK_FRAME => {
+                let id = payload
+                    .try_into()
+                    .map_err(|_| corrupt("fork id is not eight bytes"))?;
+                *last_fork = Some(Fork {
+                    offset: at,
+                    id: u64::from_le_bytes(id),
+                });
+            }
+            _ => return Err(corrupt("snapshot after the first frame")),
+        }
+    }
+    Ok(frames.consumed())
+}
+
+/// Reads the snapshot and every frame the head covers.
+fn load(path: &Path) -> Result<Option<(Graph, Saved)>, VectorError> {
+    let mut attempt = 1;
+    let (head, mut file) = loop {
+        let Some(head) = read_head(path)? else {
+            return Ok(None);
+        };
+        match File::open(data_path(path, head.data)) {
+            Ok(file) => break (head, file),
+            // Compacted between reading the head and opening its data file.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && attempt < LOAD_ATTEMPTS => {
+                attempt += 1
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+    let generation = read_header(&mut file)?;
+    let body_len = head
+        .len
+        .checked_sub(HEADER_LEN)
+        .ok_or_else(|| corrupt("head ends inside the header"))?;
+    let mut body = Vec::new();
+    file.take(body_len).read_to_end(&mut body)?;
+
+    let mut frames = Frames::new(&body);
+    let mut snapshot: Hnsw = match frames.next()? {
+        Some((SNAPSHOT_FRAME, snapshot)) => serde_json::from_slice(snapshot)?,
+        _ => return Err(corrupt("missing snapshot")),
+    };
+    snapshot.reindex();
+    let mut graph = Graph::new(snapshot);
+    let snapshot_len = frames.consumed();
+    let mut last_fork = None;
+    let changes_len = replay(
+        &mut graph,
+        &body[snapshot_len as usize..],
+        HEADER_LEN + snapshot_len,
+        &mut last_fork,
+    )?;
+    Ok(Some((
+        graph,
+        Saved {
+            data: head.data,
+            generation,
+            offset: HEADER_LEN + snapshot_len + changes_len,
+            snapshot_len,
+            last_fork,
+        },
+    )))
+}
+
+fn read_header(file: &mut File) -> Result<u64, VectorError> {
+    let mut header = [0; HEADER_LEN as usize];
+    file.read_exact(&mut header)?;
+    if &header[..8] != MAGIC {
+        return Err(corrupt("not a vector store file"));
+    }
+    Ok(u64::from_le_bytes(
+        header[8..].try_into().expect("eight bytes"),
+    ))
+}
+
+fn read_head(path: &Path) -> Result<Option<Head>, VectorError> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if bytes.len() != HEAD_LEN || &bytes[..8] != HEAD_MAGIC {
+        return Err(corrupt("not a vector store head"));
+    }
+    Ok(Some(Head {
+        data: u64::from_le_bytes(bytes[8..16].try_into().expect("eight bytes")),
+        len: u64::from_le_bytes(bytes[16..].try_into().expect("eight bytes")),
+    }))
+}
+
+/// Replaces the head by rename, so readers see the old or the new one.
+fn write_head(path: &Path, head: Head) -> Result<(), VectorError> {
+    let mut bytes = Vec::with_capacity(HEAD_LEN);
+    bytes.extend_from_slice(HEAD_MAGIC);
+    bytes.extend_from_slice(&head.data.to_le_bytes());
+    bytes.extend_from_slice(&head.len.to_le_bytes());
+    let staging = path.with_extension(format!("{}.{:x}.tmp", std::process::id(), unique_suffix()));
+    let mut file = File::create(&staging)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&staging, path)?;
+    Ok(())
+}
+
+/// The data file `id` beside the head at `path`.
+fn data_path(path: &Path, id: u64) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("vectors");
+    path.with_file_name(format!("{stem}.{id:016x}.log"))
+}
+
+fn apply(graph: &mut Graph, change: Change) -> Result<(), VectorError> {
+    match change {
+        Change::Upsert(point) => graph.insert(point.doc_id, point.vector)?,
+        Change::Delete(doc_id) => graph.remove(&doc_id)?,
+        Change::Clear => *graph = Graph::default(),
+    }
+    Ok(())
+}
+
+fn fork_frame(id: u64) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FORK_FRAME_LEN);
+    write_frame(&mut frame, FORK_FRAME, &id.to_le_bytes());
+    frame
+}
+
+fn write_frame(out: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+    out.push(kind);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+}
+
+fn encode_changes(changes: &[Change]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let put_str = |out: &mut Vec<u8>, value: &str| {
+        out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+    };
+    for change in changes {
+        match change {
+            Change::Upsert(point) => {
+                out.push(UPSERT);
+                put_str(&mut out, &point.doc_id);
+                out.extend_from_slice(&(point.vector.len() as u32).to_le_bytes());
+                for value in &point.vector {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Change::Delete(doc_id) => {
+                out.push(DELETE);
+                put_str(&mut out, doc_id);
+            }
+            Change::Clear => out.push(CLEAR),
+        }
+    }
+    out
+}
+
+fn decode_changes(mut bytes: &[u8]) -> Result<Vec<Change>, VectorError> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], VectorError> {
+        if bytes.len() < len {
+            return Err(corrupt("truncated change"));
+        }
+        let (head, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(head)
+    }
+    fn take_u32(bytes: &mut &[u8]) -> Result<u32, VectorError> {
+        Ok(u32::from_le_bytes(
+            take(bytes, 4)?.try_into().expect("four bytes"),
+        ))
+    }
+    fn take_str(bytes: &mut &[u8]) -> Result<String, VectorError> {
+        let len = take_u32(bytes)? as usize;
+        String::from_utf8(take(bytes, len)?.to_vec()).map_err(|_| corrupt("doc_id is not UTF-8"))
+    }
+
+    let mut changes = Vec::new();
+    while !bytes.is_empty() {
+        let change = match take(&mut bytes, 1)?[0] {
+            UPSERT => {
+                let doc_id = take_str(&mut bytes)?;
+                let dimensions = take_u32(&mut bytes)? as usize;
+                let vector = take(&mut bytes, dimensions * 4)?
+                    .chunks_exact(4)
+                    .map(|value| f32::from_le_bytes(value.try_into().expect("four bytes")))
+                    .collect();
+                Change::Upsert(VectorPoint { doc_id, vector })
+            }
+            DELETE => Change::Delete(take_str(&mut bytes)?),
+            CLEAR => Change::Clear,
+            _ => return Err(corrupt("unknown change")),
+        };
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Complete frames in a byte range. A frame still being appended by the
+/// writer ends the iteration and is picked up by a later read.
+struct Frames<'a> {
+    bytes: &'a [u8],
+    consumed: usize,
+}
+
+impl<'a> Frames<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, consumed: 0 }
+    }
+
+    fn next(&mut self) -> Result<Option<(u8, &'a [u8])>, VectorError> {
+        let rest = &self.bytes[self.consumed..];
+        if rest.len() < FRAME_HEADER_LEN as usize {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes(rest[1..5].try_into().expect("four bytes")) as usize;
+        let Some(payload) = rest.get(FRAME_HEADER_LEN as usize..FRAME_HEADER_LEN as usize + len)
+        else {
+            return Ok(None);
+        };
+        self.consumed += FRAME_HEADER_LEN as usize + len;
+        Ok(Some((rest[0], payload)))
+    }
+
+    fn consumed(&self) -> u64 {
+        self.consumed as u64
+    }
+}
+
+fn corrupt(reason: &str) -> VectorError {
+    VectorError::Corrupt(reason.to_string())
+}
+
+fn unique_suffix() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    nanos ^ (u64::from(std::process::id()) << 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Embedder, HashingEmbedder};
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("vector-search-{:x}", unique_suffix()))
+    }
+
+    fn point(embedder: &HashingEmbedder, doc_id: &str, text: &str) -> VectorPoint {
+        VectorPoint {
+            doc_id: doc_id.to_string(),
+            vector: embedder.embed(text),
+        }
+    }
+
+    #[tokio::test]
+    async fn readers_see_committed_points_from_another_store() {
+        let dir = temp_dir();
+        let path = dir.join("vectors.bin");
+        let embedder = HashingEmbedder::default();
+        let writer = LocalVectorStore::open(&path).expect("writer");
+        let reader = LocalVectorStore::open(&path)
+            .expect("reader")
+            .with_reload_interval(Duration::ZERO);
+        let query = embedder.embed("garden");
+
+        writer
+            .upsert(vec![
+                point(&embedder, "pod:1", "community garden"),
+                point(&embedder, "quest:2", "tax report"),
+            ])
+            .await
+            .expect("upsert");
+        assert!(reader.search(&query, 1).await.expect("search").is_empty());
+
+        writer.commit().await.expect("commit");
+        let found = reader.search(&query, 1).await.expect("search");
+        assert_eq!(found[0].doc_id, "pod:1");
+
+        writer.delete(&["pod:1".to_string()]).await.expect("delete");
+        writer.commit().await.expect("commit");
+        let found = reader.search(&query, 2).await.expect("search");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].doc_id, "quest:2");
+        let reopened = LocalVectorStore::open(&path).expect("reopen");
+        assert_eq!(reopened.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn commits_append_their_changes_until_compacted() {
+        let dir = temp_dir();
+        let path = dir.join("vectors.bin");
+        let embedder = HashingEmbedder::default();
+        let writer = LocalVectorStore::open(&path).expect("writer");
+
+        writer
+            .upsert(vec![point(&embedder, "pod:1", "community garden")])
+            .await
+            .expect("upsert");
+        writer.commit().await.expect("commit");
+        let data = |path: &Path| {
+            let head = read_head(path).expect("head").expect("committed");
+            let bytes = std::fs::read(data_path(path, head.data)).expect("data");
+            bytes[..head.len as usize].to_vec()
+        };
+        let snapshot = data(&path);
+
+        writer
+            .upsert(vec![point(&embedder, "quest:2", "tax report")])
+            .await
+            .expect("upsert");
+        writer.clear().await.expect("clear");
+        writer
+            .upsert(vec![point(&embedder, "artifact:3", "seed library")])
+            .await
+            .expect("upsert");
+        writer.commit().await.expect("commit");
+        let appended = data(&path);
+        assert!(appended.len() > snapshot.len());
+        assert_eq!(&appended[..snapshot.len()], &snapshot[..]);
+
+        let reopened = LocalVectorStore::open(&path).expect("reopen");
+        assert_eq!(reopened.len(), 1);
+        let found = reopened
+            .search(&embedder.embed("seed library"), 1)
+            .await
+            .expect("search");
+        assert_eq!(found[0].doc_id, "artifact:3");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn forks_leave_their_origin_alone_and_readers_follow_them() {
+        let dir = temp_dir();
+        let embedder = HashingEmbedder::default();
+        let origin = LocalVectorStore::open(dir.join("a").join(VECTOR_FILE)).expect("origin");
+        origin
+            .upsert(vec![point(&embedder, "pod:1", "community garden")])
+            .await
+            .expect("upsert");
+        origin.commit().await.expect("commit");
+        let reader: Arc<dyn VectorStore> =
+            Arc::new(LocalVectorStore::open(dir.join("a").join(VECTOR_FILE)).expect("reader"));
+
+        let forked = origin.fork(&dir.join("b")).await.expect("fork");
+        forked
+            .upsert(vec![point(&embedder, "quest:2", "tax report")])
+            .await
+            .expect("upsert");
+        forked.commit().await.expect("commit");
+        // Diverges from the first fork at the same offset, with more bytes.
+        let sibling = origin.fork(&dir.join("c")).await.expect("fork");
+        sibling
+            .upsert(vec![
+                point(&embedder, "artifact:3", "seed library"),
+                point(&embedder, "artifact:4", "tool shed"),
+            ])
+            .await
+            .expect("upsert");
+        sibling
+            .delete(&["pod:1".to_string()])
+            .await
+            .expect("delete");
+        sibling.commit().await.expect("commit");
+        let reopened = LocalVectorStore::open(dir.join("a").join(VECTOR_FILE)).expect("reopen");
+        assert_eq!(reopened.len(), 1);
+
+        let query = embedder.embed("tax report");
+        let reader = reader.follow(&dir.join("b")).await.expect("follow");
+        let found = reader.search(&query, 5).await.expect("search");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].doc_id, "quest:2");
+
+        let reader = reader.follow(&dir.join("c")).await.expect("follow");
+        let mut found: Vec<String> = reader
+            .search(&query, 5)
+            .await
+            .expect("search")
+            .into_iter()
+            .map(|neighbour| neighbour.doc_id)
+            .collect();
+        found.sort();
+        assert_eq!(found, ["artifact:3", "artifact:4"]);
+
+        let reader = reader.follow(&dir.join("missing")).await.expect("follow");
+        assert!(reader.search(&query, 5).await.expect("search").is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn forks_and_followers_share_the_data_file_and_graph() {
+        let dir = temp_dir();
+        let embedder = HashingEmbedder::default();
+        let origin = LocalVectorStore::open(dir.join("a").join(VECTOR_FILE)).expect("origin");
+        origin
+            .upsert(vec![point(&embedder, "pod:1", "community garden")])
+            .await
+            .expect("upsert");
+        origin.commit().await.expect("commit");
+        let reader = LocalVectorStore::open(dir.join("a").join(VECTOR_FILE)).expect("reader");
+
+        let forked = origin
+            .shared
+            .fork(&dir.join("b").join(VECTOR_FILE))
+            .expect("fork");
+        let followed = reader
+            .shared
+            .follow(&dir.join("b").join(VECTOR_FILE))
+            .expect("follow");
+        let base = |state: &State| state.graph.base.clone();
+        let origin_state = origin.shared.state.read().expect("state");
+        assert!(Arc::ptr_eq(&base(&origin_state), &base(&forked)));
+        let saved = forked.saved.expect("saved");
+        assert_eq!(saved.data, origin_state.saved.expect("saved").data);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let inode = |version: &str| {
+                let path = data_path(&dir.join(version).join(VECTOR_FILE), saved.data);
+                std::fs::metadata(path).expect("data").ino()
+            };
+            assert_eq!(inode("a"), inode("b"));
+        }
+        assert!(Arc::ptr_eq(
+            &base(&reader.shared.state.read().expect("state")),
+            &base(&followed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changes_to_a_shared_graph_are_searched_beside_it_until_folded() {
+        let embedder = HashingEmbedder::default();
+        let mut origin = Graph::default();
+        for n in 0..10 {
+            origin
+                .insert(format!("doc:{n}"), embedder.embed(&format!("topic {n}")))
+                .expect("insert");
+        }
+        let mut graph = origin.clone();
+        graph
+            .insert("doc:1".to_string(), embedder.embed("seed library"))
+            .expect("replace");
+        graph.remove("doc:2").expect("remove");
+        graph
+            .insert("doc:10".to_string(), embedder.embed("tool shed"))
+            .expect("insert");
+        assert!(Arc::ptr_eq(&origin.base, &graph.base));
+        assert_eq!(graph.len(), 10);
+        assert_eq!(origin.len(), 10);
+
+        let ids = |graph: &Graph, query: &str| {
+            graph
+                .search(&embedder.embed(query), 3)
+                .expect("search")
+                .into_iter()
+                .map(|neighbour| neighbour.doc_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&graph, "seed library")[0], "doc:1");
+        assert_eq!(ids(&origin, "topic 1")[0], "doc:1");
+        assert!(!ids(&graph, "topic 2").contains(&"doc:2".to_string()));
+        assert_eq!(ids(&graph, "tool shed")[0], "doc:10");
+
+        for n in 0..MIN_OVERLAY {
+            graph
+                .insert(format!("extra:{n}"), embedder.embed(&format!("extra {n}")))
+                .expect("insert");
+        }
+        assert!(!Arc::ptr_eq(&origin.base, &graph.base));
+        assert!(graph.upserts.is_empty() && graph.removed.is_empty());
+        assert_eq!(graph.len(), 10 + MIN_OVERLAY);
+        assert_eq!(ids(&graph, "seed library")[0], "doc:1");
+        assert_eq!(origin.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn reload_checks_are_throttled() {
+        let dir = temp_dir();
+        let path = dir.join("vectors.bin");
+        let embedder = HashingEmbedder::default();
+        let writer = LocalVectorStore::open(&path).expect("writer");
+        let reader = LocalVectorStore::open(&path)
+            .expect("reader")
+            .with_reload_interval(Duration::from_secs(3600));
+        let query = embedder.embed("garden");
+
+        assert!(reader.search(&query, 1).await.expect("search").is_empty());
+        writer
+            .upsert(vec![point(&embedder, "pod:1", "community garden")])
+            .await
+            .expect("upsert");
+        writer.commit().await.expect("commit");
+        assert!(reader.search(&query, 1).await.expect("search").is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
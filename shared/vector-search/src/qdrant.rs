@@ -0,0 +1,208 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::embed::fnv1a;
+use crate::{Neighbour, VectorError, VectorPoint, VectorStore};
+
+/// Points per upsert request.
+const UPSERT_BATCH: usize = 256;
+
+/// Collection in a Qdrant server, reached over its REST API. Point ids are
+/// hashes of the `doc_id`, which is also kept in the payload and returned
+/// from searches. One collection serves every index version, so rolling
+/// the index back leaves the vectors as they are.
+#[derive(Clone)]
+pub struct QdrantStore {
+    client: Client,
+    base_url: String,
+    collection: String,
+    dimensions: usize,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    result: Vec<ScoredPoint>,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    score: f32,
+    payload: Option<Payload>,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    doc_id: String,
+}
+
+impl QdrantStore {
+    /// Connects to `base_url` (e.g. `http://localhost:6333`) and creates the
+    /// collection with cosine distance if it does not exist yet.
+    pub async fn connect(
+        base_url: impl Into<String>,
+        collection: impl Into<String>,
+        dimensions: usize,
+    ) -> Result<Self, VectorError> {
+        let store = Self {
+            client: Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            collection: collection.into(),
+            dimensions,
+        };
+        let response = store
+            .client
+            .get(store.url(""))
+            .send()
+            .await
+            .map_err(qdrant_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            store.create_collection().await?;
+        } else {
+            check(response).await?;
+        }
+        Ok(store)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/collections/{}{path}", self.base_url, self.collection)
+    }
+
+    async fn create_collection(&self) -> Result<(), VectorError> {
+        let body = json!({ "vectors": { "size": self.dimensions, "distance": "Cosine" } });
+        let response = self
+            .client
+            .put(self.url(""))
+            .json(&body)
+            .send()
+            .await
+            .map_err(qdrant_error)?;
+        check(response).await?;
+        Ok(())
+    }
+
+    fn check_dimensions(&self, vector: &[f32]) -> Result<(), VectorError> {
+        if vector.len() == self.dimensions {
+            Ok(())
+        } else {
+            Err(VectorError::Dimensions {
+                expected: self.dimensions,
+                actual: vector.len(),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    async fn upsert(&self, points: Vec<VectorPoint>) -> Result<(), VectorError> {
+        for batch in points.chunks(UPSERT_BATCH) {
+            let mut body = Vec::with_capacity(batch.len());
+            for point in batch {
+                self.check_dimensions(&point.vector)?;
+                body.push(json!({
+                    "id": point_id(&point.doc_id),
+                    "vector": point.vector,
+                    "payload": { "doc_id": point.doc_id },
+                }));
+            }
+            let response = self
+                .client
+                .put(self.url("/points?wait=true"))
+                .json(&json!({ "points": body }))
+                .send()
+                .await
+                .map_err(qdrant_error)?;
+            check(response).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, doc_ids: &[String]) -> Result<(), VectorError> {
+        if doc_ids.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<u64> = doc_ids.iter().map(|doc_id| point_id(doc_id)).collect();
+        let response = self
+            .client
+            .post(self.url("/points/delete?wait=true"))
+            .json(&json!({ "points": ids }))
+            .send()
+            .await
+            .map_err(qdrant_error)?;
+        check(response).await?;
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), VectorError> {
+        let response = self
+            .client
+            .delete(self.url(""))
+            .send()
+            .await
+            .map_err(qdrant_error)?;
+        if response.status() != StatusCode::NOT_FOUND {
+            check(response).await?;
+        }
+        self.create_collection().await
+    }
+
+    /// Writes are sent with `wait=true`, so they are already searchable.
+    async fn commit(&self) -> Result<(), VectorError> {
+        Ok(())
+    }
+
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<Neighbour>, VectorError> {
+        self.check_dimensions(vector)?;
+        let body = json!({ "vector": vector, "limit": limit, "with_payload": ["doc_id"] });
+        let response = self
+            .client
+            .post(self.url("/points/search"))
+            .json(&body)
+            .send()
+            .await
+            .map_err(qdrant_error)?;
+        let response: SearchResponse = check(response).await?.json().await.map_err(qdrant_error)?;
+        Ok(response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                point.payload.map(|payload| Neighbour {
+                    doc_id: payload.doc_id,
+                    score: point.score,
+                })
+            })
+            .collect())
+    }
+
+    async fn fork(&self, _dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError> {
+        Ok(Arc::new(self.clone()))
+    }
+
+    async fn follow(&self, _dir: &Path) -> Result<Arc<dyn VectorStore>, VectorError> {
+        Ok(Arc::new(self.clone()))
+    }
+}
+
+fn point_id(doc_id: &str) -> u64 {
+    fnv1a(doc_id.as_bytes())
+}
+
+/// Passes successful responses through and turns the rest into errors
+/// carrying Qdrant's explanation.
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, VectorError> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(VectorError::Qdrant(format!("{status}: {body}")))
+}
+
+fn qdrant_error(err: reqwest::Error) -> VectorError {
+    VectorError::Qdrant(err.to_string())
+}